@@ -0,0 +1,128 @@
+use platformer_shared::{
+    bevy::{prelude::*, ui::FocusPolicy},
+    game::AdvantageState,
+};
+
+use crate::DisplayedGame;
+
+// A faint glow along the edges of the screen in the color of the player holding the advantage,
+// so both players can tell the phase of the round at a glance. The glow is stacked from strips
+// of growing width, brightest at the very edge.
+
+const LAYERS: usize = 4;
+/// Width each layer adds to the one before it.
+const LAYER_WIDTH: f32 = 12.0;
+const LAYER_ALPHA: f32 = 0.05;
+/// Seconds the glow takes to come up or fade out.
+const FADE_SECONDS: f32 = 0.3;
+
+pub struct AdvantageTint {
+    material: Handle<ColorMaterial>,
+    /// Color of the last player to hold the advantage, kept while the glow fades out.
+    color: Color,
+    intensity: f32,
+}
+
+pub fn setup_advantage_tint(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let material = materials.add(Color::NONE.into());
+    for layer in 0..LAYERS {
+        let width = Val::Px(LAYER_WIDTH * (layer + 1) as f32);
+        for (size, position) in [
+            (
+                Size::new(Val::Percent(100.0), width),
+                Rect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+            ),
+            (
+                Size::new(Val::Percent(100.0), width),
+                Rect {
+                    bottom: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+            ),
+            (
+                Size::new(width, Val::Percent(100.0)),
+                Rect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+            ),
+            (
+                Size::new(width, Val::Percent(100.0)),
+                Rect {
+                    top: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    ..Default::default()
+                },
+            ),
+        ]
+        .iter()
+        {
+            commands.spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: *size,
+                    position: *position,
+                    ..Default::default()
+                },
+                material: material.clone(),
+                // the clicks go through to whatever is below
+                focus_policy: FocusPolicy::Pass,
+                visible: Visible {
+                    is_visible: true,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            });
+        }
+    }
+    commands.insert_resource(AdvantageTint {
+        material,
+        color: Color::NONE,
+        intensity: 0.0,
+    });
+}
+
+pub fn update_advantage_tint(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut tint: ResMut<AdvantageTint>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let advantage_state = displayed_game
+        .state
+        .as_ref()
+        .map_or(AdvantageState::Neutral, |display_state| {
+            display_state.advantage_state
+        });
+    let target = match advantage_state {
+        AdvantageState::Neutral => 0.0,
+        AdvantageState::Player1 => {
+            tint.color = Color::BLUE;
+            1.0
+        }
+        AdvantageState::Player2 => {
+            tint.color = Color::RED;
+            1.0
+        }
+    };
+    let step = time.delta_seconds() / FADE_SECONDS;
+    tint.intensity = if tint.intensity < target {
+        (tint.intensity + step).min(target)
+    } else {
+        (tint.intensity - step).max(target)
+    };
+
+    let mut color = tint.color;
+    color.set_a(LAYER_ALPHA * tint.intensity);
+    if let Some(material) = materials.get_mut(&tint.material) {
+        if material.color != color {
+            material.color = color;
+        }
+    }
+}
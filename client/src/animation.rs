@@ -0,0 +1,264 @@
+use platformer_shared::{
+    bevy::{
+        prelude::*,
+        render::texture::{Extent3d, TextureDimension},
+        sprite::SpriteResizeMode,
+    },
+    game::{Facing, GameEvent, PlayerId, PlayerMotion},
+};
+
+use crate::DisplayedGame;
+
+// Animations of the players, played from a sprite sheet with a row per animation and a frame per
+// column, drawn facing right. They're picked from the motion the display state shows and timed
+// by the frame clock, so they play as smoothly at any tick rate. Until the sheet loads, or if it
+// can't be, the players stay colored squares.
+
+pub const PLAYER_SHEET: &str = "textures/player_sheet.png";
+/// Columns of the sheet, the frames of the longest animation.
+const SHEET_COLUMNS: usize = 4;
+/// Horizontal speed in pixels per second from which a player runs.
+const RUN_SPEED: f32 = 20.0;
+/// Seconds the fire animation takes over after firing the cannon.
+const FIRE_SECONDS: f64 = 0.3;
+/// Seconds a player blinks white for after a projectile breaks their shield.
+const HIT_FLASH_SECONDS: f64 = 0.5;
+const HIT_BLINKS_PER_SECOND: f64 = 12.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Animation {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Fire,
+    Death,
+}
+
+impl Animation {
+    /// In the order of the sheet's rows.
+    const ALL: [Animation; 6] = [
+        Animation::Idle,
+        Animation::Run,
+        Animation::Jump,
+        Animation::Fall,
+        Animation::Fire,
+        Animation::Death,
+    ];
+
+    fn frames_per_second(self) -> f64 {
+        match self {
+            Animation::Idle => 4.0,
+            Animation::Run => 12.0,
+            Animation::Jump | Animation::Fall => 8.0,
+            Animation::Fire => 12.0,
+            Animation::Death => 6.0,
+        }
+    }
+
+    /// Animations that don't loop stop on their last frame.
+    fn looping(self) -> bool {
+        !matches!(self, Animation::Fire | Animation::Death)
+    }
+
+    /// What the player's motion looks like, upwards being away from their half's gravity.
+    fn of(motion: &PlayerMotion, player_id: PlayerId, firing: bool) -> Self {
+        let upwards_velocity = match player_id {
+            PlayerId::Player1 => motion.velocity.y,
+            PlayerId::Player2 => -motion.velocity.y,
+        };
+        if motion.dead {
+            Animation::Death
+        } else if firing {
+            Animation::Fire
+        } else if !motion.grounded && upwards_velocity > 0.0 {
+            Animation::Jump
+        } else if !motion.grounded {
+            Animation::Fall
+        } else if motion.velocity.x.abs() > RUN_SPEED {
+            Animation::Run
+        } else {
+            Animation::Idle
+        }
+    }
+}
+
+/// The sheet, and its frames once it's loaded and cut up.
+pub struct PlayerSheet {
+    sheet: Handle<Texture>,
+    /// A row of frames per animation, in the order of `Animation::ALL`.
+    frames: Vec<Vec<Handle<Texture>>>,
+}
+
+pub struct PlayerAnimation {
+    player_id: PlayerId,
+    /// Color the frames are multiplied by.
+    tint: Color,
+    animation: Animation,
+    started_at: f64,
+    fired_at: Option<f64>,
+    hit_at: Option<f64>,
+    animated_until: Option<u32>,
+    shown_frame: Option<(Animation, usize)>,
+}
+
+impl PlayerAnimation {
+    pub fn new(player_id: PlayerId, tint: Color) -> Self {
+        Self {
+            player_id,
+            tint,
+            animation: Animation::Idle,
+            started_at: 0.0,
+            fired_at: None,
+            hit_at: None,
+            animated_until: None,
+            shown_frame: None,
+        }
+    }
+}
+
+pub fn load_player_sheet(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PlayerSheet {
+        sheet: asset_server.load(PLAYER_SHEET),
+        frames: vec![],
+    });
+}
+
+/// Cuts the sheet into a texture per frame, as sprites show whole textures.
+pub fn cut_player_sheet(mut sheet: ResMut<PlayerSheet>, mut textures: ResMut<Assets<Texture>>) {
+    if !sheet.frames.is_empty() {
+        return;
+    }
+    let (data, size, format) = match textures.get(&sheet.sheet) {
+        Some(texture) => (texture.data.clone(), texture.size, texture.format),
+        None => return,
+    };
+    let pixel_size = format.pixel_size();
+    let sheet_width = size.width as usize;
+    let frame_width = sheet_width / SHEET_COLUMNS;
+    let frame_height = size.height as usize / Animation::ALL.len();
+    sheet.frames = (0..Animation::ALL.len())
+        .map(|row| {
+            (0..SHEET_COLUMNS)
+                .map(|column| {
+                    let mut frame = Vec::with_capacity(frame_width * frame_height * pixel_size);
+                    for y in row * frame_height..(row + 1) * frame_height {
+                        let start = (y * sheet_width + column * frame_width) * pixel_size;
+                        frame.extend_from_slice(&data[start..start + frame_width * pixel_size]);
+                    }
+                    textures.add(Texture::new(
+                        Extent3d::new(frame_width as u32, frame_height as u32, 1),
+                        TextureDimension::D2,
+                        frame,
+                        format,
+                    ))
+                })
+                .collect()
+        })
+        .collect();
+}
+
+pub fn animate_players(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    sheet: Res<PlayerSheet>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut players: Query<(
+        &mut PlayerAnimation,
+        &mut Transform,
+        &mut Sprite,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let display_state = match &displayed_game.state {
+        Some(display_state) => display_state,
+        None => return,
+    };
+    let now = time.seconds_since_startup();
+    for (mut player, mut transform, mut sprite, material) in players.iter_mut() {
+        // the events from before joining or reconnecting are left out
+        let since = match player.animated_until {
+            Some(since) if since <= display_state.tick => since,
+            _ => display_state.tick,
+        };
+        player.animated_until = Some(display_state.tick);
+        let player_id = player.player_id;
+        let new_events = display_state
+            .events
+            .iter()
+            .filter(|(tick, _)| *tick > since)
+            .map(|(_, event)| *event);
+        let mut fired = false;
+        for event in new_events {
+            if event == GameEvent::Fired(Some(player_id)) {
+                fired = true;
+            } else if event == GameEvent::ShieldBroken(player_id) {
+                player.hit_at = Some(now);
+            }
+        }
+        if fired {
+            player.fired_at = Some(now);
+            // firing again starts the animation over
+            player.started_at = now;
+        }
+        let firing = player
+            .fired_at
+            .map_or(false, |fired_at| now - fired_at < FIRE_SECONDS);
+
+        let motion = match player_id {
+            PlayerId::Player1 => &display_state.player1_motion,
+            PlayerId::Player2 => &display_state.player2_motion,
+        };
+        let animation = Animation::of(motion, player_id, firing);
+        if animation != player.animation {
+            player.animation = animation;
+            player.started_at = now;
+        }
+
+        // the other half of the map is upside down, and flipping the view turns both around
+        let flipped = displayed_game.world_flipped();
+        let facing_right = (motion.facing == Facing::Right) != flipped;
+        let upside_down = (player_id == PlayerId::Player2) != flipped;
+        transform.scale = Vec3::new(
+            if facing_right { 1.0 } else { -1.0 },
+            if upside_down { -1.0 } else { 1.0 },
+            1.0,
+        );
+
+        // the frames are multiplied by white in the flash's blinks, showing them uncolored
+        let blinking = player.hit_at.map_or(false, |hit_at| {
+            now - hit_at < HIT_FLASH_SECONDS
+                && ((now - hit_at) * HIT_BLINKS_PER_SECOND) as u32 % 2 == 0
+        });
+        let color = if blinking { Color::WHITE } else { player.tint };
+        if let Some(material) = materials.get_mut(material) {
+            if material.color != color {
+                material.color = color;
+            }
+        }
+
+        let row = Animation::ALL
+            .iter()
+            .position(|candidate| *candidate == animation)
+            .unwrap();
+        let frames = match sheet.frames.get(row) {
+            Some(frames) => frames,
+            None => continue,
+        };
+        let elapsed_frames = ((now - player.started_at) * animation.frames_per_second()) as usize;
+        let frame = if animation.looping() {
+            elapsed_frames % frames.len()
+        } else {
+            elapsed_frames.min(frames.len() - 1)
+        };
+        if player.shown_frame == Some((animation, frame)) {
+            continue;
+        }
+        player.shown_frame = Some((animation, frame));
+        // the sprite keeps the character's size instead of taking the frame's
+        sprite.resize_mode = SpriteResizeMode::Manual;
+        if let Some(material) = materials.get_mut(material) {
+            material.texture = Some(frames[frame].clone());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use platformer_shared::{bevy::prelude::*, tournament::Bracket};
+
+use crate::{DisplayedGame, UI_FONT};
+
+// Bracket of the tournament the server runs, shown in the lobby and to spectators
+
+/// Latest bracket the server sent, unset on servers that don't run a tournament.
+#[derive(Default)]
+pub struct TournamentBracket(pub Option<Bracket>);
+
+pub struct BracketText;
+
+pub fn setup_bracket_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(40.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(BracketText);
+}
+
+pub fn update_bracket_panel(
+    bracket: Res<TournamentBracket>,
+    displayed_game: Res<DisplayedGame>,
+    mut query: Query<(&mut Text, &mut Visible), With<BracketText>>,
+) {
+    // players in a match have it to themselves
+    let playing = displayed_game.local_player.is_some()
+        && displayed_game
+            .state
+            .as_ref()
+            .map_or(false, |display_state| {
+                !display_state.lobby && display_state.match_result.is_none()
+            });
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = bracket.0.is_some() && !playing;
+        if bracket.is_changed() {
+            if let Some(bracket) = &bracket.0 {
+                let mut lines = vec!["Tournament".to_string()];
+                lines.extend(bracket.summary());
+                text.sections[0].value = lines.join("\n");
+            }
+        }
+    }
+}
@@ -3,7 +3,15 @@ use platformer_shared::bevy::{
     render::camera::{CameraProjection, DepthCalculation},
 };
 
-// Camera that adjusts to window size + maintains aspect ratio
+use crate::DisplayedGame;
+
+// Camera that adjusts to window size + maintains aspect ratio, centering the map between black
+// bars when the window is wider or taller than it
+
+/// Above every layer of the level.
+const LETTERBOX_Z: f32 = 20.0;
+/// How far the bars reach past the map, in map sizes, enough for any window's shape.
+const LETTERBOX_EXTENT: f32 = 10.0;
 
 pub struct SimpleOrthoProjection {
     pub far: f32,
@@ -22,15 +30,12 @@ impl CameraProjection for SimpleOrthoProjection {
         } else {
             (1.0, 1.0 / self.aspect)
         };
+        let (width, height) = (right * self.multiplier, top * self.multiplier);
+        // the space the map doesn't fill is split between both sides
+        let left = (self.map_pixel_width - width) / 2.0;
+        let bottom = (self.map_pixel_height - height) / 2.0;
 
-        Mat4::orthographic_rh(
-            0.0,
-            right * self.multiplier,
-            0.0,
-            top * self.multiplier,
-            0.0,
-            self.far,
-        )
+        Mat4::orthographic_rh(left, left + width, bottom, bottom + height, 0.0, self.far)
     }
 
     // what to do on window resize
@@ -63,3 +68,43 @@ impl SimpleOrthoProjection {
         }
     }
 }
+
+/// Covers whatever is drawn past the map's edges, which the window shows when its shape differs
+/// from the map's.
+pub fn setup_letterbox(
+    mut commands: Commands,
+    displayed_game: Res<DisplayedGame>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(Color::BLACK.into());
+    let (width, height) = (displayed_game.map.width, displayed_game.map.height);
+    let reach = LETTERBOX_EXTENT * width.max(height);
+    let bars = [
+        // left and right, tall enough to cover the corners
+        (
+            Vec2::new(-reach / 2.0, height / 2.0),
+            Vec2::new(reach, height + 2.0 * reach),
+        ),
+        (
+            Vec2::new(width + reach / 2.0, height / 2.0),
+            Vec2::new(reach, height + 2.0 * reach),
+        ),
+        // bottom and top
+        (
+            Vec2::new(width / 2.0, -reach / 2.0),
+            Vec2::new(width, reach),
+        ),
+        (
+            Vec2::new(width / 2.0, height + reach / 2.0),
+            Vec2::new(width, reach),
+        ),
+    ];
+    for (center, size) in bars.iter() {
+        commands.spawn_bundle(SpriteBundle {
+            material: material.clone(),
+            sprite: Sprite::new(*size),
+            transform: Transform::from_xyz(center.x, center.y, LETTERBOX_Z),
+            ..Default::default()
+        });
+    }
+}
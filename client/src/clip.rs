@@ -0,0 +1,124 @@
+use platformer_shared::{bevy::prelude::*, MapDefinition};
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::BufWriter,
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    raster::{Frame, Raster, PALETTE},
+    DisplayedGame,
+};
+
+// Clips of the last round as animated GIFs, saved with F9 for sharing highlights. The frames are
+// drawn from the display states the round showed by the software renderer, at a quarter of the
+// arena's size. Only built with the `clip` feature.
+
+const CLIPS_DIRECTORY: &str = "clips";
+const CLIP_FPS: f64 = 15.0;
+/// The start of longer rounds is left out.
+const MAX_CLIP_SECONDS: f64 = 30.0;
+/// Display units per pixel of the clip.
+const CLIP_SCALE: f32 = 4.0;
+
+#[derive(Default)]
+pub struct ClipRecorder {
+    round: Option<u8>,
+    /// Frames of the round under way, oldest first.
+    frames: Vec<Frame>,
+    /// Frames of the round before it.
+    last_round: Vec<Frame>,
+    recorded_at: f64,
+    /// Map the frames were recorded on.
+    map: MapDefinition,
+}
+
+pub fn record_clip(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut recorder: ResMut<ClipRecorder>,
+) {
+    let display_state = match &displayed_game.state {
+        Some(display_state) if !display_state.lobby => display_state,
+        _ => return,
+    };
+    let now = time.seconds_since_startup();
+    if now - recorder.recorded_at < 1.0 / CLIP_FPS {
+        return;
+    }
+    recorder.recorded_at = now;
+    recorder.map = displayed_game.map;
+
+    if recorder.round != Some(display_state.round) {
+        recorder.round = Some(display_state.round);
+        recorder.last_round = std::mem::take(&mut recorder.frames);
+    }
+    recorder.frames.push(Frame::of(display_state));
+    let max_frames = (MAX_CLIP_SECONDS * CLIP_FPS) as usize;
+    if recorder.frames.len() > max_frames {
+        let excess = recorder.frames.len() - max_frames;
+        recorder.frames.drain(..excess);
+    }
+}
+
+/// Saves the last round, or the one under way if there wasn't one before it, in the background.
+pub fn save_clip(input: Res<Input<KeyCode>>, recorder: Res<ClipRecorder>) {
+    if !input.just_pressed(KeyCode::F9) {
+        return;
+    }
+    let frames = if recorder.last_round.is_empty() {
+        recorder.frames.clone()
+    } else {
+        recorder.last_round.clone()
+    };
+    if frames.is_empty() {
+        return;
+    }
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = PathBuf::from(CLIPS_DIRECTORY).join(format!("{}.gif", time));
+    let map = recorder.map;
+    thread::spawn(move || {
+        let saved = fs::create_dir_all(CLIPS_DIRECTORY)
+            .and_then(|_| File::create(&path))
+            .map_err(|error| error.to_string())
+            .and_then(|file| write_gif(BufWriter::new(file), &map, &frames));
+        match saved {
+            Ok(()) => info!("Saved the clip to {}", path.display()),
+            Err(error) => warn!("Can't save the clip to {}: {}", path.display(), error),
+        }
+    });
+}
+
+fn write_gif(
+    writer: impl std::io::Write,
+    map: &MapDefinition,
+    frames: &[Frame],
+) -> Result<(), String> {
+    let level = Raster::level(map, CLIP_SCALE);
+    let mut encoder = gif::Encoder::new(writer, level.width, level.height, &PALETTE)
+        .map_err(|error| error.to_string())?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|error| error.to_string())?;
+    for frame in frames {
+        let mut raster = level.clone();
+        raster.draw(frame);
+        encoder
+            .write_frame(&gif::Frame {
+                width: raster.width,
+                height: raster.height,
+                // in hundredths of a second
+                delay: (100.0 / CLIP_FPS).round() as u16,
+                buffer: Cow::Owned(raster.pixels),
+                ..Default::default()
+            })
+            .map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,499 @@
+#[cfg(feature = "encryption")]
+use platformer_shared::encryption;
+use platformer_shared::{
+    bevy::prelude::*,
+    conditions::{self, NetworkConditions},
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+    settings::NetworkSettings,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+use crate::{
+    discovery::DiscoveredServers,
+    menu::{ClientState, MenuButton},
+    names::PlayerName,
+    UI_FONT,
+};
+
+// Connect screen Play leads to from the main menu, where the server address and the player's name
+// get typed in, or a server found on the LAN gets picked. Passing `--server <host:port>` skips it
+// and connects right away.
+
+/// Server picked with `--server <host:port>`.
+pub struct ServerAddress(pub Option<SocketAddr>);
+
+#[derive(Default)]
+pub struct ConnectScreen {
+    address: String,
+    name: String,
+    /// Typing goes to the name instead of the address, Tab switches between them.
+    editing_name: bool,
+    error: Option<String>,
+    /// Set once a connection was attempted, hiding the screen.
+    connecting: bool,
+    /// Server of the last connection attempt.
+    server: Option<SocketAddr>,
+}
+
+impl ConnectScreen {
+    /// Brings the screen back, telling why the connection didn't work out.
+    pub fn show_error(&mut self, error: String) {
+        self.connecting = false;
+        self.error = Some(error);
+    }
+
+    /// Leaves the server, bringing the screen back without an error.
+    pub fn disconnect(&mut self) {
+        self.connecting = false;
+        self.error = None;
+    }
+
+    /// Server being connected or reconnected to, while the screen is hidden.
+    pub fn server(&self) -> Option<SocketAddr> {
+        if self.connecting {
+            self.server
+        } else {
+            None
+        }
+    }
+}
+
+/// Every entity of the screen, hidden together.
+pub struct ConnectScreenPart;
+
+pub struct AddressText;
+
+pub struct NameText;
+
+pub struct ConnectButton;
+
+pub struct ConnectErrorText;
+
+/// Parent of the buttons of the servers found on the LAN.
+pub struct ServerList;
+
+pub struct ServerEntry(SocketAddr);
+
+/// Hides the screen while connecting. It comes back when the traffic can't be encrypted, rather
+/// than sending it in plaintext.
+pub fn connect(
+    screen: &mut ConnectScreen,
+    net: &mut NetworkResource,
+    conditions: &NetworkConditions,
+    socket_address: SocketAddr,
+) {
+    screen.connecting = true;
+    screen.server = Some(socket_address);
+    if let Err(error) = open_connection(net, conditions, socket_address) {
+        screen.show_error(error);
+    }
+}
+
+fn open_connection(
+    net: &mut NetworkResource,
+    conditions: &NetworkConditions,
+    socket_address: SocketAddr,
+) -> Result<(), String> {
+    info!("Connecting to {}", socket_address);
+    let localhost: IpAddr = if socket_address.is_ipv6() {
+        Ipv6Addr::LOCALHOST.into()
+    } else {
+        Ipv4Addr::LOCALHOST.into()
+    };
+    let listen = SocketAddr::new(localhost, 0);
+    let socket_address = if conditions.is_simulated() {
+        match conditions::spawn_proxy(listen, socket_address, *conditions) {
+            Ok(proxy) => {
+                info!("Simulating {:?} through {}", conditions, proxy);
+                proxy
+            }
+            Err(error) => {
+                warn!("Can't simulate the network conditions: {}", error);
+                socket_address
+            }
+        }
+    } else {
+        socket_address
+    };
+    // sealed ahead of the simulated network
+    #[cfg(feature = "encryption")]
+    let socket_address = encryption::spawn_client_tunnel(listen, socket_address)
+        .map_err(|error| format!("Can't encrypt the connection: {}", error))?;
+    net.connect(socket_address);
+    Ok(())
+}
+
+/// Takes `host:port` and `[ipv6]:port`, or either without the port to use the default one. Bare
+/// IPv6 addresses have colons of their own, so they only come with a port in brackets.
+pub fn resolve(address: &str, default_port: u16) -> Result<SocketAddr, String> {
+    let address = address.trim();
+    let ip_address = address
+        .strip_prefix('[')
+        .and_then(|address| address.strip_suffix(']'))
+        .unwrap_or(address)
+        .parse::<IpAddr>();
+    if let Ok(ip_address) = ip_address {
+        return Ok(SocketAddr::new(ip_address, default_port));
+    }
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, default_port)
+    };
+    address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| {
+            format!(
+                "Can't resolve {}, expected host:port or [ipv6]:port",
+                address
+            )
+        })
+}
+
+/// Native clients connect to the game port.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_port(network_settings: &NetworkSettings) -> u16 {
+    network_settings.port
+}
+
+/// Browsers connect to the WebRTC signaling port.
+#[cfg(target_arch = "wasm32")]
+pub fn default_port(network_settings: &NetworkSettings) -> u16 {
+    network_settings.webrtc_port
+}
+
+/// Servers usually run on this machine during development.
+#[cfg(not(target_arch = "wasm32"))]
+fn default_address(network_settings: &NetworkSettings) -> String {
+    use platformer_shared::crystalorb_bevy_networking_turbulence::bevy_networking_turbulence;
+
+    bevy_networking_turbulence::find_my_ip_address()
+        .map(|ip_address| SocketAddr::new(ip_address, network_settings.port).to_string())
+        .unwrap_or_default()
+}
+
+/// Browsers can't look up their own address, and connect to the WebRTC signaling port instead.
+#[cfg(target_arch = "wasm32")]
+fn default_address(network_settings: &NetworkSettings) -> String {
+    format!("127.0.0.1:{}", network_settings.webrtc_port)
+}
+
+pub fn setup_connect_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    server_address: Res<ServerAddress>,
+    network_settings: Res<NetworkSettings>,
+    player_name: Res<PlayerName>,
+    conditions: Res<NetworkConditions>,
+    mut screen: ResMut<ConnectScreen>,
+    mut net: ResMut<NetworkResource>,
+) {
+    if let Some(socket_address) = server_address.0 {
+        connect(&mut screen, &mut net, &conditions, socket_address);
+    } else if let Some(address) = &network_settings.server {
+        screen.address = address.clone();
+    } else {
+        screen.address = default_address(&network_settings);
+    }
+
+    screen.name = player_name.0.clone();
+
+    let font = asset_server.load(UI_FONT);
+    let visible = Visible {
+        is_visible: !screen.connecting,
+        is_transparent: true,
+    };
+    let text = |value: &str, font_size: f32, color: Color| {
+        Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color,
+            },
+            Default::default(),
+        )
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            visible: visible.clone(),
+            ..Default::default()
+        })
+        .insert(ConnectScreenPart)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: text("Server address", 24.0, Color::WHITE),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart);
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: text("", 32.0, Color::YELLOW),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(AddressText);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: text("Name", 24.0, Color::WHITE),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart);
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: text("", 32.0, Color::YELLOW),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(NameText);
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        padding: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::DARK_GRAY.into()),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(ConnectButton)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: text("Connect", 24.0, Color::WHITE),
+                            visible: visible.clone(),
+                            ..Default::default()
+                        })
+                        .insert(ConnectScreenPart);
+                });
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: text("", 20.0, Color::RED),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(ConnectErrorText);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: text("Servers on the LAN", 24.0, Color::WHITE),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(ServerList);
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        padding: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::DARK_GRAY.into()),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .insert(ConnectScreenPart)
+                .insert(MenuButton::Back)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: text("Back", 24.0, Color::WHITE),
+                            visible: visible.clone(),
+                            ..Default::default()
+                        })
+                        .insert(ConnectScreenPart);
+                });
+        });
+}
+
+/// Typing edits the address or the name, Enter or the button connects.
+pub fn connect_screen_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ConnectButton>)>,
+    entries: Query<(&Interaction, &ServerEntry), Changed<Interaction>>,
+    mut screen: ResMut<ConnectScreen>,
+    mut player_name: ResMut<PlayerName>,
+    conditions: Res<NetworkConditions>,
+    network_settings: Res<NetworkSettings>,
+    mut net: ResMut<NetworkResource>,
+) {
+    if screen.connecting {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        screen.editing_name = !screen.editing_name;
+    }
+    let screen = &mut *screen;
+    let field = if screen.editing_name {
+        &mut screen.name
+    } else {
+        &mut screen.address
+    };
+    for event in characters.iter() {
+        if !event.char.is_control() {
+            field.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        field.pop();
+    }
+
+    let picked = entries
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Clicked);
+    if let Some((_, entry)) = picked {
+        screen.address = entry.0.to_string();
+    }
+
+    let clicked = buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    if picked.is_some() || clicked || keys.just_pressed(KeyCode::Return) {
+        match resolve(&screen.address, default_port(&network_settings)) {
+            Ok(socket_address) => {
+                screen.error = None;
+                player_name.0 = screen.name.clone();
+                connect(screen, &mut net, &conditions, socket_address);
+            }
+            Err(error) => screen.error = Some(error),
+        }
+    }
+}
+
+/// Rebuilds the buttons of the LAN servers whenever a server shows up, changes or goes quiet.
+pub fn update_server_list(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut button_material: Local<Option<Handle<ColorMaterial>>>,
+    servers: Res<DiscoveredServers>,
+    lists: Query<(Entity, &Visible, Option<&Children>), With<ServerList>>,
+) {
+    if !servers.is_changed() {
+        return;
+    }
+
+    let button_material = button_material
+        .get_or_insert_with(|| materials.add(Color::DARK_GRAY.into()))
+        .clone();
+    let font = asset_server.load(UI_FONT);
+    for (list, visible, children) in lists.iter() {
+        let visible = visible.clone();
+        for child in children.iter().flat_map(|children| children.iter()) {
+            commands.entity(*child).despawn_recursive();
+        }
+        commands.entity(list).with_children(|parent| {
+            for server in servers.0.iter() {
+                let label = format!(
+                    "{} - {}/2 players - {} ({})",
+                    server.beacon.name, server.beacon.players, server.beacon.map, server.address
+                );
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(4.0)),
+                            padding: Rect::all(Val::Px(6.0)),
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        visible: visible.clone(),
+                        ..Default::default()
+                    })
+                    .insert(ConnectScreenPart)
+                    .insert(ServerEntry(server.address))
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    label,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 18.0,
+                                        color: Color::WHITE,
+                                    },
+                                    Default::default(),
+                                ),
+                                visible: visible.clone(),
+                                ..Default::default()
+                            })
+                            .insert(ConnectScreenPart);
+                    });
+            }
+        });
+    }
+}
+
+pub fn update_connect_screen(
+    state: Res<State<ClientState>>,
+    screen: Res<ConnectScreen>,
+    mut parts: Query<&mut Visible, With<ConnectScreenPart>>,
+    mut texts: QuerySet<(
+        Query<&mut Text, With<AddressText>>,
+        Query<&mut Text, With<NameText>>,
+        Query<&mut Text, With<ConnectErrorText>>,
+    )>,
+) {
+    if !screen.is_changed() && !state.is_changed() {
+        return;
+    }
+
+    for mut visible in parts.iter_mut() {
+        visible.is_visible = !screen.connecting && *state.current() == ClientState::Connect;
+    }
+    let cursor = |editing: bool| if editing { "_" } else { "" };
+    for mut text in texts.q0_mut().iter_mut() {
+        text.sections[0].value = format!("{}{}", screen.address, cursor(!screen.editing_name));
+    }
+    for mut text in texts.q1_mut().iter_mut() {
+        text.sections[0].value = format!("{}{}", screen.name, cursor(screen.editing_name));
+    }
+    for mut text in texts.q2_mut().iter_mut() {
+        text.sections[0].value = screen.error.clone().unwrap_or_default();
+    }
+}
@@ -0,0 +1,136 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::NetworkResource,
+        crystalorb::client::{stage::Stage, Client},
+    },
+    game::GameWorld,
+};
+
+use crate::{connect::ConnectScreen, reconnect::Reconnect, DisplayedGame, UI_FONT};
+
+// What the connection is up to until the game shows: connecting, syncing the clock with the
+// server and waiting for the first snapshot. Failures bring the connect screen back with the
+// error, and dropped connections show the reconnect overlay instead.
+//
+// The window title follows along, with the score and round once the game shows, to keep track of
+// a client that's alt-tabbed away from or one of several side by side.
+
+pub const WINDOW_TITLE: &str = "Platformer";
+
+pub struct ConnectionStatusText;
+
+pub fn setup_connection_status(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(40.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 32.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(ConnectionStatusText);
+        });
+}
+
+pub fn update_connection_status(
+    mut previous: Local<Option<String>>,
+    screen: Res<ConnectScreen>,
+    reconnect: Res<Reconnect>,
+    client: Res<Client<GameWorld>>,
+    net: Res<NetworkResource>,
+    mut query: Query<(&mut Text, &mut Visible), With<ConnectionStatusText>>,
+) {
+    let status = match screen.server() {
+        // the connect screen and the reconnect overlay speak for themselves
+        None => None,
+        Some(_) if reconnect.is_reconnecting() => None,
+        Some(server) if net.connections.is_empty() => Some(format!("Connecting to {}...", server)),
+        Some(_) => match client.stage() {
+            Stage::SyncingClock(syncing_clock) => Some(format!(
+                "Syncing the clock with the server... {}/{}",
+                syncing_clock.sample_count(),
+                syncing_clock.samples_needed()
+            )),
+            Stage::SyncingInitialState(_) => Some("Waiting for the game state...".to_string()),
+            Stage::Ready(_) => None,
+        },
+    };
+    if *previous == status {
+        return;
+    }
+    info!(
+        "Connection status: {}",
+        status.as_deref().unwrap_or("ready")
+    );
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = status.is_some();
+        if let Some(status) = &status {
+            text.sections[0].value = status.clone();
+        }
+    }
+    *previous = status;
+}
+
+pub fn update_window_title(
+    screen: Res<ConnectScreen>,
+    reconnect: Res<Reconnect>,
+    client: Res<Client<GameWorld>>,
+    net: Res<NetworkResource>,
+    displayed_game: Res<DisplayedGame>,
+    mut windows: ResMut<Windows>,
+) {
+    let stage = match screen.server() {
+        None => "Menu",
+        Some(_) if reconnect.is_reconnecting() => "Reconnecting",
+        Some(_) if net.connections.is_empty() => "Connecting",
+        Some(_) => match client.stage() {
+            Stage::SyncingClock(_) => "Syncing",
+            Stage::SyncingInitialState(_) => "Loading",
+            Stage::Ready(_) => "Ready",
+        },
+    };
+    let title = match (stage, &displayed_game.state) {
+        ("Ready", Some(display_state)) if display_state.lobby => {
+            format!("{} \u{2014} Lobby", WINDOW_TITLE)
+        }
+        ("Ready", Some(display_state)) => format!(
+            "{} \u{2014} Ready \u{2014} {}:{} R{}",
+            WINDOW_TITLE, display_state.scores[0], display_state.scores[1], display_state.round
+        ),
+        _ => format!("{} \u{2014} {}", WINDOW_TITLE, stage),
+    };
+    if let Some(window) = windows.get_primary_mut() {
+        if window.title() != title {
+            window.set_title(title);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use platformer_shared::{
+    bevy::{prelude::*, ui::FocusPolicy},
+    game::{GameEvent, PlayerId},
+};
+
+use crate::DisplayedGame;
+
+// A flash over the whole screen in the color of the player who died, so that a death doesn't go
+// unnoticed in the moment before the round resets.
+
+const FLASH_SECONDS: f64 = 0.4;
+/// Opacity the flash starts at, kept low to go easy on the eyes.
+const FLASH_ALPHA: f32 = 0.35;
+
+pub struct DeathFlash;
+
+#[derive(Default)]
+pub struct DeathFlashState {
+    flashed_until: Option<u32>,
+    /// The dead player's color and when they died.
+    flash: Option<(Color, f64)>,
+}
+
+pub fn setup_death_flash(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            // the clicks go through to whatever is below
+            focus_policy: FocusPolicy::Pass,
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(DeathFlash);
+}
+
+pub fn update_death_flash(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut state: Local<DeathFlashState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&mut Visible, &Handle<ColorMaterial>), With<DeathFlash>>,
+) {
+    let now = time.seconds_since_startup();
+    match &displayed_game.state {
+        Some(display_state) => {
+            // the events from before joining or reconnecting are left out
+            let since = match state.flashed_until {
+                Some(since) if since <= display_state.tick => since,
+                _ => display_state.tick,
+            };
+            state.flashed_until = Some(display_state.tick);
+            for (tick, event) in display_state.events.iter() {
+                if let GameEvent::Died(player_id) = event {
+                    if *tick > since {
+                        let color = match player_id {
+                            PlayerId::Player1 => Color::BLUE,
+                            PlayerId::Player2 => Color::RED,
+                        };
+                        state.flash = Some((color, now));
+                    }
+                }
+            }
+        }
+        None => state.flashed_until = None,
+    }
+
+    let flash = state.flash.and_then(|(mut color, started_at)| {
+        let progress = (now - started_at) / FLASH_SECONDS;
+        if progress < 1.0 {
+            color.set_a(FLASH_ALPHA * (1.0 - progress as f32));
+            Some(color)
+        } else {
+            None
+        }
+    });
+    for (mut visible, material) in query.iter_mut() {
+        visible.is_visible = flash.is_some();
+        if let (Some(color), Some(material)) = (flash, materials.get_mut(material)) {
+            material.color = color;
+        }
+    }
+}
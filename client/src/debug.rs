@@ -0,0 +1,173 @@
+use platformer_shared::bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{net_stats::NetStats, DisplayedGame, UI_FONT};
+
+// Debug overlay with netcode diagnostics, toggled with F3
+
+const ROLLBACK_ERROR_WINDOW_SECONDS: f64 = 10.0;
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+pub struct DebugOverlayText;
+
+/// How far the server's snapshots moved the local player from where the client predicted it,
+/// one sample per snapshot: the position predicted for the snapshot's tick, recorded just before
+/// the snapshot replaced it, against the position in the snapshot.
+#[derive(Default)]
+pub struct RollbackError {
+    samples: VecDeque<(f64, f32)>,
+    /// Tick of the last snapshot sampled.
+    last_tick: Option<u32>,
+}
+
+impl RollbackError {
+    fn record(&mut self, now: f64, error: f32) {
+        self.samples.push_back((now, error));
+        while let Some((time, _)) = self.samples.front() {
+            if now - time > ROLLBACK_ERROR_WINDOW_SECONDS {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The last sample and when it was taken.
+    pub fn latest(&self) -> Option<(f64, f32)> {
+        self.samples.back().copied()
+    }
+
+    pub fn count_above(&self, threshold: f32) -> usize {
+        self.samples
+            .iter()
+            .filter(|(_, error)| *error > threshold)
+            .count()
+    }
+
+    pub fn p95(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut errors = self
+            .samples
+            .iter()
+            .map(|(_, error)| *error)
+            .collect::<Vec<f32>>();
+        errors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let index = ((errors.len() - 1) as f32 * 0.95).round() as usize;
+        Some(errors[index])
+    }
+}
+
+pub fn setup_debug_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(DebugOverlayText);
+}
+
+pub fn toggle_debug_overlay(input: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if input.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+pub fn measure_rollback_error(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut rollback_error: ResMut<RollbackError>,
+) {
+    let display_state = match &displayed_game.state {
+        Some(display_state) => display_state,
+        None => return,
+    };
+    let player_id = match displayed_game.local_player {
+        Some(player_id) => player_id,
+        None => return,
+    };
+    // snapshots of ticks the client didn't predict, right after joining or a new round, have
+    // nothing to compare with
+    if let (Some((tick, predicted)), Some(corrected)) = (
+        display_state.predicted_positions,
+        display_state.snapshot_positions,
+    ) {
+        if rollback_error.last_tick != Some(tick) {
+            rollback_error.last_tick = Some(tick);
+            let index = player_id.as_usize();
+            let error = (corrected[index] - predicted[index]).norm();
+            rollback_error.record(time.seconds_since_startup(), error);
+        }
+    }
+}
+
+pub fn update_debug_overlay(
+    overlay: Res<DebugOverlay>,
+    rollback_error: Res<RollbackError>,
+    net_stats: Option<Res<NetStats>>,
+    mut query: Query<(&mut Text, &mut Visible), With<DebugOverlayText>>,
+) {
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = overlay.visible;
+        if overlay.visible {
+            let mut value = match rollback_error.p95() {
+                Some(error) => format!(
+                    "rollback error p95: {:.1} px ({} s)",
+                    error, ROLLBACK_ERROR_WINDOW_SECONDS
+                ),
+                None => "rollback error p95: -".to_string(),
+            };
+            if let Some(net_stats) = &net_stats {
+                value.push_str(&format_net_stats(net_stats));
+            }
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn format_net_stats(net_stats: &NetStats) -> String {
+    let seconds = |value: Option<f64>| {
+        value.map_or("-".to_string(), |value| format!("{:.0} ms", value * 1000.0))
+    };
+    let percent = |value: Option<f32>| {
+        value.map_or("-".to_string(), |value| format!("{:.1}%", value * 100.0))
+    };
+    format!(
+        "\nrtt: {}\nloss: up {} down {}\nbandwidth: up {:.1} kB/s down {:.1} kB/s\nsnapshot age: {}\ncorrections: {} ({} s)",
+        seconds(net_stats.rtt),
+        percent(net_stats.upload_loss),
+        percent(net_stats.download_loss),
+        net_stats.bytes_sent_per_second / 1000.0,
+        net_stats.bytes_received_per_second / 1000.0,
+        seconds(net_stats.snapshot_age),
+        net_stats.corrections,
+        ROLLBACK_ERROR_WINDOW_SECONDS
+    )
+}
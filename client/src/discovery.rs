@@ -0,0 +1,74 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    discovery::{Beacon, BEACON_TIMEOUT_SECONDS, DISCOVERY_PORT},
+};
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+};
+
+// Listens for the beacons servers broadcast on the LAN
+
+pub struct DiscoveredServer {
+    pub address: SocketAddr,
+    pub beacon: Beacon,
+}
+
+/// Servers heard from lately, in the order they were first heard.
+#[derive(Default)]
+pub struct DiscoveredServers(pub Vec<DiscoveredServer>);
+
+/// Socket bound to the discovery port, missing when another client on this machine took it.
+pub struct DiscoverySocket(Option<UdpSocket>);
+
+pub fn setup_discovery(mut commands: Commands) {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)).and_then(|socket| {
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    });
+    if let Err(error) = &socket {
+        warn!("Can't listen for LAN servers: {}", error);
+    }
+    commands.insert_resource(DiscoverySocket(socket.ok()));
+}
+
+/// Only touches the server list when a server shows up, changes or goes quiet, since the
+/// connect screen rebuilds its list on every change.
+pub fn discover_servers(
+    time: Res<Time>,
+    socket: Res<DiscoverySocket>,
+    mut last_heard: Local<HashMap<SocketAddr, f64>>,
+    mut servers: ResMut<DiscoveredServers>,
+) {
+    let now = time.seconds_since_startup();
+
+    if let Some(socket) = &socket.0 {
+        let mut buffer = [0; 512];
+        while let Ok((len, source)) = socket.recv_from(&mut buffer) {
+            let beacon = match Beacon::decode(&buffer[..len]) {
+                Some(beacon) => beacon,
+                None => continue,
+            };
+            let address = SocketAddr::new(source.ip(), beacon.port);
+            last_heard.insert(address, now);
+            match servers
+                .0
+                .iter()
+                .position(|server| server.address == address)
+            {
+                Some(index) if servers.0[index].beacon == beacon => (),
+                Some(index) => servers.0[index].beacon = beacon,
+                None => servers.0.push(DiscoveredServer { address, beacon }),
+            }
+        }
+    }
+
+    let quiet = |address: &SocketAddr| {
+        last_heard
+            .get(address)
+            .map_or(true, |heard| now - heard > BEACON_TIMEOUT_SECONDS)
+    };
+    if servers.0.iter().any(|server| quiet(&server.address)) {
+        servers.0.retain(|server| !quiet(&server.address));
+    }
+}
@@ -0,0 +1,62 @@
+use platformer_shared::{
+    bevy::{prelude::*, window::WindowMode},
+    settings::{DisplayMode, DisplaySettings},
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// The native window: windowed or fullscreen, vsync and a frame cap, set from the settings file
+// and changed from the settings menu or with F11 while the game runs.
+
+/// Frame caps the settings menu goes through, after none.
+pub const FRAME_CAPS: [u32; 4] = [30, 60, 120, 144];
+
+pub fn window_mode(mode: DisplayMode) -> WindowMode {
+    match mode {
+        DisplayMode::Windowed => WindowMode::Windowed,
+        DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+        DisplayMode::Fullscreen => WindowMode::Fullscreen { use_size: false },
+    }
+}
+
+/// Switches between the window and borderless fullscreen, or back from exclusive fullscreen.
+pub fn toggle_fullscreen(input: Res<Input<KeyCode>>, mut display: ResMut<DisplaySettings>) {
+    if input.just_pressed(KeyCode::F11) {
+        display.mode = match display.mode {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless | DisplayMode::Fullscreen => DisplayMode::Windowed,
+        };
+    }
+}
+
+/// The window is created with the settings, so only their changes are applied.
+pub fn apply_display_settings(display: Res<DisplaySettings>, mut windows: ResMut<Windows>) {
+    if !display.is_changed() || display.is_added() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = window_mode(display.mode);
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+        if window.vsync() != display.vsync {
+            window.set_vsync(display.vsync);
+        }
+    }
+}
+
+/// Sleeps away what's left of the frame's share of a second, at the end of the frame.
+pub fn limit_frame_rate(
+    display: Res<DisplaySettings>,
+    mut frame_started_at: Local<Option<Instant>>,
+) {
+    if let (Some(frame_cap), Some(started_at)) = (display.frame_cap, *frame_started_at) {
+        let frame_time = Duration::from_secs_f64(1.0 / frame_cap.max(1) as f64);
+        if let Some(left) = frame_time.checked_sub(started_at.elapsed()) {
+            thread::sleep(left);
+        }
+    }
+    *frame_started_at = Some(Instant::now());
+}
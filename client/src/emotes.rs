@@ -0,0 +1,144 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{Emote, GameCommand, PlayerId},
+};
+
+use crate::{session::Seat, settings_menu::SettingsMenu, update_transform, DisplayedGame, UI_FONT};
+
+// Emotes sent with the number keys 1 to 4 and shown in a speech bubble above the sender on both
+// clients. The world drops the ones sent too soon after the last one.
+
+/// Above the name tags.
+const EMOTE_BUBBLE_Z: f32 = 9.5;
+const EMOTE_OFFSET: f32 = 40.0;
+const BUBBLE_WIDTH: f32 = 48.0;
+const BUBBLE_HEIGHT: f32 = 24.0;
+
+/// Keys of `Emote::ALL`, in its order.
+const EMOTE_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+
+fn label(emote: Emote) -> &'static str {
+    match emote {
+        Emote::Wave => "o/",
+        Emote::Laugh => "haha",
+        Emote::Angry => "grr",
+        Emote::GoodGame => "GG",
+    }
+}
+
+pub struct EmoteBubble(PlayerId);
+
+pub struct EmoteText(PlayerId);
+
+pub fn setup_emote_bubbles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(Color::WHITE.into());
+    for player_id in [PlayerId::Player1, PlayerId::Player2].iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::new(BUBBLE_WIDTH, BUBBLE_HEIGHT)),
+                transform: Transform::from_xyz(0.0, 0.0, EMOTE_BUBBLE_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(EmoteBubble(*player_id));
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(UI_FONT),
+                        font_size: 16.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, EMOTE_BUBBLE_Z + 0.1),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(EmoteText(*player_id));
+    }
+}
+
+pub fn send_emotes(
+    input: Res<Input<KeyCode>>,
+    settings_menu: Res<SettingsMenu>,
+    seat: Res<Seat>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    let player_id = match seat.player_id {
+        Some(player_id) if !settings_menu.open => player_id,
+        _ => return,
+    };
+    for (key, emote) in EMOTE_KEYS.iter().zip(Emote::ALL.iter()) {
+        if input.just_pressed(*key) {
+            game_commands.send(GameCommand::Emote(player_id, *emote));
+        }
+    }
+}
+
+pub fn emote_bubble_view(
+    displayed_game: Res<DisplayedGame>,
+    mut q: QuerySet<(
+        Query<(&EmoteBubble, &mut Transform, &mut Visible)>,
+        Query<(&EmoteText, &mut Text, &mut Transform, &mut Visible)>,
+    )>,
+) {
+    // where each player's bubble goes, if they're emoting
+    let bubble = |player_id: PlayerId| {
+        let display_state = displayed_game.state.as_ref()?;
+        let (position, character, emote) = match player_id {
+            PlayerId::Player1 => (
+                display_state.player1_position,
+                display_state.player1_character,
+                display_state.player1_emote,
+            ),
+            PlayerId::Player2 => (
+                display_state.player2_position,
+                display_state.player2_character,
+                display_state.player2_emote,
+            ),
+        };
+        let position = position.translation.vector;
+        emote.map(|emote| (position, character.stats().size, emote))
+    };
+
+    for (emote_bubble, mut transform, mut visible) in q.q0_mut().iter_mut() {
+        visible.is_visible = match bubble(emote_bubble.0) {
+            Some((position, size, _)) => {
+                update_transform(&mut transform, position.x, position.y, &displayed_game);
+                // above the name tag on screen, whichever way the world is flipped
+                transform.translation.y += size / 2.0 + EMOTE_OFFSET;
+                true
+            }
+            None => false,
+        };
+    }
+    for (emote_text, mut text, mut transform, mut visible) in q.q1_mut().iter_mut() {
+        visible.is_visible = match bubble(emote_text.0) {
+            Some((position, size, emote)) => {
+                if text.sections[0].value != label(emote) {
+                    text.sections[0].value = label(emote).to_string();
+                }
+                update_transform(&mut transform, position.x, position.y, &displayed_game);
+                transform.translation.y += size / 2.0 + EMOTE_OFFSET;
+                true
+            }
+            None => false,
+        };
+    }
+}
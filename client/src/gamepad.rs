@@ -0,0 +1,79 @@
+use platformer_shared::{
+    bevy::{input::gamepad::GamepadEventType, prelude::*},
+    game::PlayerInput,
+    settings::ControllerSettings,
+};
+
+// Controllers play alongside the keyboard: the left stick or the d-pad moves, grapples (up) and
+// fires spread-shots (down), South jumps and fires, the right bumper lobs a mortar, the left one
+// fires a ricochet shot and Start readies up in the lobby. Every connected controller is read, so one plugged in mid-match
+// takes over right away.
+
+/// Controllers connected, as they come and go.
+#[derive(Default)]
+pub struct Controllers(Vec<Gamepad>);
+
+/// What the controllers hold this frame, merged with the keyboard by `player_input`.
+#[derive(Default)]
+pub struct ControllerInput {
+    pub input: PlayerInput,
+    /// Start, which readies up like Enter.
+    pub ready: bool,
+}
+
+pub fn track_controllers(
+    mut events: EventReader<GamepadEvent>,
+    mut controllers: ResMut<Controllers>,
+) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                info!("Controller {} connected", gamepad.0 + 1);
+                controllers.0.push(*gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                info!("Controller {} disconnected", gamepad.0 + 1);
+                controllers.0.retain(|connected| connected != gamepad);
+            }
+            _ => (),
+        }
+    }
+}
+
+pub fn read_controllers(
+    settings: Res<ControllerSettings>,
+    controllers: Res<Controllers>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut controller_input: ResMut<ControllerInput>,
+) {
+    let mut input = PlayerInput::default();
+    let mut ready = false;
+    if settings.enabled {
+        for gamepad in controllers.0.iter().copied() {
+            let held = |button| buttons.pressed(GamepadButton(gamepad, button));
+            let tapped = |button| buttons.just_pressed(GamepadButton(gamepad, button));
+            // inside the dead zone the stick counts as centered
+            let stick = |axis| {
+                let value = axes.get(GamepadAxis(gamepad, axis)).unwrap_or(0.0);
+                if value.abs() < settings.dead_zone {
+                    0.0
+                } else {
+                    value
+                }
+            };
+            let x = stick(GamepadAxisType::LeftStickX);
+            let y = stick(GamepadAxisType::LeftStickY);
+
+            input.left |= x < 0.0 || held(GamepadButtonType::DPadLeft);
+            input.right |= x > 0.0 || held(GamepadButtonType::DPadRight);
+            input.grapple |= y > 0.0 || held(GamepadButtonType::DPadUp);
+            input.ability |= y < 0.0 || held(GamepadButtonType::DPadDown);
+            input.action |= tapped(GamepadButtonType::South);
+            input.mortar |= tapped(GamepadButtonType::RightTrigger);
+            input.ricochet |= tapped(GamepadButtonType::LeftTrigger);
+            ready |= tapped(GamepadButtonType::Start);
+        }
+    }
+    *controller_input = ControllerInput { input, ready };
+}
@@ -0,0 +1,111 @@
+use platformer_shared::{bevy::prelude::*, settings::GraphicsSettings};
+
+use crate::{animation::PlayerAnimation, DisplayedGame, GameContext, Projectile};
+
+// Accessibility mode turned on in the settings menu: the level's background is dimmed, players
+// and projectiles get thick outlines behind their sprites, and the cannon's advantage outline
+// grows. All of it is drawn over whatever textures are in use, and hidden again when it's off.
+
+/// Over the background layer, under the terrain.
+const DIMMER_Z: f32 = 0.5;
+const DIMMER_ALPHA: f32 = 0.7;
+const OUTLINE_WIDTH: f32 = 4.0;
+/// Behind the outlined sprite, relative to it.
+const OUTLINE_Z: f32 = -0.1;
+/// Margin of the cannon's advantage outline around the cannon, normally and in high contrast.
+const CANNON_MARKER_MARGIN: f32 = 6.0;
+const HIGH_CONTRAST_CANNON_MARKER_MARGIN: f32 = 14.0;
+
+pub struct ContrastDimmer;
+
+/// Sprite behind its parent's, a little bigger all around.
+pub struct Outline;
+
+pub fn setup_high_contrast(
+    mut commands: Commands,
+    displayed_game: Res<DisplayedGame>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let map = &displayed_game.map;
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, DIMMER_ALPHA).into()),
+            sprite: Sprite::new(Vec2::new(map.width, map.height)),
+            transform: Transform::from_xyz(map.center().x, map.center().y, DIMMER_Z),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(ContrastDimmer);
+}
+
+pub fn add_outlines(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<Entity, Added<PlayerAnimation>>,
+    projectiles: Query<Entity, Added<Projectile>>,
+) {
+    let outlined = players
+        .iter()
+        .map(|entity| (entity, Color::WHITE))
+        .chain(projectiles.iter().map(|entity| (entity, Color::YELLOW)));
+    for (entity, color) in outlined {
+        let outline = commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                transform: Transform::from_xyz(0.0, 0.0, OUTLINE_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(Outline)
+            .id();
+        commands.entity(entity).push_children(&[outline]);
+    }
+}
+
+pub fn high_contrast_view(
+    graphics: Res<GraphicsSettings>,
+    game_context: Res<GameContext>,
+    mut dimmers: Query<&mut Visible, (With<ContrastDimmer>, Without<Outline>)>,
+    mut outlines: Query<(&Parent, &mut Sprite, &mut Visible), With<Outline>>,
+    mut sprites: Query<&mut Sprite, Without<Outline>>,
+) {
+    let on = graphics.high_contrast;
+    for mut visible in dimmers.iter_mut() {
+        if visible.is_visible != on {
+            visible.is_visible = on;
+        }
+    }
+    // the outlines follow their sprites, which change size with the character
+    for (parent, mut sprite, mut visible) in outlines.iter_mut() {
+        if visible.is_visible != on {
+            visible.is_visible = on;
+        }
+        if let Ok(outlined) = sprites.get_mut(parent.0) {
+            let size = outlined.size + Vec2::new(OUTLINE_WIDTH, OUTLINE_WIDTH) * 2.0;
+            if sprite.size != size {
+                sprite.size = size;
+            }
+        }
+    }
+
+    if graphics.is_changed() {
+        let cannon_size = match sprites.get_mut(game_context.cannon) {
+            Ok(cannon) => cannon.size,
+            Err(_) => return,
+        };
+        let margin = if on {
+            HIGH_CONTRAST_CANNON_MARKER_MARGIN
+        } else {
+            CANNON_MARKER_MARGIN
+        };
+        if let Ok(mut marker) = sprites.get_mut(game_context.cannon_marker) {
+            marker.size = cannon_size + Vec2::new(margin, margin) * 2.0;
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::crystalorb::{fixed_timestepper::Stepper, world::World},
+    game::{GameCommand, GameWorld, PlayerId, PlayerInput},
+    TIMESTEP,
+};
+
+use crate::{settings_menu::SettingsMenu, DisplayedGame, UI_FONT};
+
+// Two players on one keyboard, playing a local `GameWorld` without a server. Neither of them is
+// the local player, so the view stays the right way up for both, and the second player's left and
+// right are swapped back to match the screen.
+
+/// Keys of a player: left, right, jump and fire, grapple, spread-shot, mortar and ricochet.
+struct HotseatKeys {
+    left: KeyCode,
+    right: KeyCode,
+    action: KeyCode,
+    grapple: KeyCode,
+    ability: KeyCode,
+    mortar: KeyCode,
+    ricochet: KeyCode,
+}
+
+const PLAYER1_KEYS: HotseatKeys = HotseatKeys {
+    left: KeyCode::A,
+    right: KeyCode::D,
+    action: KeyCode::W,
+    grapple: KeyCode::Q,
+    ability: KeyCode::S,
+    mortar: KeyCode::E,
+    ricochet: KeyCode::R,
+};
+
+const PLAYER2_KEYS: HotseatKeys = HotseatKeys {
+    left: KeyCode::Left,
+    right: KeyCode::Right,
+    action: KeyCode::Up,
+    grapple: KeyCode::RControl,
+    ability: KeyCode::Down,
+    mortar: KeyCode::RShift,
+    ricochet: KeyCode::RAlt,
+};
+
+const CONTROLS_PROMPT: &str =
+    "Blue: A/D move, W jump and fire, Q grapple, S spread-shot, E mortar, R ricochet\n\
+     Red: arrows move, Up jump and fire, Right Ctrl grapple, Down spread-shot, Right Shift mortar, \
+     Right Alt ricochet";
+const REMATCH_PROMPT: &str = "Press Enter for a rematch";
+
+pub struct Hotseat {
+    world: GameWorld,
+    accumulator: f64,
+    inputs: [PlayerInput; 2],
+}
+
+pub struct HotseatPromptText;
+
+pub fn setup_hotseat(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Hotseat {
+        world: GameWorld::new(),
+        accumulator: 0.0,
+        inputs: Default::default(),
+    });
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(HotseatPromptText);
+}
+
+fn read_keys(input: &Input<KeyCode>, keys: &HotseatKeys, swapped: bool) -> PlayerInput {
+    let (left, right) = (input.pressed(keys.left), input.pressed(keys.right));
+    let (left, right) = if swapped {
+        (right, left)
+    } else {
+        (left, right)
+    };
+    PlayerInput {
+        action: input.just_pressed(keys.action),
+        left,
+        right,
+        grapple: input.pressed(keys.grapple),
+        ability: input.pressed(keys.ability),
+        mortar: input.just_pressed(keys.mortar),
+        ricochet: input.just_pressed(keys.ricochet),
+    }
+}
+
+pub fn hotseat_input(
+    input: Res<Input<KeyCode>>,
+    settings_menu: Res<SettingsMenu>,
+    hotseat: Res<Hotseat>,
+    displayed_game: Res<DisplayedGame>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    for (player_id, keys, swapped) in [
+        (PlayerId::Player1, &PLAYER1_KEYS, false),
+        (PlayerId::Player2, &PLAYER2_KEYS, true),
+    ]
+    .iter()
+    {
+        // both players let go of everything while in the settings menu
+        let player_input = if settings_menu.open {
+            PlayerInput::default()
+        } else {
+            read_keys(&input, keys, *swapped)
+        };
+        if player_input != hotseat.inputs[player_id.as_usize()] {
+            game_commands.send(GameCommand::Input(*player_id, player_input.into()));
+        }
+    }
+
+    let over = displayed_game
+        .state
+        .as_ref()
+        .map_or(false, |display_state| display_state.match_result.is_some());
+    if over && input.just_pressed(KeyCode::Return) {
+        game_commands.send(GameCommand::Rematch(PlayerId::Player1));
+        game_commands.send(GameCommand::Rematch(PlayerId::Player2));
+    }
+}
+
+pub fn step_hotseat(
+    time: Res<Time>,
+    mut game_commands: EventReader<GameCommand>,
+    mut hotseat: ResMut<Hotseat>,
+    mut displayed_game: ResMut<DisplayedGame>,
+) {
+    for command in game_commands.iter() {
+        if let GameCommand::Input(player_id, flags) = command {
+            hotseat.inputs[player_id.as_usize()] = (*flags).into();
+        }
+        hotseat.world.apply_command(command);
+    }
+
+    hotseat.accumulator += time.delta_seconds_f64();
+    while hotseat.accumulator >= TIMESTEP {
+        hotseat.accumulator -= TIMESTEP;
+        hotseat.world.step();
+    }
+
+    displayed_game.show(hotseat.world.display_state(), time.seconds_since_startup());
+}
+
+pub fn update_hotseat_prompt(
+    displayed_game: Res<DisplayedGame>,
+    mut query: Query<&mut Text, With<HotseatPromptText>>,
+) {
+    let over = displayed_game
+        .state
+        .as_ref()
+        .map_or(false, |display_state| display_state.match_result.is_some());
+    let prompt = if over {
+        REMATCH_PROMPT
+    } else {
+        CONTROLS_PROMPT
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != prompt {
+            text.sections[0].value = prompt.to_string();
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{AdvantageState, GameDisplayState, MatchResult, PlayerId, RoundResult},
+    TIMESTEP,
+};
+
+use crate::{names::PlayerNames, session::Seat, DisplayedGame, UI_FONT};
+
+// Match status along the top edge of the screen: score, targets, round timer and who holds the
+// advantage, plus banners announcing kill streaks, advantage changes, round results and the
+// start of every round
+
+const STREAK_BANNER_SECONDS: f64 = 2.0;
+const ADVANTAGE_BANNER_SECONDS: f64 = 1.5;
+const ROUND_BANNER_SECONDS: f64 = 1.5;
+
+pub struct HudText;
+
+pub struct StreakBannerText;
+
+pub struct AdvantageBannerText;
+
+pub struct RoundResultText;
+
+pub struct RoundBannerText;
+
+#[derive(Default)]
+pub struct StreakBannerState {
+    streaks: [u8; 2],
+    hide_at: f64,
+}
+
+fn format_hud(
+    display_state: &GameDisplayState,
+    local_player: Option<PlayerId>,
+    names: &PlayerNames,
+    spectating: bool,
+) -> String {
+    let settings = &display_state.match_config.match_settings;
+    let [player1_score, player2_score] = display_state.scores;
+
+    if display_state.lobby {
+        return "lobby - warm up with practice shots on the mortar key, press Enter when ready"
+            .to_string();
+    }
+    if let Some((away, ticks_left)) = display_state.waiting_for {
+        let away_names: Vec<_> = [PlayerId::Player1, PlayerId::Player2]
+            .iter()
+            .filter(|player_id| away[player_id.as_usize()])
+            .map(|player_id| names.get(*player_id))
+            .collect();
+        let seconds = (ticks_left as f64 * TIMESTEP).ceil() as u32;
+        return format!(
+            "paused - waiting for {} to reconnect, forfeit in {}:{:02}",
+            away_names.join(" and "),
+            seconds / 60,
+            seconds % 60
+        );
+    }
+
+    let mut parts = vec![format!(
+        "{} {} - {} {}",
+        names.get(PlayerId::Player1),
+        player1_score,
+        player2_score,
+        names.get(PlayerId::Player2)
+    )];
+    if let Some(score_target) = settings.score_target {
+        parts.push(format!("first to {}", score_target));
+    }
+    parts.push(match settings.round_limit {
+        Some(round_limit) => format!("round {}/{}", display_state.round, round_limit),
+        None => format!("round {}", display_state.round),
+    });
+    if let Some(ticks_left) = display_state.round_ticks_left {
+        let seconds = (ticks_left as f64 * TIMESTEP).ceil() as u32;
+        parts.push(format!("{}:{:02}", seconds / 60, seconds % 60));
+    }
+    parts.push(match display_state.advantage_state {
+        AdvantageState::Neutral => "advantage: nobody".to_string(),
+        AdvantageState::Player1 => format!("advantage: {}", names.get(PlayerId::Player1)),
+        AdvantageState::Player2 => format!("advantage: {}", names.get(PlayerId::Player2)),
+    });
+    let advantaged = matches!(
+        (display_state.advantage_state, local_player),
+        (AdvantageState::Player1, Some(PlayerId::Player1))
+            | (AdvantageState::Player2, Some(PlayerId::Player2))
+    );
+    if advantaged {
+        parts.push(match display_state.spread_shot_cooldown_ticks {
+            0 => "spread-shot ready".to_string(),
+            ticks => format!("spread-shot in {:.1}s", ticks as f64 * TIMESTEP),
+        });
+    }
+    match display_state.match_result {
+        Some(MatchResult::Winner(player_id)) => {
+            parts.push(format!("{} wins the match!", names.get(player_id)))
+        }
+        Some(MatchResult::Draw) => parts.push("the match is a draw".to_string()),
+        None => (),
+    }
+    if spectating {
+        parts.push("spectating".to_string());
+    }
+
+    parts.join("   ")
+}
+
+pub fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(HudText);
+        });
+}
+
+pub fn setup_streak_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(30.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 64.0,
+                            color: Color::GOLD,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(StreakBannerText);
+        });
+}
+
+pub fn update_streak_banner(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    mut state: Local<StreakBannerState>,
+    mut query: Query<(&mut Text, &mut Visible), With<StreakBannerText>>,
+) {
+    let now = time.seconds_since_startup();
+    if let Some(display_state) = &displayed_game.state {
+        let previous_streaks = state.streaks;
+        for (player_id, (streak, previous)) in [PlayerId::Player1, PlayerId::Player2]
+            .iter()
+            .zip(display_state.streaks.iter().zip(previous_streaks.iter()))
+        {
+            if *streak >= 2 && streak > previous {
+                for (mut text, _) in query.iter_mut() {
+                    text.sections[0].value = format!("{} x{}", names.get(*player_id), streak);
+                }
+                state.hide_at = now + STREAK_BANNER_SECONDS;
+            }
+        }
+        state.streaks = display_state.streaks;
+    }
+
+    for (_, mut visible) in query.iter_mut() {
+        visible.is_visible = now < state.hide_at;
+    }
+}
+
+#[derive(Default)]
+pub struct AdvantageBannerState {
+    advantage_state: Option<AdvantageState>,
+    hide_at: f64,
+}
+
+pub fn setup_advantage_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(20.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(AdvantageBannerText);
+        });
+}
+
+/// Announces the player taking the advantage, in their color.
+pub fn update_advantage_banner(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    mut state: Local<AdvantageBannerState>,
+    mut query: Query<(&mut Text, &mut Visible), With<AdvantageBannerText>>,
+) {
+    let now = time.seconds_since_startup();
+    if let Some(display_state) = &displayed_game.state {
+        let advantage_state = display_state.advantage_state;
+        let taken_by = match advantage_state {
+            AdvantageState::Neutral => None,
+            AdvantageState::Player1 => Some((PlayerId::Player1, Color::BLUE)),
+            AdvantageState::Player2 => Some((PlayerId::Player2, Color::RED)),
+        };
+        // joining a match in progress isn't news
+        let changed = state
+            .advantage_state
+            .map_or(false, |previous| previous != advantage_state);
+        if let Some((player_id, color)) = taken_by.filter(|_| changed) {
+            for (mut text, _) in query.iter_mut() {
+                text.sections[0].value = if displayed_game.local_player == Some(player_id) {
+                    "You have the advantage".to_string()
+                } else {
+                    format!("{} has the advantage", names.get(player_id))
+                };
+                text.sections[0].style.color = color;
+            }
+            state.hide_at = now + ADVANTAGE_BANNER_SECONDS;
+        }
+        state.advantage_state = Some(advantage_state);
+    }
+
+    for (_, mut visible) in query.iter_mut() {
+        visible.is_visible = now < state.hide_at;
+    }
+}
+
+pub fn setup_round_result(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(45.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 48.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(RoundResultText);
+        });
+}
+
+/// Shown while the game is frozen between rounds.
+pub fn update_round_result(
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    mut query: Query<(&mut Text, &mut Visible), With<RoundResultText>>,
+) {
+    let round_result = displayed_game
+        .state
+        .as_ref()
+        .and_then(|display_state| display_state.round_result);
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = round_result.is_some();
+        if let Some(round_result) = round_result {
+            text.sections[0].value = match round_result {
+                RoundResult::Winner(player_id) => {
+                    format!("{} takes the round", names.get(player_id))
+                }
+                RoundResult::Draw => "The round is a draw".to_string(),
+            };
+        }
+    }
+}
+
+pub fn update_hud(
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    // only there when playing online
+    seat: Option<Res<Seat>>,
+    mut query: Query<&mut Text, With<HudText>>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        let spectating = seat.map_or(false, |seat| seat.spectating);
+        let value = format_hud(
+            display_state,
+            displayed_game.local_player,
+            &names,
+            spectating,
+        );
+        for mut text in query.iter_mut() {
+            if text.sections[0].value != value {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RoundBannerState {
+    /// Last round announced, 0 while waiting for the first one.
+    announced_round: Option<u8>,
+    hide_at: f64,
+}
+
+pub fn setup_round_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(45.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 56.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(RoundBannerText);
+        });
+}
+
+/// Announces every round as the players are put back on their spawn points.
+pub fn update_round_banner(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut state: Local<RoundBannerState>,
+    mut query: Query<(&mut Text, &mut Visible), With<RoundBannerText>>,
+) {
+    let now = time.seconds_since_startup();
+    if let Some(display_state) = &displayed_game.state {
+        if display_state.lobby || display_state.match_result.is_some() {
+            state.announced_round = Some(0);
+        } else if display_state.round_result.is_none() {
+            let round = display_state.round;
+            // joining a match in progress isn't news
+            if state
+                .announced_round
+                .map_or(false, |announced| announced != round)
+            {
+                for (mut text, _) in query.iter_mut() {
+                    text.sections[0].value =
+                        if display_state.match_config.match_settings.round_limit == Some(round) {
+                            "Final round".to_string()
+                        } else {
+                            format!("Round {}", round)
+                        };
+                }
+                state.hide_at = now + ROUND_BANNER_SECONDS;
+            }
+            state.announced_round = Some(round);
+        }
+    }
+
+    for (_, mut visible) in query.iter_mut() {
+        visible.is_visible = now < state.hide_at;
+    }
+}
@@ -0,0 +1,115 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{GameCommand, PlayerInput},
+};
+use std::collections::VecDeque;
+
+use crate::{DisplayedGame, UI_FONT};
+
+// Training widget listing the local player's recent inputs, toggled with F4.
+// Built from the issued commands, so it shows exactly what the simulation received.
+
+const INPUT_HISTORY_LENGTH: usize = 16;
+
+#[derive(Default)]
+pub struct InputDisplay {
+    pub visible: bool,
+    current: PlayerInput,
+    /// Distinct input states with the number of frames each was held, newest first.
+    history: VecDeque<(PlayerInput, u32)>,
+}
+
+pub struct InputDisplayText;
+
+fn format_input(input: &PlayerInput) -> String {
+    [
+        (input.left, '←'),
+        (input.right, '→'),
+        (input.action, '●'),
+        (input.grapple, '↑'),
+        (input.ability, '↓'),
+        (input.mortar, '○'),
+        (input.ricochet, '◇'),
+    ]
+    .iter()
+    .map(|(pressed, icon)| if *pressed { *icon } else { '·' })
+    .collect()
+}
+
+pub fn setup_input_display(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(InputDisplayText);
+}
+
+pub fn toggle_input_display(input: Res<Input<KeyCode>>, mut display: ResMut<InputDisplay>) {
+    if input.just_pressed(KeyCode::F4) {
+        display.visible = !display.visible;
+    }
+}
+
+pub fn record_inputs(
+    displayed_game: Res<DisplayedGame>,
+    mut game_commands: EventReader<GameCommand>,
+    mut display: ResMut<InputDisplay>,
+) {
+    for command in game_commands.iter() {
+        if let GameCommand::Input(player_id, flags) = command {
+            if Some(*player_id) == displayed_game.local_player {
+                display.current = (*flags).into();
+            }
+        }
+    }
+
+    let current = display.current;
+    match display.history.front_mut() {
+        Some((input, frames)) if *input == current => *frames += 1,
+        _ => {
+            display.history.push_front((current, 1));
+            display.history.truncate(INPUT_HISTORY_LENGTH);
+        }
+    }
+}
+
+pub fn update_input_display(
+    display: Res<InputDisplay>,
+    mut query: Query<(&mut Text, &mut Visible), With<InputDisplayText>>,
+) {
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = display.visible;
+        if display.visible {
+            let mut lines = vec![format!("now {}", format_input(&display.current))];
+            lines.extend(
+                display
+                    .history
+                    .iter()
+                    .map(|(input, frames)| format!("{:>3} {}", frames, format_input(input))),
+            );
+            text.sections[0].value = lines.join("\n");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{GameDisplayState, GameEvent, PlayerId, CORPSE_TICKS, POST_ROUND_TICKS},
+    TIMESTEP,
+};
+use std::collections::VecDeque;
+
+use crate::{DisplayedGame, UI_FONT};
+
+// Replay of the moments before a death, played at half speed from the victim's side of the map
+// while the game stands still between rounds, to show what hit them. It goes as far back as the
+// break leaves time for, and ends with the break, before the next round is announced.
+//
+// The replayed states keep the live tick, events, score and emotes, so that sounds, toasts and
+// banners neither play the replay's events again nor miss the live ones, and the round's result
+// shows over the replay.
+
+/// Seconds of display states kept, the most a replay can show.
+const KILL_CAM_SECONDS: f64 = 3.0;
+const PLAYBACK_SPEED: f64 = 0.5;
+
+struct Replay {
+    victim: PlayerId,
+    round: u8,
+    /// Oldest first, with when each was shown live.
+    frames: Vec<(f64, GameDisplayState)>,
+    started_at: f64,
+}
+
+#[derive(Default)]
+pub struct KillCam {
+    /// Live states of the last seconds, oldest first, with when each was shown.
+    recent: VecDeque<(f64, GameDisplayState)>,
+    replay: Option<Replay>,
+    watched_until: Option<u32>,
+}
+
+impl KillCam {
+    /// The player whose death is being replayed.
+    pub fn victim(&self) -> Option<PlayerId> {
+        self.replay.as_ref().map(|replay| replay.victim)
+    }
+
+    /// The state to show in place of the live one.
+    pub fn show(&mut self, live: GameDisplayState, now: f64) -> GameDisplayState {
+        self.recent.push_back((now, live.clone()));
+        while let Some((shown_at, _)) = self.recent.front() {
+            if now - shown_at > KILL_CAM_SECONDS {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        // the deaths from before joining or reconnecting are left out
+        let since = match self.watched_until {
+            Some(since) if since <= live.tick => since,
+            _ => live.tick,
+        };
+        self.watched_until = Some(live.tick);
+        let victim = live.events.iter().find_map(|(tick, event)| match event {
+            GameEvent::Died(player_id) if *tick > since => Some(*player_id),
+            _ => None,
+        });
+        if let (Some(victim), None) = (victim, &self.replay) {
+            let break_seconds = (CORPSE_TICKS + POST_ROUND_TICKS) as f64 * TIMESTEP;
+            let window = (break_seconds * PLAYBACK_SPEED).min(KILL_CAM_SECONDS);
+            self.replay = Some(Replay {
+                victim,
+                round: live.round,
+                frames: self
+                    .recent
+                    .iter()
+                    .filter(|(shown_at, _)| now - shown_at <= window)
+                    .cloned()
+                    .collect(),
+                started_at: now,
+            });
+        }
+
+        let replayed = self.replay.as_ref().and_then(|replay| {
+            let first_shown_at = replay.frames.first().map_or(now, |(shown_at, _)| *shown_at);
+            let replayed_at = first_shown_at + (now - replay.started_at) * PLAYBACK_SPEED;
+            let done = replay
+                .frames
+                .last()
+                .map_or(true, |(shown_at, _)| replayed_at > *shown_at);
+            if done || live.round != replay.round || live.lobby {
+                return None;
+            }
+            replay
+                .frames
+                .iter()
+                .rev()
+                .find(|(shown_at, _)| *shown_at <= replayed_at)
+                .map(|(_, frame)| frame.clone())
+        });
+        match replayed {
+            Some(mut frame) => {
+                frame.tick = live.tick;
+                frame.events = live.events;
+                frame.scores = live.scores;
+                frame.streaks = live.streaks;
+                frame.stats = live.stats;
+                frame.match_result = live.match_result;
+                frame.rematch_votes = live.rematch_votes;
+                frame.round_result = live.round_result;
+                frame.player1_emote = live.player1_emote;
+                frame.player2_emote = live.player2_emote;
+                frame
+            }
+            None => {
+                self.replay = None;
+                live
+            }
+        }
+    }
+}
+
+pub struct KillCamText;
+
+pub fn setup_kill_cam_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(60.0),
+                    left: Val::Percent(45.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "Kill cam",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(KillCamText);
+}
+
+pub fn update_kill_cam_label(
+    displayed_game: Res<DisplayedGame>,
+    mut query: Query<&mut Visible, With<KillCamText>>,
+) {
+    let replaying = displayed_game.kill_cam.victim().is_some();
+    for mut visible in query.iter_mut() {
+        if visible.is_visible != replaying {
+            visible.is_visible = replaying;
+        }
+    }
+}
@@ -1,103 +1,320 @@
+// crystalorb's network resource trait has a generic associated type
+#![allow(incomplete_features)]
+#![feature(generic_associated_types)]
+
+mod advantage_tint;
+mod animation;
+mod bracket;
 mod camera;
+#[cfg(all(feature = "clip", not(target_arch = "wasm32")))]
+mod clip;
+mod connect;
+mod connection_status;
+mod death_flash;
+mod debug;
+mod discovery;
+#[cfg(not(target_arch = "wasm32"))]
+mod display;
+mod emotes;
+mod gamepad;
+mod high_contrast;
+mod hotseat;
+mod hud;
+mod input_display;
+mod kill_cam;
+mod match_over;
+mod menu;
+mod motd;
+#[cfg(not(target_arch = "wasm32"))]
+mod music;
+mod names;
+mod net_stats;
+mod netgraph;
+mod network;
+mod pad_arrow;
+mod perf_overlay;
+#[cfg(not(target_arch = "wasm32"))]
+mod raster;
+mod reconnect;
+#[cfg(not(target_arch = "wasm32"))]
+mod screenshot;
+mod server_ghost;
+mod session;
+mod settings_menu;
+mod shake;
+// bevy 0.5 has no audio in browsers
+#[cfg(not(target_arch = "wasm32"))]
+mod sound;
+mod telegraph;
+mod textures;
+mod toast;
+mod touch;
+mod tutorial;
+mod ui_scale;
 
 use camera::SimpleOrthoProjection;
 use crystalorb_bevy_networking_turbulence::{
-    bevy_networking_turbulence::{
-        MessageChannelMode, MessageChannelSettings, NetworkResource, ReliableChannelSettings,
-    },
+    bevy_networking_turbulence::{NetworkResource, NetworkingPlugin},
     crystalorb::client::{stage::Stage as ClientStage, stage::StageMut as ClientStageMut, Client},
-    CommandChannelSettings, CrystalOrbClientPlugin, WrappedNetworkResource,
 };
 use platformer_shared::{
     bevy,
     bevy::{
+        diagnostic::FrameTimeDiagnosticsPlugin,
         prelude::*,
         render::camera::{Camera, VisibleEntities},
         utils::HashSet,
     },
-    crystalorb_bevy_networking_turbulence::{self, bevy_networking_turbulence, crystalorb},
-    game::{GameCommand, GameWorld, PlayerCommand, PlayerId, PlayerInput, PowerPadStatus},
-    Position, BOTTOM_POWER_PAD_POSITIONS, BOTTOM_START_POSITION, LAVA_RECTS, PLATFORMS,
-    POWER_PAD_SIZE, PROJECTILE_SIZE, STARTING_BOTTOM_POWER_PAD_POSITION,
-    STARTING_TOP_POWER_PAD_POSITION, TOP_POWER_PAD_POSITIONS, TOP_START_POSITION,
+    conditions::NetworkConditions,
+    crystalorb_bevy_networking_turbulence,
+    game::{
+        AdvantageState, Character, GameCommand, GameDisplayState, GameWorld, PlayerId, PlayerInput,
+        SHOCKWAVE_RADIUS,
+    },
+    lobby, net,
+    settings::{CrystalOrbSettings, KeyBinds, Settings, SETTINGS_PATH},
+    LevelElementKind, MapDefinition, Position, RenderLayer, HAZARD_CANNON_SIZE, POWER_PAD_SIZE,
+    PROJECTILE_SIZE,
 };
-use std::{net::SocketAddr, time::Duration};
 
+const UI_FONT: &str = "fonts/DejaVuSansMono.ttf";
+
+/// Label of the systems reading the controllers and the touch screen, which the systems taking
+/// their taps run after.
+const READ_INPUT: &str = "read_input";
+
+// z-order of the sprites between the level layers
+const POWER_PAD_Z: f32 = 3.0;
+const POWER_PAD_PROGRESS_Z: f32 = 4.0;
+const GHOST_Z: f32 = 4.5;
+const CANNON_MARKER_Z: f32 = 4.8;
+const OBJECT_Z: f32 = 5.0;
+const SHIELD_Z: f32 = 5.5;
+
+fn layer_z(layer: RenderLayer) -> f32 {
+    match layer {
+        RenderLayer::Background => 0.0,
+        RenderLayer::Terrain => 1.0,
+        RenderLayer::Hazard => 2.0,
+        RenderLayer::Foreground => 10.0,
+    }
+}
+
+/// The game state rendered by the view systems, fed either by the network client or by a
+/// local simulation.
+#[derive(Default)]
+pub struct DisplayedGame {
+    pub state: Option<GameDisplayState>,
+    pub local_player: Option<PlayerId>,
+    pub map: MapDefinition,
+    pub kill_cam: kill_cam::KillCam,
+}
+
+impl DisplayedGame {
+    /// Shows the next state, or a replay of the last death in its place.
+    pub fn show(&mut self, state: GameDisplayState, now: f64) {
+        self.state = Some(self.kill_cam.show(state, now));
+    }
+
+    /// Whose side the world is drawn from: the local player's, or the victim's in the kill cam.
+    fn perspective(&self) -> Option<PlayerId> {
+        self.kill_cam.victim().or(self.local_player)
+    }
+
+    // world is flipped for player 2
+    pub fn world_flipped(&self) -> bool {
+        self.perspective() == Some(PlayerId::Player2)
+    }
+
+    /// Where a point of the world is drawn from the local player's point of view.
+    pub fn view_position(&self, x: f32, y: f32) -> (f32, f32) {
+        if self.world_flipped() {
+            self.map.mirror(x, y)
+        } else {
+            (x, y)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn player_input(
     mut state: Local<PlayerInput>,
     input: Res<Input<KeyCode>>,
+    keybinds: Res<KeyBinds>,
+    controller: Res<gamepad::ControllerInput>,
+    touch_controls: Res<touch::TouchControls>,
+    settings_menu: Res<settings_menu::SettingsMenu>,
+    displayed_game: Res<DisplayedGame>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    if let Some(player_id) = displayed_game.local_player {
+        // the player lets go of everything while in the settings menu
+        let player_input = &if settings_menu.open {
+            PlayerInput::default()
+        } else {
+            let controller = controller.input;
+            let touch = touch_controls.input;
+            PlayerInput {
+                action: input.just_pressed(keybinds.action) || controller.action || touch.action,
+                left: input.pressed(keybinds.left) || controller.left || touch.left,
+                right: input.pressed(keybinds.right) || controller.right || touch.right,
+                grapple: input.pressed(keybinds.grapple) || controller.grapple,
+                ability: input.pressed(keybinds.ability) || controller.ability,
+                mortar: input.just_pressed(keybinds.mortar) || controller.mortar,
+                ricochet: input.just_pressed(keybinds.ricochet) || controller.ricochet,
+            }
+        };
+
+        if *player_input != *state {
+            game_commands.send(GameCommand::Input(player_id, (*player_input).into()));
+        }
+        *state = *player_input;
+    }
+}
+
+/// Character picked with `--character <name>`.
+struct SelectedCharacter(Character);
+
+// the selection is only accepted before the match gets under way
+fn select_character(
+    mut sent: Local<bool>,
+    selected_character: Res<SelectedCharacter>,
+    displayed_game: Res<DisplayedGame>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    if !*sent {
+        if let Some(player_id) = displayed_game.local_player {
+            game_commands.send(GameCommand::SelectCharacter(
+                player_id,
+                selected_character.0,
+            ));
+            *sent = true;
+        }
+    }
+}
+
+fn issue_commands(
+    mut game_commands: EventReader<GameCommand>,
     mut client: ResMut<Client<GameWorld>>,
     mut net: ResMut<NetworkResource>,
 ) {
     if let ClientStageMut::Ready(mut ready_client) = client.stage_mut() {
-        // can a client impersonate another with this?
-        let player_id = match ready_client.client_id() as u8 {
-            0 => Some(PlayerId::Player1),
-            1 => Some(PlayerId::Player2),
-            _ => None,
-        };
-
-        if let Some(player_id) = player_id {
-            let player_input = &PlayerInput {
-                action: input.just_pressed(KeyCode::Space),
-                left: input.pressed(KeyCode::Left),
-                right: input.pressed(KeyCode::Right),
-            };
-
-            if player_input.action != state.action {
-                ready_client.issue_command(
-                    GameCommand::Input(player_id, PlayerCommand::Action, player_input.action),
-                    &mut WrappedNetworkResource(&mut *net),
-                );
-            }
-            if player_input.left != state.left {
-                ready_client.issue_command(
-                    GameCommand::Input(player_id, PlayerCommand::Left, player_input.left),
-                    &mut WrappedNetworkResource(&mut *net),
-                );
-            }
-            if player_input.right != state.right {
-                ready_client.issue_command(
-                    GameCommand::Input(player_id, PlayerCommand::Right, player_input.right),
-                    &mut WrappedNetworkResource(&mut *net),
-                );
-            }
-            *state = *player_input;
+        for command in game_commands.iter() {
+            ready_client.issue_command(command.clone(), &mut network::EncodingNetwork(&mut *net));
         }
     }
 }
 
+fn sync_displayed_game(
+    time: Res<Time>,
+    client: Res<Client<GameWorld>>,
+    seat: Res<session::Seat>,
+    mut displayed_game: ResMut<DisplayedGame>,
+) {
+    if let ClientStage::Ready(client) = client.stage() {
+        displayed_game.show(
+            GameDisplayState::clone(&client.display_state()),
+            time.seconds_since_startup(),
+        );
+        displayed_game.local_player = seat.player_id;
+    }
+}
+
+/// Where the client finds the server it hosts, over loopback.
+#[cfg(not(target_arch = "wasm32"))]
+fn hosted_server_address(port: u16) -> std::net::SocketAddr {
+    std::net::SocketAddr::new(std::net::Ipv4Addr::LOCALHOST.into(), port)
+}
+
+/// Runs the server in this process, taking every address so that the other players can connect
+/// to this machine.
+#[cfg(not(target_arch = "wasm32"))]
+fn host_server(config_path: &str, port: u16) -> std::net::SocketAddr {
+    let args = vec![
+        "platformer-server".to_string(),
+        "--bind".to_string(),
+        "0.0.0.0".to_string(),
+        "--config".to_string(),
+        config_path.to_string(),
+    ];
+    std::thread::spawn(move || platformer_server::run(args, true));
+    hosted_server_address(port)
+}
+
 fn main() {
     use bevy::render::camera::camera_system;
 
-    App::build()
-        // You can optionally override some message channel settings
-        // There is `CommandChannelSettings`, `SnapshotChannelSettings`, and `ClockSyncChannelSettings`
-        // Make sure you apply the same settings for both client and server.
-        .insert_resource(CommandChannelSettings(MessageChannelSettings {
-            channel: 0,
-            channel_mode: MessageChannelMode::Compressed {
-                reliability_settings: ReliableChannelSettings {
-                    bandwidth: 4096,
-                    recv_window_size: 1024,
-                    send_window_size: 1024,
-                    burst_bandwidth: 1024,
-                    init_send: 512,
-                    wakeup_time: Duration::from_millis(100),
-                    initial_rtt: Duration::from_millis(200),
-                    max_rtt: Duration::from_secs(2),
-                    rtt_update_factor: 0.1,
-                    rtt_resend_factor: 1.5,
-                },
-                max_chunk_len: 1024,
-            },
-            message_buffer_size: 64,
-            packet_buffer_size: 64,
-        }))
+    let config_path = std::env::args()
+        .skip_while(|arg| arg != "--config")
+        .nth(1)
+        .unwrap_or_else(|| SETTINGS_PATH.to_string());
+    let settings = Settings::load(&config_path);
+    let tutorial = std::env::args().any(|arg| arg == "--tutorial");
+    let hotseat = std::env::args().any(|arg| arg == "--hotseat");
+    let character = std::env::args()
+        .skip_while(|arg| arg != "--character")
+        .nth(1)
+        .map(|name| name.parse().expect("invalid character"))
+        .unwrap_or_default();
+    let player_name = std::env::args()
+        .skip_while(|arg| arg != "--name")
+        .nth(1)
+        .or_else(|| settings.player.name.clone())
+        .unwrap_or_default();
+    let server_address = std::env::args()
+        .skip_while(|arg| arg != "--server")
+        .nth(1)
+        .map(|address| {
+            connect::resolve(&address, connect::default_port(&settings.network))
+                .unwrap_or_else(|error| panic!("{}", error))
+        });
+    #[cfg(not(target_arch = "wasm32"))]
+    let hosted = std::env::args().any(|arg| arg == "--host");
+    #[cfg(not(target_arch = "wasm32"))]
+    let server_address = if hosted {
+        Some(host_server(&config_path, settings.network.port))
+    } else {
+        server_address
+    };
+    let simulated = |name: &str| {
+        std::env::args()
+            .skip_while(|arg| arg != name)
+            .nth(1)
+            .map(|value| value.parse().expect("invalid network condition"))
+    };
+    let conditions = NetworkConditions {
+        latency_ms: simulated("--latency").unwrap_or(0),
+        jitter_ms: simulated("--jitter").unwrap_or(0),
+        loss: simulated("--loss").map_or(0.0, |percent: u64| percent as f32 / 100.0),
+    };
+    // the environment overrides the settings file, the command line both
+    let mut crystalorb = settings.crystalorb.clone();
+    crystalorb
+        .override_with(CrystalOrbSettings::env_var)
+        .and_then(|()| {
+            crystalorb.override_with(|option| {
+                let flag = format!("--{}", option);
+                std::env::args().skip_while(|arg| *arg != flag).nth(1)
+            })
+        })
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let map = MapDefinition::default();
+    let mut app = App::build();
+    app.insert_resource(net::command_channel(&settings.channel))
+        .insert_resource(net::snapshot_channel())
+        .insert_resource(net::clock_sync_channel())
         .insert_resource(WindowDescriptor {
-            height: 1000.0,
-            width: 1000.0,
-            resizable: false,
+            title: connection_status::WINDOW_TITLE.to_string(),
+            height: map.height,
+            width: map.width,
+            #[cfg(not(target_arch = "wasm32"))]
+            mode: display::window_mode(settings.display.mode),
+            #[cfg(not(target_arch = "wasm32"))]
+            vsync: settings.display.vsync,
+            // the canvas of client/web/index.html
+            #[cfg(target_arch = "wasm32")]
+            canvas: Some("#platformer".to_string()),
             ..Default::default()
         })
         .insert_resource(ClearColor(Color::BLACK))
@@ -107,67 +324,299 @@ fn main() {
             camera_system::<SimpleOrthoProjection>.system(),
         )
         .add_startup_system(setup_scene.system())
-        .add_plugin(CrystalOrbClientPlugin::<GameWorld>::new(
-            platformer_shared::crystal_orb_config(),
-        ))
-        .add_startup_system(setup_network.system())
-        .add_system(player_input.system())
-        .add_system(bevy::input::system::exit_on_esc_system.system())
-        .add_system(show_state.system())
+        .add_startup_system(camera::setup_letterbox.system())
+        .insert_resource(DisplayedGame {
+            map,
+            ..Default::default()
+        })
+        .add_event::<GameCommand>()
+        .insert_resource(SelectedCharacter(character))
+        .insert_resource(settings.keybinds)
+        .insert_resource(settings.controller)
+        .init_resource::<gamepad::Controllers>()
+        .init_resource::<gamepad::ControllerInput>()
+        .add_system(gamepad::track_controllers.system())
+        .add_system(gamepad::read_controllers.system().label(READ_INPUT))
+        .insert_resource(settings.audio)
+        .insert_resource(settings.graphics)
+        .add_system(ui_scale::record_ui_base.system())
+        .add_system(ui_scale::apply_ui_scale.system())
+        .insert_resource(crystalorb.clone())
+        .insert_resource(names::PlayerName(player_name))
+        .init_resource::<names::PlayerNames>()
+        .add_startup_system(names::setup_name_tags.system())
+        .add_system(names::name_tag_view.system())
+        .add_startup_system(emotes::setup_emote_bubbles.system())
+        .add_system(emotes::emote_bubble_view.system())
+        .add_system(shake::shake_camera.system())
+        .init_resource::<touch::TouchControls>()
+        .add_startup_system(touch::setup_touch_controls.system())
+        .add_system(touch::read_touches.system().label(READ_INPUT))
+        .add_system(touch::update_touch_controls.system())
+        .add_system(player_input.system().after(READ_INPUT))
+        .add_system(select_character.system())
         .add_system(projectile_view_lifecycle.system())
+        .add_system(textures::apply_textures.system())
+        .add_startup_system(animation::load_player_sheet.system())
+        .add_system(animation::cut_player_sheet.system())
+        .add_system(animation::animate_players.system())
         .add_system(view.system())
-        .run();
+        .add_system(power_pad_progress_view.system())
+        .add_system(switch_gate_view.system())
+        .add_system(rope_view.system())
+        .add_system(character_view.system())
+        .add_system(ghost_view.system())
+        .init_resource::<server_ghost::ServerGhost>()
+        .add_startup_system(server_ghost::setup_server_ghosts.system())
+        .add_system(server_ghost::toggle_server_ghosts.system())
+        .add_system(server_ghost::server_ghost_view.system())
+        .add_system(shockwave_view.system())
+        .add_system(shield_view.system())
+        .add_system(cannon_marker_view.system())
+        .add_startup_system(high_contrast::setup_high_contrast.system())
+        .add_system(high_contrast::add_outlines.system())
+        .add_system(high_contrast::high_contrast_view.system())
+        .add_startup_system(pad_arrow::setup_pad_arrow.system())
+        .add_system(pad_arrow::pad_arrow_view.system())
+        .add_startup_system(telegraph::setup_telegraph.system())
+        .add_system(telegraph::telegraph_view.system())
+        .init_resource::<debug::DebugOverlay>()
+        .init_resource::<debug::RollbackError>()
+        .add_startup_system(debug::setup_debug_overlay.system())
+        .add_system(debug::toggle_debug_overlay.system())
+        .add_system(debug::measure_rollback_error.system())
+        .add_system(debug::update_debug_overlay.system())
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .init_resource::<perf_overlay::PerfOverlay>()
+        .add_startup_system(perf_overlay::setup_perf_overlay.system())
+        .add_system(perf_overlay::toggle_perf_overlay.system())
+        .add_system(perf_overlay::update_perf_overlay.system())
+        .add_startup_system(hud::setup_hud.system())
+        .add_system(hud::update_hud.system())
+        .add_startup_system(hud::setup_streak_banner.system())
+        .add_system(hud::update_streak_banner.system())
+        .add_startup_system(hud::setup_round_result.system())
+        .add_system(hud::update_round_result.system())
+        .add_startup_system(hud::setup_advantage_banner.system())
+        .add_system(hud::update_advantage_banner.system())
+        .add_startup_system(hud::setup_round_banner.system())
+        .add_system(hud::update_round_banner.system())
+        .add_startup_system(advantage_tint::setup_advantage_tint.system())
+        .add_system(advantage_tint::update_advantage_tint.system())
+        .add_startup_system(death_flash::setup_death_flash.system())
+        .add_system(death_flash::update_death_flash.system())
+        .add_startup_system(kill_cam::setup_kill_cam_label.system())
+        .add_system(kill_cam::update_kill_cam_label.system())
+        .init_resource::<input_display::InputDisplay>()
+        .add_startup_system(input_display::setup_input_display.system())
+        .add_system(input_display::toggle_input_display.system())
+        .add_system(input_display::record_inputs.system())
+        .add_system(input_display::update_input_display.system())
+        .init_resource::<toast::Toasts>()
+        .add_startup_system(toast::setup_toasts.system())
+        .add_system(toast::update_toasts.system())
+        .add_system(toast::gamepad_toasts.system())
+        .add_system(toast::crowd_event_toasts.system())
+        .init_resource::<settings_menu::SettingsMenu>()
+        .add_startup_system(settings_menu::setup_settings_menu.system())
+        .add_system(settings_menu::settings_menu_keys.system())
+        .add_system(settings_menu::settings_menu_input.system())
+        .add_system(settings_menu::update_settings_menu.system());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(settings_menu::SettingsPath(config_path))
+        .insert_resource(settings.display)
+        .add_system(display::toggle_fullscreen.system())
+        .add_system(display::apply_display_settings.system())
+        .add_system_to_stage(CoreStage::Last, display::limit_frame_rate.system())
+        .add_system(settings_menu::save_settings.system())
+        .init_resource::<sound::Sounds>()
+        .add_system(sound::make_sounds.system())
+        .add_system(sound::play_sounds.system())
+        .init_resource::<music::Music>()
+        .add_system(music::make_music.system())
+        .add_system(music::play_music.system())
+        .add_system(screenshot::save_screenshot.system());
+    #[cfg(all(feature = "clip", not(target_arch = "wasm32")))]
+    app.init_resource::<clip::ClipRecorder>()
+        .add_system(clip::record_clip.system())
+        .add_system(clip::save_clip.system());
+    #[cfg(target_arch = "wasm32")]
+    app.add_plugin(bevy_webgl2::WebGL2Plugin);
+
+    if tutorial {
+        app.add_startup_system(tutorial::setup_tutorial.system())
+            .add_system(tutorial::step_tutorial.system())
+            .add_system(tutorial::update_tutorial_prompt.system());
+    } else if hotseat {
+        app.add_startup_system(hotseat::setup_hotseat.system())
+            .add_system(hotseat::hotseat_input.system())
+            .add_system(hotseat::step_hotseat.system())
+            .add_system(hotseat::update_hotseat_prompt.system());
+    } else {
+        // what the crystalorb plugin sets up, only with the client updated through the encoding
+        // network
+        app.add_plugin(NetworkingPlugin::default())
+            .insert_resource(Client::<GameWorld>::new(crystalorb.config()))
+            .add_system(network::update_client.system())
+            .insert_resource(session::ClientConfig(crystalorb.config()))
+            .init_resource::<session::Seat>()
+            .add_system(emotes::send_emotes.system())
+            .insert_resource(connect::ServerAddress(server_address))
+            .insert_resource(conditions)
+            .insert_resource(settings.network.clone())
+            .add_state(if server_address.is_some() {
+                menu::ClientState::Connect
+            } else {
+                menu::ClientState::MainMenu
+            })
+            .add_startup_system(menu::setup_menus.system())
+            .add_system(menu::menu_input.system())
+            .add_system(menu::update_menus.system())
+            .add_startup_system(match_over::setup_match_over_screen.system())
+            .add_system(match_over::enter_match_over.system())
+            .add_system(match_over::match_over_input.system())
+            .add_system(match_over::update_match_over_screen.system())
+            .add_system(settings_menu::disconnect.system())
+            .init_resource::<connect::ConnectScreen>()
+            .add_startup_system(connect::setup_connect_screen.system())
+            .add_system_set(
+                SystemSet::on_update(menu::ClientState::Connect)
+                    .with_system(connect::connect_screen_input.system()),
+            )
+            .add_system(connect::update_connect_screen.system())
+            .init_resource::<discovery::DiscoveredServers>()
+            .add_startup_system(discovery::setup_discovery.system())
+            .add_system(discovery::discover_servers.system())
+            .add_system(connect::update_server_list.system())
+            .add_startup_system_to_stage(StartupStage::PostStartup, lobby::setup_channels.system())
+            .add_system(session::send_hello.system())
+            .add_system(session::receive_lobby_messages.system())
+            .add_system(session::reset_client_on_reconnect.system())
+            .add_system(session::apply_tweening_settings.system())
+            .add_system(session::adopt_server_timing.system())
+            .init_resource::<session::ServerMatchConfig>()
+            .add_system(session::apply_match_config.system())
+            .add_system(session::ready_up.system().after(READ_INPUT))
+            .add_system(session::drop_silent_server.system())
+            .init_resource::<reconnect::Reconnect>()
+            .add_startup_system(reconnect::setup_reconnect_overlay.system())
+            .add_system(reconnect::watch_connection.system())
+            .add_system(reconnect::retry_connection.system())
+            .add_system(reconnect::update_reconnect_overlay.system())
+            .init_resource::<net_stats::NetStats>()
+            .add_system(net_stats::send_pings.system())
+            .add_system(net_stats::update_net_stats.system())
+            .init_resource::<netgraph::NetGraph>()
+            .add_startup_system(netgraph::setup_netgraph.system())
+            .add_system(netgraph::toggle_netgraph.system())
+            .add_system(netgraph::record_netgraph.system())
+            .add_system(netgraph::update_netgraph.system())
+            .add_system(issue_commands.system())
+            .add_system(sync_displayed_game.system())
+            .add_startup_system(connection_status::setup_connection_status.system())
+            .add_system(connection_status::update_connection_status.system())
+            .add_system(connection_status::update_window_title.system())
+            .add_system(toast::connection_toasts.system())
+            .add_system(toast::seat_toasts.system())
+            .init_resource::<bracket::TournamentBracket>()
+            .add_startup_system(bracket::setup_bracket_panel.system())
+            .add_system(bracket::update_bracket_panel.system())
+            .init_resource::<motd::ServerMotd>()
+            .add_startup_system(motd::setup_motd_panel.system())
+            .add_system(motd::update_motd_panel.system())
+            .add_system(toast::game_toasts.system());
+        #[cfg(not(target_arch = "wasm32"))]
+        app.insert_resource(menu::HostOptions {
+            port: settings.network.port,
+            hosted,
+        })
+        .add_system(menu::host_and_quit_input.system());
+    }
+
+    app.run();
 }
 
-struct GameContext {
+pub struct GameContext {
     player1: Entity,
     player2: Entity,
     cannon: Entity,
+    cannon_marker: Entity,
     bottom_power_pad: Entity,
     top_power_pad: Entity,
+    bottom_power_pad_progress: Entity,
+    top_power_pad_progress: Entity,
+    switches: Vec<Entity>,
+    gates: Vec<Entity>,
+    player1_rope: Entity,
+    player2_rope: Entity,
+    player1_ghost: Entity,
+    player2_ghost: Entity,
+    player1_shockwave: Entity,
+    player2_shockwave: Entity,
+    player1_shield: Entity,
+    player2_shield: Entity,
 }
 
-struct Projectile(u16);
+pub struct Projectile(u16);
+
+fn setup_scene(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    displayed_game: Res<DisplayedGame>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let map = &displayed_game.map;
 
-fn setup_scene(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
     // camera
-    let projection = SimpleOrthoProjection::new(1000.0, 1000.0);
+    let projection = SimpleOrthoProjection::new(map.height, map.width);
     let cam_name = bevy::render::render_graph::base::camera::CAMERA_2D;
     let camera = Camera {
         name: Some(cam_name.to_string()),
         ..Default::default()
     };
 
+    let camera_transform = Transform::from_xyz(0.0, 0.0, projection.far - 0.1);
     commands.spawn_bundle((
-        Transform::from_xyz(0.0, 0.0, projection.far - 0.1),
+        camera_transform,
         GlobalTransform::default(),
         VisibleEntities::default(),
         camera,
         projection,
+        shake::CameraShake::new(camera_transform.translation),
     ));
+    commands.spawn_bundle(UiCameraBundle::default());
 
     // player 1
-    let start_position = BOTTOM_START_POSITION;
+    let start_position = map.spawn_point(0, false);
     let size = Vec2::new(20.0, 20.0);
     let player1 = commands
         .spawn_bundle(SpriteBundle {
             material: materials.add(Color::BLUE.into()),
             sprite: Sprite::new(size),
-            transform: Transform::from_xyz(start_position.x, start_position.y, 0.0),
+            transform: Transform::from_xyz(start_position.x, start_position.y, OBJECT_Z),
             ..Default::default()
         })
+        .insert(animation::PlayerAnimation::new(
+            PlayerId::Player1,
+            Color::BLUE,
+        ))
         .id();
 
     // player 2
-    let start_position = TOP_START_POSITION;
+    let start_position = map.spawn_point(0, true);
     let size = Vec2::new(20.0, 20.0);
     let player2 = commands
         .spawn_bundle(SpriteBundle {
             material: materials.add(Color::RED.into()),
             sprite: Sprite::new(size),
-            transform: Transform::from_xyz(start_position.x, start_position.y, 0.0),
+            transform: Transform::from_xyz(start_position.x, start_position.y, OBJECT_Z),
             ..Default::default()
         })
+        .insert(animation::PlayerAnimation::new(
+            PlayerId::Player2,
+            Color::RED,
+        ))
         .id();
 
     let size = Vec2::new(40.0, 40.0);
@@ -175,109 +624,268 @@ fn setup_scene(mut commands: Commands, mut materials: ResMut<Assets<ColorMateria
         .spawn_bundle(SpriteBundle {
             material: materials.add(Color::ORANGE_RED.into()),
             sprite: Sprite::new(size),
-            transform: Transform::from_xyz(500.0, 500.0, 0.0),
+            transform: Transform::from_xyz(map.center().x, map.center().y, OBJECT_Z),
             ..Default::default()
         })
+        .insert(textures::Textured::new(
+            &asset_server,
+            textures::CANNON_TEXTURE,
+            Color::WHITE,
+        ))
         .id();
 
-    for platform in PLATFORMS.iter() {
+    // outlines the cannon in the color of the player holding the advantage
+    let cannon_marker = commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::NONE.into()),
+            sprite: Sprite::new(size + Vec2::new(12.0, 12.0)),
+            transform: Transform::from_xyz(map.center().x, map.center().y, CANNON_MARKER_Z),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .id();
+
+    for element in map.level.iter() {
+        let color = match element.kind {
+            LevelElementKind::Platform => Color::WHITE,
+            LevelElementKind::Lava => Color::ORANGE,
+            LevelElementKind::Decoration { color: [r, g, b] } => Color::rgb(r, g, b),
+        };
+
         for (x, y) in [
-            (platform.x, platform.y),
-            (1000.0 - platform.x, 1000.0 - platform.y),
+            (element.rect.x, element.rect.y),
+            map.mirror(element.rect.x, element.rect.y),
         ]
         .iter()
         {
-            let size = Vec2::new(platform.w, platform.h);
-            commands.spawn_bundle(SpriteBundle {
-                transform: Transform::from_xyz(*x, *y, 0.0),
-                material: materials.add(Color::WHITE.into()),
+            let size = Vec2::new(element.rect.w, element.rect.h);
+            let mut sprite = commands.spawn_bundle(SpriteBundle {
+                transform: Transform::from_xyz(*x, *y, layer_z(element.layer)),
+                material: materials.add(color.into()),
                 sprite: Sprite::new(size),
                 ..Default::default()
             });
+            // decorations keep the color the level gives them
+            match element.kind {
+                LevelElementKind::Platform => {
+                    sprite.insert(textures::Textured::new(
+                        &asset_server,
+                        textures::PLATFORM_TEXTURE,
+                        Color::WHITE,
+                    ));
+                }
+                LevelElementKind::Lava => {
+                    sprite.insert(textures::Textured::animated(
+                        &asset_server,
+                        &textures::LAVA_TEXTURES,
+                        Color::WHITE,
+                    ));
+                }
+                LevelElementKind::Decoration { .. } => (),
+            }
         }
     }
 
-    for lava_rect in LAVA_RECTS.iter() {
-        for (x, y) in [
-            (lava_rect.x, lava_rect.y),
-            (1000.0 - lava_rect.x, 1000.0 - lava_rect.y),
-        ]
-        .iter()
-        {
-            let size = Vec2::new(lava_rect.w, lava_rect.h);
-            commands.spawn_bundle(SpriteBundle {
-                transform: Transform::from_xyz(*x, *y, 1.0),
-                material: materials.add(Color::ORANGE.into()),
+    // hazard cannons, symmetrical so they don't need flipping
+    let size = Vec2::new(HAZARD_CANNON_SIZE.w, HAZARD_CANNON_SIZE.h);
+    for top_half in [false, true].iter() {
+        let position = map.hazard_cannon_position(*top_half);
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform::from_xyz(
+                    position.x,
+                    position.y,
+                    layer_z(RenderLayer::Terrain),
+                ),
+                material: materials.add(Color::DARK_GRAY.into()),
                 sprite: Sprite::new(size),
                 ..Default::default()
-            });
-        }
+            })
+            .insert(textures::Textured::new(
+                &asset_server,
+                textures::HAZARD_CANNON_TEXTURE,
+                Color::WHITE,
+            ));
     }
 
     let size = Vec2::new(POWER_PAD_SIZE.w, POWER_PAD_SIZE.h);
-    let position = STARTING_BOTTOM_POWER_PAD_POSITION;
+    let position = map.starting_power_pad_position(false);
     let bottom_power_pad = commands
         .spawn_bundle(SpriteBundle {
-            transform: Transform::from_xyz(position.x, position.y, 2.0),
+            transform: Transform::from_xyz(position.x, position.y, POWER_PAD_Z),
             material: materials.add(Color::CRIMSON.into()),
             sprite: Sprite::new(size),
             ..Default::default()
         })
+        .insert(textures::Textured::new(
+            &asset_server,
+            textures::POWER_PAD_TEXTURE,
+            Color::WHITE,
+        ))
         .id();
-    let position = STARTING_TOP_POWER_PAD_POSITION;
+    let position = map.starting_power_pad_position(true);
     let top_power_pad = commands
         .spawn_bundle(SpriteBundle {
-            transform: Transform::from_xyz(position.x, position.y, 2.0),
+            transform: Transform::from_xyz(position.x, position.y, POWER_PAD_Z),
             material: materials.add(Color::CRIMSON.into()),
             sprite: Sprite::new(size),
             ..Default::default()
         })
+        .insert(textures::Textured::new(
+            &asset_server,
+            textures::POWER_PAD_TEXTURE,
+            Color::WHITE,
+        ))
         .id();
 
+    // capture progress fills, grown over the pads while they are being captured
+    let mut spawn_power_pad_progress = |position: Position| {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform::from_xyz(position.x, position.y, POWER_PAD_PROGRESS_Z),
+                material: materials.add(Color::GOLD.into()),
+                sprite: Sprite::new(Vec2::new(0.0, POWER_PAD_SIZE.h)),
+                ..Default::default()
+            })
+            .id()
+    };
+    let bottom_power_pad_progress =
+        spawn_power_pad_progress(map.starting_power_pad_position(false));
+    let top_power_pad_progress = spawn_power_pad_progress(map.starting_power_pad_position(true));
+
+    let mut switches = vec![];
+    let mut gates = vec![];
+    for switch_gate in map.all_switch_gates() {
+        for (rect, color, entities) in [
+            (&switch_gate.switch, Color::YELLOW, &mut switches),
+            (&switch_gate.gate, Color::GRAY, &mut gates),
+        ]
+        .iter_mut()
+        {
+            entities.push(
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        transform: Transform::from_xyz(
+                            rect.x,
+                            rect.y,
+                            layer_z(RenderLayer::Terrain),
+                        ),
+                        material: materials.add((*color).into()),
+                        sprite: Sprite::new(Vec2::new(rect.w, rect.h)),
+                        ..Default::default()
+                    })
+                    .id(),
+            );
+        }
+    }
+
+    // grappling hook ropes, stretched between the players and their anchors
+    let mut spawn_rope = || {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(Color::SILVER.into()),
+                sprite: Sprite::new(Vec2::new(0.0, 2.0)),
+                transform: Transform::from_xyz(0.0, 0.0, OBJECT_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .id()
+    };
+    let player1_rope = spawn_rope();
+    let player2_rope = spawn_rope();
+
+    // ghosts replaying the previous round's winner
+    let mut spawn_ghost = |color: Color| {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(Vec2::new(20.0, 20.0)),
+                transform: Transform::from_xyz(0.0, 0.0, GHOST_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .id()
+    };
+    let player1_ghost = spawn_ghost(Color::rgba(0.0, 0.0, 1.0, 0.3));
+    let player2_ghost = spawn_ghost(Color::rgba(1.0, 0.0, 0.0, 0.3));
+
+    // stomp shockwaves, grown over the landing spot
+    let shockwave_material = materials.add(Color::rgba(1.0, 1.0, 1.0, 0.4).into());
+    let mut spawn_shockwave = || {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: shockwave_material.clone(),
+                sprite: Sprite::new(Vec2::ZERO),
+                transform: Transform::from_xyz(0.0, 0.0, GHOST_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .id()
+    };
+    let player1_shockwave = spawn_shockwave();
+    let player2_shockwave = spawn_shockwave();
+
+    // shields, drawn over the players
+    let shield_material = materials.add(Color::rgba(0.3, 0.8, 1.0, 0.5).into());
+    let mut spawn_shield = || {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: shield_material.clone(),
+                sprite: Sprite::new(Vec2::ZERO),
+                transform: Transform::from_xyz(0.0, 0.0, SHIELD_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .id()
+    };
+    let player1_shield = spawn_shield();
+    let player2_shield = spawn_shield();
+
     commands.insert_resource(GameContext {
         player1,
         player2,
         cannon,
+        cannon_marker,
         bottom_power_pad,
         top_power_pad,
+        bottom_power_pad_progress,
+        top_power_pad_progress,
+        switches,
+        gates,
+        player1_rope,
+        player2_rope,
+        player1_ghost,
+        player2_ghost,
+        player1_shockwave,
+        player2_shockwave,
+        player1_shield,
+        player2_shield,
     });
 }
 
-fn setup_network(mut net: ResMut<NetworkResource>) {
-    let ip_address =
-        bevy_networking_turbulence::find_my_ip_address().expect("can't find ip address");
-    let socket_address = SocketAddr::new(ip_address, platformer_shared::SERVER_PORT);
-    info!("Connecting to {}", socket_address);
-    net.connect(socket_address);
-}
-
-fn show_state(mut previous: Local<String>, client: ResMut<Client<GameWorld>>) {
-    use crystalorb::client::stage::Stage;
-    let text = match client.stage() {
-        Stage::SyncingClock(c) => {
-            format!("SyncingClock {}/{}", c.sample_count(), c.samples_needed())
-        }
-        Stage::SyncingInitialState(_) => "SyncingInitialState".to_string(),
-        Stage::Ready(_) => "Ready".to_string(),
-    };
-    if *previous != text {
-        info!("State: {}", text);
-        *previous = text;
-    }
-}
-
 fn projectile_view_lifecycle(
     mut commands: Commands,
+    asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    client: Res<Client<GameWorld>>,
+    displayed_game: Res<DisplayedGame>,
     projectiles: Query<(Entity, &Projectile)>,
 ) {
-    if let ClientStage::Ready(client) = client.stage() {
-        // world is flipped for player 2
-        let world_flipped = client.client_id() as u8 == 1;
-
-        let display_state = client.display_state();
-
+    if let Some(display_state) = &displayed_game.state {
         let current_views = projectiles
             .iter()
             .map(|(_, p)| p.0)
@@ -308,11 +916,8 @@ fn projectile_view_lifecycle(
                 .translation
                 .vector;
 
-            let transform = if world_flipped {
-                Transform::from_xyz(1000.0 - position.x, 1000.0 - position.y, 2.0)
-            } else {
-                Transform::from_xyz(position.x, position.y, 2.0)
-            };
+            let (x, y) = displayed_game.view_position(position.x, position.y);
+            let transform = Transform::from_xyz(x, y, OBJECT_Z);
 
             commands
                 .spawn_bundle(SpriteBundle {
@@ -321,30 +926,28 @@ fn projectile_view_lifecycle(
                     sprite: Sprite::new(Vec2::new(PROJECTILE_SIZE.w, PROJECTILE_SIZE.h)),
                     ..Default::default()
                 })
-                .insert(Projectile(*projectile_id));
+                .insert(Projectile(*projectile_id))
+                .insert(textures::Textured::new(
+                    &asset_server,
+                    textures::PROJECTILE_TEXTURE,
+                    Color::WHITE,
+                ));
         }
     }
 }
 
-// helper
-fn update_transform(transform: &mut Transform, x: f32, y: f32, world_flipped: bool) {
-    if world_flipped {
-        transform.translation = Vec3::new(1000.0 - x, 1000.0 - y, 0.0);
-    } else {
-        transform.translation = Vec3::new(x, y, 0.0);
-    }
+// helper, keeps the z-order of the transform
+fn update_transform(transform: &mut Transform, x: f32, y: f32, displayed_game: &DisplayedGame) {
+    let (x, y) = displayed_game.view_position(x, y);
+    transform.translation = Vec3::new(x, y, transform.translation.z);
 }
 
 fn view(
-    client: Res<Client<GameWorld>>,
+    displayed_game: Res<DisplayedGame>,
     game_context: Res<GameContext>,
     mut q: QuerySet<(Query<&mut Transform>, Query<(&Projectile, &mut Transform)>)>,
 ) {
-    if let ClientStage::Ready(client) = client.stage() {
-        // world is flipped for player 2
-        let world_flipped = client.client_id() as u8 == 1;
-
-        let display_state = client.display_state();
+    if let Some(display_state) = &displayed_game.state {
         for (entity, pos) in [
             (game_context.player1, display_state.player1_position),
             (game_context.player2, display_state.player2_position),
@@ -357,32 +960,32 @@ fn view(
                 &mut transform,
                 pos.translation.vector.x,
                 pos.translation.vector.y,
-                world_flipped,
+                &displayed_game,
             );
+            // only corpses rotate, and flipping the world doesn't change their angle
+            transform.rotation = Quat::from_rotation_z(pos.rotation.angle());
         }
 
-        for (entity, status, positions) in [
+        for (entity, pos) in [
             (
                 game_context.bottom_power_pad,
-                display_state.bottom_power_pad_status,
-                BOTTOM_POWER_PAD_POSITIONS,
+                display_state.bottom_power_pad_position,
             ),
             (
                 game_context.top_power_pad,
-                display_state.top_power_pad_status,
-                TOP_POWER_PAD_POSITIONS,
+                display_state.top_power_pad_position,
             ),
         ]
         .iter()
         {
             let mut transform = q.q0_mut().get_mut(*entity).unwrap();
 
-            let Position { x, y } = match status {
-                PowerPadStatus::Left => positions.left,
-                PowerPadStatus::Right => positions.right,
-            };
-
-            update_transform(&mut transform, x, y, world_flipped);
+            update_transform(
+                &mut transform,
+                pos.translation.vector.x,
+                pos.translation.vector.y,
+                &displayed_game,
+            );
         }
 
         let mut transform = q.q0_mut().get_mut(game_context.cannon).unwrap();
@@ -391,7 +994,7 @@ fn view(
             &mut transform,
             display_state.cannon_x_position,
             y,
-            world_flipped,
+            &displayed_game,
         );
 
         for (projectile, mut transform) in q.q1_mut().iter_mut() {
@@ -404,9 +1007,258 @@ fn view(
                     // TODO: correlation
                     pos.x,
                     pos.y,
-                    world_flipped,
+                    &displayed_game,
                 );
             }
         }
     }
 }
+
+fn character_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<&mut Sprite>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (entity, character) in [
+            (game_context.player1, display_state.player1_character),
+            (game_context.player2, display_state.player2_character),
+        ]
+        .iter()
+        {
+            let size = character.stats().size;
+            let mut sprite = q.get_mut(*entity).unwrap();
+            sprite.size = Vec2::new(size, size);
+        }
+    }
+}
+
+fn power_pad_progress_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Sprite)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (entity, pos, progress) in [
+            (
+                game_context.bottom_power_pad_progress,
+                display_state.bottom_power_pad_position,
+                display_state.bottom_power_pad_capture_progress,
+            ),
+            (
+                game_context.top_power_pad_progress,
+                display_state.top_power_pad_position,
+                display_state.top_power_pad_capture_progress,
+            ),
+        ]
+        .iter()
+        {
+            let (mut transform, mut sprite) = q.get_mut(*entity).unwrap();
+
+            update_transform(
+                &mut transform,
+                pos.translation.vector.x,
+                pos.translation.vector.y,
+                &displayed_game,
+            );
+            sprite.size = Vec2::new(POWER_PAD_SIZE.w * progress, POWER_PAD_SIZE.h);
+        }
+    }
+}
+
+fn switch_gate_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Visible)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (index, switch_gate) in displayed_game.map.all_switch_gates().enumerate() {
+            let (mut transform, _) = q.get_mut(game_context.switches[index]).unwrap();
+            update_transform(
+                &mut transform,
+                switch_gate.switch.x,
+                switch_gate.switch.y,
+                &displayed_game,
+            );
+
+            let (mut transform, mut visible) = q.get_mut(game_context.gates[index]).unwrap();
+            update_transform(
+                &mut transform,
+                switch_gate.gate.x,
+                switch_gate.gate.y,
+                &displayed_game,
+            );
+            visible.is_visible = display_state.gates_visible[index];
+        }
+    }
+}
+
+fn rope_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Sprite, &mut Visible)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (entity, position, anchor) in [
+            (
+                game_context.player1_rope,
+                display_state.player1_position,
+                display_state.player1_grapple_anchor,
+            ),
+            (
+                game_context.player2_rope,
+                display_state.player2_position,
+                display_state.player2_grapple_anchor,
+            ),
+        ]
+        .iter()
+        {
+            let (mut transform, mut sprite, mut visible) = q.get_mut(*entity).unwrap();
+
+            visible.is_visible = anchor.is_some();
+            if let Some(anchor) = anchor {
+                let position = position.translation.vector;
+                let middle = (position + anchor) / 2.0;
+                let offset = anchor - position;
+
+                update_transform(&mut transform, middle.x, middle.y, &displayed_game);
+                // flipping the world is a 180 degree rotation, which keeps the rope's angle
+                transform.rotation = Quat::from_rotation_z(offset.y.atan2(offset.x));
+                sprite.size = Vec2::new(offset.norm(), 2.0);
+            }
+        }
+    }
+}
+
+fn ghost_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Sprite, &mut Visible)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (player_id, entity, character) in [
+            (
+                PlayerId::Player1,
+                game_context.player1_ghost,
+                display_state.player1_character,
+            ),
+            (
+                PlayerId::Player2,
+                game_context.player2_ghost,
+                display_state.player2_character,
+            ),
+        ]
+        .iter()
+        {
+            let (mut transform, mut sprite, mut visible) = q.get_mut(*entity).unwrap();
+
+            match display_state.ghost {
+                Some((ghost_player_id, position)) if ghost_player_id == *player_id => {
+                    update_transform(&mut transform, position.x, position.y, &displayed_game);
+                    let size = character.stats().size;
+                    sprite.size = Vec2::new(size, size);
+                    visible.is_visible = true;
+                }
+                _ => visible.is_visible = false,
+            }
+        }
+    }
+}
+
+fn shockwave_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Sprite, &mut Visible)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (entity, position, shockwave) in [
+            (
+                game_context.player1_shockwave,
+                display_state.player1_position,
+                display_state.player1_shockwave,
+            ),
+            (
+                game_context.player2_shockwave,
+                display_state.player2_position,
+                display_state.player2_shockwave,
+            ),
+        ]
+        .iter()
+        {
+            let (mut transform, mut sprite, mut visible) = q.get_mut(*entity).unwrap();
+
+            visible.is_visible = shockwave.is_some();
+            if let Some(progress) = shockwave {
+                let position = position.translation.vector;
+                update_transform(&mut transform, position.x, position.y, &displayed_game);
+                let size = 2.0 * SHOCKWAVE_RADIUS * progress;
+                sprite.size = Vec2::new(size, size);
+            }
+        }
+    }
+}
+
+fn cannon_marker_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut q: Query<(&mut Transform, &mut Visible, &Handle<ColorMaterial>)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        let (mut transform, mut visible, material) = q.get_mut(game_context.cannon_marker).unwrap();
+        let color = match display_state.advantage_state {
+            AdvantageState::Neutral => None,
+            AdvantageState::Player1 => Some(Color::BLUE),
+            AdvantageState::Player2 => Some(Color::RED),
+        };
+        visible.is_visible = color.is_some();
+        if let Some(color) = color {
+            let material = materials.get_mut(material).unwrap();
+            if material.color != color {
+                material.color = color;
+            }
+            // the cannon stays on the middle line, which flipping the view keeps in place
+            update_transform(
+                &mut transform,
+                display_state.cannon_x_position,
+                displayed_game.map.center().y,
+                &displayed_game,
+            );
+        }
+    }
+}
+
+fn shield_view(
+    displayed_game: Res<DisplayedGame>,
+    game_context: Res<GameContext>,
+    mut q: Query<(&mut Transform, &mut Sprite, &mut Visible)>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        for (entity, position, character, shield) in [
+            (
+                game_context.player1_shield,
+                display_state.player1_position,
+                display_state.player1_character,
+                display_state.player1_shield,
+            ),
+            (
+                game_context.player2_shield,
+                display_state.player2_position,
+                display_state.player2_character,
+                display_state.player2_shield,
+            ),
+        ]
+        .iter()
+        {
+            let (mut transform, mut sprite, mut visible) = q.get_mut(*entity).unwrap();
+
+            visible.is_visible = *shield;
+            if *shield {
+                let position = position.translation.vector;
+                update_transform(&mut transform, position.x, position.y, &displayed_game);
+                let size = character.stats().size + 10.0;
+                sprite.size = Vec2::new(size, size);
+            }
+        }
+    }
+}
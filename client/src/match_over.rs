@@ -0,0 +1,214 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+    game::{GameCommand, GameDisplayState, MatchResult, PlayerId, PlayerStats},
+};
+
+use crate::{
+    connect::ConnectScreen,
+    menu::{self, ClientState, MenuButton},
+    names::PlayerNames,
+    session::{self, Seat},
+    DisplayedGame, UI_FONT,
+};
+
+// End-of-match screen, shown over the finished match: the winner, the final score and what each
+// player did during the match. Rematch asks for another match with the same opponent, which
+// starts once they ask too, and Back to Menu leaves the server.
+
+/// Every entity of the screen, hidden together.
+#[derive(Clone, Copy)]
+pub struct MatchOverPart;
+
+/// Text of the screen, rewritten from the display state.
+#[derive(Clone, Copy)]
+pub enum MatchOverText {
+    Winner,
+    Score,
+    Stats(PlayerId),
+    Rematch,
+}
+
+pub fn setup_match_over_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let button_material = materials.add(Color::DARK_GRAY.into());
+    let text = |font_size: f32| TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(8.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    };
+
+    menu::spawn_screen(&mut commands, &mut materials, MatchOverPart, |parent| {
+        for (label, font_size) in [
+            (MatchOverText::Winner, 48.0),
+            (MatchOverText::Score, 32.0),
+            (MatchOverText::Stats(PlayerId::Player1), 20.0),
+            (MatchOverText::Stats(PlayerId::Player2), 20.0),
+            (MatchOverText::Rematch, 20.0),
+        ]
+        .iter()
+        {
+            parent
+                .spawn_bundle(text(*font_size))
+                .insert(MatchOverPart)
+                .insert(*label);
+        }
+        for (button, label) in [
+            (MenuButton::Rematch, "Rematch"),
+            (MenuButton::Leave, "Back to Menu"),
+        ]
+        .iter()
+        {
+            menu::spawn_button(
+                parent,
+                *button,
+                label,
+                MatchOverPart,
+                font.clone(),
+                button_material.clone(),
+            );
+        }
+    });
+}
+
+fn match_over(display_state: &GameDisplayState) -> bool {
+    display_state.match_result.is_some() && !display_state.lobby
+}
+
+/// Opens the screen once the match is decided, and closes it when the next one starts.
+pub fn enter_match_over(displayed_game: Res<DisplayedGame>, mut state: ResMut<State<ClientState>>) {
+    let over = displayed_game.state.as_ref().map_or(false, match_over);
+    let next = match state.current() {
+        ClientState::Connect if over => ClientState::MatchOver,
+        ClientState::MatchOver if !over => ClientState::Connect,
+        _ => return,
+    };
+    if let Err(error) = state.set(next) {
+        warn!("Can't go to {:?}: {:?}", next, error);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn match_over_input(
+    buttons: Query<(&Interaction, &MenuButton, &Visible), Changed<Interaction>>,
+    mut game_commands: EventWriter<GameCommand>,
+    mut state: ResMut<State<ClientState>>,
+    mut screen: ResMut<ConnectScreen>,
+    mut seat: ResMut<Seat>,
+    mut displayed_game: ResMut<DisplayedGame>,
+    mut net: ResMut<NetworkResource>,
+) {
+    match menu::clicked(&buttons) {
+        Some(MenuButton::Rematch) => {
+            if let Some(player_id) = seat.player_id {
+                game_commands.send(GameCommand::Rematch(player_id));
+            }
+        }
+        Some(MenuButton::Leave) => {
+            session::leave_server(&mut net, &mut screen, &mut seat, &mut displayed_game);
+            if let Err(error) = state.set(ClientState::MainMenu) {
+                warn!("Can't go back to the main menu: {:?}", error);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn stats_line(name: &str, stats: &PlayerStats) -> String {
+    let accuracy = match stats.accuracy() {
+        Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+        None => "-".to_string(),
+    };
+    format!(
+        "{}: {} shots, {} hits ({} accuracy), {} pad captures, {} deaths, {:.0}px traveled",
+        name,
+        stats.shots_fired,
+        stats.hits,
+        accuracy,
+        stats.pad_captures,
+        stats.deaths,
+        stats.distance_traveled
+    )
+}
+
+pub fn update_match_over_screen(
+    state: Res<State<ClientState>>,
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    mut parts: Query<(&mut Visible, Entity, Option<&Parent>), With<MatchOverPart>>,
+    buttons: Query<&MenuButton>,
+    mut texts: Query<(&mut Text, &MatchOverText)>,
+) {
+    let display_state = match (&displayed_game.state, state.current()) {
+        (Some(display_state), ClientState::MatchOver) => Some(display_state),
+        _ => None,
+    };
+    // spectators watch the players decide, without the rematch button or its label
+    let rematch_button = |entity: Entity| matches!(buttons.get(entity), Ok(MenuButton::Rematch));
+    let seated = displayed_game.local_player.is_some();
+    for (mut visible, entity, parent) in parts.iter_mut() {
+        let rematch =
+            rematch_button(entity) || parent.map_or(false, |parent| rematch_button(parent.0));
+        let shown = display_state.is_some() && (seated || !rematch);
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+    }
+    let display_state = match display_state {
+        Some(display_state) => display_state,
+        None => return,
+    };
+
+    let [player1_score, player2_score] = display_state.scores;
+    for (mut text, label) in texts.iter_mut() {
+        let value = match label {
+            MatchOverText::Winner => match display_state.match_result {
+                Some(MatchResult::Winner(player_id)) => {
+                    format!("{} wins the match!", names.get(player_id))
+                }
+                _ => "The match is a draw".to_string(),
+            },
+            MatchOverText::Score => format!(
+                "{} {} - {} {}",
+                names.get(PlayerId::Player1),
+                player1_score,
+                player2_score,
+                names.get(PlayerId::Player2)
+            ),
+            MatchOverText::Stats(player_id) => stats_line(
+                &names.get(*player_id),
+                &display_state.stats[player_id.as_usize()],
+            ),
+            MatchOverText::Rematch => {
+                let asked: Vec<_> = [PlayerId::Player1, PlayerId::Player2]
+                    .iter()
+                    .filter(|player_id| display_state.rematch_votes[player_id.as_usize()])
+                    .map(|player_id| names.get(*player_id))
+                    .collect();
+                match asked.as_slice() {
+                    [] => String::new(),
+                    [name] => format!("{} asked for a rematch", name),
+                    _ => "Rematch starting".to_string(),
+                }
+            }
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
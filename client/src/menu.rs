@@ -0,0 +1,253 @@
+use platformer_shared::bevy::{prelude::*, ui::FocusPolicy};
+#[cfg(not(target_arch = "wasm32"))]
+use platformer_shared::{
+    bevy::app::AppExit, conditions::NetworkConditions,
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{connect, settings_menu::SettingsPath};
+use crate::{connect::ConnectScreen, UI_FONT};
+
+// Main menu the client opens on, leading to the connect screen, a hosted server, the settings
+// or out. Passing `--server` or `--host` skips it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientState {
+    MainMenu,
+    /// The settings menu, opened from the main menu.
+    Settings,
+    /// The connect screen, and the session it leads to.
+    Connect,
+    /// The end-of-match screen, over the finished match until a rematch or the next one.
+    MatchOver,
+}
+
+/// Port a hosted server is started with.
+pub struct HostOptions {
+    pub port: u16,
+    /// Set once the server runs, it's only started once.
+    pub hosted: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum MenuButton {
+    Play,
+    #[cfg(not(target_arch = "wasm32"))]
+    Host,
+    Settings,
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+    /// Back to the main menu from the connect screen.
+    Back,
+    /// Asks for a rematch on the end-of-match screen.
+    Rematch,
+    /// Disconnects back to the main menu from the end-of-match screen.
+    Leave,
+}
+
+/// Every entity of the main menu, hidden together.
+pub struct MainMenuPart;
+
+pub fn spawn_button<Part: Copy + Send + Sync + 'static>(
+    parent: &mut ChildBuilder,
+    button: MenuButton,
+    label: &str,
+    part: Part,
+    font: Handle<Font>,
+    material: Handle<ColorMaterial>,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(6.0)),
+                padding: Rect::all(Val::Px(10.0)),
+                min_size: Size::new(Val::Px(200.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .insert(button)
+        .insert(part)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        label,
+                        TextStyle {
+                            font,
+                            font_size: 28.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(part);
+        });
+}
+
+pub fn spawn_screen<Part: Copy + Send + Sync + 'static>(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    part: Part,
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.9).into()),
+            // hidden, it mustn't keep the clicks from the screens below
+            focus_policy: FocusPolicy::Pass,
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(part)
+        .with_children(children);
+}
+
+pub fn setup_menus(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let button_material = materials.add(Color::DARK_GRAY.into());
+    let title = |value: &str, font_size: f32| TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(20.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    };
+
+    spawn_screen(&mut commands, &mut materials, MainMenuPart, |parent| {
+        parent
+            .spawn_bundle(title("Platformer", 64.0))
+            .insert(MainMenuPart);
+        let mut buttons = vec![(MenuButton::Play, "Play")];
+        #[cfg(not(target_arch = "wasm32"))]
+        buttons.push((MenuButton::Host, "Host"));
+        buttons.push((MenuButton::Settings, "Settings"));
+        #[cfg(not(target_arch = "wasm32"))]
+        buttons.push((MenuButton::Quit, "Quit"));
+        for (button, label) in buttons {
+            spawn_button(
+                parent,
+                button,
+                label,
+                MainMenuPart,
+                font.clone(),
+                button_material.clone(),
+            );
+        }
+    });
+}
+
+/// Hidden screens keep their buttons, only the shown ones are taken.
+pub fn clicked(
+    buttons: &Query<(&Interaction, &MenuButton, &Visible), Changed<Interaction>>,
+) -> Option<MenuButton> {
+    buttons
+        .iter()
+        .find(|(interaction, _, visible)| {
+            **interaction == Interaction::Clicked && visible.is_visible
+        })
+        .map(|(_, button, _)| *button)
+}
+
+/// Buttons, Enter for Play and Escape for Back move between the screens.
+pub fn menu_input(
+    buttons: Query<(&Interaction, &MenuButton, &Visible), Changed<Interaction>>,
+    keys: Res<Input<KeyCode>>,
+    screen: Res<ConnectScreen>,
+    mut state: ResMut<State<ClientState>>,
+) {
+    let clicked = clicked(&buttons);
+    let next = match clicked {
+        Some(MenuButton::Play) => ClientState::Connect,
+        Some(MenuButton::Settings) => ClientState::Settings,
+        Some(MenuButton::Back) => ClientState::MainMenu,
+        Some(MenuButton::Rematch) | Some(MenuButton::Leave) => return,
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(MenuButton::Host) | Some(MenuButton::Quit) => return,
+        None => match *state.current() {
+            ClientState::MainMenu if keys.just_pressed(KeyCode::Return) => ClientState::Connect,
+            // while connecting or playing, Escape opens the settings menu instead
+            ClientState::Connect
+                if screen.server().is_none() && keys.just_pressed(KeyCode::Escape) =>
+            {
+                ClientState::MainMenu
+            }
+            _ => return,
+        },
+    };
+    if let Err(error) = state.set(next) {
+        warn!("Can't go to {:?}: {:?}", next, error);
+    }
+}
+
+/// Host starts the server in this process, once, and connects to it. Quit closes the game.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn host_and_quit_input(
+    buttons: Query<(&Interaction, &MenuButton, &Visible), Changed<Interaction>>,
+    mut host_options: ResMut<HostOptions>,
+    settings_path: Res<SettingsPath>,
+    conditions: Res<NetworkConditions>,
+    mut state: ResMut<State<ClientState>>,
+    mut screen: ResMut<ConnectScreen>,
+    mut net: ResMut<NetworkResource>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let clicked = clicked(&buttons);
+    match clicked {
+        Some(MenuButton::Host) => {
+            let server = if host_options.hosted {
+                crate::hosted_server_address(host_options.port)
+            } else {
+                host_options.hosted = true;
+                crate::host_server(&settings_path.0, host_options.port)
+            };
+            connect::connect(&mut screen, &mut net, &conditions, server);
+            if let Err(error) = state.set(ClientState::Connect) {
+                warn!("Can't go to the connect screen: {:?}", error);
+            }
+        }
+        Some(MenuButton::Quit) => exit.send(AppExit),
+        _ => {}
+    }
+}
+
+pub fn update_menus(
+    state: Res<State<ClientState>>,
+    mut parts: Query<&mut Visible, With<MainMenuPart>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut visible in parts.iter_mut() {
+        visible.is_visible = *state.current() == ClientState::MainMenu;
+    }
+}
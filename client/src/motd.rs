@@ -0,0 +1,68 @@
+use platformer_shared::bevy::prelude::*;
+
+use crate::{DisplayedGame, UI_FONT};
+
+// The server's message of the day and rules, shown while connecting and in the lobby
+
+/// Latest message of the day and rules the server sent, unset when it has none.
+#[derive(Default)]
+pub struct ServerMotd(pub Option<(String, String)>);
+
+pub struct MotdText;
+
+pub fn setup_motd_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(40.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                max_size: Size::new(Val::Px(360.0), Val::Undefined),
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(MotdText);
+}
+
+pub fn update_motd_panel(
+    motd: Res<ServerMotd>,
+    displayed_game: Res<DisplayedGame>,
+    mut query: Query<(&mut Text, &mut Visible), With<MotdText>>,
+) {
+    let in_lobby = displayed_game
+        .state
+        .as_ref()
+        .map_or(true, |display_state| display_state.lobby);
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = motd.0.is_some() && in_lobby;
+        if motd.is_changed() {
+            if let Some((message, rules)) = &motd.0 {
+                let mut sections = vec![];
+                if !message.is_empty() {
+                    sections.push(message.clone());
+                }
+                if !rules.is_empty() {
+                    sections.push(format!("Rules:\n{}", rules));
+                }
+                text.sections[0].value = sections.join("\n\n");
+            }
+        }
+    }
+}
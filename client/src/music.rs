@@ -0,0 +1,174 @@
+use platformer_shared::{
+    bevy::{audio::AudioSource, prelude::*},
+    game::{AdvantageState, GameDisplayState},
+    settings::AudioSettings,
+    TIMESTEP,
+};
+use std::f32::consts::PI;
+
+use crate::{
+    sound::{wav, SAMPLE_RATE},
+    DisplayedGame,
+};
+
+// Background music of the match, a bar looped for as long as it's played. The game has no sudden
+// death, so the decider is its last stretch instead: match point, the last round, or the last
+// seconds of a timed round. Bevy's audio can't stop a sound once it plays, so the music switches
+// between the phases on the next bar.
+
+/// Four beats at 120 beats per minute.
+const BAR_SECONDS: f64 = 2.0;
+const BEAT_SECONDS: f32 = 0.5;
+/// Seconds left in a timed round from which it's the decider.
+const DECIDER_SECONDS: f64 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Bass alone.
+    Calm,
+    /// An arpeggio over the bass, while a player holds the advantage.
+    Advantage,
+    /// The arpeggio, a driving bass and a kick drum.
+    Decider,
+}
+
+impl Phase {
+    const ALL: [Phase; 3] = [Phase::Calm, Phase::Advantage, Phase::Decider];
+
+    /// Phase of the match, `None` outside of it.
+    fn of(display_state: &GameDisplayState) -> Option<Self> {
+        if display_state.lobby || display_state.match_result.is_some() {
+            return None;
+        }
+        let settings = &display_state.match_config.match_settings;
+        let match_point = settings.score_target.map_or(false, |score_target| {
+            display_state
+                .scores
+                .iter()
+                .any(|score| score.saturating_add(1) >= score_target)
+        });
+        let last_round = settings.round_limit == Some(display_state.round);
+        let running_out = display_state.round_ticks_left.map_or(false, |ticks_left| {
+            ticks_left as f64 * TIMESTEP <= DECIDER_SECONDS
+        });
+        Some(if match_point || last_round || running_out {
+            Phase::Decider
+        } else if display_state.advantage_state != AdvantageState::Neutral {
+            Phase::Advantage
+        } else {
+            Phase::Calm
+        })
+    }
+
+    fn bar(self) -> Vec<f32> {
+        let mut bar = vec![0.0; (BAR_SECONDS * SAMPLE_RATE as f64) as usize];
+        // A minor
+        let bass = [110.0, 110.0, 130.81, 98.0];
+        let arpeggio = [440.0, 523.25, 659.25, 523.25];
+        for (beat, hz) in bass.iter().enumerate() {
+            let start = beat as f32 * BEAT_SECONDS;
+            if self == Phase::Decider {
+                mix(&mut bar, start, &note(*hz, BEAT_SECONDS / 2.0, 0.35));
+                mix(
+                    &mut bar,
+                    start + BEAT_SECONDS / 2.0,
+                    &note(*hz, BEAT_SECONDS / 2.0, 0.35),
+                );
+                mix(&mut bar, start, &kick());
+            } else {
+                mix(&mut bar, start, &note(*hz, BEAT_SECONDS, 0.4));
+            }
+        }
+        if self != Phase::Calm {
+            for (eighth, hz) in arpeggio.iter().cycle().take(8).enumerate() {
+                let start = eighth as f32 * BEAT_SECONDS / 2.0;
+                mix(&mut bar, start, &note(*hz, BEAT_SECONDS / 2.0, 0.15));
+            }
+        }
+        bar
+    }
+}
+
+/// A sine fading out over the note.
+fn note(hz: f32, seconds: f32, amplitude: f32) -> Vec<f32> {
+    let sample_count = (seconds * SAMPLE_RATE as f32) as usize;
+    (0..sample_count)
+        .map(|index| {
+            let progress = index as f32 / sample_count as f32;
+            let time = index as f32 / SAMPLE_RATE as f32;
+            (2.0 * PI * hz * time).sin() * amplitude * (1.0 - progress).powi(2)
+        })
+        .collect()
+}
+
+/// A low thump, the pitch dropping fast.
+fn kick() -> Vec<f32> {
+    let sample_count = (0.12 * SAMPLE_RATE as f32) as usize;
+    let mut phase = 0.0;
+    (0..sample_count)
+        .map(|index| {
+            let progress = index as f32 / sample_count as f32;
+            phase += 2.0 * PI * (120.0 - 80.0 * progress) / SAMPLE_RATE as f32;
+            phase.sin() * 0.4 * (1.0 - progress)
+        })
+        .collect()
+}
+
+fn mix(bar: &mut [f32], start_seconds: f32, samples: &[f32]) {
+    let start = (start_seconds * SAMPLE_RATE as f32) as usize;
+    for (sample, added) in bar.iter_mut().skip(start).zip(samples) {
+        *sample += added;
+    }
+}
+
+/// Handles of the bars, in the order of `Phase::ALL`.
+#[derive(Default)]
+pub struct Music(Vec<Handle<AudioSource>>);
+
+pub fn make_music(
+    audio_settings: Res<AudioSettings>,
+    mut audio_sources: ResMut<Assets<AudioSource>>,
+    mut music: ResMut<Music>,
+) {
+    if !audio_settings.is_changed() {
+        return;
+    }
+    music.0 = Phase::ALL
+        .iter()
+        .map(|phase| {
+            audio_sources.add(AudioSource {
+                bytes: wav(&phase.bar(), audio_settings.volume).into(),
+            })
+        })
+        .collect();
+}
+
+/// Plays the bar of the match's phase whenever the previous one ends.
+pub fn play_music(
+    mut next_bar_at: Local<f64>,
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    audio_settings: Res<AudioSettings>,
+    music: Res<Music>,
+    audio: Res<Audio>,
+) {
+    let phase = displayed_game.state.as_ref().and_then(Phase::of);
+    let now = time.seconds_since_startup();
+    let phase = match phase {
+        Some(phase) if audio_settings.music && audio_settings.volume > 0.0 => phase,
+        // the bar being played finishes on its own
+        _ => return,
+    };
+    if now < *next_bar_at {
+        return;
+    }
+    if let Some(bar) = music.0.get(phase as usize) {
+        audio.play(bar.clone());
+    }
+    // a late frame doesn't push the following bars back, a pause in the music does
+    *next_bar_at = if now - *next_bar_at < BAR_SECONDS {
+        *next_bar_at + BAR_SECONDS
+    } else {
+        now + BAR_SECONDS
+    };
+}
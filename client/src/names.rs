@@ -0,0 +1,87 @@
+use platformer_shared::{bevy::prelude::*, game::PlayerId, settings::GraphicsSettings};
+
+use crate::{update_transform, DisplayedGame, UI_FONT};
+
+// Player names, sent to the server when connecting and shown above the players and in the HUD
+
+// above the players, under the foreground layer
+const NAME_TAG_Z: f32 = 9.0;
+const NAME_TAG_OFFSET: f32 = 12.0;
+
+/// Name the local player picked.
+pub struct PlayerName(pub String);
+
+/// Names of both players as broadcast by the server.
+#[derive(Default)]
+pub struct PlayerNames(pub [Option<String>; 2]);
+
+impl PlayerNames {
+    /// The player's name, or their id if they didn't pick one.
+    pub fn get(&self, player_id: PlayerId) -> String {
+        self.0[player_id.as_usize()]
+            .clone()
+            .unwrap_or_else(|| player_id.to_string())
+    }
+}
+
+pub struct NameTag(PlayerId);
+
+pub fn setup_name_tags(mut commands: Commands, asset_server: Res<AssetServer>) {
+    for player_id in [PlayerId::Player1, PlayerId::Player2].iter() {
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(UI_FONT),
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Bottom,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, NAME_TAG_Z),
+                ..Default::default()
+            })
+            .insert(NameTag(*player_id));
+    }
+}
+
+pub fn name_tag_view(
+    displayed_game: Res<DisplayedGame>,
+    names: Res<PlayerNames>,
+    graphics: Res<GraphicsSettings>,
+    mut q: Query<(&NameTag, &mut Text, &mut Transform, &mut Visible)>,
+) {
+    for (name_tag, mut text, mut transform, mut visible) in q.iter_mut() {
+        let display_state = match &displayed_game.state {
+            Some(display_state) if graphics.name_tags => display_state,
+            _ => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        let (position, character) = match name_tag.0 {
+            PlayerId::Player1 => (
+                display_state.player1_position,
+                display_state.player1_character,
+            ),
+            PlayerId::Player2 => (
+                display_state.player2_position,
+                display_state.player2_character,
+            ),
+        };
+
+        visible.is_visible = true;
+        let name = names.get(name_tag.0);
+        if text.sections[0].value != name {
+            text.sections[0].value = name;
+        }
+        let position = position.translation.vector;
+        update_transform(&mut transform, position.x, position.y, &displayed_game);
+        // above the player on screen, whichever way the world is flipped
+        transform.translation.y += character.stats().size / 2.0 + NAME_TAG_OFFSET;
+    }
+}
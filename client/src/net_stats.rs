@@ -0,0 +1,144 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+    lobby::LobbyMessage, settings::NetworkSettings,
+};
+
+use crate::debug::RollbackError;
+
+// Connection statistics shown in the debug overlay. Turbulence only counts packets and bytes per
+// connection, so the bandwidth covers all channels together, and the snapshots, which make up
+// most of the traffic from the server, are timed by the last packet heard.
+
+const RATE_PERIOD_SECONDS: f64 = 1.0;
+/// Rollback errors above this many pixels count as corrections.
+const CORRECTION_THRESHOLD: f32 = 1.0;
+
+#[derive(Default)]
+pub struct NetStats {
+    /// Round trip time of the last ping, in seconds.
+    pub rtt: Option<f64>,
+    /// Share of the packets lost on the way to the server since the previous ping.
+    pub upload_loss: Option<f32>,
+    /// Share of the packets lost on the way from the server since the previous ping.
+    pub download_loss: Option<f32>,
+    pub bytes_sent_per_second: f32,
+    pub bytes_received_per_second: f32,
+    /// Seconds since the last packet from the server.
+    pub snapshot_age: Option<f64>,
+    /// Corrections of the local prediction within the rollback error window.
+    pub corrections: usize,
+    last_pong: Option<PacketCounts>,
+    last_rate_sample: Option<(f64, usize, usize)>,
+    last_heard: Option<(f64, usize)>,
+}
+
+/// Packets received and sent by both ends, at the time of a pong.
+#[derive(Clone, Copy)]
+struct PacketCounts {
+    server_received: u64,
+    server_sent: u64,
+    client_received: u64,
+    client_sent: u64,
+}
+
+fn loss(sent: u64, received: u64) -> Option<f32> {
+    if sent == 0 {
+        None
+    } else {
+        Some((1.0 - received as f32 / sent as f32).max(0.0))
+    }
+}
+
+impl NetStats {
+    pub fn record_pong(
+        &mut self,
+        now: f64,
+        sent_at: f64,
+        packets_received: u64,
+        packets_sent: u64,
+        local_packets_received: u64,
+        local_packets_sent: u64,
+    ) {
+        self.rtt = Some(now - sent_at);
+        let counts = PacketCounts {
+            server_received: packets_received,
+            server_sent: packets_sent,
+            client_received: local_packets_received,
+            client_sent: local_packets_sent,
+        };
+        if let Some(last) = self.last_pong {
+            self.upload_loss = loss(
+                counts.client_sent.saturating_sub(last.client_sent),
+                counts.server_received.saturating_sub(last.server_received),
+            );
+            self.download_loss = loss(
+                counts.server_sent.saturating_sub(last.server_sent),
+                counts.client_received.saturating_sub(last.client_received),
+            );
+        }
+        self.last_pong = Some(counts);
+    }
+}
+
+/// Doubles as the keepalive, the server's answers keep the connection from going silent.
+pub fn send_pings(
+    time: Res<Time>,
+    network_settings: Res<NetworkSettings>,
+    mut last_ping: Local<Option<f64>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let now = time.seconds_since_startup();
+    if net.connections.is_empty()
+        || matches!(*last_ping, Some(last_ping) if now - last_ping < network_settings.keepalive_seconds)
+    {
+        return;
+    }
+    net.broadcast_message(LobbyMessage::Ping { sent_at: now });
+    *last_ping = Some(now);
+}
+
+pub fn update_net_stats(
+    time: Res<Time>,
+    net: Res<NetworkResource>,
+    rollback_error: Res<RollbackError>,
+    mut stats: ResMut<NetStats>,
+) {
+    let now = time.seconds_since_startup();
+    let connection_stats = match net.connections.values().next() {
+        Some(connection) => connection.stats(),
+        None => {
+            if stats.last_heard.is_some() {
+                *stats = NetStats::default();
+            }
+            return;
+        }
+    };
+
+    match stats.last_heard {
+        Some((_, packets)) if packets == connection_stats.packets_rx => (),
+        _ => stats.last_heard = Some((now, connection_stats.packets_rx)),
+    }
+    stats.snapshot_age = stats.last_heard.map(|(heard_at, _)| now - heard_at);
+
+    match stats.last_rate_sample {
+        Some((sampled_at, bytes_sent, bytes_received))
+            if now - sampled_at >= RATE_PERIOD_SECONDS =>
+        {
+            let elapsed = (now - sampled_at) as f32;
+            stats.bytes_sent_per_second =
+                connection_stats.bytes_tx.saturating_sub(bytes_sent) as f32 / elapsed;
+            stats.bytes_received_per_second =
+                connection_stats.bytes_rx.saturating_sub(bytes_received) as f32 / elapsed;
+            stats.last_rate_sample =
+                Some((now, connection_stats.bytes_tx, connection_stats.bytes_rx));
+        }
+        Some(_) => (),
+        None => {
+            stats.last_rate_sample =
+                Some((now, connection_stats.bytes_tx, connection_stats.bytes_rx))
+        }
+    }
+
+    stats.corrections = rollback_error.count_above(CORRECTION_THRESHOLD);
+}
@@ -0,0 +1,289 @@
+use platformer_shared::{
+    bevy::{prelude::*, ui::FocusPolicy},
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+};
+use std::collections::VecDeque;
+
+use crate::{debug::RollbackError, net_stats::NetStats, session::ClientConfig, UI_FONT};
+
+// Scrolling graphs of the netcode, a column per frame, toggled with F5: the gaps between the
+// snapshots, how deep the client rolls back when one arrives and how far the correction moved the
+// local player. Meant for watching crystalorb while tuning its settings.
+//
+// Crystalorb doesn't tell how many ticks it simulated again, so the depth is estimated as the time
+// the client runs ahead of the snapshot it just received: half the round trip plus the lag
+// compensation latency.
+
+const COLUMNS: usize = 150;
+const COLUMN_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 40.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Graph {
+    SnapshotGap,
+    RollbackDepth,
+    PredictionError,
+}
+
+impl Graph {
+    const ALL: [Graph; 3] = [
+        Graph::SnapshotGap,
+        Graph::RollbackDepth,
+        Graph::PredictionError,
+    ];
+
+    /// Value at the top of the graph, higher ones are cut off.
+    fn scale(self) -> f32 {
+        match self {
+            Graph::SnapshotGap => 200.0,
+            Graph::RollbackDepth => 30.0,
+            Graph::PredictionError => 20.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Graph::SnapshotGap => Color::CYAN,
+            Graph::RollbackDepth => Color::ORANGE,
+            Graph::PredictionError => Color::PINK,
+        }
+    }
+
+    fn label(self, latest: f32, peak: f32) -> String {
+        match self {
+            Graph::SnapshotGap => format!("snapshot gap: {:.0} ms (max {:.0})", latest, peak),
+            Graph::RollbackDepth => {
+                format!("rollback depth: {:.0} ticks (max {:.0})", latest, peak)
+            }
+            Graph::PredictionError => {
+                format!("prediction error: {:.1} px (max {:.1})", latest, peak)
+            }
+        }
+    }
+}
+
+/// A frame's column: zero for the first two unless a snapshot arrived during it.
+#[derive(Clone, Copy, Default)]
+struct Sample {
+    /// Milliseconds since the previous snapshot.
+    snapshot_gap: f32,
+    /// Estimated ticks simulated again from the snapshot.
+    rollback_depth: f32,
+    /// Pixels a snapshot moved the local player from where it was predicted.
+    prediction_error: f32,
+}
+
+impl Sample {
+    fn value(&self, graph: Graph) -> f32 {
+        match graph {
+            Graph::SnapshotGap => self.snapshot_gap,
+            Graph::RollbackDepth => self.rollback_depth,
+            Graph::PredictionError => self.prediction_error,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NetGraph {
+    pub visible: bool,
+    /// Newest first.
+    samples: VecDeque<Sample>,
+    /// When the last snapshot arrived, and the packets received by then.
+    last_heard: Option<(f64, usize)>,
+    /// The prediction error sample already graphed.
+    last_error_at: Option<f64>,
+}
+
+impl NetGraph {
+    /// Latest nonzero value and the highest one on the graph.
+    fn summary(&self, graph: Graph) -> (f32, f32) {
+        let values = self.samples.iter().map(|sample| sample.value(graph));
+        let latest = values.clone().find(|value| *value > 0.0).unwrap_or(0.0);
+        let peak = values.fold(0.0, f32::max);
+        (latest, peak)
+    }
+}
+
+/// Every entity of the graphs, hidden together.
+pub struct NetGraphPart;
+
+pub struct NetGraphColumn {
+    graph: Graph,
+    /// Frames ago, counted from the right.
+    age: usize,
+}
+
+pub struct NetGraphLabel(Graph);
+
+pub fn setup_netgraph(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+    let background = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(160.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            visible: hidden.clone(),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        })
+        .insert(NetGraphPart)
+        .with_children(|parent| {
+            for graph in Graph::ALL.iter() {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 14.0,
+                                color: graph.color(),
+                            },
+                            Default::default(),
+                        ),
+                        visible: hidden.clone(),
+                        ..Default::default()
+                    })
+                    .insert(NetGraphPart)
+                    .insert(NetGraphLabel(*graph));
+
+                let column_material = materials.add(graph.color().into());
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(
+                                Val::Px(COLUMNS as f32 * COLUMN_WIDTH),
+                                Val::Px(GRAPH_HEIGHT),
+                            ),
+                            // the columns stand on the bottom, y pointing up
+                            align_items: AlignItems::FlexStart,
+                            margin: Rect {
+                                bottom: Val::Px(4.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        material: background.clone(),
+                        visible: hidden.clone(),
+                        focus_policy: FocusPolicy::Pass,
+                        ..Default::default()
+                    })
+                    .insert(NetGraphPart)
+                    .with_children(|parent| {
+                        for column in 0..COLUMNS {
+                            parent
+                                .spawn_bundle(NodeBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(COLUMN_WIDTH), Val::Px(0.0)),
+                                        ..Default::default()
+                                    },
+                                    material: column_material.clone(),
+                                    visible: hidden.clone(),
+                                    focus_policy: FocusPolicy::Pass,
+                                    ..Default::default()
+                                })
+                                .insert(NetGraphPart)
+                                .insert(NetGraphColumn {
+                                    graph: *graph,
+                                    age: COLUMNS - 1 - column,
+                                });
+                        }
+                    });
+            }
+        });
+}
+
+pub fn toggle_netgraph(input: Res<Input<KeyCode>>, mut netgraph: ResMut<NetGraph>) {
+    if input.just_pressed(KeyCode::F5) {
+        netgraph.visible = !netgraph.visible;
+    }
+}
+
+pub fn record_netgraph(
+    time: Res<Time>,
+    net: Res<NetworkResource>,
+    net_stats: Res<NetStats>,
+    config: Res<ClientConfig>,
+    rollback_error: Res<RollbackError>,
+    mut netgraph: ResMut<NetGraph>,
+) {
+    let now = time.seconds_since_startup();
+    let packets_received = match net.connections.values().next() {
+        Some(connection) => connection.stats().packets_rx,
+        None => {
+            netgraph.last_heard = None;
+            return;
+        }
+    };
+
+    let mut sample = Sample::default();
+    // as in the debug overlay, every packet from the server counts as a snapshot
+    match netgraph.last_heard {
+        Some((_, packets)) if packets == packets_received => (),
+        last_heard => {
+            if let Some((heard_at, _)) = last_heard {
+                sample.snapshot_gap = ((now - heard_at) * 1000.0) as f32;
+                let ahead = net_stats.rtt.unwrap_or(0.0) / 2.0 + config.0.lag_compensation_latency;
+                sample.rollback_depth = (ahead / config.0.timestep_seconds).round() as f32;
+            }
+            netgraph.last_heard = Some((now, packets_received));
+        }
+    }
+    if let Some((sampled_at, error)) = rollback_error.latest() {
+        if netgraph.last_error_at != Some(sampled_at) {
+            sample.prediction_error = error;
+            netgraph.last_error_at = Some(sampled_at);
+        }
+    }
+
+    netgraph.samples.push_front(sample);
+    netgraph.samples.truncate(COLUMNS);
+}
+
+pub fn update_netgraph(
+    netgraph: Res<NetGraph>,
+    mut parts: Query<&mut Visible, With<NetGraphPart>>,
+    mut columns: Query<(&mut Style, &NetGraphColumn)>,
+    mut labels: Query<(&mut Text, &NetGraphLabel)>,
+) {
+    if !netgraph.is_changed() {
+        return;
+    }
+    for mut visible in parts.iter_mut() {
+        if visible.is_visible != netgraph.visible {
+            visible.is_visible = netgraph.visible;
+        }
+    }
+    if !netgraph.visible {
+        return;
+    }
+
+    for (mut style, column) in columns.iter_mut() {
+        let value = netgraph
+            .samples
+            .get(column.age)
+            .map_or(0.0, |sample| sample.value(column.graph));
+        let height = (value / column.graph.scale()).min(1.0) * GRAPH_HEIGHT;
+        style.size.height = Val::Px(height);
+    }
+    for (mut text, label) in labels.iter_mut() {
+        let (latest, peak) = netgraph.summary(label.0);
+        text.sections[0].value = label.0.label(latest, peak);
+    }
+}
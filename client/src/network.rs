@@ -0,0 +1,91 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{self, ConnectionHandle, NetworkResource},
+        crystalorb::{
+            client::Client,
+            clocksync::ClockSyncMessage,
+            network_resource::{self, Connection},
+            timestamp::Timestamped,
+        },
+    },
+    game::{GameCommand, GameSnapshot, GameWorld},
+    net,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+// The client's network, as crystalorb sees it: the crystalorb plugin's, only with the snapshots
+// and commands encoded with `DefaultCodec` on their way through the channels, like the server's.
+
+pub struct EncodingNetwork<'n>(pub &'n mut NetworkResource);
+
+impl<'n> network_resource::NetworkResource<GameWorld> for EncodingNetwork<'n> {
+    type ConnectionType<'a> = EncodingConnection<'a>;
+
+    fn get_connection(&mut self, handle: usize) -> Option<Self::ConnectionType<'_>> {
+        self.0
+            .connections
+            .get_mut(&(handle as ConnectionHandle))
+            .map(EncodingConnection)
+    }
+
+    fn connections<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = (usize, Self::ConnectionType<'a>)> + 'a> {
+        Box::new(
+            self.0
+                .connections
+                .iter_mut()
+                .map(|(handle, connection)| (*handle as usize, EncodingConnection(connection))),
+        )
+    }
+}
+
+pub struct EncodingConnection<'a>(&'a mut Box<dyn bevy_networking_turbulence::Connection>);
+
+impl Connection<GameWorld> for EncodingConnection<'_> {
+    fn recv_command(&mut self) -> Option<Timestamped<GameCommand>> {
+        net::recv_command(self.0.channels()?)
+    }
+
+    fn recv_snapshot(&mut self) -> Option<Timestamped<GameSnapshot>> {
+        net::recv_snapshot(self.0.channels()?)
+    }
+
+    fn recv_clock_sync(&mut self) -> Option<ClockSyncMessage> {
+        self.0.channels()?.recv()
+    }
+
+    fn send<MessageType>(&mut self, message: MessageType) -> Option<MessageType>
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        match self.0.channels() {
+            Some(channels) => net::send_message(channels, message),
+            None => Some(message),
+        }
+    }
+
+    fn flush<MessageType>(&mut self)
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        if let Some(channels) = self.0.channels() {
+            net::flush_messages::<MessageType>(channels);
+        }
+    }
+}
+
+/// Updates the client the way the crystalorb plugin would, only through the encoding network.
+pub fn update_client(
+    time: Res<Time>,
+    mut client: ResMut<Client<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    client.update(
+        time.delta_seconds_f64(),
+        time.seconds_since_startup(),
+        &mut EncodingNetwork(&mut *net),
+    );
+}
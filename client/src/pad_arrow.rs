@@ -0,0 +1,155 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{GameEvent, PlayerId, PowerPadStatus},
+};
+use std::f32::consts::PI;
+
+use crate::DisplayedGame;
+
+// A fading arrow from where a pad was to the side it sets off towards, once the opponent captures
+// theirs, so that the player who lost the advantage sees at once where their pad is going.
+
+const ARROW_SECONDS: f64 = 1.5;
+const ARROW_ALPHA: f32 = 0.6;
+/// Above the pads, below their capture progress.
+const PAD_ARROW_Z: f32 = 3.5;
+const SHAFT_WIDTH: f32 = 4.0;
+const HEAD_LENGTH: f32 = 24.0;
+
+#[derive(Clone, Copy)]
+pub enum PadArrowPart {
+    Shaft,
+    /// One of the two strokes of the head, on the given side of the shaft.
+    Head(f32),
+}
+
+#[derive(Default)]
+pub struct PadArrowState {
+    arrowed_until: Option<u32>,
+    /// The pad's owner, where it was and where it's going, and when it set off.
+    arrow: Option<(PlayerId, Vec2, Vec2, f64)>,
+}
+
+pub fn setup_pad_arrow(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    for part in [
+        PadArrowPart::Shaft,
+        PadArrowPart::Head(1.0),
+        PadArrowPart::Head(-1.0),
+    ]
+    .iter()
+    {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(Color::NONE.into()),
+                sprite: Sprite::new(Vec2::new(0.0, SHAFT_WIDTH)),
+                transform: Transform::from_xyz(0.0, 0.0, PAD_ARROW_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(*part);
+    }
+}
+
+pub fn pad_arrow_view(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut state: Local<PadArrowState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(
+        &PadArrowPart,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visible,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let now = time.seconds_since_startup();
+    match &displayed_game.state {
+        Some(display_state) => {
+            // the events from before joining or reconnecting are left out
+            let since = match state.arrowed_until {
+                Some(since) if since <= display_state.tick => since,
+                _ => display_state.tick,
+            };
+            state.arrowed_until = Some(display_state.tick);
+            for (tick, event) in display_state.events.iter() {
+                if let GameEvent::PadPatrolStarted(player_id, towards) = event {
+                    if *tick > since {
+                        let (position, positions) = match player_id {
+                            PlayerId::Player1 => (
+                                display_state.bottom_power_pad_position,
+                                displayed_game.map.power_pad_positions(false),
+                            ),
+                            PlayerId::Player2 => (
+                                display_state.top_power_pad_position,
+                                displayed_game.map.power_pad_positions(true),
+                            ),
+                        };
+                        let to = match towards {
+                            PowerPadStatus::Left => positions.left,
+                            PowerPadStatus::Right => positions.right,
+                        };
+                        let from =
+                            Vec2::new(position.translation.vector.x, position.translation.vector.y);
+                        state.arrow = Some((*player_id, from, Vec2::new(to.x, to.y), now));
+                    }
+                }
+            }
+            // a new round puts the pads back
+            if display_state.round_result.is_some() {
+                state.arrow = None;
+            }
+        }
+        None => {
+            state.arrowed_until = None;
+            state.arrow = None;
+        }
+    }
+
+    let arrow = state.arrow.and_then(|(player_id, from, to, started_at)| {
+        let progress = (now - started_at) / ARROW_SECONDS;
+        if progress < 1.0 {
+            let mut color = match player_id {
+                PlayerId::Player1 => Color::BLUE,
+                PlayerId::Player2 => Color::RED,
+            };
+            color.set_a(ARROW_ALPHA * (1.0 - progress as f32));
+            Some((from, to, color))
+        } else {
+            None
+        }
+    });
+    for (part, mut transform, mut sprite, mut visible, material) in query.iter_mut() {
+        let (from, to, color) = match arrow {
+            Some(arrow) => arrow,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        visible.is_visible = true;
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+
+        // drawn in view coordinates, so that the arrow turns with a flipped world
+        let from = Vec2::from(displayed_game.view_position(from.x, from.y));
+        let to = Vec2::from(displayed_game.view_position(to.x, to.y));
+        let angle = (to.y - from.y).atan2(to.x - from.x);
+        let (center, length, angle) = match part {
+            PadArrowPart::Shaft => ((from + to) / 2.0, from.distance(to), angle),
+            PadArrowPart::Head(side) => {
+                // pointing back from the tip, an eighth of a turn off the shaft
+                let angle = angle + PI + side * PI / 4.0;
+                let center = to + Vec2::new(angle.cos(), angle.sin()) * HEAD_LENGTH / 2.0;
+                (center, HEAD_LENGTH, angle)
+            }
+        };
+        transform.translation = Vec3::new(center.x, center.y, transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(angle);
+        sprite.size = Vec2::new(length, SHAFT_WIDTH);
+    }
+}
@@ -0,0 +1,98 @@
+use platformer_shared::bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{net_stats::NetStats, UI_FONT};
+
+// Frame rate and round trip time in the bottom left corner, toggled with F2, to tell a slow
+// machine from a slow connection at a glance.
+
+/// Frame rate below which the counter turns yellow.
+const LOW_FPS: f64 = 50.0;
+/// Round trip time in seconds above which the counter turns yellow.
+const HIGH_RTT: f64 = 0.15;
+
+#[derive(Default)]
+pub struct PerfOverlay {
+    pub visible: bool,
+}
+
+pub struct PerfOverlayText;
+
+pub fn setup_perf_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load(UI_FONT),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: String::new(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style,
+                    },
+                ],
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(PerfOverlayText);
+}
+
+pub fn toggle_perf_overlay(input: Res<Input<KeyCode>>, mut overlay: ResMut<PerfOverlay>) {
+    if input.just_pressed(KeyCode::F2) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+pub fn update_perf_overlay(
+    overlay: Res<PerfOverlay>,
+    diagnostics: Res<Diagnostics>,
+    net_stats: Option<Res<NetStats>>,
+    mut query: Query<(&mut Text, &mut Visible), With<PerfOverlayText>>,
+) {
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average());
+    // the tutorial runs without a server
+    let rtt = net_stats.as_ref().map(|net_stats| net_stats.rtt);
+    let warning = |bad: bool| if bad { Color::YELLOW } else { Color::WHITE };
+
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = overlay.visible;
+        if !overlay.visible {
+            continue;
+        }
+        text.sections[0].value = match fps {
+            Some(fps) => format!("{:.0} fps", fps),
+            None => "- fps".to_string(),
+        };
+        text.sections[0].style.color = warning(fps.map_or(false, |fps| fps < LOW_FPS));
+        text.sections[1].value = match rtt {
+            Some(Some(rtt)) => format!("  {:.0} ms", rtt * 1000.0),
+            Some(None) => "  - ms".to_string(),
+            None => "  offline".to_string(),
+        };
+        text.sections[1].style.color = warning(matches!(rtt, Some(Some(rtt)) if rtt > HIGH_RTT));
+    }
+}
@@ -0,0 +1,172 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{AdvantageState, GameDisplayState},
+    LevelElementKind, MapDefinition, HAZARD_CANNON_SIZE, POWER_PAD_SIZE, PROJECTILE_SIZE,
+};
+
+// Software rendering of the arena for the clips and screenshots, which bevy 0.5 can't read back
+// from the screen. Frames are drawn from display states as plain rectangles, one palette index
+// per pixel: the level, the pads, the cannon, the projectiles and the players, the right way up
+// for the first player.
+
+/// Indices into `PALETTE`.
+const BACKGROUND: u8 = 0;
+const PLATFORM: u8 = 1;
+const LAVA: u8 = 2;
+const DECORATION: u8 = 3;
+const PLAYER1: u8 = 4;
+const PLAYER2: u8 = 5;
+const PROJECTILE: u8 = 6;
+const PAD: u8 = 7;
+const CANNON: u8 = 8;
+
+pub const PALETTE: [u8; 27] = [
+    0, 0, 0, // background
+    255, 255, 255, // platform
+    255, 165, 0, // lava
+    64, 64, 64, // decoration
+    0, 0, 255, // player 1
+    255, 0, 0, // player 2
+    255, 255, 0, // projectile
+    0, 200, 0, // pad
+    255, 69, 0, // cannon
+];
+
+/// What a frame draws, taken from a display state.
+#[derive(Clone)]
+pub struct Frame {
+    /// Position and size of each player.
+    players: [(Vec2, f32); 2],
+    pads: [Vec2; 2],
+    cannon_x: f32,
+    /// Color of the cannon's outline, while a player holds the advantage.
+    advantage: Option<u8>,
+    projectiles: Vec<Vec2>,
+}
+
+impl Frame {
+    pub fn of(display_state: &GameDisplayState) -> Self {
+        let player1 = display_state.player1_position.translation.vector;
+        let player2 = display_state.player2_position.translation.vector;
+        let bottom_pad = display_state.bottom_power_pad_position.translation.vector;
+        let top_pad = display_state.top_power_pad_position.translation.vector;
+        Self {
+            players: [
+                (
+                    Vec2::new(player1.x, player1.y),
+                    display_state.player1_character.stats().size,
+                ),
+                (
+                    Vec2::new(player2.x, player2.y),
+                    display_state.player2_character.stats().size,
+                ),
+            ],
+            pads: [
+                Vec2::new(bottom_pad.x, bottom_pad.y),
+                Vec2::new(top_pad.x, top_pad.y),
+            ],
+            cannon_x: display_state.cannon_x_position,
+            advantage: match display_state.advantage_state {
+                AdvantageState::Neutral => None,
+                AdvantageState::Player1 => Some(PLAYER1),
+                AdvantageState::Player2 => Some(PLAYER2),
+            },
+            projectiles: display_state
+                .projectile_positions
+                .values()
+                .map(|position| {
+                    Vec2::new(position.translation.vector.x, position.translation.vector.y)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Image of the arena, rows top down.
+#[derive(Clone)]
+pub struct Raster {
+    pub width: u16,
+    pub height: u16,
+    /// Display units per pixel.
+    scale: f32,
+    map: MapDefinition,
+    /// Indices into `PALETTE`.
+    pub pixels: Vec<u8>,
+}
+
+impl Raster {
+    /// The level of the map alone, the background of every frame.
+    pub fn level(map: &MapDefinition, scale: f32) -> Self {
+        let width = (map.width / scale) as u16;
+        let height = (map.height / scale) as u16;
+        let mut raster = Self {
+            width,
+            height,
+            scale,
+            map: *map,
+            pixels: vec![BACKGROUND; width as usize * height as usize],
+        };
+        for element in map.level.iter() {
+            let color = match element.kind {
+                LevelElementKind::Platform => PLATFORM,
+                LevelElementKind::Lava => LAVA,
+                LevelElementKind::Decoration { .. } => DECORATION,
+            };
+            let size = Vec2::new(element.rect.w, element.rect.h);
+            let (mirrored_x, mirrored_y) = map.mirror(element.rect.x, element.rect.y);
+            raster.fill_rect(Vec2::new(element.rect.x, element.rect.y), size, color);
+            raster.fill_rect(Vec2::new(mirrored_x, mirrored_y), size, color);
+        }
+        let size = Vec2::new(HAZARD_CANNON_SIZE.w, HAZARD_CANNON_SIZE.h);
+        for top_half in [false, true].iter() {
+            let position = map.hazard_cannon_position(*top_half);
+            raster.fill_rect(Vec2::new(position.x, position.y), size, DECORATION);
+        }
+        raster
+    }
+
+    pub fn draw(&mut self, frame: &Frame) {
+        for pad in frame.pads.iter() {
+            self.fill_rect(*pad, Vec2::new(POWER_PAD_SIZE.w, POWER_PAD_SIZE.h), PAD);
+        }
+
+        let cannon = Vec2::new(frame.cannon_x, self.map.center().y);
+        if let Some(color) = frame.advantage {
+            self.fill_rect(cannon, Vec2::new(52.0, 52.0), color);
+        }
+        self.fill_rect(cannon, Vec2::new(40.0, 40.0), CANNON);
+
+        for projectile in frame.projectiles.iter() {
+            self.fill_rect(
+                *projectile,
+                Vec2::new(PROJECTILE_SIZE.w, PROJECTILE_SIZE.h),
+                PROJECTILE,
+            );
+        }
+
+        for ((position, size), color) in frame.players.iter().zip([PLAYER1, PLAYER2].iter()) {
+            self.fill_rect(*position, Vec2::new(*size, *size), *color);
+        }
+    }
+
+    /// Fills a rectangle given by its center and size in display units, clipped to the image.
+    fn fill_rect(&mut self, center: Vec2, size: Vec2, color: u8) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let min = (center - size / 2.0) / self.scale;
+        let max = (center + size / 2.0) / self.scale;
+        // images go top down
+        let (left, right) = (
+            (min.x.round() as i32).max(0),
+            (max.x.round() as i32).min(width),
+        );
+        let (top, bottom) = (
+            (height - max.y.round() as i32).max(0),
+            (height - min.y.round() as i32).min(height),
+        );
+        for y in top..bottom {
+            for x in left..right {
+                self.pixels[(y * width + x) as usize] = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    conditions::NetworkConditions,
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::{
+        NetworkEvent, NetworkResource,
+    },
+};
+
+use crate::{
+    connect::{self, ConnectScreen},
+    UI_FONT,
+};
+
+// Reconnecting on its own after the connection drops, waiting longer after every failed attempt.
+// The server keeps the seat meanwhile, and the seat token sent in the greeting reclaims it.
+// Connections that never came up bring the connect screen back instead.
+
+const FIRST_RETRY_SECONDS: f64 = 1.0;
+const MAX_RETRY_SECONDS: f64 = 16.0;
+/// Attempts before giving up and going back to the connect screen.
+const MAX_ATTEMPTS: u32 = 8;
+
+#[derive(Default)]
+pub struct Reconnect {
+    /// Set once the connection was up, only those are reconnected.
+    was_connected: bool,
+    /// Attempts made since the connection dropped.
+    attempts: u32,
+    next_attempt_at: Option<f64>,
+}
+
+impl Reconnect {
+    pub fn is_reconnecting(&self) -> bool {
+        self.attempts > 0 || self.next_attempt_at.is_some()
+    }
+
+    /// Schedules the next attempt, or brings the connect screen back with `error` when the
+    /// connection never came up or the attempts ran out.
+    pub fn connection_lost(&mut self, now: f64, screen: &mut ConnectScreen, error: String) {
+        // the player already went back to the screen, or an attempt is due anyway
+        if screen.server().is_none() || self.next_attempt_at.is_some() {
+            return;
+        }
+        if !self.was_connected {
+            screen.show_error(error);
+        } else if self.attempts >= MAX_ATTEMPTS {
+            *self = Reconnect::default();
+            screen.show_error(format!("Couldn't reconnect: {}", error));
+        } else {
+            let delay = FIRST_RETRY_SECONDS * 2f64.powi(self.attempts as i32);
+            self.next_attempt_at = Some(now + delay.min(MAX_RETRY_SECONDS));
+        }
+    }
+}
+
+pub struct ReconnectText;
+
+pub fn setup_reconnect_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(30.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(UI_FONT),
+                            font_size: 32.0,
+                            color: Color::YELLOW,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(ReconnectText);
+        });
+}
+
+/// Tells dropped connections from those that never came up.
+pub fn watch_connection(
+    time: Res<Time>,
+    mut events: EventReader<NetworkEvent>,
+    mut reconnect: ResMut<Reconnect>,
+    mut screen: ResMut<ConnectScreen>,
+) {
+    let now = time.seconds_since_startup();
+    for event in events.iter() {
+        match event {
+            NetworkEvent::Connected(_) => {
+                if reconnect.is_reconnecting() {
+                    info!("Reconnected");
+                }
+                reconnect.was_connected = true;
+                reconnect.attempts = 0;
+            }
+            NetworkEvent::Disconnected(_) => reconnect.connection_lost(
+                now,
+                &mut screen,
+                "Disconnected from the server".to_string(),
+            ),
+            NetworkEvent::Error(_, error) => reconnect.connection_lost(
+                now,
+                &mut screen,
+                format!("Connection failed: {:?}", error),
+            ),
+            _ => (),
+        }
+    }
+}
+
+pub fn retry_connection(
+    time: Res<Time>,
+    conditions: Res<NetworkConditions>,
+    mut reconnect: ResMut<Reconnect>,
+    mut screen: ResMut<ConnectScreen>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let server = match screen.server() {
+        Some(server) => server,
+        None => {
+            // connecting again from the screen starts over
+            if reconnect.was_connected {
+                *reconnect = Reconnect::default();
+            }
+            return;
+        }
+    };
+    match reconnect.next_attempt_at {
+        Some(attempt_at) if time.seconds_since_startup() >= attempt_at => (),
+        _ => return,
+    }
+    reconnect.next_attempt_at = None;
+    reconnect.attempts += 1;
+    info!(
+        "Reconnecting to {}, attempt {} of {}",
+        server, reconnect.attempts, MAX_ATTEMPTS
+    );
+    // whatever is left of the dropped connection goes first
+    let handles: Vec<_> = net.connections.keys().copied().collect();
+    for handle in handles {
+        net.disconnect(handle);
+    }
+    connect::connect(&mut screen, &mut net, &conditions, server);
+}
+
+pub fn update_reconnect_overlay(
+    reconnect: Res<Reconnect>,
+    screen: Res<ConnectScreen>,
+    mut query: Query<(&mut Text, &mut Visible), With<ReconnectText>>,
+) {
+    if !reconnect.is_changed() && !screen.is_changed() {
+        return;
+    }
+    let reconnecting = reconnect.is_reconnecting() && screen.server().is_some();
+    for (mut text, mut visible) in query.iter_mut() {
+        visible.is_visible = reconnecting;
+        text.sections[0].value = format!(
+            "Reconnecting... attempt {} of {}",
+            reconnect.attempts.max(1),
+            MAX_ATTEMPTS
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use platformer_shared::bevy::prelude::*;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    raster::{Frame, Raster, PALETTE},
+    DisplayedGame,
+};
+
+// Screenshots for bug reports, saved with F12 as BMP files named after the time they were taken.
+// Bevy 0.5 can't read a frame back from the window, so the screenshot isn't the framebuffer: it's
+// the display state of the frame re-rendered by the software renderer, at the arena's full size.
+// It shows where the client drew everything, predictions and all, as plain shapes without the
+// sprites, the HUD or the effects.
+
+const SCREENSHOTS_DIRECTORY: &str = "screenshots";
+/// Display units per pixel of the screenshot.
+const SCREENSHOT_SCALE: f32 = 1.0;
+
+pub fn save_screenshot(input: Res<Input<KeyCode>>, displayed_game: Res<DisplayedGame>) {
+    if !input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let frame = match &displayed_game.state {
+        Some(display_state) => Frame::of(display_state),
+        None => return,
+    };
+
+    // in milliseconds, several can be taken in a second
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let path = PathBuf::from(SCREENSHOTS_DIRECTORY).join(format!("{}.bmp", time));
+    let map = displayed_game.map;
+    thread::spawn(move || {
+        let mut raster = Raster::level(&map, SCREENSHOT_SCALE);
+        raster.draw(&frame);
+        let saved = fs::create_dir_all(SCREENSHOTS_DIRECTORY)
+            .and_then(|_| File::create(&path))
+            .and_then(|file| write_bmp(BufWriter::new(file), &raster));
+        match saved {
+            Ok(()) => info!("Saved the screenshot to {}", path.display()),
+            Err(error) => warn!("Can't save the screenshot to {}: {}", path.display(), error),
+        }
+    });
+}
+
+/// Uncompressed 24-bit BMP, which needs no encoder and opens everywhere.
+fn write_bmp(mut writer: impl Write, raster: &Raster) -> io::Result<()> {
+    const HEADERS_LEN: u32 = 14 + 40;
+    let (width, height) = (raster.width as usize, raster.height as usize);
+    // rows are padded to four bytes
+    let row_len = (width * 3 + 3) / 4 * 4;
+    let file_len = HEADERS_LEN + (row_len * height) as u32;
+
+    // file header
+    writer.write_all(b"BM")?;
+    writer.write_all(&file_len.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&HEADERS_LEN.to_le_bytes())?;
+    // info header, a negative height has the rows go top down like the raster's
+    writer.write_all(&40u32.to_le_bytes())?;
+    writer.write_all(&(width as i32).to_le_bytes())?;
+    writer.write_all(&(-(height as i32)).to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&24u16.to_le_bytes())?;
+    // no compression, no resolution and no palette
+    writer.write_all(&[0; 24])?;
+
+    let mut row = vec![0; row_len];
+    for pixels in raster.pixels.chunks(width) {
+        for (pixel, color) in row.chunks_mut(3).zip(pixels.iter()) {
+            let rgb = &PALETTE[*color as usize * 3..*color as usize * 3 + 3];
+            // blue first
+            pixel.copy_from_slice(&[rgb[2], rgb[1], rgb[0]]);
+        }
+        writer.write_all(&row)?;
+    }
+    writer.flush()
+}
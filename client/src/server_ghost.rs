@@ -0,0 +1,78 @@
+use platformer_shared::{bevy::prelude::*, game::PlayerId};
+
+use crate::{update_transform, DisplayedGame};
+
+// Translucent copies of the players where the last snapshot from the server put them, drawn over
+// the predicted players and toggled with F6. The snapshot is a little in the past, so the copies
+// trail the players; a gap that jumps around shows the prediction going wrong.
+
+/// Above the players, below the name tags.
+const SERVER_GHOST_Z: f32 = 8.0;
+
+#[derive(Default)]
+pub struct ServerGhost {
+    pub visible: bool,
+}
+
+pub struct ServerGhostSprite(PlayerId);
+
+pub fn setup_server_ghosts(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    for (player_id, color) in [
+        (PlayerId::Player1, Color::rgba(0.5, 0.5, 1.0, 0.5)),
+        (PlayerId::Player2, Color::rgba(1.0, 0.5, 0.5, 0.5)),
+    ]
+    .iter()
+    {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add((*color).into()),
+                sprite: Sprite::new(Vec2::new(20.0, 20.0)),
+                transform: Transform::from_xyz(0.0, 0.0, SERVER_GHOST_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(ServerGhostSprite(*player_id));
+    }
+}
+
+pub fn toggle_server_ghosts(input: Res<Input<KeyCode>>, mut server_ghost: ResMut<ServerGhost>) {
+    if input.just_pressed(KeyCode::F6) {
+        server_ghost.visible = !server_ghost.visible;
+    }
+}
+
+pub fn server_ghost_view(
+    server_ghost: Res<ServerGhost>,
+    displayed_game: Res<DisplayedGame>,
+    mut query: Query<(
+        &ServerGhostSprite,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visible,
+    )>,
+) {
+    let display_state = displayed_game.state.as_ref();
+    let positions = display_state.and_then(|display_state| display_state.snapshot_positions);
+    for (ghost, mut transform, mut sprite, mut visible) in query.iter_mut() {
+        let (display_state, [player1, player2]) = match (display_state, positions) {
+            (Some(display_state), Some(positions)) if server_ghost.visible => {
+                (display_state, positions)
+            }
+            _ => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        let (position, character) = match ghost.0 {
+            PlayerId::Player1 => (player1, display_state.player1_character),
+            PlayerId::Player2 => (player2, display_state.player2_character),
+        };
+        update_transform(&mut transform, position.x, position.y, &displayed_game);
+        let size = character.stats().size;
+        sprite.size = Vec2::new(size, size);
+        visible.is_visible = true;
+    }
+}
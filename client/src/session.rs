@@ -0,0 +1,368 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{NetworkEvent, NetworkResource},
+        crystalorb::{
+            client::{stage::StageMut as ClientStageMut, Client},
+            clocksync::ClockSyncMessage,
+            network_resource::{self, Connection},
+            timestamp::Timestamped,
+            Config,
+        },
+    },
+    game::{GameCommand, GameSnapshot, GameWorld, MatchConfig, PlayerId},
+    lobby::LobbyMessage,
+    net::LastHeard,
+    protocol::{self, PROTOCOL_VERSION},
+    settings::{CrystalOrbSettings, NetworkSettings},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+use crate::{
+    bracket::TournamentBracket,
+    connect::ConnectScreen,
+    gamepad::ControllerInput,
+    motd::ServerMotd,
+    names::{PlayerName, PlayerNames},
+    net_stats::NetStats,
+    reconnect::Reconnect,
+    toast, DisplayedGame,
+};
+
+// The client's side of the lobby channel: greeting the server, taking a seat and readying up
+
+/// Seat the server gave this client, and the token that reclaims it after reconnecting.
+#[derive(Default)]
+pub struct Seat {
+    pub player_id: Option<PlayerId>,
+    token: Option<u64>,
+    /// Set when both seats were taken, or the tournament moved on without this player. The match
+    /// is only watched then.
+    pub spectating: bool,
+}
+
+/// Config the crystalorb client was created with, to recreate it when reconnecting.
+pub struct ClientConfig(pub Config);
+
+/// Rules, tuning and settings of the match, which the server sends once as the client joins
+/// instead of in every snapshot. Kept to give them to every crystalorb client recreated since.
+#[derive(Default)]
+pub struct ServerMatchConfig {
+    config: Option<MatchConfig>,
+    /// Set once the current crystalorb client was given the config.
+    applied: bool,
+    /// Set once the current crystalorb client shows the config, from then on it follows the
+    /// changes the server relays.
+    shown: bool,
+}
+
+pub fn send_hello(
+    mut events: EventReader<NetworkEvent>,
+    player_name: Res<PlayerName>,
+    seat: Res<Seat>,
+    mut net: ResMut<NetworkResource>,
+) {
+    for event in events.iter() {
+        if let NetworkEvent::Connected(handle) = event {
+            let message = LobbyMessage::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                content_hash: protocol::content_hash(),
+                name: player_name.0.clone(),
+                seat_token: seat.token,
+            };
+            if let Err(error) = net.send_message(*handle, message) {
+                error!("Can't greet the server: {:?}", error);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn receive_lobby_messages(
+    mut net: ResMut<NetworkResource>,
+    mut seat: ResMut<Seat>,
+    mut names: ResMut<PlayerNames>,
+    mut bracket: ResMut<TournamentBracket>,
+    mut motd: ResMut<ServerMotd>,
+    mut config: ResMut<ClientConfig>,
+    mut match_config: ResMut<ServerMatchConfig>,
+    mut connect_screen: ResMut<ConnectScreen>,
+    mut net_stats: ResMut<NetStats>,
+    time: Res<Time>,
+) {
+    let mut rejected_by = vec![];
+    for (handle, connection) in net.connections.iter_mut() {
+        let stats = connection.stats();
+        let channels = connection.channels().unwrap();
+        while let Some(message) = channels.recv::<LobbyMessage>() {
+            match message {
+                LobbyMessage::Welcome {
+                    player_id,
+                    seat_token,
+                } => {
+                    info!("Seated as {}", player_id);
+                    seat.player_id = Some(player_id);
+                    seat.token = Some(seat_token);
+                    seat.spectating = false;
+                }
+                LobbyMessage::Spectate => {
+                    seat.player_id = None;
+                    seat.token = None;
+                    seat.spectating = true;
+                }
+                LobbyMessage::Timing {
+                    lag_compensation_latency,
+                    snapshot_send_period,
+                } => {
+                    // the server sends the same timing on every reconnect
+                    let differs = |a: f64, b: f64| (a - b).abs() > f64::EPSILON;
+                    if differs(config.0.lag_compensation_latency, lag_compensation_latency)
+                        || differs(config.0.snapshot_send_period, snapshot_send_period)
+                    {
+                        info!(
+                            "Adopting the server's {}s lag compensation and {}s snapshot period",
+                            lag_compensation_latency, snapshot_send_period
+                        );
+                        config.0.lag_compensation_latency = lag_compensation_latency;
+                        config.0.snapshot_send_period = snapshot_send_period;
+                    }
+                }
+                LobbyMessage::MatchConfig(server_config) => {
+                    *match_config = ServerMatchConfig {
+                        config: Some(server_config),
+                        ..Default::default()
+                    };
+                }
+                LobbyMessage::Names(player_names) => names.0 = player_names,
+                LobbyMessage::Bracket(new_bracket) => bracket.0 = Some(new_bracket),
+                LobbyMessage::Motd { message, rules } => {
+                    motd.0 = if message.is_empty() && rules.is_empty() {
+                        None
+                    } else {
+                        Some((message, rules))
+                    };
+                }
+                LobbyMessage::Rejected { reason } => {
+                    connect_screen.show_error(format!("The server turned us away: {}", reason));
+                    rejected_by.push(*handle);
+                }
+                LobbyMessage::Pong {
+                    sent_at,
+                    packets_received,
+                    packets_sent,
+                } => net_stats.record_pong(
+                    time.seconds_since_startup(),
+                    sent_at,
+                    packets_received,
+                    packets_sent,
+                    stats.packets_rx as u64,
+                    stats.packets_tx as u64,
+                ),
+                LobbyMessage::Hello { .. } | LobbyMessage::Ready | LobbyMessage::Ping { .. } => {
+                    warn!("The server sent a client message")
+                }
+            }
+        }
+    }
+    for handle in rejected_by {
+        net.disconnect(handle);
+    }
+}
+
+/// A new connection starts from a fresh crystalorb client, which syncs its clock and waits for
+/// the initial snapshot again instead of carrying on from the dropped session.
+pub fn reset_client_on_reconnect(
+    mut connected_before: Local<bool>,
+    mut events: EventReader<NetworkEvent>,
+    config: Res<ClientConfig>,
+    mut client: ResMut<Client<GameWorld>>,
+    mut displayed_game: ResMut<DisplayedGame>,
+) {
+    for event in events.iter() {
+        if let NetworkEvent::Connected(_) = event {
+            if *connected_before {
+                *client = Client::new(config.0.clone());
+                *displayed_game = DisplayedGame::default();
+            }
+            *connected_before = true;
+        }
+    }
+}
+
+/// Gives the server's match config to the crystalorb client, as commands only applied locally:
+/// the snapshots leave it out, so it stays in the client's worlds once they applied it.
+pub fn apply_match_config(
+    mut match_config: ResMut<ServerMatchConfig>,
+    mut client: ResMut<Client<GameWorld>>,
+) {
+    let config = match match_config.config {
+        Some(config) => config,
+        None => return,
+    };
+    let mut ready_client = match client.stage_mut() {
+        ClientStageMut::Ready(ready_client) => ready_client,
+        // recreated, or still syncing
+        _ => {
+            match_config.applied = false;
+            match_config.shown = false;
+            return;
+        }
+    };
+    if !match_config.applied {
+        for command in [
+            GameCommand::SetRules(config.rules),
+            GameCommand::SetTuning(config.tuning),
+            GameCommand::SetMatchSettings(config.match_settings),
+        ]
+        .iter()
+        {
+            ready_client.issue_command(command.clone(), &mut LocalOnly);
+        }
+        match_config.applied = true;
+        return;
+    }
+    let shown = ready_client.display_state().inner().match_config;
+    if match_config.shown {
+        match_config.config = Some(shown);
+    } else {
+        match_config.shown = shown == config;
+    }
+}
+
+/// A network without connections, for commands the client only applies to its own worlds.
+struct LocalOnly;
+
+enum NoConnection {}
+
+impl network_resource::NetworkResource<GameWorld> for LocalOnly {
+    type ConnectionType<'a> = NoConnection;
+
+    fn get_connection(&mut self, _handle: usize) -> Option<Self::ConnectionType<'_>> {
+        None
+    }
+
+    fn connections<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = (usize, Self::ConnectionType<'a>)> + 'a> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl Connection<GameWorld> for NoConnection {
+    fn recv_command(&mut self) -> Option<Timestamped<GameCommand>> {
+        match *self {}
+    }
+
+    fn recv_snapshot(&mut self) -> Option<Timestamped<GameSnapshot>> {
+        match *self {}
+    }
+
+    fn recv_clock_sync(&mut self) -> Option<ClockSyncMessage> {
+        match *self {}
+    }
+
+    fn send<MessageType>(&mut self, _message: MessageType) -> Option<MessageType>
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        match *self {}
+    }
+
+    fn flush<MessageType>(&mut self)
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        match *self {}
+    }
+}
+
+/// Takes the tweening picked in the settings menu. Crystalorb reads its config once, so the client
+/// is recreated and the game pauses while it syncs again.
+pub fn apply_tweening_settings(
+    crystalorb: Res<CrystalOrbSettings>,
+    mut config: ResMut<ClientConfig>,
+) {
+    if !crystalorb.is_changed() || crystalorb.is_added() {
+        return;
+    }
+    let picked = crystalorb.config();
+    config.0.tweening_method = picked.tweening_method;
+    config.0.blend_latency = picked.blend_latency;
+}
+
+/// Recreates the crystalorb client when its config changes, to the server's timing or to the
+/// tweening picked in the settings menu. The client is still syncing its clock right after the
+/// handshake, so nothing is lost then.
+pub fn adopt_server_timing(config: Res<ClientConfig>, mut client: ResMut<Client<GameWorld>>) {
+    if config.is_changed() && !config.is_added() {
+        *client = Client::new(config.0.clone());
+    }
+}
+
+/// Drops a server that went silent, which would otherwise keep the client waiting forever.
+pub fn drop_silent_server(
+    time: Res<Time>,
+    network_settings: Res<NetworkSettings>,
+    mut last_heard: Local<LastHeard>,
+    mut net: ResMut<NetworkResource>,
+    mut connect_screen: ResMut<ConnectScreen>,
+    mut reconnect: ResMut<Reconnect>,
+) {
+    let now = time.seconds_since_startup();
+    let silent = last_heard.silent(&net, now, network_settings.idle_timeout_seconds);
+    for handle in silent {
+        warn!("The server stopped responding, disconnecting");
+        net.disconnect(handle);
+        reconnect.connection_lost(
+            now,
+            &mut connect_screen,
+            "The server stopped responding".to_string(),
+        );
+    }
+}
+
+/// Drops the connection to the server, along with the seat and the game it showed.
+pub fn leave_server(
+    net: &mut NetworkResource,
+    screen: &mut ConnectScreen,
+    seat: &mut Seat,
+    displayed_game: &mut DisplayedGame,
+) {
+    info!("Disconnecting");
+    let handles: Vec<_> = net.connections.keys().copied().collect();
+    for handle in handles {
+        net.disconnect(handle);
+    }
+    screen.disconnect();
+    *seat = Seat::default();
+    *displayed_game = DisplayedGame::default();
+}
+
+// the server starts the match once both players pressed Enter, or Start, in the lobby
+pub fn ready_up(
+    mut sent: Local<bool>,
+    input: Res<Input<KeyCode>>,
+    controller: Res<ControllerInput>,
+    displayed_game: Res<DisplayedGame>,
+    mut net: ResMut<NetworkResource>,
+    mut toasts: ResMut<toast::Toasts>,
+) {
+    let in_lobby = displayed_game
+        .state
+        .as_ref()
+        .map_or(false, |display_state| display_state.lobby);
+    // spectators have nothing to ready up
+    let seated = displayed_game.local_player.is_some();
+    // the server can send the players back to the lobby for a new match
+    if !in_lobby {
+        *sent = false;
+    }
+    let ready = input.just_pressed(KeyCode::Return) || controller.ready;
+    if !*sent && in_lobby && seated && ready {
+        net.broadcast_message(LobbyMessage::Ready);
+        toasts.push(toast::Severity::Info, "Ready, waiting for the opponent");
+        *sent = true;
+    }
+}
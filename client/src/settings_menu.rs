@@ -0,0 +1,676 @@
+#[cfg(not(target_arch = "wasm32"))]
+use platformer_shared::bevy::app::AppExit;
+#[cfg(not(target_arch = "wasm32"))]
+use platformer_shared::settings::{DisplayMode, DisplaySettings, Settings};
+use platformer_shared::{
+    bevy::{prelude::*, ui::FocusPolicy},
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkResource,
+    settings::{
+        AudioSettings, ControllerSettings, CrystalOrbSettings, GraphicsSettings, KeyBinds, Tweening,
+    },
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::display::FRAME_CAPS;
+use crate::{
+    connect::ConnectScreen, debug::DebugOverlay, input_display::InputDisplay, menu::ClientState,
+    perf_overlay::PerfOverlay, session, DisplayedGame, UI_FONT,
+};
+
+// Settings menu, opened with Escape over the game or from the main menu. The volume, the controls,
+// what gets drawn besides the game and how the game is smoothed over the network are changed
+// there, and saved to the settings file once it closes. In game it also disconnects, and natively
+// quits.
+
+const VOLUME_STEP: f32 = 0.1;
+/// Seconds, a setting of crystalorb's blending of corrections.
+const BLEND_LATENCY_STEP: f64 = 0.05;
+const MAX_BLEND_LATENCY: f64 = 0.5;
+const UI_SCALE_STEP: f32 = 0.25;
+const MIN_UI_SCALE: f32 = 0.5;
+const MAX_UI_SCALE: f32 = 2.0;
+/// Pixels.
+const SAFE_AREA_STEP: f32 = 10.0;
+const MAX_SAFE_AREA: f32 = 100.0;
+
+/// Settings file the client was started with.
+pub struct SettingsPath(pub String);
+
+#[derive(Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    /// Control waiting for its new key, the next one pressed.
+    rebinding: Option<Binding>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Action,
+    Left,
+    Right,
+    Grapple,
+    Ability,
+    Mortar,
+    Ricochet,
+}
+
+impl Binding {
+    const ALL: [Binding; 7] = [
+        Binding::Left,
+        Binding::Right,
+        Binding::Action,
+        Binding::Grapple,
+        Binding::Ability,
+        Binding::Mortar,
+        Binding::Ricochet,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Binding::Action => "Jump and fire",
+            Binding::Left => "Left",
+            Binding::Right => "Right",
+            Binding::Grapple => "Grapple",
+            Binding::Ability => "Spread-shot",
+            Binding::Mortar => "Mortar",
+            Binding::Ricochet => "Ricochet",
+        }
+    }
+
+    fn key(self, keybinds: &mut KeyBinds) -> &mut KeyCode {
+        match self {
+            Binding::Action => &mut keybinds.action,
+            Binding::Left => &mut keybinds.left,
+            Binding::Right => &mut keybinds.right,
+            Binding::Grapple => &mut keybinds.grapple,
+            Binding::Ability => &mut keybinds.ability,
+            Binding::Mortar => &mut keybinds.mortar,
+            Binding::Ricochet => &mut keybinds.ricochet,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SettingsButton {
+    VolumeDown,
+    VolumeUp,
+    #[cfg(not(target_arch = "wasm32"))]
+    Music,
+    Rebind(Binding),
+    Controller,
+    NameTags,
+    ScreenShake,
+    UiScaleDown,
+    UiScaleUp,
+    SafeAreaDown,
+    SafeAreaUp,
+    HighContrast,
+    #[cfg(not(target_arch = "wasm32"))]
+    DisplayMode,
+    #[cfg(not(target_arch = "wasm32"))]
+    Vsync,
+    #[cfg(not(target_arch = "wasm32"))]
+    FrameCap,
+    PerfOverlay,
+    DebugOverlay,
+    InputDisplay,
+    Tweening,
+    BlendLatencyDown,
+    BlendLatencyUp,
+    /// Back to the game, or to the main menu.
+    Resume,
+    Disconnect,
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+}
+
+/// Every entity of the menu, hidden together.
+pub struct SettingsMenuPart;
+
+/// Text of a button, rewritten as the setting changes.
+pub struct SettingsLabel(SettingsButton);
+
+/// Text between a pair of - and + buttons, showing the value they change.
+#[derive(Clone, Copy)]
+pub enum SettingsValue {
+    Volume,
+    UiScale,
+    SafeArea,
+    BlendLatency,
+}
+
+fn tweening_name(tweening: Tweening) -> &'static str {
+    match tweening {
+        Tweening::MostRecentlyPassed => "most recent tick",
+        Tweening::Nearest => "nearest tick",
+        Tweening::Interpolated => "interpolated",
+    }
+}
+
+pub fn setup_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let button_material = materials.add(Color::DARK_GRAY.into());
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+    let text = |value: &str, font_size: f32| TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        visible: hidden.clone(),
+        ..Default::default()
+    };
+    let button = |parent: &mut ChildBuilder, button: SettingsButton| {
+        parent
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(3.0)),
+                    padding: Rect::all(Val::Px(6.0)),
+                    min_size: Size::new(Val::Px(40.0), Val::Auto),
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                material: button_material.clone(),
+                visible: hidden.clone(),
+                ..Default::default()
+            })
+            .insert(SettingsMenuPart)
+            .insert(button)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(text("", 20.0))
+                    .insert(SettingsMenuPart)
+                    .insert(SettingsLabel(button));
+            });
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.85).into()),
+            visible: hidden.clone(),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        })
+        .insert(SettingsMenuPart)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(text("Settings", 40.0))
+                .insert(SettingsMenuPart);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    visible: hidden.clone(),
+                    ..Default::default()
+                })
+                .insert(SettingsMenuPart)
+                .with_children(|parent| {
+                    button(parent, SettingsButton::VolumeDown);
+                    parent
+                        .spawn_bundle(text("", 20.0))
+                        .insert(SettingsMenuPart)
+                        .insert(SettingsValue::Volume);
+                    button(parent, SettingsButton::VolumeUp);
+                });
+            #[cfg(not(target_arch = "wasm32"))]
+            button(parent, SettingsButton::Music);
+
+            parent
+                .spawn_bundle(text("Controls", 24.0))
+                .insert(SettingsMenuPart);
+            for binding in Binding::ALL.iter() {
+                button(parent, SettingsButton::Rebind(*binding));
+            }
+            button(parent, SettingsButton::Controller);
+
+            parent
+                .spawn_bundle(text("Graphics", 24.0))
+                .insert(SettingsMenuPart);
+            button(parent, SettingsButton::NameTags);
+            button(parent, SettingsButton::ScreenShake);
+            button(parent, SettingsButton::HighContrast);
+            for (down, value, up) in [
+                (
+                    SettingsButton::UiScaleDown,
+                    SettingsValue::UiScale,
+                    SettingsButton::UiScaleUp,
+                ),
+                (
+                    SettingsButton::SafeAreaDown,
+                    SettingsValue::SafeArea,
+                    SettingsButton::SafeAreaUp,
+                ),
+            ]
+            .iter()
+            {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: materials.add(Color::NONE.into()),
+                        visible: hidden.clone(),
+                        ..Default::default()
+                    })
+                    .insert(SettingsMenuPart)
+                    .with_children(|parent| {
+                        button(parent, *down);
+                        parent
+                            .spawn_bundle(text("", 20.0))
+                            .insert(SettingsMenuPart)
+                            .insert(*value);
+                        button(parent, *up);
+                    });
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                button(parent, SettingsButton::DisplayMode);
+                button(parent, SettingsButton::Vsync);
+                button(parent, SettingsButton::FrameCap);
+            }
+            button(parent, SettingsButton::PerfOverlay);
+            button(parent, SettingsButton::DebugOverlay);
+            button(parent, SettingsButton::InputDisplay);
+
+            // the smoother, the further the game lags behind the inputs
+            parent
+                .spawn_bundle(text("Netcode", 24.0))
+                .insert(SettingsMenuPart);
+            button(parent, SettingsButton::Tweening);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    visible: hidden.clone(),
+                    ..Default::default()
+                })
+                .insert(SettingsMenuPart)
+                .with_children(|parent| {
+                    button(parent, SettingsButton::BlendLatencyDown);
+                    parent
+                        .spawn_bundle(text("", 20.0))
+                        .insert(SettingsMenuPart)
+                        .insert(SettingsValue::BlendLatency);
+                    button(parent, SettingsButton::BlendLatencyUp);
+                });
+
+            parent.spawn_bundle(text("", 12.0)).insert(SettingsMenuPart);
+            button(parent, SettingsButton::Resume);
+            button(parent, SettingsButton::Disconnect);
+            #[cfg(not(target_arch = "wasm32"))]
+            button(parent, SettingsButton::Quit);
+        });
+}
+
+/// Whether the menu is over the game, instead of over the main menu. The tutorial has neither a
+/// main menu nor a connect screen.
+fn in_game(
+    state: &Option<ResMut<State<ClientState>>>,
+    screen: &Option<Res<ConnectScreen>>,
+) -> bool {
+    state.as_ref().map_or(true, |state| {
+        matches!(
+            state.current(),
+            ClientState::Connect | ClientState::MatchOver
+        )
+    }) && screen
+        .as_ref()
+        .map_or(true, |screen| screen.server().is_some())
+}
+
+/// Escape opens and closes the menu, and a control being rebound takes the next key pressed.
+pub fn settings_menu_keys(
+    keys: Res<Input<KeyCode>>,
+    state: Option<ResMut<State<ClientState>>>,
+    screen: Option<Res<ConnectScreen>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut keybinds: ResMut<KeyBinds>,
+) {
+    if let Some(binding) = menu.rebinding {
+        // Escape leaves the control as it was
+        if let Some(key) = keys.get_just_pressed().next() {
+            if *key != KeyCode::Escape {
+                *binding.key(&mut keybinds) = *key;
+            }
+            menu.rebinding = None;
+        }
+        return;
+    }
+
+    let escape = keys.just_pressed(KeyCode::Escape);
+    let open = match state.as_ref().map(|state| *state.current()) {
+        Some(ClientState::Settings) => true,
+        _ if in_game(&state, &screen) => menu.open != escape,
+        _ => false,
+    };
+    if menu.open != open {
+        menu.open = open;
+    }
+    if let Some(mut state) = state {
+        if *state.current() == ClientState::Settings && escape {
+            if let Err(error) = state.set(ClientState::MainMenu) {
+                warn!("Can't go back to the main menu: {:?}", error);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn settings_menu_input(
+    buttons: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut menu: ResMut<SettingsMenu>,
+    state: Option<ResMut<State<ClientState>>>,
+    mut audio: ResMut<AudioSettings>,
+    mut controller: ResMut<ControllerSettings>,
+    mut graphics: ResMut<GraphicsSettings>,
+    mut perf_overlay: ResMut<PerfOverlay>,
+    mut debug_overlay: ResMut<DebugOverlay>,
+    mut input_display: ResMut<InputDisplay>,
+    mut crystalorb: ResMut<CrystalOrbSettings>,
+    #[cfg(not(target_arch = "wasm32"))] mut display: ResMut<DisplaySettings>,
+    #[cfg(not(target_arch = "wasm32"))] mut exit: EventWriter<AppExit>,
+) {
+    if !menu.open {
+        return;
+    }
+    let clicked = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Clicked)
+        .map(|(_, button)| *button);
+    match clicked {
+        Some(SettingsButton::VolumeDown) => {
+            audio.volume = ((audio.volume - VOLUME_STEP) * 10.0).round() / 10.0;
+            audio.volume = audio.volume.max(0.0);
+        }
+        Some(SettingsButton::VolumeUp) => {
+            audio.volume = ((audio.volume + VOLUME_STEP) * 10.0).round() / 10.0;
+            audio.volume = audio.volume.min(1.0);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(SettingsButton::Music) => audio.music = !audio.music,
+        Some(SettingsButton::Rebind(binding)) => menu.rebinding = Some(binding),
+        Some(SettingsButton::Controller) => controller.enabled = !controller.enabled,
+        Some(SettingsButton::NameTags) => graphics.name_tags = !graphics.name_tags,
+        Some(SettingsButton::ScreenShake) => graphics.screen_shake = !graphics.screen_shake,
+        Some(SettingsButton::HighContrast) => graphics.high_contrast = !graphics.high_contrast,
+        Some(SettingsButton::UiScaleDown) => {
+            graphics.ui_scale = (graphics.ui_scale - UI_SCALE_STEP).max(MIN_UI_SCALE);
+        }
+        Some(SettingsButton::UiScaleUp) => {
+            graphics.ui_scale = (graphics.ui_scale + UI_SCALE_STEP).min(MAX_UI_SCALE);
+        }
+        Some(SettingsButton::SafeAreaDown) => {
+            graphics.safe_area = (graphics.safe_area - SAFE_AREA_STEP).max(0.0);
+        }
+        Some(SettingsButton::SafeAreaUp) => {
+            graphics.safe_area = (graphics.safe_area + SAFE_AREA_STEP).min(MAX_SAFE_AREA);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(SettingsButton::DisplayMode) => {
+            display.mode = match display.mode {
+                DisplayMode::Windowed => DisplayMode::Borderless,
+                DisplayMode::Borderless => DisplayMode::Fullscreen,
+                DisplayMode::Fullscreen => DisplayMode::Windowed,
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(SettingsButton::Vsync) => display.vsync = !display.vsync,
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(SettingsButton::FrameCap) => {
+            // none, then each cap from the lowest
+            let next = match display.frame_cap {
+                None => Some(0),
+                Some(cap) => FRAME_CAPS
+                    .iter()
+                    .position(|candidate| *candidate == cap)
+                    .map(|index| index + 1)
+                    .filter(|index| *index < FRAME_CAPS.len()),
+            };
+            display.frame_cap = next.map(|index| FRAME_CAPS[index]);
+        }
+        Some(SettingsButton::PerfOverlay) => perf_overlay.visible = !perf_overlay.visible,
+        Some(SettingsButton::DebugOverlay) => debug_overlay.visible = !debug_overlay.visible,
+        Some(SettingsButton::InputDisplay) => input_display.visible = !input_display.visible,
+        Some(SettingsButton::Tweening) => {
+            let current = Tweening::from(crystalorb.config().tweening_method);
+            let index = Tweening::ALL
+                .iter()
+                .position(|tweening| *tweening == current);
+            let next = index.map_or(0, |index| (index + 1) % Tweening::ALL.len());
+            crystalorb.tweening_method = Some(Tweening::ALL[next]);
+        }
+        Some(SettingsButton::BlendLatencyDown) => {
+            let blend_latency = crystalorb.config().blend_latency - BLEND_LATENCY_STEP;
+            crystalorb.blend_latency = Some((blend_latency * 100.0).round().max(0.0) / 100.0);
+        }
+        Some(SettingsButton::BlendLatencyUp) => {
+            let blend_latency = crystalorb.config().blend_latency + BLEND_LATENCY_STEP;
+            crystalorb.blend_latency = Some(
+                (blend_latency * 100.0)
+                    .round()
+                    .min(MAX_BLEND_LATENCY * 100.0)
+                    / 100.0,
+            );
+        }
+        Some(SettingsButton::Resume) => match state {
+            Some(mut state) if *state.current() == ClientState::Settings => {
+                if let Err(error) = state.set(ClientState::MainMenu) {
+                    warn!("Can't go back to the main menu: {:?}", error);
+                }
+            }
+            _ => menu.open = false,
+        },
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(SettingsButton::Quit) => exit.send(AppExit),
+        // handled by `disconnect`, which only runs with a server to disconnect from
+        Some(SettingsButton::Disconnect) | None => {}
+    }
+}
+
+/// Leaves the server for the main menu.
+pub fn disconnect(
+    buttons: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut state: ResMut<State<ClientState>>,
+    mut screen: ResMut<ConnectScreen>,
+    mut seat: ResMut<session::Seat>,
+    mut displayed_game: ResMut<DisplayedGame>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let clicked = buttons.iter().any(|(interaction, button)| {
+        *interaction == Interaction::Clicked && *button == SettingsButton::Disconnect
+    });
+    if !menu.open || !clicked {
+        return;
+    }
+    session::leave_server(&mut net, &mut screen, &mut seat, &mut displayed_game);
+    menu.open = false;
+    if let Err(error) = state.set(ClientState::MainMenu) {
+        warn!("Can't go back to the main menu: {:?}", error);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_settings_menu(
+    menu: Res<SettingsMenu>,
+    state: Option<Res<State<ClientState>>>,
+    audio: Res<AudioSettings>,
+    keybinds: Res<KeyBinds>,
+    controller: Res<ControllerSettings>,
+    graphics: Res<GraphicsSettings>,
+    perf_overlay: Res<PerfOverlay>,
+    debug_overlay: Res<DebugOverlay>,
+    input_display: Res<InputDisplay>,
+    crystalorb: Res<CrystalOrbSettings>,
+    #[cfg(not(target_arch = "wasm32"))] display: Res<DisplaySettings>,
+    mut parts: Query<(&mut Visible, Option<&SettingsButton>), With<SettingsMenuPart>>,
+    mut texts: QuerySet<(
+        Query<(&mut Text, &SettingsLabel)>,
+        Query<(&mut Text, &SettingsValue)>,
+    )>,
+) {
+    let changed = menu.is_changed()
+        || state.as_ref().map_or(false, |state| state.is_changed())
+        || audio.is_changed()
+        || keybinds.is_changed()
+        || controller.is_changed()
+        || graphics.is_changed()
+        || perf_overlay.is_changed()
+        || debug_overlay.is_changed()
+        || input_display.is_changed()
+        || crystalorb.is_changed();
+    #[cfg(not(target_arch = "wasm32"))]
+    let changed = changed || display.is_changed();
+    if !changed {
+        return;
+    }
+
+    let over_main_menu = state
+        .as_ref()
+        .map_or(false, |state| *state.current() == ClientState::Settings);
+    for (mut visible, button) in parts.iter_mut() {
+        visible.is_visible = match button {
+            // there's nothing to disconnect from in the tutorial or the main menu
+            Some(SettingsButton::Disconnect) => menu.open && !over_main_menu && state.is_some(),
+            _ => menu.open,
+        };
+    }
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let mut keybinds = *keybinds;
+    let config = crystalorb.config();
+    let tweening = Tweening::from(config.tweening_method);
+    for (mut text, label) in texts.q0_mut().iter_mut() {
+        text.sections[0].value = match label.0 {
+            SettingsButton::VolumeDown => "-".to_string(),
+            SettingsButton::VolumeUp => "+".to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            SettingsButton::Music => format!("Music: {}", on_off(audio.music)),
+            SettingsButton::Rebind(binding) if menu.rebinding == Some(binding) => {
+                format!("{}: press a key", binding.name())
+            }
+            SettingsButton::Rebind(binding) => {
+                format!("{}: {:?}", binding.name(), binding.key(&mut keybinds))
+            }
+            SettingsButton::Controller => format!("Controller: {}", on_off(controller.enabled)),
+            SettingsButton::NameTags => format!("Name tags: {}", on_off(graphics.name_tags)),
+            SettingsButton::ScreenShake => {
+                format!("Screen shake: {}", on_off(graphics.screen_shake))
+            }
+            SettingsButton::HighContrast => {
+                format!("High contrast: {}", on_off(graphics.high_contrast))
+            }
+            SettingsButton::UiScaleDown | SettingsButton::SafeAreaDown => "-".to_string(),
+            SettingsButton::UiScaleUp | SettingsButton::SafeAreaUp => "+".to_string(),
+            SettingsButton::PerfOverlay => {
+                format!("FPS and ping (F2): {}", on_off(perf_overlay.visible))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            SettingsButton::DisplayMode => format!(
+                "Display: {}",
+                match display.mode {
+                    DisplayMode::Windowed => "windowed",
+                    DisplayMode::Borderless => "borderless",
+                    DisplayMode::Fullscreen => "fullscreen",
+                }
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            SettingsButton::Vsync => format!("Vsync: {}", on_off(display.vsync)),
+            #[cfg(not(target_arch = "wasm32"))]
+            SettingsButton::FrameCap => match display.frame_cap {
+                Some(cap) => format!("Frame cap: {} fps", cap),
+                None => "Frame cap: off".to_string(),
+            },
+            SettingsButton::DebugOverlay => {
+                format!("Debug overlay (F3): {}", on_off(debug_overlay.visible))
+            }
+            SettingsButton::InputDisplay => {
+                format!("Input display (F4): {}", on_off(input_display.visible))
+            }
+            SettingsButton::Tweening => format!("Tweening: {}", tweening_name(tweening)),
+            SettingsButton::BlendLatencyDown => "-".to_string(),
+            SettingsButton::BlendLatencyUp => "+".to_string(),
+            SettingsButton::Resume if over_main_menu => "Back".to_string(),
+            SettingsButton::Resume => "Resume".to_string(),
+            SettingsButton::Disconnect => "Disconnect".to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            SettingsButton::Quit => "Quit".to_string(),
+        };
+    }
+    for (mut text, value) in texts.q1_mut().iter_mut() {
+        text.sections[0].value = match value {
+            SettingsValue::Volume => format!("Volume: {}%", (audio.volume * 100.0).round()),
+            SettingsValue::UiScale => {
+                format!("HUD scale: {}%", (graphics.ui_scale * 100.0).round())
+            }
+            SettingsValue::SafeArea => format!("Safe area: {} px", graphics.safe_area.round()),
+            SettingsValue::BlendLatency => {
+                format!("Blend latency: {:.0} ms", config.blend_latency * 1000.0)
+            }
+        };
+    }
+}
+
+/// Saves the settings changed in the menu once it closes, keeping the rest of the file.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn save_settings(
+    mut was_open: Local<bool>,
+    menu: Res<SettingsMenu>,
+    settings_path: Res<SettingsPath>,
+    keybinds: Res<KeyBinds>,
+    controller: Res<ControllerSettings>,
+    audio: Res<AudioSettings>,
+    graphics: Res<GraphicsSettings>,
+    crystalorb: Res<CrystalOrbSettings>,
+    display: Res<DisplaySettings>,
+) {
+    let closed = *was_open && !menu.open;
+    *was_open = menu.open;
+    if !closed {
+        return;
+    }
+    let saved = Settings::read(&settings_path.0).and_then(|mut settings| {
+        settings.keybinds = *keybinds;
+        settings.controller = *controller;
+        settings.audio = *audio;
+        settings.graphics = *graphics;
+        settings.crystalorb.tweening_method = crystalorb.tweening_method;
+        settings.crystalorb.blend_latency = crystalorb.blend_latency;
+        settings.display = *display;
+        settings.save(&settings_path.0)
+    });
+    if let Err(error) = saved {
+        warn!("Can't save the settings to {}: {}", settings_path.0, error);
+    }
+}
@@ -0,0 +1,81 @@
+use platformer_shared::{bevy::prelude::*, game::GameEvent, settings::GraphicsSettings};
+
+use crate::DisplayedGame;
+
+// Camera shake on deaths and projectile impacts. The events add trauma that decays over time,
+// and the camera is pushed off its resting place by the square of it, so that small hits barely
+// move it and big ones throw it around.
+
+/// Pixels the camera moves at full trauma.
+const MAX_SHAKE_OFFSET: f32 = 12.0;
+/// Radians the camera turns at full trauma.
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+const DEATH_TRAUMA: f32 = 0.6;
+const IMPACT_TRAUMA: f32 = 0.3;
+
+/// Shake of the camera, layered over the position it rests at.
+pub struct CameraShake {
+    rest: Vec3,
+    /// From 0 for none to 1 for the most.
+    trauma: f32,
+    shaken_until: Option<u32>,
+}
+
+impl CameraShake {
+    pub fn new(rest: Vec3) -> Self {
+        Self {
+            rest,
+            trauma: 0.0,
+            shaken_until: None,
+        }
+    }
+}
+
+pub fn shake_camera(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    graphics: Res<GraphicsSettings>,
+    mut cameras: Query<(&mut CameraShake, &mut Transform)>,
+) {
+    for (mut shake, mut transform) in cameras.iter_mut() {
+        match &displayed_game.state {
+            Some(display_state) => {
+                // the events from before joining or reconnecting are left out
+                let since = match shake.shaken_until {
+                    Some(since) if since <= display_state.tick => since,
+                    _ => display_state.tick,
+                };
+                shake.shaken_until = Some(display_state.tick);
+                for (tick, event) in display_state.events.iter() {
+                    if *tick > since {
+                        shake.trauma += match event {
+                            GameEvent::Died(_) => DEATH_TRAUMA,
+                            GameEvent::Impact => IMPACT_TRAUMA,
+                            _ => 0.0,
+                        };
+                    }
+                }
+            }
+            None => shake.shaken_until = None,
+        }
+        if !graphics.screen_shake {
+            shake.trauma = 0.0;
+        }
+        shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds())
+            .max(0.0)
+            .min(1.0);
+
+        // sines of unrelated frequencies wander about like noise
+        let amount = shake.trauma * shake.trauma;
+        let t = time.seconds_since_startup() as f32;
+        let offset = Vec3::new(
+            (t * 47.0).sin() + (t * 31.0).sin() * 0.5,
+            (t * 53.0).sin() + (t * 29.0).sin() * 0.5,
+            0.0,
+        ) / 1.5;
+        transform.translation = shake.rest + offset * MAX_SHAKE_OFFSET * amount;
+        transform.rotation = Quat::from_rotation_z((t * 37.0).sin() * MAX_SHAKE_ANGLE * amount);
+    }
+}
@@ -0,0 +1,165 @@
+use platformer_shared::{
+    bevy::{audio::AudioSource, prelude::*},
+    game::GameEvent,
+    settings::AudioSettings,
+};
+use std::f32::consts::PI;
+
+use crate::DisplayedGame;
+
+// Sound effects for the events of the game, synthesized at startup instead of shipped as files.
+// They're made again at the new volume whenever it changes, bevy's audio has no volume control.
+
+pub const SAMPLE_RATE: u32 = 22050;
+
+#[derive(Clone, Copy)]
+enum Sound {
+    Jump,
+    Fire,
+    Impact,
+    Death,
+    PadCapture,
+    RoundStart,
+}
+
+impl Sound {
+    const ALL: [Sound; 6] = [
+        Sound::Jump,
+        Sound::Fire,
+        Sound::Impact,
+        Sound::Death,
+        Sound::PadCapture,
+        Sound::RoundStart,
+    ];
+
+    fn of(event: GameEvent) -> Option<Self> {
+        match event {
+            GameEvent::Jumped(_) => Some(Sound::Jump),
+            GameEvent::Fired(_) => Some(Sound::Fire),
+            GameEvent::Impact => Some(Sound::Impact),
+            // the impact on the shield already has its sound
+            GameEvent::ShieldBroken(_) => None,
+            GameEvent::Died(_) => Some(Sound::Death),
+            GameEvent::PadCaptured(_) => Some(Sound::PadCapture),
+            // the capture that sent the pad off already has its sound
+            GameEvent::PadPatrolStarted(..) => None,
+            GameEvent::RoundStarted => Some(Sound::RoundStart),
+        }
+    }
+
+    /// Samples between -1 and 1.
+    fn samples(self) -> Vec<f32> {
+        match self {
+            Sound::Jump => sweep(0.12, 300.0, 600.0, 0.0),
+            Sound::Fire => sweep(0.15, 200.0, 80.0, 0.5),
+            Sound::Impact => sweep(0.08, 120.0, 60.0, 0.8),
+            Sound::Death => sweep(0.4, 500.0, 100.0, 0.1),
+            Sound::PadCapture => {
+                let mut samples = sweep(0.1, 660.0, 660.0, 0.0);
+                samples.extend(sweep(0.15, 880.0, 880.0, 0.0));
+                samples
+            }
+            Sound::RoundStart => sweep(0.2, 440.0, 440.0, 0.0),
+        }
+    }
+}
+
+/// A tone gliding from one pitch to the other while it fades out, mixed with as much noise.
+fn sweep(seconds: f32, from_hz: f32, to_hz: f32, noise: f32) -> Vec<f32> {
+    let sample_count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut phase = 0.0;
+    // xorshift, the noise doesn't have to be any good
+    let mut noise_state = 0x2545_f491_u32;
+    (0..sample_count)
+        .map(|index| {
+            let progress = index as f32 / sample_count as f32;
+            let hz = from_hz + (to_hz - from_hz) * progress;
+            phase += 2.0 * PI * hz / SAMPLE_RATE as f32;
+            noise_state ^= noise_state << 13;
+            noise_state ^= noise_state >> 17;
+            noise_state ^= noise_state << 5;
+            let random = noise_state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+            let value = phase.sin() * (1.0 - noise) + random * noise;
+            value * (1.0 - progress)
+        })
+        .collect()
+}
+
+/// 16-bit mono WAV file of the samples.
+pub fn wav(samples: &[f32], volume: f32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let sample = (sample * volume).max(-1.0).min(1.0) * i16::MAX as f32;
+        bytes.extend_from_slice(&(sample as i16).to_le_bytes());
+    }
+    bytes
+}
+
+/// Handles of the sounds, in the order of `Sound::ALL`.
+#[derive(Default)]
+pub struct Sounds(Vec<Handle<AudioSource>>);
+
+pub fn make_sounds(
+    audio_settings: Res<AudioSettings>,
+    mut audio_sources: ResMut<Assets<AudioSource>>,
+    mut sounds: ResMut<Sounds>,
+) {
+    if !audio_settings.is_changed() {
+        return;
+    }
+    sounds.0 = Sound::ALL
+        .iter()
+        .map(|sound| {
+            audio_sources.add(AudioSource {
+                bytes: wav(&sound.samples(), audio_settings.volume).into(),
+            })
+        })
+        .collect();
+}
+
+/// Plays the events the game log gained since the last frame.
+pub fn play_sounds(
+    mut played_until: Local<Option<u32>>,
+    displayed_game: Res<DisplayedGame>,
+    audio_settings: Res<AudioSettings>,
+    sounds: Res<Sounds>,
+    audio: Res<Audio>,
+) {
+    let display_state = match &displayed_game.state {
+        Some(display_state) => display_state,
+        None => {
+            *played_until = None;
+            return;
+        }
+    };
+    // the events from before joining or reconnecting are left out
+    let since = match *played_until {
+        Some(since) if since <= display_state.tick => since,
+        _ => display_state.tick,
+    };
+    *played_until = Some(display_state.tick);
+    if audio_settings.volume <= 0.0 {
+        return;
+    }
+    for (tick, event) in display_state.events.iter() {
+        if *tick > since {
+            if let Some(handle) = Sound::of(*event).and_then(|sound| sounds.0.get(sound as usize)) {
+                audio.play(handle.clone());
+            }
+        }
+    }
+}
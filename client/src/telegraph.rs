@@ -0,0 +1,158 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    game::{AdvantageState, GameDisplayState},
+    settings::GraphicsSettings,
+    LevelElementKind, MapDefinition,
+};
+
+use crate::{update_transform, DisplayedGame};
+
+// Faint line from the cannon to where its next straight shot lands, the first platform in the
+// defender's half or the arena's edge, so the defender can see where to step out of. Spread and
+// ricochets aren't traced, only the straight shot they start from.
+
+/// Between the cannon's outline and the objects.
+const TELEGRAPH_Z: f32 = 4.9;
+const LINE_WIDTH: f32 = 2.0;
+const LANDING_MARKER_WIDTH: f32 = 30.0;
+const LANDING_MARKER_HEIGHT: f32 = 4.0;
+const TELEGRAPH_ALPHA: f32 = 0.25;
+/// Multiplier of the line's width in high contrast, which also makes it more opaque.
+const HIGH_CONTRAST_SCALE: f32 = 2.0;
+
+#[derive(Clone, Copy)]
+pub enum TelegraphPart {
+    Line,
+    LandingMarker,
+}
+
+pub fn setup_telegraph(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    for (part, size) in [
+        (TelegraphPart::Line, Vec2::new(LINE_WIDTH, 0.0)),
+        (
+            TelegraphPart::LandingMarker,
+            Vec2::new(LANDING_MARKER_WIDTH, LANDING_MARKER_HEIGHT),
+        ),
+    ]
+    .iter()
+    {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(Color::NONE.into()),
+                sprite: Sprite::new(*size),
+                transform: Transform::from_xyz(0.0, 0.0, TELEGRAPH_Z),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(*part);
+    }
+}
+
+/// Height a straight shot from the cannon at `x` stops at, fired up or down from the middle line.
+fn landing_y(map: &MapDefinition, x: f32, upwards: bool) -> f32 {
+    let center_y = map.center().y;
+    map.level
+        .iter()
+        .filter(|element| matches!(element.kind, LevelElementKind::Platform))
+        .flat_map(|element| {
+            let rect = &element.rect;
+            let (mirrored_x, mirrored_y) = map.mirror(rect.x, rect.y);
+            vec![
+                (rect.x, rect.y, rect.w, rect.h),
+                (mirrored_x, mirrored_y, rect.w, rect.h),
+            ]
+        })
+        .filter(|(platform_x, _, w, _)| (x - platform_x).abs() <= w / 2.0)
+        // the face the shot hits
+        .map(|(_, platform_y, _, h)| {
+            if upwards {
+                platform_y - h / 2.0
+            } else {
+                platform_y + h / 2.0
+            }
+        })
+        .filter(|y| {
+            if upwards {
+                *y > center_y
+            } else {
+                *y < center_y
+            }
+        })
+        .fold(if upwards { map.height } else { 0.0 }, |nearest, y| {
+            if upwards {
+                nearest.min(y)
+            } else {
+                nearest.max(y)
+            }
+        })
+}
+
+/// Where the line starts and ends, and the attacker's color, while a player holds the advantage.
+fn telegraph(map: &MapDefinition, display_state: &GameDisplayState) -> Option<(Vec2, Vec2, Color)> {
+    let (upwards, color) = match display_state.advantage_state {
+        AdvantageState::Neutral => return None,
+        // the first player fires into the second one's half, above the middle line
+        AdvantageState::Player1 => (true, Color::BLUE),
+        AdvantageState::Player2 => (false, Color::RED),
+    };
+    if display_state.round_result.is_some() || display_state.match_result.is_some() {
+        return None;
+    }
+    let x = display_state.cannon_x_position;
+    let start = Vec2::new(x, map.center().y);
+    let end = Vec2::new(x, landing_y(map, x, upwards));
+    Some((start, end, color))
+}
+
+pub fn telegraph_view(
+    displayed_game: Res<DisplayedGame>,
+    graphics: Res<GraphicsSettings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(
+        &TelegraphPart,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visible,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let telegraph = displayed_game
+        .state
+        .as_ref()
+        .and_then(|display_state| telegraph(&displayed_game.map, display_state));
+    let scale = if graphics.high_contrast {
+        HIGH_CONTRAST_SCALE
+    } else {
+        1.0
+    };
+    for (part, mut transform, mut sprite, mut visible, material) in query.iter_mut() {
+        let (start, end, mut color) = match telegraph {
+            Some(telegraph) => telegraph,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        visible.is_visible = true;
+        color.set_a(TELEGRAPH_ALPHA * scale);
+        if let Some(material) = materials.get_mut(material) {
+            if material.color != color {
+                material.color = color;
+            }
+        }
+        match part {
+            TelegraphPart::Line => {
+                let middle = (start + end) / 2.0;
+                update_transform(&mut transform, middle.x, middle.y, &displayed_game);
+                sprite.size = Vec2::new(LINE_WIDTH * scale, (end.y - start.y).abs());
+            }
+            TelegraphPart::LandingMarker => {
+                update_transform(&mut transform, end.x, end.y, &displayed_game);
+                sprite.size = Vec2::new(LANDING_MARKER_WIDTH, LANDING_MARKER_HEIGHT) * scale;
+            }
+        }
+    }
+}
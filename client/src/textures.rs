@@ -0,0 +1,72 @@
+use platformer_shared::bevy::{asset::LoadState, prelude::*, sprite::SpriteResizeMode};
+
+// Textures of the scene's sprites. The sprites are spawned as colored quads and take their
+// texture once it loads, so a texture that's missing or broken leaves its quad as it was.
+
+pub const PLATFORM_TEXTURE: &str = "textures/platform.png";
+pub const LAVA_TEXTURES: [&str; 4] = [
+    "textures/lava_0.png",
+    "textures/lava_1.png",
+    "textures/lava_2.png",
+    "textures/lava_3.png",
+];
+pub const POWER_PAD_TEXTURE: &str = "textures/power_pad.png";
+pub const CANNON_TEXTURE: &str = "textures/cannon.png";
+pub const HAZARD_CANNON_TEXTURE: &str = "textures/hazard_cannon.png";
+pub const PROJECTILE_TEXTURE: &str = "textures/projectile.png";
+
+/// Seconds each frame of an animated texture is shown for.
+const FRAME_SECONDS: f64 = 0.25;
+
+/// Texture a sprite takes over its color, a frame after the other if it's animated.
+pub struct Textured {
+    frames: Vec<Handle<Texture>>,
+    /// Color the texture is multiplied by, white to show it as it is.
+    tint: Color,
+    shown_frame: Option<usize>,
+}
+
+impl Textured {
+    pub fn new(asset_server: &AssetServer, path: &str, tint: Color) -> Self {
+        Self::animated(asset_server, &[path], tint)
+    }
+
+    pub fn animated(asset_server: &AssetServer, paths: &[&str], tint: Color) -> Self {
+        Self {
+            frames: paths.iter().map(|path| asset_server.load(*path)).collect(),
+            tint,
+            shown_frame: None,
+        }
+    }
+}
+
+pub fn apply_textures(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut sprites: Query<(Entity, &mut Textured, &mut Sprite, &Handle<ColorMaterial>)>,
+) {
+    for (entity, mut textured, mut sprite, material) in sprites.iter_mut() {
+        match asset_server.get_group_load_state(textured.frames.iter().map(|frame| frame.id)) {
+            LoadState::Loaded => (),
+            // the asset server already logged why
+            LoadState::Failed => {
+                commands.entity(entity).remove::<Textured>();
+                continue;
+            }
+            _ => continue,
+        }
+        let frame = (time.seconds_since_startup() / FRAME_SECONDS) as usize % textured.frames.len();
+        if textured.shown_frame == Some(frame) {
+            continue;
+        }
+        textured.shown_frame = Some(frame);
+        // the sprite keeps the size of its quad instead of taking the texture's
+        sprite.resize_mode = SpriteResizeMode::Manual;
+        if let Some(material) = materials.get_mut(material) {
+            material.color = textured.tint;
+            material.texture = Some(textured.frames[frame].clone());
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use platformer_shared::{
+    bevy::{input::gamepad::GamepadEventType, prelude::*},
+    crystalorb_bevy_networking_turbulence::bevy_networking_turbulence::NetworkEvent,
+    game::{AdvantageState, CrowdEvent, PlayerId},
+};
+use std::collections::VecDeque;
+
+use crate::{bracket::TournamentBracket, session::Seat, DisplayedGame, UI_FONT};
+
+// Short notifications stacked in the top right corner.
+// Anything the player should notice goes through `Toasts` instead of only being logged.
+
+const TOAST_SECONDS: f64 = 4.0;
+const MAX_VISIBLE_TOASTS: usize = 4;
+/// How long the opponent has to stand still before they are reported as away.
+const AFK_WARNING_SECONDS: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::WHITE,
+            Severity::Warning => Color::YELLOW,
+            Severity::Error => Color::RED,
+        }
+    }
+}
+
+struct Toast {
+    severity: Severity,
+    message: String,
+    /// Set once the toast is shown.
+    expires_at: Option<f64>,
+}
+
+/// Toasts waiting to be shown and the ones on screen. Toasts beyond `MAX_VISIBLE_TOASTS` wait
+/// in the queue until a slot frees up.
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    shown: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Info => info!("{}", message),
+            Severity::Warning => warn!("{}", message),
+            Severity::Error => error!("{}", message),
+        }
+        self.queue.push_back(Toast {
+            severity,
+            message,
+            expires_at: None,
+        });
+    }
+
+    fn needs_update(&self, now: f64) -> bool {
+        (!self.queue.is_empty() && self.shown.len() < MAX_VISIBLE_TOASTS)
+            || self.shown.iter().any(|toast| {
+                toast
+                    .expires_at
+                    .map_or(false, |expires_at| expires_at <= now)
+            })
+    }
+}
+
+/// Slot index of a toast line, from the top.
+pub struct ToastText(usize);
+
+pub fn setup_toasts(mut commands: Commands, asset_server: Res<AssetServer>) {
+    for slot in 0..MAX_VISIBLE_TOASTS {
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(5.0 + slot as f32 * 24.0),
+                        right: Val::Px(5.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(UI_FONT),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(ToastText(slot));
+    }
+}
+
+pub fn update_toasts(
+    time: Res<Time>,
+    mut toasts: ResMut<Toasts>,
+    mut query: Query<(&ToastText, &mut Text, &mut Visible)>,
+) {
+    let now = time.seconds_since_startup();
+    if !toasts.needs_update(now) {
+        return;
+    }
+
+    toasts
+        .shown
+        .retain(|toast| toast.expires_at.map_or(true, |expires_at| expires_at > now));
+    while toasts.shown.len() < MAX_VISIBLE_TOASTS {
+        match toasts.queue.pop_front() {
+            Some(mut toast) => {
+                toast.expires_at = Some(now + TOAST_SECONDS);
+                toasts.shown.push(toast);
+            }
+            None => break,
+        }
+    }
+
+    for (slot, mut text, mut visible) in query.iter_mut() {
+        match toasts.shown.get(slot.0) {
+            Some(toast) => {
+                text.sections[0].value = toast.message.clone();
+                text.sections[0].style.color = toast.severity.color();
+                visible.is_visible = true;
+            }
+            None => visible.is_visible = false,
+        }
+    }
+}
+
+pub fn connection_toasts(mut events: EventReader<NetworkEvent>, mut toasts: ResMut<Toasts>) {
+    for event in events.iter() {
+        match event {
+            NetworkEvent::Connected(_) => toasts.push(Severity::Info, "Connected to the server"),
+            NetworkEvent::Disconnected(_) => {
+                toasts.push(Severity::Error, "Disconnected from the server")
+            }
+            _ => (),
+        }
+    }
+}
+
+pub fn seat_toasts(seat: Res<Seat>, bracket: Res<TournamentBracket>, mut toasts: ResMut<Toasts>) {
+    if seat.is_changed() && seat.spectating {
+        if bracket.0.is_some() {
+            toasts.push(Severity::Info, "Watching the tournament");
+        } else {
+            toasts.push(Severity::Info, "Both seats are taken, spectating");
+        }
+    }
+}
+
+pub fn gamepad_toasts(mut events: EventReader<GamepadEvent>, mut toasts: ResMut<Toasts>) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => toasts.push(
+                Severity::Info,
+                format!("Controller {} connected", gamepad.0 + 1),
+            ),
+            GamepadEventType::Disconnected => toasts.push(
+                Severity::Warning,
+                format!("Controller {} disconnected", gamepad.0 + 1),
+            ),
+            _ => (),
+        }
+    }
+}
+
+/// Announces crowd events as they start.
+pub fn crowd_event_toasts(
+    displayed_game: Res<DisplayedGame>,
+    mut previous: Local<Option<CrowdEvent>>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if let Some(display_state) = &displayed_game.state {
+        if display_state.crowd_event != *previous {
+            if let Some(crowd_event) = display_state.crowd_event {
+                toasts.push(Severity::Info, crowd_event.to_string());
+            }
+            *previous = display_state.crowd_event;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameToastsState {
+    advantage_state: Option<AdvantageState>,
+    opponent_activity: Option<Vec2>,
+    opponent_still_since: f64,
+    afk_warned: bool,
+}
+
+/// Reports game events that are easy to miss: the local player's pad starting to move and
+/// an opponent who stopped playing.
+pub fn game_toasts(
+    time: Res<Time>,
+    displayed_game: Res<DisplayedGame>,
+    mut state: Local<GameToastsState>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let (display_state, local_player) = match (&displayed_game.state, displayed_game.local_player) {
+        (Some(display_state), Some(local_player)) => (display_state, local_player),
+        _ => return,
+    };
+    let (opponent_advantage, opponent_position) = match local_player {
+        PlayerId::Player1 => (AdvantageState::Player2, display_state.player2_position),
+        PlayerId::Player2 => (AdvantageState::Player1, display_state.player1_position),
+    };
+
+    // capturing a pad sends the opponent's pad patrolling
+    if state.advantage_state != Some(display_state.advantage_state) {
+        if state.advantage_state.is_some() && display_state.advantage_state == opponent_advantage {
+            toasts.push(Severity::Warning, "Your power pad is on the move!");
+        }
+        state.advantage_state = Some(display_state.advantage_state);
+    }
+
+    // an advantaged player stands still while aiming the cannon
+    let now = time.seconds_since_startup();
+    let activity = if display_state.advantage_state == opponent_advantage {
+        Vec2::new(display_state.cannon_x_position, 0.0)
+    } else {
+        Vec2::new(
+            opponent_position.translation.vector.x,
+            opponent_position.translation.vector.y,
+        )
+    };
+    if state
+        .opponent_activity
+        .map_or(true, |previous| previous.distance(activity) > 1.0)
+    {
+        state.opponent_activity = Some(activity);
+        state.opponent_still_since = now;
+        state.afk_warned = false;
+    } else if !state.afk_warned && now - state.opponent_still_since > AFK_WARNING_SECONDS {
+        toasts.push(Severity::Warning, "Your opponent seems to be away");
+        state.afk_warned = true;
+    }
+}
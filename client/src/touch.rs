@@ -0,0 +1,155 @@
+use platformer_shared::{bevy::prelude::*, game::PlayerInput};
+
+use crate::{settings_menu::SettingsMenu, DisplayedGame, UI_FONT};
+
+// On-screen buttons for touch screens, so that the browser build plays on phones and tablets.
+// They show up with the first touch and are held like keys: left and right in the bottom left
+// corner, jump and fire in the bottom right one.
+
+const TOUCH_BUTTON_SIZE: f32 = 96.0;
+const TOUCH_BUTTON_MARGIN: f32 = 16.0;
+
+#[derive(Default)]
+pub struct TouchControls {
+    /// Set once the screen was touched, the buttons are hidden until then.
+    shown: bool,
+    /// What the buttons hold this frame, merged with the keyboard by `player_input`.
+    pub input: PlayerInput,
+}
+
+#[derive(Clone, Copy)]
+pub enum TouchButton {
+    Left,
+    Right,
+    Action,
+}
+
+pub fn setup_touch_controls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let material = materials.add(Color::rgba(1.0, 1.0, 1.0, 0.2).into());
+    let buttons = [
+        (
+            TouchButton::Left,
+            "<",
+            Rect {
+                left: Val::Px(TOUCH_BUTTON_MARGIN),
+                bottom: Val::Px(TOUCH_BUTTON_MARGIN),
+                ..Default::default()
+            },
+        ),
+        (
+            TouchButton::Right,
+            ">",
+            Rect {
+                left: Val::Px(TOUCH_BUTTON_SIZE + 2.0 * TOUCH_BUTTON_MARGIN),
+                bottom: Val::Px(TOUCH_BUTTON_MARGIN),
+                ..Default::default()
+            },
+        ),
+        (
+            TouchButton::Action,
+            "●",
+            Rect {
+                right: Val::Px(TOUCH_BUTTON_MARGIN),
+                bottom: Val::Px(TOUCH_BUTTON_MARGIN),
+                ..Default::default()
+            },
+        ),
+    ];
+    for (button, label, position) in buttons.iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: *position,
+                    size: Size::new(Val::Px(TOUCH_BUTTON_SIZE), Val::Px(TOUCH_BUTTON_SIZE)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: material.clone(),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(*button)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        *label,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 48.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                });
+            });
+    }
+}
+
+/// Holds the buttons under the fingers. Touches are laid over the buttons the way bevy's UI
+/// focus lays the cursor over them.
+pub fn read_touches(
+    touches: Res<Touches>,
+    buttons: Query<(&TouchButton, &Node, &GlobalTransform, &Visible)>,
+    mut controls: ResMut<TouchControls>,
+) {
+    if !controls.shown && touches.iter().next().is_some() {
+        controls.shown = true;
+    }
+
+    let mut input = PlayerInput::default();
+    for (button, node, transform, visible) in buttons.iter() {
+        if !visible.is_visible {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let min = position - node.size / 2.0;
+        let max = position + node.size / 2.0;
+        let under = |touch: &Touch| {
+            let touched = touch.position();
+            touched.x >= min.x && touched.x <= max.x && touched.y >= min.y && touched.y <= max.y
+        };
+        match button {
+            TouchButton::Left => input.left |= touches.iter().any(under),
+            TouchButton::Right => input.right |= touches.iter().any(under),
+            TouchButton::Action => input.action |= touches.iter_just_pressed().any(under),
+        }
+    }
+    controls.input = input;
+}
+
+/// Shows the buttons to touch screen players while they play.
+pub fn update_touch_controls(
+    controls: Res<TouchControls>,
+    displayed_game: Res<DisplayedGame>,
+    settings_menu: Res<SettingsMenu>,
+    mut buttons: Query<(&mut Visible, &Children), With<TouchButton>>,
+    mut labels: Query<&mut Visible, Without<TouchButton>>,
+) {
+    let visible = controls.shown && displayed_game.local_player.is_some() && !settings_menu.open;
+    for (mut button_visible, children) in buttons.iter_mut() {
+        if button_visible.is_visible == visible {
+            continue;
+        }
+        button_visible.is_visible = visible;
+        for child in children.iter() {
+            if let Ok(mut label_visible) = labels.get_mut(*child) {
+                label_visible.is_visible = visible;
+            }
+        }
+    }
+}
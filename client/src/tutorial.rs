@@ -0,0 +1,280 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::crystalorb::world::World,
+    game::{
+        AdvantageState, GameCommand, GameDisplayState, GameWorld, MatchSettings, PlayerId,
+        PlayerInput,
+    },
+    scenario::{Scenario, ScenarioAction},
+    TIMESTEP,
+};
+
+use crate::{DisplayedGame, UI_FONT};
+
+// Guided tutorial played against a scripted opponent in a local `GameWorld`.
+// Each step runs its own scenario and is completed once its condition holds on the display state.
+
+const TICKS_PER_SECOND: u32 = 60;
+
+struct StepContext<'a> {
+    start: &'a GameDisplayState,
+    previous: &'a GameDisplayState,
+    current: &'a GameDisplayState,
+    elapsed_ticks: u32,
+}
+
+struct TutorialStep {
+    prompt: &'static str,
+    /// Scenario actions, scheduled relative to the start of the step.
+    script: fn() -> Vec<(u32, ScenarioAction)>,
+    completed: fn(&StepContext) -> bool,
+    /// When true, the step is started over.
+    failed: fn(&StepContext) -> bool,
+}
+
+pub struct Tutorial {
+    world: GameWorld,
+    scenario: Scenario,
+    steps: Vec<TutorialStep>,
+    current_step: usize,
+    step_start: GameDisplayState,
+    previous: GameDisplayState,
+    accumulator: f64,
+}
+
+pub struct TutorialPromptText;
+
+fn no_script() -> Vec<(u32, ScenarioAction)> {
+    vec![]
+}
+
+fn never(_: &StepContext) -> bool {
+    false
+}
+
+fn opponent_input(tick: u32, input: PlayerInput) -> (u32, ScenarioAction) {
+    (
+        tick,
+        ScenarioAction::Command(GameCommand::Input(PlayerId::Player2, input.into())),
+    )
+}
+
+const JUMP: PlayerInput = PlayerInput {
+    action: true,
+    left: false,
+    right: false,
+    grapple: false,
+    ability: false,
+    mortar: false,
+    ricochet: false,
+};
+
+const RELEASED: PlayerInput = PlayerInput {
+    action: false,
+    left: false,
+    right: false,
+    grapple: false,
+    ability: false,
+    mortar: false,
+    ricochet: false,
+};
+
+fn steps() -> Vec<TutorialStep> {
+    vec![
+        TutorialStep {
+            prompt: "Use the Left and Right arrow keys to move.",
+            script: no_script,
+            completed: |ctx| {
+                (ctx.current.player1_position.translation.vector.x
+                    - ctx.start.player1_position.translation.vector.x)
+                    .abs()
+                    > 100.0
+            },
+            failed: never,
+        },
+        TutorialStep {
+            prompt: "Press Space to jump.",
+            script: no_script,
+            completed: |ctx| {
+                ctx.current.player1_position.translation.vector.y
+                    - ctx.previous.player1_position.translation.vector.y
+                    > 4.0
+            },
+            failed: never,
+        },
+        TutorialStep {
+            prompt: "Your opponent lives in the upper half, where gravity is mirrored.\nWatch them jump.",
+            script: || {
+                vec![
+                    opponent_input(TICKS_PER_SECOND, JUMP),
+                    opponent_input(TICKS_PER_SECOND + 1, RELEASED),
+                    opponent_input(3 * TICKS_PER_SECOND, JUMP),
+                    opponent_input(3 * TICKS_PER_SECOND + 1, RELEASED),
+                ]
+            },
+            completed: |ctx| ctx.elapsed_ticks > 5 * TICKS_PER_SECOND,
+            failed: never,
+        },
+        TutorialStep {
+            prompt: "Stand on your power pad until it fills up\nto gain the advantage. Avoid the lava!",
+            script: no_script,
+            completed: |ctx| ctx.current.advantage_state == AdvantageState::Player1,
+            failed: never,
+        },
+        TutorialStep {
+            prompt: "You control the cannon now: aim with Left and Right\nand fire with Space. Hit your opponent!",
+            script: no_script,
+            completed: |ctx| ctx.current.round != ctx.start.round,
+            failed: never,
+        },
+        TutorialStep {
+            prompt: "Your opponent has the advantage now.\nDodge their shots for 10 seconds!",
+            script: || {
+                let mut script = vec![(0, ScenarioAction::SetAdvantage(AdvantageState::Player2))];
+                for i in 0..10 {
+                    let tick = i * TICKS_PER_SECOND;
+                    // sweep the cannon back and forth, firing in between
+                    let turn = PlayerInput {
+                        left: i % 2 == 0,
+                        right: i % 2 == 1,
+                        ..RELEASED
+                    };
+                    script.push(opponent_input(tick, turn));
+                    // the action fires the cannon while holding the advantage
+                    script.push(opponent_input(tick + TICKS_PER_SECOND / 2, JUMP));
+                    script.push(opponent_input(tick + TICKS_PER_SECOND / 2 + 1, RELEASED));
+                }
+                script
+            },
+            completed: |ctx| ctx.elapsed_ticks > 10 * TICKS_PER_SECOND,
+            failed: |ctx| ctx.current.round != ctx.start.round,
+        },
+        TutorialStep {
+            prompt: "Tutorial complete! Press Escape to quit.",
+            script: no_script,
+            completed: never,
+            failed: never,
+        },
+    ]
+}
+
+impl Tutorial {
+    fn new() -> Self {
+        let mut world = GameWorld::new();
+        // the tutorial goes on for as long as it takes
+        world.set_match_settings(MatchSettings {
+            score_target: None,
+            round_limit: None,
+            round_time_limit: None,
+            ..Default::default()
+        });
+        let display_state = world.display_state();
+        let steps = steps();
+        Self {
+            world,
+            scenario: Scenario::new((steps[0].script)()),
+            steps,
+            current_step: 0,
+            step_start: display_state.clone(),
+            previous: display_state,
+            accumulator: 0.0,
+        }
+    }
+
+    fn start_step(&mut self, step: usize) {
+        self.current_step = step;
+        self.scenario = Scenario::new((self.steps[step].script)());
+        self.step_start = self.world.display_state();
+    }
+
+    fn tick(&mut self) {
+        self.scenario.step(&mut self.world);
+
+        let current = self.world.display_state();
+        let step = &self.steps[self.current_step];
+        let ctx = StepContext {
+            start: &self.step_start,
+            previous: &self.previous,
+            current: &current,
+            elapsed_ticks: self.scenario.tick(),
+        };
+        let completed = (step.completed)(&ctx);
+        let failed = (step.failed)(&ctx);
+        self.previous = current;
+
+        if failed {
+            self.start_step(self.current_step);
+        } else if completed {
+            self.start_step(self.current_step + 1);
+        }
+    }
+}
+
+pub fn setup_tutorial(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut displayed_game: ResMut<DisplayedGame>,
+) {
+    displayed_game.local_player = Some(PlayerId::Player1);
+    commands.insert_resource(Tutorial::new());
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(TutorialPromptText);
+}
+
+pub fn step_tutorial(
+    time: Res<Time>,
+    mut game_commands: EventReader<GameCommand>,
+    mut tutorial: ResMut<Tutorial>,
+    mut displayed_game: ResMut<DisplayedGame>,
+) {
+    for command in game_commands.iter() {
+        tutorial.world.apply_command(command);
+    }
+
+    tutorial.accumulator += time.delta_seconds_f64();
+    while tutorial.accumulator >= TIMESTEP {
+        tutorial.accumulator -= TIMESTEP;
+        tutorial.tick();
+    }
+
+    displayed_game.show(tutorial.world.display_state(), time.seconds_since_startup());
+}
+
+pub fn update_tutorial_prompt(
+    tutorial: Res<Tutorial>,
+    mut query: Query<&mut Text, With<TutorialPromptText>>,
+) {
+    if tutorial.is_changed() {
+        let step = &tutorial.steps[tutorial.current_step];
+        for mut text in query.iter_mut() {
+            text.sections[0].value = format!(
+                "{}/{}: {}",
+                tutorial.current_step + 1,
+                tutorial.steps.len(),
+                step.prompt
+            );
+        }
+    }
+}
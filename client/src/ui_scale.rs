@@ -0,0 +1,80 @@
+use platformer_shared::{bevy::prelude::*, settings::GraphicsSettings};
+
+use crate::settings_menu::SettingsMenuPart;
+
+// The HUD's size and its distance from the edges of the window, set in the settings menu. Text
+// is scaled from the size it was spawned with, and the panels placed in pixels from an edge are
+// moved by the scale and the safe area margin. The settings menu keeps its size, so that it stays
+// usable whatever the scale.
+
+/// Font sizes a text was spawned with, one per section.
+pub struct BaseFontSizes(Vec<f32>);
+
+/// Position a panel was spawned with.
+pub struct BasePosition(Rect<Val>);
+
+/// Remembers the sizes and positions of new UI entities before they're scaled.
+pub fn record_ui_base(
+    mut commands: Commands,
+    texts: Query<
+        (Entity, &Text),
+        (
+            With<Node>,
+            Without<BaseFontSizes>,
+            Without<SettingsMenuPart>,
+        ),
+    >,
+    panels: Query<(Entity, &Style), (With<Node>, Without<Parent>, Without<BasePosition>)>,
+) {
+    for (entity, text) in texts.iter() {
+        let font_sizes = text
+            .sections
+            .iter()
+            .map(|section| section.style.font_size)
+            .collect();
+        commands.entity(entity).insert(BaseFontSizes(font_sizes));
+    }
+    for (entity, style) in panels.iter() {
+        commands.entity(entity).insert(BasePosition(style.position));
+    }
+}
+
+fn scaled_offset(base: Val, graphics: &GraphicsSettings) -> Val {
+    match base {
+        Val::Px(offset) => Val::Px(offset * graphics.ui_scale + graphics.safe_area),
+        other => other,
+    }
+}
+
+pub fn apply_ui_scale(
+    graphics: Res<GraphicsSettings>,
+    mut texts: Query<(&BaseFontSizes, &mut Text)>,
+    mut panels: Query<(&BasePosition, &mut Style)>,
+) {
+    for (base, mut text) in texts.iter_mut() {
+        // only written when it differs, as changed text is laid out again
+        let scaled = base
+            .0
+            .iter()
+            .zip(text.sections.iter())
+            .all(|(size, section)| {
+                (section.style.font_size - size * graphics.ui_scale).abs() < f32::EPSILON
+            });
+        if !scaled {
+            for (size, section) in base.0.iter().zip(text.sections.iter_mut()) {
+                section.style.font_size = size * graphics.ui_scale;
+            }
+        }
+    }
+    for (base, mut style) in panels.iter_mut() {
+        let position = Rect {
+            left: scaled_offset(base.0.left, &graphics),
+            right: scaled_offset(base.0.right, &graphics),
+            top: scaled_offset(base.0.top, &graphics),
+            bottom: scaled_offset(base.0.bottom, &graphics),
+        };
+        if style.position != position {
+            style.position = position;
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Compares the codecs on a mid-fight snapshot and on a command, the messages sent most often.
+//! Run with `cargo bench --package platformer-core`.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::{
+    codec::{Bincode, Codec, CompactBincode},
+    game::{AdvantageState, GameCommand, GameWorld, PlayerId, PlayerInput},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Instant;
+
+const ITERATIONS: u32 = 10_000;
+
+fn measure<C: Codec, T: Serialize + DeserializeOwned>(label: &str, value: &T) {
+    let bytes = C::encode(value).unwrap();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let bytes = C::encode(value).unwrap();
+        C::decode::<T>(&bytes).unwrap();
+    }
+    println!(
+        "{:>16} {:>10}: {:>5} bytes, {:>8.2?} per round trip",
+        C::NAME,
+        label,
+        bytes.len(),
+        start.elapsed() / ITERATIONS
+    );
+}
+
+fn main() {
+    // same fight as the snapshot size tests
+    let mut world = GameWorld::new();
+    world.set_advantage_state(AdvantageState::Player1);
+    let firing = PlayerInput {
+        action: true,
+        ..Default::default()
+    };
+    let command = GameCommand::Input(PlayerId::Player1, firing.into());
+    world.apply_command(&command);
+    for _ in 0..10 {
+        world.step();
+    }
+    let snapshot = world.snapshot();
+
+    measure::<Bincode, _>("snapshot", &snapshot);
+    measure::<CompactBincode, _>("snapshot", &snapshot);
+    measure::<Bincode, _>("command", &command);
+    measure::<CompactBincode, _>("command", &command);
+}
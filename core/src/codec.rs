@@ -0,0 +1,53 @@
+//! Encodings of the messages the game serializes itself: the snapshots and commands on the
+//! crystalorb channels, and the LAN beacons. `DefaultCodec` is plain bincode, unless the
+//! `compact-codec` feature swaps in variable-length integers. `cargo bench --package
+//! platformer-core` compares their sizes on snapshots and commands.
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub type CodecError = bincode::Error;
+
+pub trait Codec {
+    /// Shown in benchmarks.
+    const NAME: &'static str;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError>;
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Bincode with fixed-size integers.
+pub struct Bincode;
+
+impl Codec for Bincode {
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::serialize(value)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// Bincode with variable-length integers, which shrinks the many small counters and ids.
+pub struct CompactBincode;
+
+impl Codec for CompactBincode {
+    const NAME: &'static str = "compact bincode";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::DefaultOptions::new().serialize(value)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+        bincode::DefaultOptions::new().deserialize(bytes)
+    }
+}
+
+#[cfg(not(feature = "compact-codec"))]
+pub type DefaultCodec = Bincode;
+#[cfg(feature = "compact-codec")]
+pub type DefaultCodec = CompactBincode;
@@ -0,0 +1,2845 @@
+//! Main game logic.
+//! Based on https://github.com/ErnWong/crystalorb/blob/master/examples/demo/src/lib.rs
+
+use crystalorb::{
+    command::Command,
+    fixed_timestepper::Stepper,
+    world::{DisplayState, World},
+};
+use log::debug;
+use rapier2d::{na::Vector2, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Display},
+    sync::Arc,
+};
+
+use crate::{
+    quantize::{QuantizedPosition, QuantizedVelocity},
+    LevelElementKind, MapDefinition, Position, PowerPadPositions, Rect, POWER_PAD_SIZE,
+    PROJECTILE_SIZE, TIMESTEP,
+};
+
+pub const PHYSICS_SCALE: f32 = 20.0;
+const GRAVITY: Vector2<Real> = Vector2::new(0.0, 0.0);
+/// Number of ticks a player has to stand on their power pad to capture it.
+const POWER_PAD_CAPTURE_TICKS: u16 = 60;
+/// Number of ticks a patrolling power pad takes to move from one side to the other.
+const POWER_PAD_PATROL_TICKS: u32 = 600;
+/// Number of ticks a gate stays open after its switch is hit.
+const GATE_OPEN_TICKS: u16 = 180;
+/// Number of ticks between the shots of the hazard cannons.
+const HAZARD_CANNON_PERIOD_TICKS: u32 = 600;
+const HAZARD_PROJECTILE_SPEED: f32 = 4.0;
+/// Ticks the applied commands are kept around for, see `GameDisplayState::applied_commands`.
+const APPLIED_COMMANDS_TICKS: u32 = 120;
+/// Ticks the game events are kept around for, see `GameDisplayState::events`.
+const EVENTS_TICKS: u32 = 120;
+/// Number of ticks the dead players ragdoll for before the next round starts.
+pub const CORPSE_TICKS: u16 = 30;
+/// Angular velocity a corpse starts tumbling with.
+const CORPSE_SPIN: f32 = 10.0;
+/// Longest stretch of a round that gets recorded for the ghost.
+const GHOST_MAX_TICKS: usize = 2 * 60 * 60;
+/// Angles of the spread-shot's projectiles, relative to straight ahead, in radians.
+const SPREAD_SHOT_ANGLES: [f32; 3] = [-0.25, 0.0, 0.25];
+/// Number of ticks before the spread-shot can be used again.
+const SPREAD_SHOT_COOLDOWN_TICKS: u16 = 180;
+/// Speed at which stomping players plunge towards their floor.
+const STOMP_SPEED: f32 = 30.0;
+const STOMP_COOLDOWN_TICKS: u16 = 120;
+/// Projectiles within this distance of a landing stomp are destroyed, in display units.
+pub const SHOCKWAVE_RADIUS: f32 = 80.0;
+/// Number of ticks the shockwave of a stomp is shown for.
+const SHOCKWAVE_TICKS: u16 = 15;
+/// Ticks between the practice shots of a player warming up in the lobby.
+const PRACTICE_SHOT_COOLDOWN_TICKS: u32 = 20;
+/// Ticks the advantaged player has to keep standing on their pad to earn a shield.
+const SHIELD_PAD_HOLD_TICKS: u16 = 300;
+/// Ticks an emote is shown above the player for.
+const EMOTE_TICKS: u32 = 120;
+/// Ticks a player has to wait between emotes, the ones sent sooner are dropped.
+const EMOTE_COOLDOWN_TICKS: u32 = 60;
+/// Most points a single kill can be worth on a streak.
+pub const MAX_STREAK_MULTIPLIER: u8 = 3;
+/// Sideways launch angle of a mortar shot fired while the cannon is moving, in radians.
+const MORTAR_ANGLE: f32 = 0.8;
+const MORTAR_SPEED: f32 = 4.0;
+/// Multiplier of the arena gravity applied to mortar shots.
+const MORTAR_GRAVITY_SCALE: f32 = 0.3;
+/// Ticks the game stays frozen between rounds, while the round result is shown.
+pub const POST_ROUND_TICKS: u16 = 120;
+/// Ticks between the starts of two crowd events.
+const CROWD_EVENT_PERIOD_TICKS: u32 = 2700;
+/// Ticks a crowd event lasts.
+const CROWD_EVENT_TICKS: u32 = 300;
+/// Platform bounces of a ricochet shot before it despawns.
+const RICOCHET_BOUNCES: u8 = 3;
+/// Largest deflection of a cannon shot with the spread rule, in radians.
+const CANNON_SPREAD_MAX_ANGLE: f32 = 0.15;
+const CANNON_SPREAD_SEED: u32 = 0x9e37_79b9;
+const SPAWN_POINT_SEED: u32 = 0x85eb_ca6b;
+/// Maximum length of the grappling hook rope, in display units.
+const GRAPPLE_MAX_LENGTH: f32 = 300.0;
+/// How much of the movement speed is applied per tick while swinging.
+const GRAPPLE_SWING_CONTROL: f32 = 0.02;
+
+/// Identifies a player. Used as key in maps.
+/// Uses the same value as the client's `client_handle`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlayerId {
+    Player1,
+    Player2,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdvantageState {
+    Neutral,
+    Player1,
+    Player2,
+}
+
+/// Direction a player looks in, in the world's coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    Left,
+    Right,
+}
+
+impl Facing {
+    /// Towards the middle of the map, the way players look when they spawn.
+    fn towards_center(map: &MapDefinition, position: Position) -> Self {
+        if position.x < map.center().x {
+            Facing::Right
+        } else {
+            Facing::Left
+        }
+    }
+}
+
+/// Balance values of the simulation, picked by the server so they can be changed without
+/// rebuilding the clients.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GameTuning {
+    /// Horizontal movement speed of the players, scaled by their character.
+    pub move_speed: f32,
+    /// Vertical velocity at the start of a jump, scaled by the player's character.
+    pub jump_velocity: f32,
+    /// Multiplier of the gravity pulling the players (and mortar shots) to their floor.
+    pub gravity_scale: f32,
+    /// Distance the cannon moves per tick while being aimed, in display units.
+    pub cannon_speed: f32,
+    pub projectile_speed: f32,
+    /// Most projectiles the players have in flight at once, the hazard cannons' shots aside.
+    pub max_projectiles: u8,
+}
+
+impl Default for GameTuning {
+    fn default() -> Self {
+        Self {
+            move_speed: 15.0,
+            jump_velocity: 20.0,
+            gravity_scale: 5.0,
+            cannon_speed: 5.0,
+            projectile_speed: 6.0,
+            max_projectiles: 10,
+        }
+    }
+}
+
+/// Optional rules, picked by the server.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleVariants {
+    /// Deflects each cannon shot by a pseudo-random angle, so that advantage play requires
+    /// prediction rather than pixel-perfect aiming.
+    pub cannon_spread: bool,
+    /// Enables crowd events, short twists of the balance values at fixed times.
+    pub crowd_events: bool,
+}
+
+/// Short twist of the balance values, started every `CROWD_EVENT_PERIOD_TICKS`. Events are
+/// derived from the tick alone, so they replay identically under rollback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrowdEvent {
+    FastCannon,
+    LowGravity,
+    FastProjectiles,
+}
+
+const CROWD_EVENTS: [CrowdEvent; 3] = [
+    CrowdEvent::FastCannon,
+    CrowdEvent::LowGravity,
+    CrowdEvent::FastProjectiles,
+];
+
+impl CrowdEvent {
+    fn apply(&self, tuning: &mut GameTuning) {
+        match self {
+            CrowdEvent::FastCannon => tuning.cannon_speed *= 2.0,
+            CrowdEvent::LowGravity => tuning.gravity_scale *= 0.5,
+            CrowdEvent::FastProjectiles => tuning.projectile_speed *= 1.5,
+        }
+    }
+}
+
+impl Display for CrowdEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = (CROWD_EVENT_TICKS as f64 * TIMESTEP).round();
+        match self {
+            CrowdEvent::FastCannon => write!(f, "Double cannon speed for {} seconds!", seconds),
+            CrowdEvent::LowGravity => write!(f, "Low gravity for {} seconds!", seconds),
+            CrowdEvent::FastProjectiles => {
+                write!(f, "Faster projectiles for {} seconds!", seconds)
+            }
+        }
+    }
+}
+
+/// Match parameters, picked by the server.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchSettings {
+    /// Points a player has to score to win the match, if the match isn't endless.
+    pub score_target: Option<u8>,
+    /// Rounds after which the match ends even if nobody reached the score target.
+    pub round_limit: Option<u8>,
+    /// Seconds after which a round ends in a draw.
+    pub round_time_limit: Option<u16>,
+    /// Seconds the game waits for a disconnected player before they forfeit the match.
+    pub disconnect_grace: u16,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            score_target: Some(5),
+            round_limit: None,
+            round_time_limit: None,
+            disconnect_grace: 30,
+        }
+    }
+}
+
+/// Everything the server picks about the match, which the clients get once as they join and
+/// then through the commands changing it, rather than in every snapshot.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct MatchConfig {
+    pub rules: RuleVariants,
+    pub tuning: GameTuning,
+    pub match_settings: MatchSettings,
+}
+
+/// Running totals of a player over the whole match.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerStats {
+    /// Cannon shots, counting every projectile of a spread-shot.
+    pub shots_fired: u16,
+    /// Cannon shots that hit the opponent, shielded or not.
+    pub hits: u16,
+    pub pad_captures: u16,
+    pub deaths: u16,
+    /// In display units.
+    pub distance_traveled: f32,
+}
+
+impl PlayerStats {
+    /// Share of the shots that hit, `None` before the first shot.
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.shots_fired > 0 {
+            Some(self.hits as f32 / self.shots_fired as f32)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundResult {
+    Winner(PlayerId),
+    Draw,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchResult {
+    Winner(PlayerId),
+    Draw,
+}
+
+fn seconds_to_ticks(seconds: u16) -> u32 {
+    (seconds as f64 / TIMESTEP).round() as u32
+}
+
+/// Advances the xorshift state and returns the new value.
+fn next_random(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+/// Advances the xorshift state and returns a value in the range [-1, 1].
+fn next_spread(state: &mut u32) -> f32 {
+    next_random(state) as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// Every shot takes its id here, ids wrap around in long matches.
+fn take_projectile_id(next_projectile_id: &mut u16) -> u16 {
+    let id = *next_projectile_id;
+    *next_projectile_id = next_projectile_id.wrapping_add(1);
+    id
+}
+
+/// Picks the start positions of both players for the next round.
+fn next_spawn_points(map: &MapDefinition, state: &mut u32) -> [Position; 2] {
+    let player1_index = next_random(state) as usize;
+    let player2_index = next_random(state) as usize;
+    [
+        map.spawn_point(player1_index, false),
+        map.spawn_point(player2_index, true),
+    ]
+}
+
+/// Emotes the players can show above themselves.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emote {
+    Wave,
+    Laugh,
+    Angry,
+    GoodGame,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 4] = [Emote::Wave, Emote::Laugh, Emote::Angry, Emote::GoodGame];
+}
+
+/// Selectable characters, each with its own movement stats and size.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Character {
+    Balanced,
+    /// Slow, but a bigger target that is harder to push around.
+    Heavy,
+    /// Fast and small, with a weaker jump.
+    Light,
+    /// Jumps the highest.
+    Jumper,
+}
+
+pub struct CharacterStats {
+    /// Multiplier of the tuned movement speed.
+    pub speed: f32,
+    /// Multiplier of the tuned jump velocity.
+    pub jump_velocity: f32,
+    /// Side length of the square collider, in display units.
+    pub size: f32,
+}
+
+impl Default for Character {
+    fn default() -> Self {
+        Character::Balanced
+    }
+}
+
+impl Character {
+    pub fn stats(&self) -> CharacterStats {
+        match self {
+            Character::Balanced => CharacterStats {
+                speed: 1.0,
+                jump_velocity: 1.0,
+                size: 20.0,
+            },
+            Character::Heavy => CharacterStats {
+                speed: 0.75,
+                jump_velocity: 0.95,
+                size: 26.0,
+            },
+            Character::Light => CharacterStats {
+                speed: 1.25,
+                jump_velocity: 0.9,
+                size: 16.0,
+            },
+            Character::Jumper => CharacterStats {
+                speed: 0.85,
+                jump_velocity: 1.25,
+                size: 20.0,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Character {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "balanced" => Ok(Character::Balanced),
+            "heavy" => Ok(Character::Heavy),
+            "light" => Ok(Character::Light),
+            "jumper" => Ok(Character::Jumper),
+            _ => Err(format!("unknown character: {}", s)),
+        }
+    }
+}
+
+pub struct GameWorld {
+    map: MapDefinition,
+    pipeline: PhysicsPipeline,
+    island_manager: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    joints: JointSet,
+    ccd_solver: CCDSolver,
+    player1: Player,
+    player2: Player,
+    cannon_x_position: f32,
+    bottom_power_pad: PowerPad,
+    top_power_pad: PowerPad,
+    next_projectile_id: u16,
+    projectiles: HashMap<u16, Projectile>,
+    switch_gates: Vec<SwitchGateState>,
+    advantage_state: AdvantageState,
+    round: u8,
+    tick: u32,
+    rules: RuleVariants,
+    tuning: GameTuning,
+    match_settings: MatchSettings,
+    /// Points of each player, indexed by `PlayerId::as_usize`.
+    scores: [u8; 2],
+    /// Consecutive kills of each player while holding the advantage.
+    streaks: [u8; 2],
+    stats: [PlayerStats; 2],
+    /// Set once the match is over, which stops the simulation.
+    match_result: Option<MatchResult>,
+    /// Set while the server waits for both players to ready up, which the seated players spend
+    /// warming up: nothing counts, pads can't be captured, deaths just respawn the players and
+    /// they can fire practice shots on their own.
+    lobby: bool,
+    /// Players who asked for a rematch of the finished match.
+    rematch_votes: [bool; 2],
+    /// Advanced with every cannon shot while the spread rule is on.
+    spread_rng_state: u32,
+    /// Advanced at the start of every round to pick the spawn points.
+    spawn_rng_state: u32,
+    spread_shot_cooldown_ticks: u16,
+    /// Ticks left until the round is reset, while the dead players ragdoll.
+    corpse_ticks_left: u16,
+    /// Ticks left of the frozen phase between rounds.
+    post_round_ticks_left: u16,
+    last_round_result: RoundResult,
+    round_start_tick: u32,
+    /// Players whose connection dropped, the game is paused while any of them is away.
+    away: [bool; 2],
+    /// Ticks left until the away players forfeit.
+    away_ticks_left: u32,
+    /// Player positions for every tick of the current round. Each peer records its own
+    /// simulation, keeping the recordings out of the snapshots.
+    round_recording: Vec<[Vector2<Real>; 2]>,
+    /// Previous round winner's positions, replayed during the current round.
+    ghost: Option<Ghost>,
+    /// Commands applied over the last `APPLIED_COMMANDS_TICKS` ticks, with the tick each was
+    /// applied on. Kept out of the snapshots like the round recording.
+    applied_commands: Vec<(u32, GameCommand)>,
+    /// Events of the last `EVENTS_TICKS` ticks, with the tick each happened on. Kept out of the
+    /// snapshots like the applied commands.
+    events: Vec<(u32, GameEvent)>,
+    /// Tick the current match started on, the state it started from and its configuration,
+    /// which the snapshots leave out, for replays.
+    match_start: Option<(u32, Arc<GameSnapshot>, MatchConfig)>,
+    /// Player positions of the last snapshot applied, which the clients get from the server.
+    /// Kept out of the snapshots like the round recording.
+    snapshot_positions: Option<[Vector2<Real>; 2]>,
+    /// Tick of the last snapshot applied and the player positions this world predicted for it,
+    /// if it simulated that tick before the snapshot replaced it.
+    predicted_positions: Option<(u32, [Vector2<Real>; 2])>,
+}
+
+struct Ghost {
+    player_id: PlayerId,
+    positions: Vec<Vector2<Real>>,
+}
+
+pub struct Player {
+    body_handle: RigidBodyHandle,
+    _collider_handle: ColliderHandle,
+    input: PlayerInput,
+    grapple: Option<Grapple>,
+    character: Character,
+    /// The way the player last moved in.
+    facing: Facing,
+    /// Dead players ragdoll with unlocked rotations until the round is reset.
+    dead: bool,
+    stomping: bool,
+    stomp_cooldown_ticks: u16,
+    /// Ticks left of the shockwave of the last stomp, only shown.
+    shockwave_ticks_left: u16,
+    /// Absorbs one projectile hit, then breaks.
+    shield: bool,
+    /// Ticks spent on the captured pad in a row while holding the advantage.
+    pad_hold_ticks: u16,
+    /// The last emote and the tick it was sent on.
+    emote: Option<(Emote, u32)>,
+}
+
+impl Player {
+    fn new(
+        bodies: &mut RigidBodySet,
+        colliders: &mut ColliderSet,
+        start_position: Position,
+        facing: Facing,
+        character: Character,
+    ) -> Self {
+        let body_handle = bodies.insert(
+            RigidBodyBuilder::new_dynamic()
+                .translation(vector![
+                    start_position.x / PHYSICS_SCALE,
+                    start_position.y / PHYSICS_SCALE
+                ])
+                .ccd_enabled(true)
+                .lock_rotations()
+                .build(),
+        );
+        let collider_handle =
+            colliders.insert_with_parent(Self::collider(character), body_handle, bodies);
+        Player {
+            body_handle,
+            _collider_handle: collider_handle,
+            input: Default::default(),
+            grapple: None,
+            character,
+            facing,
+            dead: false,
+            stomping: false,
+            stomp_cooldown_ticks: 0,
+            shockwave_ticks_left: 0,
+            shield: false,
+            pad_hold_ticks: 0,
+            emote: None,
+        }
+    }
+
+    /// The emote shown above the player at the given tick.
+    fn shown_emote(&self, tick: u32) -> Option<Emote> {
+        self.emote
+            .filter(|(_, sent_at)| tick.wrapping_sub(*sent_at) < EMOTE_TICKS)
+            .map(|(emote, _)| emote)
+    }
+
+    fn shockwave(&self) -> Option<f32> {
+        if self.shockwave_ticks_left > 0 {
+            Some(1.0 - self.shockwave_ticks_left as f32 / SHOCKWAVE_TICKS as f32)
+        } else {
+            None
+        }
+    }
+
+    fn collider(character: Character) -> Collider {
+        let half_size = character.stats().size / 2.0 / PHYSICS_SCALE;
+        ColliderBuilder::cuboid(half_size, half_size)
+            .active_events(ActiveEvents::CONTACT_EVENTS)
+            .active_events(ActiveEvents::INTERSECTION_EVENTS)
+            .friction(0.0)
+            // .density(0.1)
+            // .restitution(0.5)
+            .build()
+    }
+}
+
+/// An attached grappling hook. Implemented as a rope constraint applied after each physics
+/// step instead of a rapier joint, since the players' rotations are locked and the 2D joints
+/// would make the rope a rigid rod.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Grapple {
+    anchor: Vector2<Real>,
+    length: Real,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PowerPadStatus {
+    Left,
+    Right,
+}
+
+pub struct PowerPad {
+    body_handle: RigidBodyHandle,
+    _collider_handle: ColliderHandle,
+    /// Side the pad's patrol starts from.
+    patrol_origin: PowerPadStatus,
+    /// Tick at which the pad was at its patrol origin, if it is patrolling.
+    patrol_start_tick: Option<u32>,
+    capture_progress: u16,
+}
+
+impl PowerPad {
+    /// Starts patrolling from the pad's current position towards the given side.
+    fn start_patrol(
+        &mut self,
+        bodies: &RigidBodySet,
+        positions: &PowerPadPositions,
+        towards: PowerPadStatus,
+        tick: u32,
+    ) {
+        let (origin, from, to) = match towards {
+            PowerPadStatus::Left => (PowerPadStatus::Right, positions.right, positions.left),
+            PowerPadStatus::Right => (PowerPadStatus::Left, positions.left, positions.right),
+        };
+        let x = bodies.get(self.body_handle).unwrap().translation().x * PHYSICS_SCALE;
+        let progress = ((x - from.x) / (to.x - from.x)).max(0.0).min(1.0);
+
+        self.patrol_origin = origin;
+        self.patrol_start_tick =
+            Some(tick.wrapping_sub((progress * POWER_PAD_PATROL_TICKS as f32).round() as u32));
+    }
+
+    /// Where a patrolling pad should be at the given tick.
+    fn patrol_translation(&self, positions: &PowerPadPositions, tick: u32) -> Option<Vector<Real>> {
+        let patrol_start_tick = self.patrol_start_tick?;
+        let (from, to) = match self.patrol_origin {
+            PowerPadStatus::Left => (positions.left, positions.right),
+            PowerPadStatus::Right => (positions.right, positions.left),
+        };
+
+        // go back and forth between the sides
+        let phase = tick.wrapping_sub(patrol_start_tick) % (2 * POWER_PAD_PATROL_TICKS);
+        let t = if phase <= POWER_PAD_PATROL_TICKS {
+            phase as f32 / POWER_PAD_PATROL_TICKS as f32
+        } else {
+            (2 * POWER_PAD_PATROL_TICKS - phase) as f32 / POWER_PAD_PATROL_TICKS as f32
+        };
+
+        Some(vector![
+            (from.x + (to.x - from.x) * t) / PHYSICS_SCALE,
+            (from.y + (to.y - from.y) * t) / PHYSICS_SCALE
+        ])
+    }
+}
+
+pub struct Projectile {
+    body_handle: RigidBodyHandle,
+    _collider_handle: ColliderHandle,
+    /// Multiplier of the arena gravity, zero for projectiles flying in a straight line.
+    gravity_scale: Real,
+    /// Player who fired the projectile, `None` for the hazard cannons.
+    owner: Option<PlayerId>,
+    /// Bounces left of a ricochet shot, `None` for projectiles that don't bounce.
+    bounces_left: Option<u8>,
+}
+
+pub struct SwitchGateState {
+    switch_collider_handle: ColliderHandle,
+    /// Kept for the whole game, an open gate just collides with nothing.
+    gate_collider_handle: ColliderHandle,
+    /// The gate is open while nonzero.
+    open_ticks_left: u16,
+}
+
+/// Something that happened in the game, for the clients to play sounds for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    Jumped(PlayerId),
+    /// A projectile was fired, by the cannon for the player or by a hazard cannon for nobody.
+    Fired(Option<PlayerId>),
+    /// A projectile hit a solid, or a shield.
+    Impact,
+    /// A projectile broke a player's shield instead of killing them.
+    ShieldBroken(PlayerId),
+    Died(PlayerId),
+    PadCaptured(PlayerId),
+    /// A player's power pad started patrolling towards a side, after the opponent captured theirs.
+    PadPatrolStarted(PlayerId, PowerPadStatus),
+    /// The players can move again after the round result.
+    RoundStarted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum GameCommand {
+    /// Everything the player holds, sent once on every tick it changes.
+    Input(PlayerId, InputFlags),
+    /// Ignored once the match is under way.
+    SelectCharacter(PlayerId, Character),
+    /// Only issued by the server.
+    SetRules(RuleVariants),
+    /// Only issued by the server.
+    SetTuning(GameTuning),
+    /// Only issued by the server, before the match.
+    SetMatchSettings(MatchSettings),
+    /// Only issued by the server, holds the match in warmup until both players are ready.
+    OpenLobby,
+    /// Only issued by the server, once both players sent it their ready message. Starts the
+    /// match over from the first round.
+    StartMatch,
+    /// Only issued by the server when a player's connection drops, pausing the game.
+    PlayerLeft(PlayerId),
+    /// Only issued by the server when a player reconnects to their seat, or once it's freed for
+    /// someone else.
+    PlayerReturned(PlayerId),
+    /// Only issued by the server when a player is kicked or banned, ending the match under way
+    /// in their opponent's favor.
+    Forfeit(PlayerId),
+    /// Asks for a rematch once the match is over, which starts as soon as both players asked.
+    Rematch(PlayerId),
+    /// Shows an emote above the player for a moment. `command_is_valid` can't see the world, so
+    /// the world drops the emotes sent within `EMOTE_COOLDOWN_TICKS` of the last one instead.
+    Emote(PlayerId, Emote),
+}
+
+impl Command for GameCommand {}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    pub action: bool,
+    pub left: bool,
+    pub right: bool,
+    pub grapple: bool,
+    pub ability: bool,
+    pub mortar: bool,
+    pub ricochet: bool,
+}
+
+impl Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerId::Player1 => write!(f, "P1"),
+            PlayerId::Player2 => write!(f, "P2"),
+        }
+    }
+}
+
+impl PlayerId {
+    pub fn as_usize(&self) -> usize {
+        match self {
+            PlayerId::Player1 => 0,
+            PlayerId::Player2 => 1,
+        }
+    }
+}
+
+/// `PlayerInput` packed into a byte, one bit per input, as it's sent in commands.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFlags(u8);
+
+impl InputFlags {
+    const ACTION: u8 = 1;
+    const LEFT: u8 = 1 << 1;
+    const RIGHT: u8 = 1 << 2;
+    const GRAPPLE: u8 = 1 << 3;
+    /// Fires the spread-shot while holding the advantage.
+    const ABILITY: u8 = 1 << 4;
+    /// Lobs a shot affected by gravity while holding the advantage.
+    const MORTAR: u8 = 1 << 5;
+    /// Fires a shot bouncing off the platforms while holding the advantage.
+    const RICOCHET: u8 = 1 << 6;
+
+    fn has(&self, flag: u8) -> bool {
+        self.0 & flag != 0
+    }
+}
+
+impl From<PlayerInput> for InputFlags {
+    fn from(input: PlayerInput) -> Self {
+        let flag = |held: bool, flag: u8| if held { flag } else { 0 };
+        Self(
+            flag(input.action, Self::ACTION)
+                | flag(input.left, Self::LEFT)
+                | flag(input.right, Self::RIGHT)
+                | flag(input.grapple, Self::GRAPPLE)
+                | flag(input.ability, Self::ABILITY)
+                | flag(input.mortar, Self::MORTAR)
+                | flag(input.ricochet, Self::RICOCHET),
+        )
+    }
+}
+
+impl From<InputFlags> for PlayerInput {
+    fn from(flags: InputFlags) -> Self {
+        Self {
+            action: flags.has(InputFlags::ACTION),
+            left: flags.has(InputFlags::LEFT),
+            right: flags.has(InputFlags::RIGHT),
+            grapple: flags.has(InputFlags::GRAPPLE),
+            ability: flags.has(InputFlags::ABILITY),
+            mortar: flags.has(InputFlags::MORTAR),
+            ricochet: flags.has(InputFlags::RICOCHET),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameSnapshot {
+    round: u8,
+    tick: u32,
+    scores: [u8; 2],
+    streaks: [u8; 2],
+    stats: [PlayerStats; 2],
+    match_result: Option<MatchResult>,
+    lobby: bool,
+    rematch_votes: [bool; 2],
+    spread_rng_state: u32,
+    spawn_rng_state: u32,
+    spread_shot_cooldown_ticks: u16,
+    corpse_ticks_left: u16,
+    post_round_ticks_left: u16,
+    last_round_result: RoundResult,
+    round_start_tick: u32,
+    away: [bool; 2],
+    away_ticks_left: u32,
+    next_projectile_id: u16,
+    advantage_state: AdvantageState,
+    player1: PlayerSnapshot,
+    player2: PlayerSnapshot,
+    cannon_x_position: f32,
+    bottom_power_pad: PowerPadSnapshot,
+    top_power_pad: PowerPadSnapshot,
+    projectiles: HashMap<u16, ProjectileSnapshot>,
+    gate_open_ticks_left: Vec<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSnapshot {
+    position: QuantizedPosition,
+    velocity: QuantizedVelocity,
+    input: PlayerInput,
+    grapple: Option<Grapple>,
+    character: Character,
+    facing: Facing,
+    dead: bool,
+    stomping: bool,
+    stomp_cooldown_ticks: u16,
+    shockwave_ticks_left: u16,
+    shield: bool,
+    pad_hold_ticks: u16,
+    emote: Option<(Emote, u32)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PowerPadSnapshot {
+    position: QuantizedPosition,
+    patrol_origin: PowerPadStatus,
+    patrol_start_tick: Option<u32>,
+    capture_progress: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectileSnapshot {
+    position: QuantizedPosition,
+    velocity: QuantizedVelocity,
+    gravity_scale: Real,
+    owner: Option<PlayerId>,
+    bounces_left: Option<u8>,
+}
+
+/// How a player moves, for animating them.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerMotion {
+    /// In pixels per second.
+    pub velocity: Vector2<Real>,
+    pub grounded: bool,
+    pub facing: Facing,
+    pub dead: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct GameDisplayState {
+    pub tick: u32,
+    pub round: u8,
+    pub advantage_state: AdvantageState,
+    pub player1_position: Isometry<Real>,
+    pub player2_position: Isometry<Real>,
+    pub player1_grapple_anchor: Option<Vector2<Real>>,
+    pub player2_grapple_anchor: Option<Vector2<Real>>,
+    pub player1_character: Character,
+    pub player2_character: Character,
+    pub player1_motion: PlayerMotion,
+    pub player2_motion: PlayerMotion,
+    pub match_config: MatchConfig,
+    pub scores: [u8; 2],
+    /// Consecutive kills while holding the advantage, each worth as many points (up to
+    /// `MAX_STREAK_MULTIPLIER`).
+    pub streaks: [u8; 2],
+    /// Match statistics, for the end-of-match summary.
+    pub stats: [PlayerStats; 2],
+    pub match_result: Option<MatchResult>,
+    /// Set until both players are ready, while they warm up.
+    pub lobby: bool,
+    /// Players who asked for a rematch of the finished match.
+    pub rematch_votes: [bool; 2],
+    pub crowd_event: Option<CrowdEvent>,
+    /// Result of the last round, set while the game is frozen between rounds.
+    pub round_result: Option<RoundResult>,
+    /// Players the paused game is waiting for, and the ticks left until they forfeit.
+    pub waiting_for: Option<([bool; 2], u32)>,
+    /// Shockwave of the players' last stomp, growing from 0 to 1 while it lasts.
+    pub player1_shockwave: Option<f32>,
+    pub player2_shockwave: Option<f32>,
+    pub player1_shield: bool,
+    pub player2_shield: bool,
+    /// Emotes shown above the players.
+    pub player1_emote: Option<Emote>,
+    pub player2_emote: Option<Emote>,
+    /// Ticks until the spread-shot can be used again.
+    pub spread_shot_cooldown_ticks: u16,
+    /// Ticks left until the round ends in a draw, if the rounds are timed.
+    pub round_ticks_left: Option<u32>,
+    /// Position of the previous round's winner at the same point of their round.
+    pub ghost: Option<(PlayerId, Vector2<Real>)>,
+    pub cannon_x_position: f32,
+    pub bottom_power_pad_position: Isometry<Real>,
+    pub top_power_pad_position: Isometry<Real>,
+    /// Capture progress in the range [0, 1].
+    pub bottom_power_pad_capture_progress: f32,
+    /// Capture progress in the range [0, 1].
+    pub top_power_pad_capture_progress: f32,
+    pub projectile_positions: HashMap<u16, Isometry<Real>>,
+    /// Whether each gate of `MapDefinition::all_switch_gates` is closed and should be drawn.
+    pub gates_visible: Vec<bool>,
+    /// Commands applied over the last couple of seconds, with the tick each was applied on,
+    /// which the server records replays from.
+    pub applied_commands: Vec<(u32, GameCommand)>,
+    /// Events of the last couple of seconds, with the tick each happened on.
+    pub events: Vec<(u32, GameEvent)>,
+    /// Tick the current match started on, the state it started from and its configuration.
+    pub match_start: Option<(u32, Arc<GameSnapshot>, MatchConfig)>,
+    /// Player positions of the last snapshot applied, the server's on the clients, to show how
+    /// far off the prediction is.
+    pub snapshot_positions: Option<[Vector2<Real>; 2]>,
+    /// Tick of the last snapshot applied and the player positions predicted for it, which the
+    /// snapshot corrected to `snapshot_positions`.
+    pub predicted_positions: Option<(u32, [Vector2<Real>; 2])>,
+}
+
+impl Default for GameWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameWorld {
+    pub fn new() -> Self {
+        Self::with_map(MapDefinition::default())
+    }
+
+    pub fn with_map(map: MapDefinition) -> Self {
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+
+        let mut spawn_rng_state = SPAWN_POINT_SEED;
+        let [player1_start_position, player2_start_position] =
+            next_spawn_points(&map, &mut spawn_rng_state);
+        let player1 = Player::new(
+            &mut bodies,
+            &mut colliders,
+            player1_start_position,
+            Facing::towards_center(&map, player1_start_position),
+            Character::default(),
+        );
+        let player2 = Player::new(
+            &mut bodies,
+            &mut colliders,
+            player2_start_position,
+            Facing::towards_center(&map, player2_start_position),
+            Character::default(),
+        );
+
+        // cannon
+        let cannon_x_position = map.center().x;
+
+        for element in map.level.iter() {
+            let builder = match element.kind {
+                LevelElementKind::Platform => ColliderBuilder::cuboid(
+                    element.rect.w / 2.0 / PHYSICS_SCALE,
+                    element.rect.h / 2.0 / PHYSICS_SCALE,
+                )
+                .friction(0.0),
+                LevelElementKind::Lava => ColliderBuilder::cuboid(
+                    element.rect.w / 2.0 / PHYSICS_SCALE,
+                    element.rect.h / 2.0 / PHYSICS_SCALE,
+                )
+                // .density(0.0) // TODO: what does this do?
+                .sensor(true)
+                .active_events(ActiveEvents::CONTACT_EVENTS)
+                .active_events(ActiveEvents::INTERSECTION_EVENTS),
+                LevelElementKind::Decoration { .. } => continue,
+            };
+
+            for (x, y) in [
+                (element.rect.x, element.rect.y),
+                map.mirror(element.rect.x, element.rect.y),
+            ]
+            .iter()
+            {
+                let collider = builder
+                    .clone()
+                    .translation(vector![x / PHYSICS_SCALE, y / PHYSICS_SCALE])
+                    .build();
+                colliders.insert(collider);
+            }
+        }
+
+        // switches and their gates
+        let switch_gates = map
+            .all_switch_gates()
+            .map(|switch_gate| {
+                let switch_collider_handle =
+                    colliders.insert(Self::rect_collider(&switch_gate.switch));
+                let gate_collider_handle = colliders.insert(Self::rect_collider(&switch_gate.gate));
+                SwitchGateState {
+                    switch_collider_handle,
+                    gate_collider_handle,
+                    open_ticks_left: 0,
+                }
+            })
+            .collect();
+
+        // power pads
+        let size = &POWER_PAD_SIZE;
+
+        let position = map.starting_power_pad_position(false);
+        let body = RigidBodyBuilder::new_static()
+            .translation(vector![
+                position.x / PHYSICS_SCALE,
+                position.y / PHYSICS_SCALE
+            ])
+            .ccd_enabled(true) // TODO: need this?
+            .build();
+        let body_handle = bodies.insert(body);
+
+        let collider =
+            ColliderBuilder::cuboid(size.w / 2.0 / PHYSICS_SCALE, size.h / 2.0 / PHYSICS_SCALE)
+                // .density(0.0) // TODO: what does this do?
+                // .sensor(true)
+                .build();
+        let collider_handle = colliders.insert_with_parent(collider, body_handle, &mut bodies);
+
+        let bottom_power_pad = PowerPad {
+            body_handle,
+            _collider_handle: collider_handle,
+            patrol_origin: PowerPadStatus::Right,
+            patrol_start_tick: None,
+            capture_progress: 0,
+        };
+
+        let position = map.starting_power_pad_position(true);
+        let body = RigidBodyBuilder::new_static()
+            .translation(vector![
+                position.x / PHYSICS_SCALE,
+                position.y / PHYSICS_SCALE
+            ])
+            .ccd_enabled(true) // TODO: need this?
+            .build();
+        let body_handle = bodies.insert(body);
+
+        let collider =
+            ColliderBuilder::cuboid(size.w / 2.0 / PHYSICS_SCALE, size.h / 2.0 / PHYSICS_SCALE)
+                // .density(0.0) // TODO: what does this do?
+                // .sensor(true)
+                .build();
+        let collider_handle = colliders.insert_with_parent(collider, body_handle, &mut bodies);
+
+        let top_power_pad = PowerPad {
+            body_handle,
+            _collider_handle: collider_handle,
+            patrol_origin: PowerPadStatus::Left,
+            patrol_start_tick: None,
+            capture_progress: 0,
+        };
+
+        Self {
+            map,
+            pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            bodies,
+            colliders,
+            joints: JointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            player1,
+            player2,
+            cannon_x_position,
+            bottom_power_pad,
+            top_power_pad,
+            next_projectile_id: 0,
+            projectiles: HashMap::default(),
+            switch_gates,
+            advantage_state: AdvantageState::Neutral,
+            round: 1,
+            tick: 0,
+            rules: Default::default(),
+            tuning: Default::default(),
+            match_settings: Default::default(),
+            scores: [0, 0],
+            streaks: [0, 0],
+            stats: Default::default(),
+            match_result: None,
+            lobby: false,
+            rematch_votes: [false, false],
+            spread_rng_state: CANNON_SPREAD_SEED,
+            spawn_rng_state,
+            spread_shot_cooldown_ticks: 0,
+            corpse_ticks_left: 0,
+            post_round_ticks_left: 0,
+            last_round_result: RoundResult::Draw,
+            away: [false, false],
+            away_ticks_left: 0,
+            round_start_tick: 0,
+            round_recording: vec![],
+            ghost: None,
+            applied_commands: vec![],
+            events: vec![],
+            match_start: None,
+            snapshot_positions: None,
+            predicted_positions: None,
+        }
+    }
+
+    fn create_projectile(
+        &mut self,
+        projectile_id: u16,
+        translation: Vector<Real>,
+        linvel: Option<Vector<Real>>,
+        gravity_scale: Real,
+        owner: Option<PlayerId>,
+        bounces_left: Option<u8>,
+    ) {
+        let body_builder = RigidBodyBuilder::new_dynamic()
+            .translation(translation)
+            .ccd_enabled(true)
+            .lock_rotations();
+        let body_builder = if let Some(linvel) = linvel {
+            body_builder.linvel(linvel)
+        } else {
+            body_builder
+        };
+        let body_handle = self.bodies.insert(body_builder.build());
+        let collider_builder = ColliderBuilder::cuboid(
+            PROJECTILE_SIZE.w / 2.0 / PHYSICS_SCALE,
+            PROJECTILE_SIZE.h / 2.0 / PHYSICS_SCALE,
+        );
+        // ricochet shots too, bounced off the solids by hand so that they don't push the players
+        let collider_builder = collider_builder
+            .active_events(ActiveEvents::INTERSECTION_EVENTS)
+            .sensor(true);
+        // .density(0.1)
+        // .restitution(0.5)
+        let collider_handle = self.colliders.insert_with_parent(
+            collider_builder.build(),
+            body_handle,
+            &mut self.bodies,
+        );
+
+        self.projectiles.insert(
+            projectile_id,
+            Projectile {
+                body_handle,
+                _collider_handle: collider_handle,
+                gravity_scale,
+                owner,
+                bounces_left,
+            },
+        );
+    }
+
+    /// Moves a ricochet shot back out of the solid it went into, through the side it's least far
+    /// in, and turns it around if it was still heading in.
+    fn bounce_projectile(&mut self, body_handle: RigidBodyHandle, solid: ColliderHandle) {
+        let solid = self.colliders.get(solid).unwrap().compute_aabb();
+        let body = self.bodies.get_mut(body_handle).unwrap();
+        let mut translation = *body.translation();
+        let mut linvel = *body.linvel();
+        let half_size = vector![PROJECTILE_SIZE.w / 2.0, PROJECTILE_SIZE.h / 2.0] / PHYSICS_SCALE;
+        // how far the shot has to move along the axis to be out, towards the nearer side
+        let way_out = |axis: usize| {
+            if translation[axis] < (solid.mins[axis] + solid.maxs[axis]) / 2.0 {
+                solid.mins[axis] - half_size[axis] - translation[axis]
+            } else {
+                solid.maxs[axis] + half_size[axis] - translation[axis]
+            }
+        };
+        let (x, y) = (way_out(0), way_out(1));
+        let (axis, way_out) = if x.abs() < y.abs() { (0, x) } else { (1, y) };
+        translation[axis] += way_out;
+        if linvel[axis] * way_out < 0.0 {
+            linvel[axis] = -linvel[axis];
+        }
+        body.set_translation(translation, true);
+        body.set_linvel(linvel, true);
+    }
+
+    fn player_mut(&mut self, player_id: PlayerId) -> &mut Player {
+        match player_id {
+            PlayerId::Player1 => &mut self.player1,
+            PlayerId::Player2 => &mut self.player2,
+        }
+    }
+
+    /// Distance to the first platform straight up from the point, where `up` is `1.0` for the
+    /// bottom half and `-1.0` for the mirrored top half. Only platforms within grappling range
+    /// are considered.
+    fn cast_grapple_ray(&self, from: Vector<Real>, up: f32) -> Option<Real> {
+        let x = from.x * PHYSICS_SCALE;
+        let y = from.y * PHYSICS_SCALE;
+
+        let mut rects = vec![];
+        for element in self
+            .map
+            .level
+            .iter()
+            .filter(|element| element.kind == LevelElementKind::Platform)
+        {
+            let rect = &element.rect;
+            rects.push((rect.x, rect.y, rect.w, rect.h));
+            let (mirrored_x, mirrored_y) = self.map.mirror(rect.x, rect.y);
+            rects.push((mirrored_x, mirrored_y, rect.w, rect.h));
+        }
+        for (switch_gate, state) in self.map.all_switch_gates().zip(self.switch_gates.iter()) {
+            if state.open_ticks_left == 0 {
+                let rect = &switch_gate.gate;
+                rects.push((rect.x, rect.y, rect.w, rect.h));
+            }
+        }
+
+        rects
+            .iter()
+            .filter(|(rect_x, _, w, _)| (x - rect_x).abs() <= w / 2.0)
+            .map(|(_, rect_y, _, h)| (rect_y - h / 2.0 * up - y) * up)
+            .filter(|distance| *distance >= 0.0 && *distance <= GRAPPLE_MAX_LENGTH)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .map(|distance| distance / PHYSICS_SCALE)
+    }
+
+    fn rect_collider(rect: &Rect) -> Collider {
+        ColliderBuilder::cuboid(rect.w / 2.0 / PHYSICS_SCALE, rect.h / 2.0 / PHYSICS_SCALE)
+            .translation(vector![rect.x / PHYSICS_SCALE, rect.y / PHYSICS_SCALE])
+            .friction(0.0)
+            .build()
+    }
+
+    /// Opens the gate for the given number of ticks, or closes it if zero. The gate's collider
+    /// stays in the physics world either way, so that restoring a snapshot never changes what
+    /// the world is made of.
+    fn set_gate_open_ticks(&mut self, index: usize, open_ticks_left: u16) {
+        let switch_gate = &mut self.switch_gates[index];
+        switch_gate.open_ticks_left = open_ticks_left;
+
+        let groups = if open_ticks_left > 0 {
+            InteractionGroups::none()
+        } else {
+            InteractionGroups::all()
+        };
+        let collider = &mut self.colliders[switch_gate.gate_collider_handle];
+        if collider.collision_groups() != groups {
+            collider.set_collision_groups(groups);
+        }
+    }
+
+    /// Characters can be picked until the first round is over.
+    fn character_selection_open(&self) -> bool {
+        self.round == 1
+    }
+
+    /// Swaps the player's character, resizing their collider.
+    fn set_character(&mut self, player_id: PlayerId, character: Character) {
+        let player = match player_id {
+            PlayerId::Player1 => &mut self.player1,
+            PlayerId::Player2 => &mut self.player2,
+        };
+        if player.character == character {
+            return;
+        }
+
+        self.colliders.remove(
+            player._collider_handle,
+            &mut self.island_manager,
+            &mut self.bodies,
+            true,
+        );
+        player._collider_handle = self.colliders.insert_with_parent(
+            Player::collider(character),
+            player.body_handle,
+            &mut self.bodies,
+        );
+        player.character = character;
+    }
+
+    pub fn set_match_settings(&mut self, match_settings: MatchSettings) {
+        self.match_settings = match_settings;
+    }
+
+    pub fn match_config(&self) -> MatchConfig {
+        MatchConfig {
+            rules: self.rules,
+            tuning: self.tuning,
+            match_settings: self.match_settings,
+        }
+    }
+
+    /// Sets everything the snapshots leave out, which replays start from.
+    pub fn set_match_config(&mut self, config: MatchConfig) {
+        self.rules = config.rules;
+        self.tuning = config.tuning;
+        self.match_settings = config.match_settings;
+    }
+
+    /// Closes the lobby, starting the match from the first round.
+    fn start_match(&mut self) {
+        self.lobby = false;
+        self.corpse_ticks_left = 0;
+        self.player1.dead = false;
+        self.player2.dead = false;
+        self.reset_round();
+        self.post_round_ticks_left = 0;
+        self.round = 1;
+        self.scores = [0, 0];
+        self.streaks = [0, 0];
+        self.stats = Default::default();
+        self.match_result = None;
+        self.rematch_votes = [false, false];
+        self.ghost = None;
+    }
+
+    /// Hands the advantage to a player (or takes it away) without them capturing a pad.
+    pub fn set_advantage_state(&mut self, advantage_state: AdvantageState) {
+        self.advantage_state = advantage_state;
+    }
+
+    /// Starts the next round, putting the players, cannon and pads back to their starting
+    /// positions and clearing the projectiles.
+    pub fn reset_round(&mut self) {
+        let round_winner = match (self.player1.dead, self.player2.dead) {
+            // deaths while warming up in the lobby don't count
+            _ if self.lobby => None,
+            (false, true) => Some(PlayerId::Player1),
+            (true, false) => Some(PlayerId::Player2),
+            _ => None,
+        };
+        if !self.lobby {
+            self.update_streaks(round_winner);
+            if let Some(player_id) = round_winner {
+                let streak = self.streaks[player_id.as_usize()];
+                let score = &mut self.scores[player_id.as_usize()];
+                *score = score.saturating_add(streak.max(1).min(MAX_STREAK_MULTIPLIER));
+            }
+            self.update_match_result(round_winner);
+
+            self.round += 1;
+            self.post_round_ticks_left = POST_ROUND_TICKS;
+            self.last_round_result = match round_winner {
+                Some(player_id) => RoundResult::Winner(player_id),
+                None => RoundResult::Draw,
+            };
+        }
+        self.advantage_state = AdvantageState::Neutral;
+
+        // the survivor's route becomes the ghost of the next round
+        let recording = std::mem::take(&mut self.round_recording);
+        self.ghost = round_winner.map(|player_id| Ghost {
+            player_id,
+            positions: recording
+                .iter()
+                .map(|positions| positions[player_id.as_usize()])
+                .collect(),
+        });
+        self.round_start_tick = self.tick;
+
+        // reset players
+        let [player1_start_position, player2_start_position] =
+            next_spawn_points(&self.map, &mut self.spawn_rng_state);
+        for (player, start_position) in [
+            (&mut self.player1, player1_start_position),
+            (&mut self.player2, player2_start_position),
+        ]
+        .iter_mut()
+        {
+            player.grapple = None;
+            player.facing = Facing::towards_center(&self.map, *start_position);
+            player.dead = false;
+            player.stomping = false;
+            player.stomp_cooldown_ticks = 0;
+            player.shockwave_ticks_left = 0;
+            player.shield = false;
+            player.pad_hold_ticks = 0;
+            let body = self.bodies.get_mut(player.body_handle).unwrap();
+            body.lock_rotations(true, true);
+            body.set_position(
+                Isometry::translation(
+                    start_position.x / PHYSICS_SCALE,
+                    start_position.y / PHYSICS_SCALE,
+                ),
+                true,
+            );
+            body.set_linvel(vector![0.0, 0.0], true);
+            body.set_angvel(0.0, true);
+        }
+
+        // reset cannon
+        self.cannon_x_position = self.map.center().x;
+        self.spread_shot_cooldown_ticks = 0;
+
+        // reset power pads
+        self.bottom_power_pad.patrol_origin = PowerPadStatus::Right;
+        self.bottom_power_pad.patrol_start_tick = None;
+        self.bottom_power_pad.capture_progress = 0;
+        self.top_power_pad.patrol_origin = PowerPadStatus::Left;
+        self.top_power_pad.patrol_start_tick = None;
+        self.top_power_pad.capture_progress = 0;
+        for (power_pad, top_half) in
+            [(&self.bottom_power_pad, false), (&self.top_power_pad, true)].iter()
+        {
+            let position = self.map.starting_power_pad_position(*top_half);
+            self.bodies
+                .get_mut(power_pad.body_handle)
+                .unwrap()
+                .set_translation(
+                    vector![position.x / PHYSICS_SCALE, position.y / PHYSICS_SCALE],
+                    true,
+                );
+        }
+
+        // clear projectiles
+        let projectile_ids = self.projectiles.keys().copied().collect::<Vec<u16>>();
+        for projectile_id in projectile_ids {
+            self.remove_projectile(projectile_id);
+        }
+
+        // close gates
+        for index in 0..self.switch_gates.len() {
+            self.set_gate_open_ticks(index, 0);
+        }
+    }
+
+    /// Lets the dead players ragdoll before the round is reset.
+    fn start_corpse_phase(&mut self, dead_players: &HashSet<PlayerId>) {
+        self.corpse_ticks_left = CORPSE_TICKS;
+        for (player_id, player) in [
+            (PlayerId::Player1, &mut self.player1),
+            (PlayerId::Player2, &mut self.player2),
+        ]
+        .iter_mut()
+        {
+            let body = self.bodies.get_mut(player.body_handle).unwrap();
+            if dead_players.contains(player_id) {
+                player.dead = true;
+                player.grapple = None;
+                body.lock_rotations(false, true);
+                // tumble away from the direction of movement
+                let spin = if body.linvel().x < 0.0 {
+                    CORPSE_SPIN
+                } else {
+                    -CORPSE_SPIN
+                };
+                body.set_angvel(spin, true);
+            } else {
+                body.set_linvel(vector![0.0, 0.0], true);
+            }
+        }
+    }
+
+    /// Only the physics keep running during the corpse phase, so the result of the round can't
+    /// change anymore.
+    fn step_corpse_phase(&mut self) {
+        for (player, mirror_multiplier) in [(&self.player1, 1.0), (&self.player2, -1.0)].iter() {
+            if player.dead {
+                let body = self.bodies.get_mut(player.body_handle).unwrap();
+                body.apply_force(
+                    vector![
+                        0.0,
+                        *mirror_multiplier * -9.81 * self.tuning().gravity_scale
+                    ],
+                    true,
+                );
+            }
+        }
+
+        self.step_physics();
+
+        self.corpse_ticks_left -= 1;
+        if self.corpse_ticks_left == 0 {
+            self.reset_round();
+        }
+    }
+
+    fn step_physics(&mut self) {
+        self.pipeline.step(
+            &GRAVITY,
+            &IntegrationParameters {
+                dt: TIMESTEP as f32,
+                ..Default::default()
+            },
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.bodies,
+            &mut self.colliders,
+            &mut self.joints,
+            &mut self.ccd_solver,
+            &(),
+            &(),
+        );
+    }
+
+    /// Counts the consecutive rounds a player won by a kill while holding the advantage.
+    fn update_streaks(&mut self, round_winner: Option<PlayerId>) {
+        for player_id in [PlayerId::Player1, PlayerId::Player2].iter() {
+            let advantaged = matches!(
+                (self.advantage_state, player_id),
+                (AdvantageState::Player1, PlayerId::Player1)
+                    | (AdvantageState::Player2, PlayerId::Player2)
+            );
+            let streak = &mut self.streaks[player_id.as_usize()];
+            if round_winner == Some(*player_id) && advantaged {
+                *streak = streak.saturating_add(1);
+            } else {
+                *streak = 0;
+            }
+        }
+    }
+
+    fn update_match_result(&mut self, round_winner: Option<PlayerId>) {
+        if let (Some(player_id), Some(score_target)) =
+            (round_winner, self.match_settings.score_target)
+        {
+            if self.scores[player_id.as_usize()] >= score_target {
+                self.match_result = Some(MatchResult::Winner(player_id));
+                return;
+            }
+        }
+
+        if let Some(round_limit) = self.match_settings.round_limit {
+            if self.round >= round_limit {
+                let [player1_score, player2_score] = self.scores;
+                self.match_result = Some(if player1_score > player2_score {
+                    MatchResult::Winner(PlayerId::Player1)
+                } else if player2_score > player1_score {
+                    MatchResult::Winner(PlayerId::Player2)
+                } else {
+                    MatchResult::Draw
+                });
+            }
+        }
+    }
+
+    /// Waits for the players who left, or makes them forfeit once the grace period is over.
+    fn step_away_phase(&mut self) {
+        if self.away_ticks_left == 0 {
+            self.match_result = Some(match self.away {
+                [false, true] => MatchResult::Winner(PlayerId::Player1),
+                [true, false] => MatchResult::Winner(PlayerId::Player2),
+                _ => MatchResult::Draw,
+            });
+            self.away = [false, false];
+            return;
+        }
+        self.away_ticks_left -= 1;
+        // the round clock stands still
+        self.round_start_tick = self.round_start_tick.wrapping_add(1);
+    }
+
+    /// Crowd event in effect this tick, if any.
+    fn crowd_event(&self) -> Option<CrowdEvent> {
+        if !self.rules.crowd_events || self.lobby || self.tick < CROWD_EVENT_PERIOD_TICKS {
+            return None;
+        }
+        if self.tick % CROWD_EVENT_PERIOD_TICKS < CROWD_EVENT_TICKS {
+            let index = (self.tick / CROWD_EVENT_PERIOD_TICKS) as usize % CROWD_EVENTS.len();
+            Some(CROWD_EVENTS[index])
+        } else {
+            None
+        }
+    }
+
+    /// Balance values in effect this tick, twisted by the current crowd event.
+    fn tuning(&self) -> GameTuning {
+        let mut tuning = self.tuning;
+        if let Some(crowd_event) = self.crowd_event() {
+            crowd_event.apply(&mut tuning);
+        }
+        tuning
+    }
+
+    fn round_ticks_left(&self) -> Option<u32> {
+        if self.lobby {
+            return None;
+        }
+        self.match_settings
+            .round_time_limit
+            .map(|round_time_limit| {
+                seconds_to_ticks(round_time_limit)
+                    .saturating_sub(self.tick.wrapping_sub(self.round_start_tick))
+            })
+    }
+
+    fn player_motion(&self, player: &Player) -> PlayerMotion {
+        PlayerMotion {
+            velocity: self.bodies.get(player.body_handle).unwrap().linvel() * PHYSICS_SCALE,
+            grounded: Self::is_grounded(&self.narrow_phase, player._collider_handle),
+            facing: player.facing,
+            dead: player.dead,
+        }
+    }
+
+    fn is_grounded(narrow_phase: &NarrowPhase, collider_handle: ColliderHandle) -> bool {
+        narrow_phase
+            .contacts_with(collider_handle)
+            .any(|contact_pair| {
+                contact_pair.manifolds.iter().any(|manifold| {
+                    manifold.local_n1[0] == 0.0
+                        && (f32::abs(manifold.local_n1[1]) - 1.0).abs() < f32::EPSILON
+                })
+            })
+    }
+
+    /// Starts stomps of airborne players pressing Down+Action and lands the ones that hit the
+    /// ground, destroying the projectiles caught in the shockwave.
+    fn update_stomps(&mut self) {
+        let mut shockwaves = vec![];
+        for player_id in [PlayerId::Player1, PlayerId::Player2].iter() {
+            let advantaged = matches!(
+                (self.advantage_state, player_id),
+                (AdvantageState::Player1, PlayerId::Player1)
+                    | (AdvantageState::Player2, PlayerId::Player2)
+            );
+            let player = match player_id {
+                PlayerId::Player1 => &mut self.player1,
+                PlayerId::Player2 => &mut self.player2,
+            };
+            let is_grounded = Self::is_grounded(&self.narrow_phase, player._collider_handle);
+
+            player.stomp_cooldown_ticks = player.stomp_cooldown_ticks.saturating_sub(1);
+            player.shockwave_ticks_left = player.shockwave_ticks_left.saturating_sub(1);
+
+            if player.stomping {
+                if player.grapple.is_some() {
+                    player.stomping = false;
+                } else if is_grounded {
+                    player.stomping = false;
+                    player.stomp_cooldown_ticks = STOMP_COOLDOWN_TICKS;
+                    player.shockwave_ticks_left = SHOCKWAVE_TICKS;
+                    shockwaves.push(*self.bodies.get(player.body_handle).unwrap().translation());
+                }
+            } else if !advantaged
+                && !is_grounded
+                && player.grapple.is_none()
+                && player.stomp_cooldown_ticks == 0
+                && player.input.ability
+                && player.input.action
+            {
+                player.stomping = true;
+            }
+        }
+
+        for position in shockwaves {
+            let caught_projectiles = self
+                .projectiles
+                .iter()
+                .filter(|(_, projectile)| {
+                    let translation = self
+                        .bodies
+                        .get(projectile.body_handle)
+                        .unwrap()
+                        .translation();
+                    (translation - position).norm() * PHYSICS_SCALE <= SHOCKWAVE_RADIUS
+                })
+                .map(|(projectile_id, _)| *projectile_id)
+                .collect::<Vec<u16>>();
+            for projectile_id in caught_projectiles {
+                self.remove_projectile(projectile_id);
+            }
+        }
+    }
+
+    /// Index of the current tick in the round's recording.
+    fn round_recording_index(&self) -> usize {
+        self.tick
+            .wrapping_sub(self.round_start_tick)
+            .saturating_sub(1) as usize
+    }
+
+    fn record_round(&mut self) {
+        let index = self.round_recording_index();
+        if index >= GHOST_MAX_TICKS {
+            return;
+        }
+
+        let positions = [
+            *self
+                .bodies
+                .get(self.player1.body_handle)
+                .unwrap()
+                .translation(),
+            *self
+                .bodies
+                .get(self.player2.body_handle)
+                .unwrap()
+                .translation(),
+        ];
+        // resimulated ticks overwrite their previous recording
+        if index < self.round_recording.len() {
+            self.round_recording[index] = positions;
+        } else {
+            // ticks before a snapshot was applied might be missing
+            self.round_recording.resize(index + 1, positions);
+        }
+    }
+
+    fn record(&mut self, event: GameEvent) {
+        self.events.push((self.tick, event));
+    }
+
+    fn remove_projectile(&mut self, projectile_id: u16) {
+        if let Some(projectile) = self.projectiles.remove(&projectile_id) {
+            self.bodies.remove(
+                projectile.body_handle,
+                &mut self.island_manager,
+                &mut self.colliders,
+                &mut self.joints,
+            );
+        }
+    }
+}
+
+impl World for GameWorld {
+    type CommandType = GameCommand;
+    type SnapshotType = GameSnapshot;
+    type DisplayStateType = GameDisplayState;
+
+    // the server only passes on the commands of the player seated on the connection
+    fn command_is_valid(command: &Self::CommandType, _client_id: usize) -> bool {
+        match command {
+            GameCommand::Input(..)
+            | GameCommand::SelectCharacter(..)
+            | GameCommand::Rematch(_)
+            | GameCommand::Emote(..) => true,
+            GameCommand::SetRules(_)
+            | GameCommand::SetTuning(_)
+            | GameCommand::SetMatchSettings(_)
+            | GameCommand::OpenLobby
+            | GameCommand::StartMatch
+            | GameCommand::PlayerLeft(_)
+            | GameCommand::PlayerReturned(_)
+            | GameCommand::Forfeit(_) => false,
+        }
+    }
+
+    fn apply_command(&mut self, command: &Self::CommandType) {
+        self.applied_commands.push((self.tick, command.clone()));
+        match command {
+            GameCommand::Input(player_id, flags) => {
+                match player_id {
+                    PlayerId::Player1 => &mut self.player1,
+                    PlayerId::Player2 => &mut self.player2,
+                }
+                .input = (*flags).into();
+            }
+            GameCommand::SelectCharacter(player_id, character) => {
+                if self.character_selection_open() {
+                    self.set_character(*player_id, *character);
+                }
+            }
+            GameCommand::SetRules(rules) => self.rules = *rules,
+            GameCommand::SetTuning(tuning) => self.tuning = *tuning,
+            GameCommand::SetMatchSettings(match_settings) => {
+                self.set_match_settings(*match_settings)
+            }
+            GameCommand::OpenLobby => {
+                // the players warm up from fresh positions, whatever was going on
+                self.lobby = true;
+                self.match_result = None;
+                self.rematch_votes = [false, false];
+                self.corpse_ticks_left = 0;
+                self.player1.dead = false;
+                self.player2.dead = false;
+                self.reset_round();
+                self.post_round_ticks_left = 0;
+            }
+            GameCommand::StartMatch => {
+                if self.lobby {
+                    self.match_start =
+                        Some((self.tick, Arc::new(self.snapshot()), self.match_config()));
+                    self.start_match();
+                }
+            }
+            GameCommand::PlayerLeft(player_id) => {
+                // the grace period starts with the first player to leave
+                if !self.away.contains(&true) {
+                    self.away_ticks_left = seconds_to_ticks(self.match_settings.disconnect_grace);
+                }
+                self.away[player_id.as_usize()] = true;
+            }
+            GameCommand::PlayerReturned(player_id) => self.away[player_id.as_usize()] = false,
+            GameCommand::Forfeit(player_id) => {
+                self.away[player_id.as_usize()] = false;
+                if !self.lobby && self.match_result.is_none() {
+                    let opponent = match player_id {
+                        PlayerId::Player1 => PlayerId::Player2,
+                        PlayerId::Player2 => PlayerId::Player1,
+                    };
+                    self.match_result = Some(MatchResult::Winner(opponent));
+                }
+            }
+            GameCommand::Rematch(player_id) => {
+                // a tournament moves on to its next match through the lobby instead
+                if self.match_result.is_some() && !self.lobby {
+                    self.rematch_votes[player_id.as_usize()] = true;
+                    if self.rematch_votes == [true, true] {
+                        self.match_start =
+                            Some((self.tick, Arc::new(self.snapshot()), self.match_config()));
+                        self.start_match();
+                    }
+                }
+            }
+            GameCommand::Emote(player_id, emote) => {
+                let tick = self.tick;
+                let player = self.player_mut(*player_id);
+                let cooled_down = player.emote.map_or(true, |(_, sent_at)| {
+                    tick.wrapping_sub(sent_at) >= EMOTE_COOLDOWN_TICKS
+                });
+                if cooled_down {
+                    player.emote = Some((*emote, tick));
+                }
+            }
+        }
+    }
+
+    fn apply_snapshot(&mut self, snapshot: Self::SnapshotType) {
+        // the commands from the snapshot on get applied again
+        self.applied_commands
+            .retain(|(applied_at, _)| *applied_at < snapshot.tick);
+        // the ticks after the snapshot happen again
+        self.events
+            .retain(|(happened_at, _)| *happened_at <= snapshot.tick);
+        // what this world made of the snapshot's tick, before the snapshot corrects it
+        let predicted = if self.round_start_tick == snapshot.round_start_tick
+            && snapshot.tick > snapshot.round_start_tick
+            && snapshot.tick <= self.tick
+        {
+            let index = (snapshot.tick - snapshot.round_start_tick - 1) as usize;
+            self.round_recording.get(index).copied()
+        } else {
+            None
+        };
+        self.predicted_positions = predicted.map(|positions| (snapshot.tick, positions));
+        self.round = snapshot.round;
+        self.tick = snapshot.tick;
+        self.scores = snapshot.scores;
+        self.streaks = snapshot.streaks;
+        self.stats = snapshot.stats;
+        self.match_result = snapshot.match_result;
+        self.lobby = snapshot.lobby;
+        self.rematch_votes = snapshot.rematch_votes;
+        self.spread_rng_state = snapshot.spread_rng_state;
+        self.spawn_rng_state = snapshot.spawn_rng_state;
+        self.spread_shot_cooldown_ticks = snapshot.spread_shot_cooldown_ticks;
+        self.corpse_ticks_left = snapshot.corpse_ticks_left;
+        self.post_round_ticks_left = snapshot.post_round_ticks_left;
+        self.away = snapshot.away;
+        self.away_ticks_left = snapshot.away_ticks_left;
+        self.last_round_result = snapshot.last_round_result;
+        self.round_start_tick = snapshot.round_start_tick;
+        self.next_projectile_id = snapshot.next_projectile_id;
+        self.advantage_state = snapshot.advantage_state;
+        self.snapshot_positions = Some([
+            snapshot
+                .player1
+                .position
+                .isometry(&self.map)
+                .translation
+                .vector,
+            snapshot
+                .player2
+                .position
+                .isometry(&self.map)
+                .translation
+                .vector,
+        ]);
+
+        let map = self.map;
+        let update_player =
+            |player_snapshot: &PlayerSnapshot, bodies: &mut RigidBodySet, player: &mut Player| {
+                let body = bodies.get_mut(player.body_handle).unwrap();
+                body.set_position(player_snapshot.position.isometry(&map), true);
+                body.set_linvel(player_snapshot.velocity.linvel(), true);
+                body.set_angvel(player_snapshot.velocity.angvel(), true);
+                body.lock_rotations(!player_snapshot.dead, true);
+                player.input = player_snapshot.input;
+                player.grapple = player_snapshot.grapple;
+                player.facing = player_snapshot.facing;
+                player.dead = player_snapshot.dead;
+                player.stomping = player_snapshot.stomping;
+                player.stomp_cooldown_ticks = player_snapshot.stomp_cooldown_ticks;
+                player.shockwave_ticks_left = player_snapshot.shockwave_ticks_left;
+                player.shield = player_snapshot.shield;
+                player.pad_hold_ticks = player_snapshot.pad_hold_ticks;
+                player.emote = player_snapshot.emote;
+            };
+
+        self.set_character(PlayerId::Player1, snapshot.player1.character);
+        self.set_character(PlayerId::Player2, snapshot.player2.character);
+        update_player(&snapshot.player1, &mut self.bodies, &mut self.player1);
+        update_player(&snapshot.player2, &mut self.bodies, &mut self.player2);
+
+        self.cannon_x_position = snapshot.cannon_x_position;
+
+        let update_power_pad = |power_pad_snapshot: &PowerPadSnapshot,
+                                bodies: &mut RigidBodySet,
+                                power_pad: &mut PowerPad| {
+            let body = bodies.get_mut(power_pad.body_handle).unwrap();
+            body.set_position(power_pad_snapshot.position.isometry(&map), true);
+            power_pad.patrol_origin = power_pad_snapshot.patrol_origin;
+            power_pad.patrol_start_tick = power_pad_snapshot.patrol_start_tick;
+            power_pad.capture_progress = power_pad_snapshot.capture_progress;
+        };
+
+        update_power_pad(
+            &snapshot.bottom_power_pad,
+            &mut self.bodies,
+            &mut self.bottom_power_pad,
+        );
+        update_power_pad(
+            &snapshot.top_power_pad,
+            &mut self.bodies,
+            &mut self.top_power_pad,
+        );
+
+        // update projectiles
+        let snapshot_projectiles = snapshot
+            .projectiles
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<HashSet<u16>>();
+        let current_projectiles = self.projectiles.keys().copied().collect::<HashSet<u16>>();
+
+        // Create objects for all projectiles in the snapshot which are not already in the game world
+        for projectile_id in snapshot_projectiles.difference(&current_projectiles) {
+            debug!("Creating projectile {} from snapshot", projectile_id);
+            // the position gets set from the snapshot below
+            let bounces_left = snapshot.projectiles[projectile_id].bounces_left;
+            self.create_projectile(
+                *projectile_id,
+                vector![0.0, 0.0],
+                None,
+                0.0,
+                None,
+                bounces_left,
+            );
+        }
+
+        // Remove objects for all projectiles that are in the game world but not in the snapshot
+        for projectile_id in current_projectiles.difference(&snapshot_projectiles) {
+            debug!("Removing projectile {} not in snapshot", projectile_id);
+            self.remove_projectile(*projectile_id);
+        }
+
+        for (projectile_id, projectile_snapshot) in snapshot.projectiles.iter() {
+            let projectile = self.projectiles.get_mut(projectile_id).unwrap();
+            let body = self.bodies.get_mut(projectile.body_handle).unwrap();
+            body.set_position(projectile_snapshot.position.isometry(&self.map), true);
+            body.set_linvel(projectile_snapshot.velocity.linvel(), true);
+            body.set_angvel(projectile_snapshot.velocity.angvel(), true);
+            projectile.gravity_scale = projectile_snapshot.gravity_scale;
+            projectile.owner = projectile_snapshot.owner;
+            projectile.bounces_left = projectile_snapshot.bounces_left;
+        }
+
+        for (index, open_ticks_left) in snapshot.gate_open_ticks_left.iter().enumerate() {
+            self.set_gate_open_ticks(index, *open_ticks_left);
+        }
+    }
+
+    fn snapshot(&self) -> Self::SnapshotType {
+        let update_player = |player: &Player| {
+            let body = self.bodies.get(player.body_handle).unwrap();
+            PlayerSnapshot {
+                position: QuantizedPosition::new(&self.map, body.position()),
+                velocity: QuantizedVelocity::new(body.linvel(), body.angvel()),
+                input: player.input,
+                grapple: player.grapple,
+                character: player.character,
+                facing: player.facing,
+                dead: player.dead,
+                stomping: player.stomping,
+                stomp_cooldown_ticks: player.stomp_cooldown_ticks,
+                shockwave_ticks_left: player.shockwave_ticks_left,
+                shield: player.shield,
+                pad_hold_ticks: player.pad_hold_ticks,
+                emote: player.emote,
+            }
+        };
+        let update_power_pad = |power_pad: &PowerPad| {
+            let body = self.bodies.get(power_pad.body_handle).unwrap();
+            PowerPadSnapshot {
+                position: QuantizedPosition::new(&self.map, body.position()),
+                patrol_origin: power_pad.patrol_origin,
+                patrol_start_tick: power_pad.patrol_start_tick,
+                capture_progress: power_pad.capture_progress,
+            }
+        };
+        GameSnapshot {
+            round: self.round,
+            tick: self.tick,
+            scores: self.scores,
+            streaks: self.streaks,
+            stats: self.stats,
+            match_result: self.match_result,
+            lobby: self.lobby,
+            rematch_votes: self.rematch_votes,
+            spread_rng_state: self.spread_rng_state,
+            spawn_rng_state: self.spawn_rng_state,
+            spread_shot_cooldown_ticks: self.spread_shot_cooldown_ticks,
+            corpse_ticks_left: self.corpse_ticks_left,
+            post_round_ticks_left: self.post_round_ticks_left,
+            last_round_result: self.last_round_result,
+            round_start_tick: self.round_start_tick,
+            away: self.away,
+            away_ticks_left: self.away_ticks_left,
+            next_projectile_id: self.next_projectile_id,
+            advantage_state: self.advantage_state,
+            player1: update_player(&self.player1),
+            player2: update_player(&self.player2),
+            cannon_x_position: self.cannon_x_position,
+            bottom_power_pad: update_power_pad(&self.bottom_power_pad),
+            top_power_pad: update_power_pad(&self.top_power_pad),
+            projectiles: self
+                .projectiles
+                .iter()
+                .map(|(id, projectile)| {
+                    let body = self.bodies.get(projectile.body_handle).unwrap();
+                    (
+                        *id,
+                        ProjectileSnapshot {
+                            position: QuantizedPosition::new(&self.map, body.position()),
+                            velocity: QuantizedVelocity::new(body.linvel(), body.angvel()),
+                            gravity_scale: projectile.gravity_scale,
+                            owner: projectile.owner,
+                            bounces_left: projectile.bounces_left,
+                        },
+                    )
+                })
+                .collect::<HashMap<u16, ProjectileSnapshot>>(),
+            gate_open_ticks_left: self
+                .switch_gates
+                .iter()
+                .map(|switch_gate| switch_gate.open_ticks_left)
+                .collect(),
+        }
+    }
+
+    fn display_state(&self) -> Self::DisplayStateType {
+        let convert_simulation_to_display_scale = |body_handle: RigidBodyHandle| {
+            let mut i = *self.bodies.get(body_handle).unwrap().position();
+            i.translation.vector *= PHYSICS_SCALE;
+            i
+        };
+        GameDisplayState {
+            tick: self.tick,
+            round: self.round,
+            advantage_state: self.advantage_state,
+            player1_position: convert_simulation_to_display_scale(self.player1.body_handle),
+            player2_position: convert_simulation_to_display_scale(self.player2.body_handle),
+            player1_grapple_anchor: self
+                .player1
+                .grapple
+                .map(|grapple| grapple.anchor * PHYSICS_SCALE),
+            player2_grapple_anchor: self
+                .player2
+                .grapple
+                .map(|grapple| grapple.anchor * PHYSICS_SCALE),
+            player1_character: self.player1.character,
+            player2_character: self.player2.character,
+            player1_motion: self.player_motion(&self.player1),
+            player2_motion: self.player_motion(&self.player2),
+            match_config: self.match_config(),
+            scores: self.scores,
+            streaks: self.streaks,
+            stats: self.stats,
+            match_result: self.match_result,
+            lobby: self.lobby,
+            rematch_votes: self.rematch_votes,
+            crowd_event: self.crowd_event(),
+            round_result: if self.post_round_ticks_left > 0 {
+                Some(self.last_round_result)
+            } else {
+                None
+            },
+            waiting_for: if self.away.contains(&true) {
+                Some((self.away, self.away_ticks_left))
+            } else {
+                None
+            },
+            player1_shockwave: self.player1.shockwave(),
+            player2_shockwave: self.player2.shockwave(),
+            player1_shield: self.player1.shield,
+            player2_shield: self.player2.shield,
+            player1_emote: self.player1.shown_emote(self.tick),
+            player2_emote: self.player2.shown_emote(self.tick),
+            spread_shot_cooldown_ticks: self.spread_shot_cooldown_ticks,
+            round_ticks_left: self.round_ticks_left(),
+            ghost: self.ghost.as_ref().and_then(|ghost| {
+                ghost
+                    .positions
+                    .get(self.round_recording_index())
+                    .map(|position| (ghost.player_id, position * PHYSICS_SCALE))
+            }),
+            cannon_x_position: self.cannon_x_position,
+            bottom_power_pad_position: convert_simulation_to_display_scale(
+                self.bottom_power_pad.body_handle,
+            ),
+            top_power_pad_position: convert_simulation_to_display_scale(
+                self.top_power_pad.body_handle,
+            ),
+            bottom_power_pad_capture_progress: self.bottom_power_pad.capture_progress as f32
+                / POWER_PAD_CAPTURE_TICKS as f32,
+            top_power_pad_capture_progress: self.top_power_pad.capture_progress as f32
+                / POWER_PAD_CAPTURE_TICKS as f32,
+            // TODO: potential caching
+            projectile_positions: self
+                .projectiles
+                .iter()
+                .map(|(id, projectile)| {
+                    (
+                        *id,
+                        convert_simulation_to_display_scale(projectile.body_handle),
+                    )
+                })
+                .collect::<HashMap<u16, Isometry<Real>>>(),
+            gates_visible: self
+                .switch_gates
+                .iter()
+                .map(|switch_gate| switch_gate.open_ticks_left == 0)
+                .collect(),
+            applied_commands: self.applied_commands.clone(),
+            events: self.events.clone(),
+            match_start: self.match_start.clone(),
+            snapshot_positions: self
+                .snapshot_positions
+                .map(|[player1, player2]| [player1 * PHYSICS_SCALE, player2 * PHYSICS_SCALE]),
+            predicted_positions: self.predicted_positions.map(|(tick, [player1, player2])| {
+                (tick, [player1 * PHYSICS_SCALE, player2 * PHYSICS_SCALE])
+            }),
+        }
+    }
+}
+
+impl Stepper for GameWorld {
+    fn step(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+        let tick = self.tick;
+        self.applied_commands
+            .retain(|(applied_at, _)| tick.wrapping_sub(*applied_at) <= APPLIED_COMMANDS_TICKS);
+        self.events
+            .retain(|(happened_at, _)| tick.wrapping_sub(*happened_at) <= EVENTS_TICKS);
+
+        if self.match_result.is_some() {
+            return;
+        }
+
+        // players who left the lobby aren't waited for, it waits for everyone to be ready anyway
+        if self.away.contains(&true) && !self.lobby {
+            self.step_away_phase();
+            return;
+        }
+
+        let tuning = self.tuning();
+
+        if self.corpse_ticks_left > 0 {
+            self.step_corpse_phase();
+            return;
+        }
+
+        // everything stays frozen while the round result is shown
+        if self.post_round_ticks_left > 0 {
+            self.post_round_ticks_left -= 1;
+            self.round_start_tick = self.tick;
+            if self.post_round_ticks_left == 0 {
+                self.record(GameEvent::RoundStarted);
+            }
+            return;
+        }
+
+        // move patrolling power pads
+        for (power_pad, top_half) in
+            [(&self.bottom_power_pad, false), (&self.top_power_pad, true)].iter()
+        {
+            let positions = self.map.power_pad_positions(*top_half);
+            if let Some(translation) = power_pad.patrol_translation(&positions, self.tick) {
+                self.bodies
+                    .get_mut(power_pad.body_handle)
+                    .unwrap()
+                    .set_translation(translation, true);
+            }
+        }
+
+        self.spread_shot_cooldown_ticks = self.spread_shot_cooldown_ticks.saturating_sub(1);
+
+        // close gates whose time is up
+        for index in 0..self.switch_gates.len() {
+            let open_ticks_left = self.switch_gates[index].open_ticks_left;
+            if open_ticks_left > 0 {
+                self.set_gate_open_ticks(index, open_ticks_left - 1);
+            }
+        }
+
+        self.update_stomps();
+
+        // attach and release grappling hooks
+        for (player_id, up) in [(PlayerId::Player1, 1.0), (PlayerId::Player2, -1.0)].iter() {
+            let player = match player_id {
+                PlayerId::Player1 => &self.player1,
+                PlayerId::Player2 => &self.player2,
+            };
+            let advantaged = matches!(
+                (self.advantage_state, player_id),
+                (AdvantageState::Player1, PlayerId::Player1)
+                    | (AdvantageState::Player2, PlayerId::Player2)
+            );
+
+            let grapple = if !player.input.grapple || advantaged {
+                None
+            } else if player.grapple.is_some() {
+                player.grapple
+            } else {
+                let translation = *self.bodies.get(player.body_handle).unwrap().translation();
+                self.cast_grapple_ray(translation, *up)
+                    .map(|distance| Grapple {
+                        anchor: translation + vector![0.0, distance * up],
+                        length: distance,
+                    })
+            };
+            self.player_mut(*player_id).grapple = grapple;
+        }
+
+        let mut new_projectiles = vec![];
+        let mut jumped = vec![];
+        // the hazard cannons' shots don't count against the players' budget
+        let player_shots = self
+            .projectiles
+            .values()
+            .filter(|projectile| projectile.owner.is_some())
+            .count();
+
+        for (player_id, player, mirror_multiplier) in [
+            (PlayerId::Player1, &self.player1, 1.0),
+            (PlayerId::Player2, &self.player2, -1.0),
+        ]
+        .iter()
+        {
+            if matches!(
+                (self.advantage_state, player_id),
+                (AdvantageState::Player1, PlayerId::Player1)
+                    | (AdvantageState::Player2, PlayerId::Player2)
+            ) {
+                // keeps the cannon away from the arena walls
+                const CANNON_MARGIN: f32 = 100.0;
+
+                if player.input.left {
+                    self.cannon_x_position = (self.cannon_x_position
+                        - tuning.cannon_speed * mirror_multiplier)
+                        .max(CANNON_MARGIN)
+                        .min(self.map.width - CANNON_MARGIN);
+                }
+                if player.input.right {
+                    self.cannon_x_position = (self.cannon_x_position
+                        + tuning.cannon_speed * mirror_multiplier)
+                        .max(CANNON_MARGIN)
+                        .min(self.map.width - CANNON_MARGIN);
+                }
+
+                let max_projectiles = tuning.max_projectiles as usize;
+                // angles of the shots fired this tick, relative to straight ahead
+                let (shot_angles, speed, gravity_scale, bounces_left) = if player.input.ability
+                    && self.spread_shot_cooldown_ticks == 0
+                    && player_shots + SPREAD_SHOT_ANGLES.len() <= max_projectiles
+                {
+                    self.spread_shot_cooldown_ticks = SPREAD_SHOT_COOLDOWN_TICKS;
+                    (
+                        SPREAD_SHOT_ANGLES.to_vec(),
+                        tuning.projectile_speed,
+                        0.0,
+                        None,
+                    )
+                } else if player.input.mortar && player_shots < max_projectiles {
+                    // lobbed towards where the cannon is heading
+                    let heading = player.input.right as i8 - player.input.left as i8;
+                    (
+                        vec![heading as f32 * MORTAR_ANGLE],
+                        MORTAR_SPEED,
+                        MORTAR_GRAVITY_SCALE,
+                        None,
+                    )
+                } else if player.input.ricochet && player_shots < max_projectiles {
+                    (
+                        vec![0.0],
+                        tuning.projectile_speed,
+                        0.0,
+                        Some(RICOCHET_BOUNCES),
+                    )
+                } else if player.input.action && player_shots < max_projectiles {
+                    // TODO: limit firerate
+                    (vec![0.0], tuning.projectile_speed, 0.0, None)
+                } else {
+                    (vec![], tuning.projectile_speed, 0.0, None)
+                };
+
+                if !shot_angles.is_empty() {
+                    let deflection = if self.rules.cannon_spread {
+                        next_spread(&mut self.spread_rng_state) * CANNON_SPREAD_MAX_ANGLE
+                    } else {
+                        0.0
+                    };
+
+                    for shot_angle in shot_angles.iter() {
+                        let id = take_projectile_id(&mut self.next_projectile_id);
+
+                        let angle = deflection + shot_angle;
+                        new_projectiles.push((
+                            id,
+                            vector![
+                                self.cannon_x_position / PHYSICS_SCALE,
+                                self.map.center().y / PHYSICS_SCALE
+                            ],
+                            vector![angle.sin(), angle.cos()] * (speed * *mirror_multiplier),
+                            gravity_scale,
+                            Some(*player_id),
+                            bounces_left,
+                        ));
+                    }
+                    let stats = &mut self.stats[player_id.as_usize()];
+                    stats.shots_fired = stats.shots_fired.saturating_add(shot_angles.len() as u16);
+                }
+            } else {
+                let stats = player.character.stats();
+                let body = self.bodies.get_mut(player.body_handle).unwrap();
+
+                let mut x_velocity = 0.0;
+
+                if player.input.left {
+                    x_velocity -= 1.0 * *mirror_multiplier;
+                }
+                if player.input.right {
+                    x_velocity += 1.0 * *mirror_multiplier;
+                }
+
+                x_velocity *= stats.speed * tuning.move_speed;
+
+                let is_grounded = Self::is_grounded(&self.narrow_phase, player._collider_handle);
+
+                if player.stomping {
+                    body.set_linvel(vector![0.0, -STOMP_SPEED * *mirror_multiplier], true);
+                } else if player.grapple.is_some() {
+                    // keep the swing momentum, only nudge it with the movement keys
+                    let linvel = *body.linvel();
+                    body.set_linvel(
+                        vector![linvel.x + x_velocity * GRAPPLE_SWING_CONTROL, linvel.y],
+                        true,
+                    );
+                } else if player.input.action && is_grounded {
+                    // the action is held for a tick or two, only its first one is the jump
+                    if body.linvel()[1] * *mirror_multiplier <= 0.0 {
+                        jumped.push(*player_id);
+                    }
+                    let y_velocity =
+                        stats.jump_velocity * tuning.jump_velocity * *mirror_multiplier;
+                    body.set_linvel(vector![x_velocity, y_velocity], true);
+                } else {
+                    let y_velocity = body.linvel()[1];
+                    body.set_linvel(vector![x_velocity, y_velocity], true);
+                }
+
+                // apply player specific gravity
+                body.apply_force(
+                    vector![0.0, *mirror_multiplier * -9.81 * tuning.gravity_scale],
+                    true,
+                );
+
+                // practice shots, fired from the cannon line straight above the player
+                if self.lobby
+                    && player.input.mortar
+                    && self.tick % PRACTICE_SHOT_COOLDOWN_TICKS == 0
+                    && player_shots < tuning.max_projectiles as usize
+                {
+                    let id = take_projectile_id(&mut self.next_projectile_id);
+
+                    new_projectiles.push((
+                        id,
+                        vector![body.translation().x, self.map.center().y / PHYSICS_SCALE],
+                        vector![0.0, tuning.projectile_speed * *mirror_multiplier],
+                        0.0,
+                        Some(*player_id),
+                        None,
+                    ));
+                }
+            }
+        }
+
+        // players keep looking the way they last moved, halves mirrored like the movement
+        for (player, mirror_multiplier) in
+            [(&mut self.player1, 1.0), (&mut self.player2, -1.0)].iter_mut()
+        {
+            let direction = (player.input.right as i8 - player.input.left as i8) as f32;
+            if !player.dead && direction * *mirror_multiplier > 0.0 {
+                player.facing = Facing::Right;
+            } else if !player.dead && direction * *mirror_multiplier < 0.0 {
+                player.facing = Facing::Left;
+            }
+        }
+
+        // the hazard cannons fire across both halves regardless of the advantage
+        if self.tick % HAZARD_CANNON_PERIOD_TICKS == 0 {
+            for (top_half, mirror_multiplier) in [(false, 1.0), (true, -1.0)].iter() {
+                let Position { x, y } = self.map.hazard_cannon_position(*top_half);
+                let id = take_projectile_id(&mut self.next_projectile_id);
+
+                new_projectiles.push((
+                    id,
+                    vector![x / PHYSICS_SCALE, y / PHYSICS_SCALE],
+                    vector![HAZARD_PROJECTILE_SPEED * mirror_multiplier, 0.0],
+                    0.0,
+                    None,
+                    None,
+                ));
+            }
+        }
+
+        for player_id in jumped {
+            self.record(GameEvent::Jumped(player_id));
+        }
+        for (projectile_id, translation, linvel, gravity_scale, owner, bounces_left) in
+            new_projectiles
+        {
+            self.record(GameEvent::Fired(owner));
+            self.create_projectile(
+                projectile_id,
+                translation,
+                Some(linvel),
+                gravity_scale,
+                owner,
+                bounces_left,
+            );
+        }
+
+        // each half of the arena pulls projectiles towards its own floor
+        for projectile in self.projectiles.values() {
+            if projectile.gravity_scale != 0.0 {
+                let body = self.bodies.get_mut(projectile.body_handle).unwrap();
+                let mirror_multiplier =
+                    if body.translation().y * PHYSICS_SCALE < self.map.center().y {
+                        1.0
+                    } else {
+                        -1.0
+                    };
+                body.apply_force(
+                    vector![
+                        0.0,
+                        mirror_multiplier * -9.81 * tuning.gravity_scale * projectile.gravity_scale
+                    ],
+                    true,
+                );
+            }
+        }
+
+        let positions_before = [
+            *self
+                .bodies
+                .get(self.player1.body_handle)
+                .unwrap()
+                .translation(),
+            *self
+                .bodies
+                .get(self.player2.body_handle)
+                .unwrap()
+                .translation(),
+        ];
+
+        self.step_physics();
+
+        for (index, player) in [&self.player1, &self.player2].iter().enumerate() {
+            let position = self.bodies.get(player.body_handle).unwrap().translation();
+            self.stats[index].distance_traveled +=
+                (position - positions_before[index]).norm() * PHYSICS_SCALE;
+        }
+
+        // keep grappling players within their rope length
+        for player in [&self.player1, &self.player2].iter() {
+            if let Some(grapple) = player.grapple {
+                let body = self.bodies.get_mut(player.body_handle).unwrap();
+                let offset = body.translation() - grapple.anchor;
+                let distance = offset.norm();
+                if distance > grapple.length {
+                    let direction = offset / distance;
+                    body.set_translation(grapple.anchor + direction * grapple.length, true);
+
+                    let linvel = *body.linvel();
+                    let outward_speed = linvel.dot(&direction);
+                    if outward_speed > 0.0 {
+                        body.set_linvel(linvel - direction * outward_speed, true);
+                    }
+                }
+            }
+        }
+
+        self.record_round();
+
+        // sensors touching the players, along with the projectile they belong to
+        let mut hits = vec![];
+        for (player_id, player) in [
+            (PlayerId::Player1, &self.player1),
+            (PlayerId::Player2, &self.player2),
+        ]
+        .iter()
+        {
+            for (c1, c2, intersecting) in self
+                .narrow_phase
+                .intersections_with(player._collider_handle)
+            {
+                if intersecting {
+                    let other_collider = if c1 == player._collider_handle {
+                        c2
+                    } else {
+                        c1
+                    };
+
+                    if self.colliders.get(other_collider).unwrap().is_sensor() {
+                        let projectile = self
+                            .projectiles
+                            .iter()
+                            .find(|(_, projectile)| projectile._collider_handle == other_collider)
+                            .map(|(projectile_id, projectile)| (*projectile_id, projectile.owner));
+                        hits.push((*player_id, projectile));
+                    }
+                }
+            }
+        }
+
+        let mut dead_players = HashSet::default();
+        for (player_id, projectile) in hits {
+            if let Some((_, Some(owner))) = projectile {
+                if owner != player_id {
+                    let stats = &mut self.stats[owner.as_usize()];
+                    stats.hits = stats.hits.saturating_add(1);
+                }
+            }
+
+            let player = self.player_mut(player_id);
+            match projectile {
+                // shields only stop projectiles
+                Some((projectile_id, _)) if player.shield => {
+                    player.shield = false;
+                    self.remove_projectile(projectile_id);
+                    self.record(GameEvent::Impact);
+                    self.record(GameEvent::ShieldBroken(player_id));
+                }
+                _ => {
+                    dead_players.insert(player_id);
+                }
+            }
+        }
+        for player_id in dead_players.iter() {
+            let stats = &mut self.stats[player_id.as_usize()];
+            stats.deaths = stats.deaths.saturating_add(1);
+            self.record(GameEvent::Died(*player_id));
+        }
+
+        if !dead_players.is_empty() {
+            self.start_corpse_phase(&dead_players);
+        } else {
+            let mut players_reached_pad = 0;
+            let mut next_state = self.advantage_state;
+            for (player_id, player, power_pad) in [
+                (
+                    PlayerId::Player1,
+                    &mut self.player1,
+                    &mut self.bottom_power_pad,
+                ),
+                (
+                    PlayerId::Player2,
+                    &mut self.player2,
+                    &mut self.top_power_pad,
+                ),
+            ]
+            .iter_mut()
+            {
+                let on_pad = self
+                    .narrow_phase
+                    .contact_pair(player._collider_handle, power_pad._collider_handle)
+                    .map_or(false, |contact_pair| contact_pair.has_any_active_contact);
+
+                match (self.advantage_state, *player_id) {
+                    (AdvantageState::Neutral, _)
+                    | (AdvantageState::Player1, PlayerId::Player2)
+                    | (AdvantageState::Player2, PlayerId::Player1)
+                        if on_pad && !self.lobby =>
+                    {
+                        player.pad_hold_ticks = 0;
+                        power_pad.capture_progress += 1;
+                        if power_pad.capture_progress >= POWER_PAD_CAPTURE_TICKS {
+                            power_pad.capture_progress = 0;
+
+                            if players_reached_pad > 0 {
+                                next_state = AdvantageState::Neutral;
+                            } else {
+                                next_state = match player_id {
+                                    PlayerId::Player1 => AdvantageState::Player1,
+                                    PlayerId::Player2 => AdvantageState::Player2,
+                                };
+                            }
+
+                            players_reached_pad += 1;
+                            let stats = &mut self.stats[player_id.as_usize()];
+                            stats.pad_captures = stats.pad_captures.saturating_add(1);
+                            self.events
+                                .push((self.tick, GameEvent::PadCaptured(*player_id)));
+                        }
+                    }
+                    // holding on to the captured pad earns a shield
+                    (AdvantageState::Player1, PlayerId::Player1)
+                    | (AdvantageState::Player2, PlayerId::Player2)
+                        if on_pad =>
+                    {
+                        power_pad.capture_progress = 0;
+                        player.pad_hold_ticks = player.pad_hold_ticks.saturating_add(1);
+                        if player.pad_hold_ticks == SHIELD_PAD_HOLD_TICKS {
+                            player.shield = true;
+                        }
+                    }
+                    _ => {
+                        power_pad.capture_progress = 0;
+                        player.pad_hold_ticks = 0;
+                    }
+                }
+            }
+            self.advantage_state = next_state;
+
+            match players_reached_pad {
+                1 => match self.advantage_state {
+                    AdvantageState::Neutral => unreachable!(),
+                    AdvantageState::Player1 => {
+                        let body = self.bodies.get_mut(self.player1.body_handle).unwrap();
+                        body.set_linvel(vector![0.0, 0.0], true);
+
+                        let body = self.bodies.get_mut(self.player2.body_handle).unwrap();
+                        let x = body.translation().x * PHYSICS_SCALE;
+
+                        // send the opponent's pad patrolling, starting away from them
+                        let towards = if x < self.map.center().x {
+                            PowerPadStatus::Right
+                        } else {
+                            PowerPadStatus::Left
+                        };
+                        self.top_power_pad.start_patrol(
+                            &self.bodies,
+                            &self.map.power_pad_positions(true),
+                            towards,
+                            self.tick,
+                        );
+                        self.bottom_power_pad.patrol_start_tick = None;
+                        self.record(GameEvent::PadPatrolStarted(PlayerId::Player2, towards));
+                    }
+                    AdvantageState::Player2 => {
+                        let body = self.bodies.get_mut(self.player2.body_handle).unwrap();
+                        body.set_linvel(vector![0.0, 0.0], true);
+
+                        let body = self.bodies.get_mut(self.player1.body_handle).unwrap();
+                        let x = body.translation().x * PHYSICS_SCALE;
+
+                        // send the opponent's pad patrolling, starting away from them
+                        let towards = if x < self.map.center().x {
+                            PowerPadStatus::Right
+                        } else {
+                            PowerPadStatus::Left
+                        };
+                        self.bottom_power_pad.start_patrol(
+                            &self.bodies,
+                            &self.map.power_pad_positions(false),
+                            towards,
+                            self.tick,
+                        );
+                        self.top_power_pad.patrol_start_tick = None;
+                        self.record(GameEvent::PadPatrolStarted(PlayerId::Player1, towards));
+                    }
+                },
+                2 => {
+                    // move both pads
+                    todo!();
+                }
+                _ => (),
+            }
+
+            let mut hit_switches = vec![];
+            // ricochet shots with bounces left, and the solids they went into
+            let mut bounces = vec![];
+            let mut projectiles_to_remove = if players_reached_pad > 0 {
+                // destroy all projectiles
+                self.projectiles.keys().copied().collect()
+            } else {
+                // destroy projectiles that hit solids (non-sensors)
+                let mut projectiles_to_remove = vec![];
+                for (projectile_id, projectile) in self.projectiles.iter() {
+                    let hit_solids = self
+                        .narrow_phase
+                        .intersections_with(projectile._collider_handle)
+                        .filter_map(|(c1, c2, intersecting)| {
+                            if intersecting {
+                                let other_collider = if c1 == projectile._collider_handle {
+                                    c2
+                                } else {
+                                    c1
+                                };
+
+                                if !self.colliders.get(other_collider).unwrap().is_sensor() {
+                                    return Some(other_collider);
+                                }
+                            }
+                            None
+                        })
+                        .collect::<Vec<ColliderHandle>>();
+
+                    // nothing stops projectiles that left the arena
+                    let translation = self
+                        .bodies
+                        .get(projectile.body_handle)
+                        .unwrap()
+                        .translation()
+                        * PHYSICS_SCALE;
+                    let out_of_bounds = !self.map.contains(translation.x, translation.y);
+
+                    if out_of_bounds {
+                        projectiles_to_remove.push(*projectile_id);
+                    } else if !hit_solids.is_empty() {
+                        match projectile.bounces_left {
+                            Some(bounces_left) if bounces_left > 0 => {
+                                bounces.push((*projectile_id, hit_solids.clone()))
+                            }
+                            _ => projectiles_to_remove.push(*projectile_id),
+                        }
+                    }
+
+                    // shot switches open their gates
+                    for (index, switch_gate) in self.switch_gates.iter().enumerate() {
+                        if hit_solids.contains(&switch_gate.switch_collider_handle) {
+                            hit_switches.push(index);
+                        }
+                    }
+                }
+                projectiles_to_remove
+            };
+
+            // one bounce a tick, however many solids the shot went into
+            for (projectile_id, solids) in bounces {
+                let projectile = self.projectiles.get_mut(&projectile_id).unwrap();
+                projectile.bounces_left = projectile.bounces_left.map(|left| left - 1);
+                let body_handle = projectile.body_handle;
+                for solid in solids {
+                    self.bounce_projectile(body_handle, solid);
+                }
+            }
+
+            // the captured pad clears the projectiles without them hitting anything
+            if players_reached_pad == 0 && !projectiles_to_remove.is_empty() {
+                self.record(GameEvent::Impact);
+            }
+            for projectile_id in projectiles_to_remove {
+                self.remove_projectile(projectile_id);
+            }
+
+            for index in hit_switches {
+                self.set_gate_open_ticks(index, GATE_OPEN_TICKS);
+            }
+
+            // rounds running out of time end in a draw
+            if self.round_ticks_left() == Some(0) {
+                self.reset_round();
+            }
+        }
+    }
+}
+
+impl DisplayState for GameDisplayState {
+    fn from_interpolation(state1: &Self, state2: &Self, t: f64) -> Self {
+        if state1.round != state2.round {
+            state2.clone()
+        } else {
+            let mut interpolated_projectile_positions = state1.projectile_positions.clone();
+            for (projectile_id, p2) in state2.projectile_positions.iter() {
+                if let Some(p1) = interpolated_projectile_positions.get_mut(&projectile_id) {
+                    p1.translation.vector[0] = (1.0 - t as f32) * p1.translation.vector[0]
+                        + t as f32 * p2.translation.vector[0];
+                    p1.translation.vector[1] = (1.0 - t as f32) * p1.translation.vector[1]
+                        + t as f32 * p2.translation.vector[1];
+                    p1.rotation = p2.rotation;
+                }
+            }
+
+            GameDisplayState {
+                tick: state2.tick,
+                round: state2.round,
+                advantage_state: state2.advantage_state,
+                player1_position: state1
+                    .player1_position
+                    .lerp_slerp(&state2.player1_position, t as f32),
+                player2_position: state1
+                    .player2_position
+                    .lerp_slerp(&state2.player2_position, t as f32),
+                player1_grapple_anchor: state2.player1_grapple_anchor,
+                player2_grapple_anchor: state2.player2_grapple_anchor,
+                player1_character: state2.player1_character,
+                player2_character: state2.player2_character,
+                player1_motion: PlayerMotion {
+                    velocity: state1
+                        .player1_motion
+                        .velocity
+                        .lerp(&state2.player1_motion.velocity, t as f32),
+                    ..state2.player1_motion
+                },
+                player2_motion: PlayerMotion {
+                    velocity: state1
+                        .player2_motion
+                        .velocity
+                        .lerp(&state2.player2_motion.velocity, t as f32),
+                    ..state2.player2_motion
+                },
+                match_config: state2.match_config,
+                scores: state2.scores,
+                streaks: state2.streaks,
+                stats: state2.stats,
+                match_result: state2.match_result,
+                lobby: state2.lobby,
+                rematch_votes: state2.rematch_votes,
+                crowd_event: state2.crowd_event,
+                round_result: state2.round_result,
+                waiting_for: state2.waiting_for,
+                player1_shockwave: state2.player1_shockwave,
+                player2_shockwave: state2.player2_shockwave,
+                player1_shield: state2.player1_shield,
+                player2_shield: state2.player2_shield,
+                player1_emote: state2.player1_emote,
+                player2_emote: state2.player2_emote,
+                spread_shot_cooldown_ticks: state2.spread_shot_cooldown_ticks,
+                round_ticks_left: state2.round_ticks_left,
+                ghost: match (state1.ghost, state2.ghost) {
+                    (Some((player_id, p1)), Some((_, p2))) => {
+                        Some((player_id, p1.lerp(&p2, t as f32)))
+                    }
+                    _ => state2.ghost,
+                },
+                cannon_x_position: (1.0 - t as f32) * state1.cannon_x_position
+                    + t as f32 * state2.cannon_x_position,
+                bottom_power_pad_position: state1
+                    .bottom_power_pad_position
+                    .lerp_slerp(&state2.bottom_power_pad_position, t as f32),
+                top_power_pad_position: state1
+                    .top_power_pad_position
+                    .lerp_slerp(&state2.top_power_pad_position, t as f32),
+                bottom_power_pad_capture_progress: state2.bottom_power_pad_capture_progress,
+                top_power_pad_capture_progress: state2.top_power_pad_capture_progress,
+                projectile_positions: interpolated_projectile_positions,
+                gates_visible: state2.gates_visible.clone(),
+                applied_commands: state2.applied_commands.clone(),
+                events: state2.events.clone(),
+                match_start: state2.match_start.clone(),
+                snapshot_positions: state2.snapshot_positions,
+                predicted_positions: state2.predicted_positions,
+            }
+        }
+    }
+}
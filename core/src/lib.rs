@@ -0,0 +1,409 @@
+//! Game rules and simulation, without any engine or networking dependencies.
+//! Everything needed to run a `GameWorld`: commands, snapshots, and level and balance data.
+
+#![feature(iter_zip)]
+
+pub mod codec;
+pub mod game;
+pub mod protocol;
+pub mod quantize;
+pub mod replay;
+pub mod scenario;
+
+pub use crystalorb;
+use crystalorb::Config;
+pub use game::PlayerId;
+use serde::{Deserialize, Serialize};
+
+pub const TIMESTEP: f64 = 1.0 / 60.0;
+
+pub fn crystal_orb_config() -> Config {
+    Config {
+        //lag_compensation_latency: (),
+        //blend_latency: 0.001,
+        timestep_seconds: TIMESTEP,
+        //clock_sync_needed_sample_count: (),
+        //clock_sync_assumed_outlier_rate: (),
+        //clock_sync_request_period: (),
+        //max_tolerable_clock_deviation: (),
+        //snapshot_send_period: (),
+        //update_delta_seconds_max: (),
+        //timestamp_skip_threshold_seconds: (),
+        //fastforward_max_per_step: (),
+        //tweening_method: (),
+        ..Default::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Position {
+    pub y: f32,
+    pub x: f32,
+}
+
+/// An arena, in display units. The top half of the arena mirrors the bottom half through its
+/// center, so only the bottom half is laid out.
+#[derive(Debug, Clone, Copy)]
+pub struct MapDefinition {
+    /// Shown to players looking for a server.
+    pub name: &'static str,
+    pub width: f32,
+    pub height: f32,
+    /// Where players start a round in the bottom half, mirrored for the top half.
+    pub spawn_points: &'static [Position],
+    /// Elements of the bottom half, mirrored for the top half.
+    pub level: &'static [LevelElement],
+    /// Switches of the bottom half with their gates, mirrored for the top half.
+    pub switch_gates: &'static [SwitchGate],
+    /// Neutral cannon firing across the bottom half, mirrored for the top half.
+    pub hazard_cannon_position: Position,
+    /// Ends of the bottom power pad's patrol, mirrored for the top pad.
+    pub power_pad_positions: PowerPadPositions,
+}
+
+impl MapDefinition {
+    pub fn center(&self) -> Position {
+        Position {
+            x: self.width / 2.0,
+            y: self.height / 2.0,
+        }
+    }
+
+    /// Mirrors a point of one half of the arena into the other.
+    pub fn mirror(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.width - x, self.height - y)
+    }
+
+    pub fn mirror_rect(&self, rect: &Rect) -> Rect {
+        let (x, y) = self.mirror(rect.x, rect.y);
+        Rect { x, y, ..*rect }
+    }
+
+    fn in_half(&self, position: Position, top_half: bool) -> Position {
+        if top_half {
+            let (x, y) = self.mirror(position.x, position.y);
+            Position { x, y }
+        } else {
+            position
+        }
+    }
+
+    /// Spawn point of the given index for a player starting in the given half.
+    pub fn spawn_point(&self, index: usize, top_half: bool) -> Position {
+        self.in_half(self.spawn_points[index % self.spawn_points.len()], top_half)
+    }
+
+    /// Switches and gates of both halves, the bottom half's first.
+    pub fn all_switch_gates(&self) -> impl Iterator<Item = SwitchGate> + '_ {
+        let mirrored = self.switch_gates.iter().map(move |switch_gate| SwitchGate {
+            switch: self.mirror_rect(&switch_gate.switch),
+            gate: self.mirror_rect(&switch_gate.gate),
+        });
+        self.switch_gates.iter().copied().chain(mirrored)
+    }
+
+    pub fn hazard_cannon_position(&self, top_half: bool) -> Position {
+        self.in_half(self.hazard_cannon_position, top_half)
+    }
+
+    /// Ends of the patrol of the power pad in the given half. Mirroring swaps them.
+    pub fn power_pad_positions(&self, top_half: bool) -> PowerPadPositions {
+        let PowerPadPositions { left, right } = self.power_pad_positions;
+        if top_half {
+            PowerPadPositions {
+                left: self.in_half(right, true),
+                right: self.in_half(left, true),
+            }
+        } else {
+            self.power_pad_positions
+        }
+    }
+
+    /// Where the power pad of the given half starts a round, the right end of the bottom pad's
+    /// patrol.
+    pub fn starting_power_pad_position(&self, top_half: bool) -> Position {
+        self.in_half(self.power_pad_positions.right, top_half)
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= 0.0 && x <= self.width && y >= 0.0 && y <= self.height
+    }
+}
+
+impl Default for MapDefinition {
+    fn default() -> Self {
+        MAP
+    }
+}
+
+pub const MAP: MapDefinition = MapDefinition {
+    name: "Twin Towers",
+    width: 1000.0,
+    height: 1000.0,
+    spawn_points: &SPAWN_POINTS,
+    level: &LEVEL,
+    switch_gates: &SWITCH_GATES,
+    hazard_cannon_position: Position { y: 350.0, x: 15.0 },
+    power_pad_positions: PowerPadPositions {
+        left: Position { x: 150.0, y: 295.0 },
+        right: Position { x: 850.0, y: 295.0 },
+    },
+};
+
+pub const SPAWN_POINTS: [Position; 3] = [
+    Position { y: 400.0, x: 150.0 },
+    Position { y: 400.0, x: 850.0 },
+    Position { y: 350.0, x: 400.0 },
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rect {
+    pub y: f32,
+    pub x: f32,
+    pub h: f32,
+    pub w: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LevelElementKind {
+    Platform,
+    Lava,
+    /// Only drawn, the simulation ignores it.
+    Decoration {
+        color: [f32; 3],
+    },
+}
+
+/// Render layers from back to front.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Background,
+    Terrain,
+    Hazard,
+    Foreground,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LevelElement {
+    pub kind: LevelElementKind,
+    pub layer: RenderLayer,
+    pub rect: Rect,
+}
+
+const LEVEL: [LevelElement; 16] = [
+    // left power platform
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 250.0,
+            x: 150.0,
+            h: 100.0,
+            w: 100.0,
+        },
+    },
+    // bottom platform
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 150.0,
+            x: 500.0,
+            h: 100.0,
+            w: 800.0,
+        },
+    },
+    // right power platform
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 250.0,
+            x: 850.0,
+            h: 100.0,
+            w: 100.0,
+        },
+    },
+    // middle platforms
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 270.0,
+            x: 250.0,
+            h: 20.0,
+            w: 40.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 230.0,
+            x: 320.0,
+            h: 60.0,
+            w: 20.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 250.0,
+            x: 400.0,
+            h: 20.0,
+            w: 60.0,
+        },
+    },
+    // 4 squares
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 260.0,
+            x: 470.0,
+            h: 20.0,
+            w: 20.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 250.0,
+            x: 515.0,
+            h: 20.0,
+            w: 20.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 270.0,
+            x: 560.0,
+            h: 20.0,
+            w: 20.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 240.0,
+            x: 605.0,
+            h: 20.0,
+            w: 20.0,
+        },
+    },
+    // platform touching lava
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 220.0,
+            x: 680.0,
+            h: 20.0,
+            w: 80.0,
+        },
+    },
+    LevelElement {
+        kind: LevelElementKind::Platform,
+        layer: RenderLayer::Terrain,
+        rect: Rect {
+            y: 260.0,
+            x: 760.0,
+            h: 20.0,
+            w: 20.0,
+        },
+    },
+    // middle lava pool
+    LevelElement {
+        kind: LevelElementKind::Lava,
+        layer: RenderLayer::Hazard,
+        rect: Rect {
+            y: 210.0,
+            x: 500.0,
+            h: 20.0,
+            w: 600.0,
+        },
+    },
+    // lava floor
+    LevelElement {
+        kind: LevelElementKind::Lava,
+        layer: RenderLayer::Hazard,
+        rect: Rect {
+            y: 20.0,
+            x: 500.0,
+            h: 40.0,
+            w: 2000.0,
+        },
+    },
+    // backdrop behind the middle platforms
+    LevelElement {
+        kind: LevelElementKind::Decoration {
+            color: [0.1, 0.1, 0.15],
+        },
+        layer: RenderLayer::Background,
+        rect: Rect {
+            y: 250.0,
+            x: 500.0,
+            h: 120.0,
+            w: 600.0,
+        },
+    },
+    // glow over the lava floor
+    LevelElement {
+        kind: LevelElementKind::Decoration {
+            color: [1.0, 0.3, 0.0],
+        },
+        layer: RenderLayer::Foreground,
+        rect: Rect {
+            y: 42.0,
+            x: 500.0,
+            h: 4.0,
+            w: 2000.0,
+        },
+    },
+];
+
+/// A switch that, when hit by a projectile, opens its gate platform for a while.
+#[derive(Debug, Clone, Copy)]
+pub struct SwitchGate {
+    pub switch: Rect,
+    pub gate: Rect,
+}
+
+const SWITCH_GATES: [SwitchGate; 1] = [
+    // bottom bridge, opened by the top player's shots
+    SwitchGate {
+        switch: Rect {
+            y: 420.0,
+            x: 500.0,
+            h: 20.0,
+            w: 20.0,
+        },
+        gate: Rect {
+            y: 300.0,
+            x: 500.0,
+            h: 10.0,
+            w: 100.0,
+        },
+    },
+];
+
+pub const HAZARD_CANNON_SIZE: Size = Size { w: 30.0, h: 30.0 };
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerPadPositions {
+    pub left: Position,
+    pub right: Position,
+}
+
+pub struct Size {
+    pub w: f32,
+    pub h: f32,
+}
+
+pub const POWER_PAD_SIZE: Size = Size { w: 70.0, h: 10.0 };
+pub const PROJECTILE_SIZE: Size = Size { w: 10.0, h: 40.0 };
@@ -0,0 +1,91 @@
+//! What a client and a server have to agree on to play together: the layout of the messages and
+//! the game data compiled into both.
+
+use crate::{
+    codec::{Codec, DefaultCodec},
+    game::{Character, GameTuning},
+    LevelElementKind, Position, Rect, MAP,
+};
+
+/// Bumped whenever a message, command or snapshot changes its layout.
+pub const PROTOCOL_VERSION: u32 = 14;
+
+/// FNV-1a, stable across builds and platforms unlike the standard library's hashers.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+
+    fn write_position(&mut self, position: &Position) {
+        self.write_f32(position.x);
+        self.write_f32(position.y);
+    }
+
+    fn write_rect(&mut self, rect: &Rect) {
+        for value in [rect.x, rect.y, rect.w, rect.h].iter() {
+            self.write_f32(*value);
+        }
+    }
+}
+
+/// Hash of the level, map, character and default balance data, and of the codec. Builds with
+/// different data simulate differently, which would only show as constant corrections, and
+/// builds with different codecs can't read each other's snapshots and commands.
+pub fn content_hash() -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+
+    hasher.write(DefaultCodec::NAME.as_bytes());
+
+    hasher.write_f32(MAP.width);
+    hasher.write_f32(MAP.height);
+    for spawn_point in MAP.spawn_points {
+        hasher.write_position(spawn_point);
+    }
+    for element in MAP.level.iter() {
+        // decorations are only drawn
+        match element.kind {
+            LevelElementKind::Platform => hasher.write(&[0]),
+            LevelElementKind::Lava => hasher.write(&[1]),
+            LevelElementKind::Decoration { .. } => continue,
+        }
+        hasher.write_rect(&element.rect);
+    }
+    for switch_gate in MAP.switch_gates.iter() {
+        hasher.write_rect(&switch_gate.switch);
+        hasher.write_rect(&switch_gate.gate);
+    }
+    hasher.write_position(&MAP.hazard_cannon_position);
+    hasher.write_position(&MAP.power_pad_positions.left);
+    hasher.write_position(&MAP.power_pad_positions.right);
+    for character in [
+        Character::Balanced,
+        Character::Heavy,
+        Character::Light,
+        Character::Jumper,
+    ]
+    .iter()
+    {
+        let stats = character.stats();
+        hasher.write_f32(stats.speed);
+        hasher.write_f32(stats.jump_velocity);
+        hasher.write_f32(stats.size);
+    }
+    let tuning = GameTuning::default();
+    hasher.write_f32(tuning.move_speed);
+    hasher.write_f32(tuning.jump_velocity);
+    hasher.write_f32(tuning.gravity_scale);
+    hasher.write_f32(tuning.cannon_speed);
+    hasher.write_f32(tuning.projectile_speed);
+    hasher.write(&[tuning.max_projectiles]);
+
+    hasher.0
+}
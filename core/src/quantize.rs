@@ -0,0 +1,102 @@
+//! Fixed-point encodings of the bodies in snapshots, trading sub-pixel precision for much smaller
+//! packets. Everything is in physics units, like the bodies themselves.
+
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::{game::PHYSICS_SCALE, MapDefinition};
+
+/// How far past the arena edges positions can be told apart, in display units. Bodies further out
+/// are clamped to the edge of that range.
+const POSITION_MARGIN: f32 = 500.0;
+/// Steps per unit of (angular) velocity, which leaves room for about 128 units per second either
+/// way.
+const VELOCITY_STEPS_PER_UNIT: f32 = 256.0;
+
+fn quantize_range(value: Real, min: Real, max: Real) -> u16 {
+    let normalized = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    (normalized * u16::MAX as Real).round() as u16
+}
+
+fn dequantize_range(value: u16, min: Real, max: Real) -> Real {
+    min + value as Real / u16::MAX as Real * (max - min)
+}
+
+/// Bounds of one axis of the arena, with the margin.
+fn axis_bounds(length: f32) -> (Real, Real) {
+    (
+        -POSITION_MARGIN / PHYSICS_SCALE,
+        (length + POSITION_MARGIN) / PHYSICS_SCALE,
+    )
+}
+
+fn quantize_velocity(value: Real) -> i16 {
+    (value * VELOCITY_STEPS_PER_UNIT)
+        .round()
+        .clamp(i16::MIN as Real, i16::MAX as Real) as i16
+}
+
+fn dequantize_velocity(value: i16) -> Real {
+    value as Real / VELOCITY_STEPS_PER_UNIT
+}
+
+/// Position within the arena bounds in 16 bits per axis, a few hundredths of a pixel apart, and
+/// the rotation in 16 bits.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedPosition {
+    x: u16,
+    y: u16,
+    angle: i16,
+}
+
+impl QuantizedPosition {
+    pub fn new(map: &MapDefinition, position: &Isometry<Real>) -> Self {
+        let (min_x, max_x) = axis_bounds(map.width);
+        let (min_y, max_y) = axis_bounds(map.height);
+        let translation = position.translation.vector;
+        Self {
+            x: quantize_range(translation.x, min_x, max_x),
+            y: quantize_range(translation.y, min_y, max_y),
+            angle: (position.rotation.angle() / PI * i16::MAX as Real).round() as i16,
+        }
+    }
+
+    pub fn isometry(&self, map: &MapDefinition) -> Isometry<Real> {
+        let (min_x, max_x) = axis_bounds(map.width);
+        let (min_y, max_y) = axis_bounds(map.height);
+        Isometry::new(
+            vector![
+                dequantize_range(self.x, min_x, max_x),
+                dequantize_range(self.y, min_y, max_y)
+            ],
+            self.angle as Real / i16::MAX as Real * PI,
+        )
+    }
+}
+
+/// Linear and angular velocity in 16 bits each.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedVelocity {
+    x: i16,
+    y: i16,
+    angular: i16,
+}
+
+impl QuantizedVelocity {
+    pub fn new(linvel: &Vector<Real>, angvel: Real) -> Self {
+        Self {
+            x: quantize_velocity(linvel.x),
+            y: quantize_velocity(linvel.y),
+            angular: quantize_velocity(angvel),
+        }
+    }
+
+    pub fn linvel(&self) -> Vector<Real> {
+        vector![dequantize_velocity(self.x), dequantize_velocity(self.y)]
+    }
+
+    pub fn angvel(&self) -> Real {
+        dequantize_velocity(self.angular)
+    }
+}
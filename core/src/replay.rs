@@ -0,0 +1,87 @@
+//! Replays of whole matches: the state a match started from and every command applied during it,
+//! each with the tick it was applied on. The simulation is deterministic, so stepping a world
+//! from the start state and applying the commands on their ticks plays the match out again.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::{
+    game::{GameCommand, GameSnapshot, GameWorld, MatchConfig},
+    protocol::{self, PROTOCOL_VERSION},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    /// Replays only play back on builds with the same protocol version and game data.
+    pub protocol_version: u32,
+    pub content_hash: u64,
+    /// Tick the match started on.
+    pub start_tick: u32,
+    /// State of the world as the match started, before the command starting it was applied.
+    pub start: GameSnapshot,
+    /// Rules, tuning and settings the match was played with, which the snapshot leaves out.
+    pub config: MatchConfig,
+    /// In the order they were applied, starting with `StartMatch` or the last player's
+    /// `Rematch`.
+    pub commands: Vec<(u32, GameCommand)>,
+    /// Tick the match was decided on, or left off at.
+    pub end_tick: u32,
+}
+
+impl Replay {
+    pub fn new(start_tick: u32, start: GameSnapshot, config: MatchConfig) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            content_hash: protocol::content_hash(),
+            start_tick,
+            start,
+            config,
+            commands: vec![],
+            end_tick: start_tick,
+        }
+    }
+
+    /// Whether this build simulates the match the way the one that recorded it did.
+    pub fn is_playable(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION && self.content_hash == protocol::content_hash()
+    }
+
+    /// The world as the match started.
+    pub fn start_world(&self) -> GameWorld {
+        let mut world = GameWorld::new();
+        world.set_match_config(self.config);
+        world.apply_snapshot(self.start.clone());
+        world
+    }
+
+    /// Applies the commands of the world's current tick and steps it. Call it on the world from
+    /// `start_world`, once per tick.
+    pub fn step(&self, world: &mut GameWorld, tick: u32) {
+        for (_, command) in self
+            .commands
+            .iter()
+            .filter(|(applied_at, _)| *applied_at == tick)
+        {
+            world.apply_command(command);
+        }
+        world.step();
+    }
+
+    /// The world simulated from the start of the match up to the given tick.
+    pub fn world_at(&self, tick: u32) -> GameWorld {
+        let mut world = self.start_world();
+        for tick in self.start_tick..tick {
+            self.step(&mut world, tick);
+        }
+        world
+    }
+
+    pub fn write(&self, writer: impl Write) -> bincode::Result<()> {
+        bincode::serialize_into(writer, self)
+    }
+
+    pub fn read(reader: impl Read) -> bincode::Result<Self> {
+        bincode::deserialize_from(reader)
+    }
+}
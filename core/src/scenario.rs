@@ -0,0 +1,49 @@
+//! Scripted scenarios for driving a local `GameWorld`, e.g. in the tutorial.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+
+use crate::game::{AdvantageState, GameCommand, GameWorld};
+
+#[derive(Debug, Clone)]
+pub enum ScenarioAction {
+    /// Applies a command as if the player had issued it.
+    Command(GameCommand),
+    /// Hands the advantage to a player (or takes it away) without them capturing a pad.
+    SetAdvantage(AdvantageState),
+    /// Ends the round as if a player had died.
+    ResetRound,
+}
+
+/// A timeline of actions applied to a world as it gets stepped.
+#[derive(Default)]
+pub struct Scenario {
+    tick: u32,
+    actions: Vec<(u32, ScenarioAction)>,
+}
+
+impl Scenario {
+    pub fn new(actions: Vec<(u32, ScenarioAction)>) -> Self {
+        Self { tick: 0, actions }
+    }
+
+    /// Number of ticks stepped since the start of the scenario.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Applies the actions scheduled for the current tick and steps the world.
+    pub fn step(&mut self, world: &mut GameWorld) {
+        for (_, action) in self.actions.iter().filter(|(tick, _)| *tick == self.tick) {
+            match action {
+                ScenarioAction::Command(command) => world.apply_command(command),
+                ScenarioAction::SetAdvantage(advantage_state) => {
+                    world.set_advantage_state(*advantage_state)
+                }
+                ScenarioAction::ResetRound => world.reset_round(),
+            }
+        }
+
+        world.step();
+        self.tick += 1;
+    }
+}
@@ -0,0 +1,31 @@
+//! A player kicked from the server forfeits the match under way, and only that one.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::game::{GameCommand, GameWorld, MatchResult, PlayerId};
+
+#[test]
+fn forfeit_ends_the_match_for_the_opponent() {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::OpenLobby);
+    world.step();
+    world.apply_command(&GameCommand::StartMatch);
+    world.step();
+    world.apply_command(&GameCommand::PlayerLeft(PlayerId::Player2));
+    world.apply_command(&GameCommand::Forfeit(PlayerId::Player2));
+    world.step();
+    let display_state = world.display_state();
+    assert_eq!(
+        display_state.match_result,
+        Some(MatchResult::Winner(PlayerId::Player1))
+    );
+    assert_eq!(display_state.waiting_for, None);
+}
+
+#[test]
+fn forfeit_is_ignored_in_the_lobby() {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::OpenLobby);
+    world.apply_command(&GameCommand::Forfeit(PlayerId::Player1));
+    world.step();
+    assert_eq!(world.display_state().match_result, None);
+}
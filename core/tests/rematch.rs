@@ -0,0 +1,49 @@
+//! A finished match starts over once both players asked for a rematch, and only then.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::game::{GameCommand, GameWorld, MatchSettings, PlayerId};
+
+/// A one-round match, played to its end.
+fn finished_match() -> GameWorld {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::SetMatchSettings(MatchSettings {
+        round_limit: Some(1),
+        ..Default::default()
+    }));
+    world.apply_command(&GameCommand::OpenLobby);
+    world.step();
+    world.apply_command(&GameCommand::StartMatch);
+    world.step();
+    world.reset_round();
+    assert!(world.display_state().match_result.is_some());
+    world
+}
+
+#[test]
+fn rematch_waits_for_both_players() {
+    let mut world = finished_match();
+    world.apply_command(&GameCommand::Rematch(PlayerId::Player1));
+    world.step();
+    let display_state = world.display_state();
+    assert!(display_state.match_result.is_some());
+    assert_eq!(display_state.rematch_votes, [true, false]);
+
+    world.apply_command(&GameCommand::Rematch(PlayerId::Player2));
+    world.step();
+    let display_state = world.display_state();
+    assert!(display_state.match_result.is_none());
+    assert_eq!(display_state.rematch_votes, [false, false]);
+    assert_eq!(display_state.round, 1);
+    assert_eq!(display_state.scores, [0, 0]);
+}
+
+#[test]
+fn rematch_is_ignored_during_the_match() {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::OpenLobby);
+    world.step();
+    world.apply_command(&GameCommand::StartMatch);
+    world.apply_command(&GameCommand::Rematch(PlayerId::Player1));
+    world.step();
+    assert_eq!(world.display_state().rematch_votes, [false, false]);
+}
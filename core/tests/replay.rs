@@ -0,0 +1,88 @@
+//! Replays have to play a match out the way it went, from nothing but the start state and the
+//! applied commands.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::{
+    game::{GameCommand, GameWorld, PlayerId, PlayerInput},
+    replay::Replay,
+};
+
+const MATCH_TICKS: u32 = 600;
+
+fn input(player_id: PlayerId, tick: u32) -> GameCommand {
+    // a different mix of buttons every half second
+    let phase = tick / 30;
+    let input = PlayerInput {
+        left: phase % 3 == 0,
+        right: phase % 3 == 1,
+        action: phase % 2 == 0,
+        grapple: phase % 5 == 2,
+        ..Default::default()
+    };
+    GameCommand::Input(player_id, input.into())
+}
+
+/// Plays a match, recording it the way the server does, from the display states.
+fn play_match() -> (GameWorld, Replay) {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::OpenLobby);
+    world.step();
+    world.apply_command(&GameCommand::StartMatch);
+    let (start_tick, start, config) = world.display_state().match_start.unwrap();
+    let mut replay = Replay::new(start_tick, (*start).clone(), config);
+    let mut recorded_up_to = None;
+    for tick in 0..MATCH_TICKS {
+        if tick % 10 == 0 {
+            world.apply_command(&input(PlayerId::Player1, tick));
+            world.apply_command(&input(PlayerId::Player2, tick + 15));
+        }
+        world.step();
+        for (applied_at, command) in world.display_state().applied_commands {
+            if applied_at >= start_tick && Some(applied_at) > recorded_up_to {
+                replay.commands.push((applied_at, command));
+            }
+        }
+        recorded_up_to = replay.commands.last().map(|(applied_at, _)| *applied_at);
+    }
+    replay.end_tick = start_tick + MATCH_TICKS;
+    (world, replay)
+}
+
+#[test]
+fn replay_matches_the_played_match() {
+    let (world, replay) = play_match();
+    assert!(replay.is_playable());
+    assert!(matches!(
+        replay.commands.first(),
+        Some((_, GameCommand::StartMatch))
+    ));
+
+    let played = world.display_state();
+    let replayed = replay.world_at(replay.end_tick).display_state();
+    assert_eq!(played.scores, replayed.scores);
+    assert_eq!(played.round, replayed.round);
+    for (played, replayed) in [
+        (played.player1_position, replayed.player1_position),
+        (played.player2_position, replayed.player2_position),
+    ]
+    .iter()
+    {
+        let distance = (played.translation.vector - replayed.translation.vector).norm();
+        assert!(
+            distance < 1e-3,
+            "replayed position is {} off the played one",
+            distance
+        );
+    }
+}
+
+#[test]
+fn replay_survives_a_round_trip_through_a_file() {
+    let (_, replay) = play_match();
+    let mut file = vec![];
+    replay.write(&mut file).unwrap();
+    let read = Replay::read(file.as_slice()).unwrap();
+    assert_eq!(read.start_tick, replay.start_tick);
+    assert_eq!(read.end_tick, replay.end_tick);
+    assert_eq!(read.commands.len(), replay.commands.len());
+}
@@ -0,0 +1,87 @@
+//! Wire-size budgets for snapshots, which are sent to every client many times per second.
+//!
+//! Snapshots are encoded with bincode, like the turbulence message channels do. The budgets keep
+//! a full snapshot well within a single UDP packet (~1200 bytes with a conservative MTU). If a
+//! test fails, a state change grew the snapshot: make sure the new field is worth the bandwidth
+//! before raising the budget.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::game::{AdvantageState, GameCommand, GameWorld, PlayerId, PlayerInput};
+
+const EMPTY_BUDGET: usize = 256;
+const MID_FIGHT_BUDGET: usize = 768;
+const WORST_CASE_BUDGET: usize = 1024;
+
+fn encoded_size(world: &GameWorld) -> usize {
+    bincode::serialized_size(&world.snapshot()).unwrap() as usize
+}
+
+fn projectile_count(world: &GameWorld) -> usize {
+    world.display_state().projectile_positions.len()
+}
+
+/// Player 1 holds the advantage and keeps firing.
+fn firing_world() -> GameWorld {
+    let mut world = GameWorld::new();
+    world.set_advantage_state(AdvantageState::Player1);
+    let firing = PlayerInput {
+        action: true,
+        ..Default::default()
+    };
+    world.apply_command(&GameCommand::Input(PlayerId::Player1, firing.into()));
+    world
+}
+
+#[test]
+fn empty_snapshot_within_budget() {
+    let world = GameWorld::new();
+    assert_eq!(projectile_count(&world), 0);
+
+    let size = encoded_size(&world);
+    assert!(
+        size <= EMPTY_BUDGET,
+        "empty snapshot is {} bytes, budget is {}",
+        size,
+        EMPTY_BUDGET
+    );
+}
+
+#[test]
+fn mid_fight_snapshot_within_budget() {
+    let mut world = firing_world();
+    for _ in 0..10 {
+        world.step();
+    }
+    assert_eq!(projectile_count(&world), 10);
+
+    let size = encoded_size(&world);
+    assert!(
+        size <= MID_FIGHT_BUDGET,
+        "snapshot with 10 projectiles is {} bytes, budget is {}",
+        size,
+        MID_FIGHT_BUDGET
+    );
+}
+
+#[test]
+fn worst_case_snapshot_within_budget() {
+    // sustained fire past the first hazard cannon volley
+    let mut world = firing_world();
+    let grappling = PlayerInput {
+        grapple: true,
+        ..Default::default()
+    };
+    world.apply_command(&GameCommand::Input(PlayerId::Player2, grappling.into()));
+    for _ in 0..610 {
+        world.step();
+    }
+    assert!(projectile_count(&world) >= 12);
+
+    let size = encoded_size(&world);
+    assert!(
+        size <= WORST_CASE_BUDGET,
+        "worst case snapshot is {} bytes, budget is {}",
+        size,
+        WORST_CASE_BUDGET
+    );
+}
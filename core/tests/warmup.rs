@@ -0,0 +1,33 @@
+//! Players warm up in the lobby with practice shots, and the match starts from scratch once
+//! they're ready.
+
+use crystalorb::{fixed_timestepper::Stepper, world::World};
+use platformer_core::game::{GameCommand, GameEvent, GameWorld, PlayerId, PlayerInput};
+
+#[test]
+fn practice_shots_are_fired_in_the_lobby_only() {
+    let mut world = GameWorld::new();
+    world.apply_command(&GameCommand::OpenLobby);
+    let mortar = PlayerInput {
+        mortar: true,
+        ..Default::default()
+    };
+    world.apply_command(&GameCommand::Input(PlayerId::Player1, mortar.into()));
+    for _ in 0..60 {
+        world.step();
+    }
+    let display_state = world.display_state();
+    assert!(display_state.lobby);
+    assert!(display_state
+        .events
+        .iter()
+        .any(|(_, event)| matches!(event, GameEvent::Fired(Some(PlayerId::Player1)))));
+
+    world.apply_command(&GameCommand::StartMatch);
+    world.step();
+    let display_state = world.display_state();
+    assert!(!display_state.lobby);
+    assert!(display_state.projectile_positions.is_empty());
+    assert_eq!(display_state.round, 1);
+    assert_eq!(display_state.scores, [0, 0]);
+}
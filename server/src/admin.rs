@@ -0,0 +1,214 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{ConnectionHandle, NetworkEvent, NetworkResource},
+        crystalorb::server::Server,
+    },
+    game::{GameCommand, GameWorld},
+    lobby::LobbyMessage,
+};
+use std::{
+    collections::HashSet,
+    io::{self, BufRead},
+    net::IpAddr,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+
+#[cfg(feature = "history")]
+use crate::history::History;
+use crate::{spectators::SpectatorDelay, Lobby};
+
+// Admin console on the server's standard input, for dealing with misbehaving connections
+
+const HELP: &str = "Commands:
+  list                 lists the connected clients with their seats
+  kick <client>        drops a client, a seated player forfeits and loses their seat
+  ban <ip | client>    drops every client from the address and refuses it from then on
+  unban <ip>           lets the address connect again
+  bans                 lists the banned addresses
+  reset                sends both players back to the lobby for a new match
+  top [count]          lists the highest rated players, when built with the history feature";
+
+/// Players listed by `top` unless it's given a count.
+#[cfg(feature = "history")]
+const TOP_PLAYERS: u32 = 10;
+
+/// Lines typed on the console, read on a thread of their own.
+pub struct AdminConsole(Mutex<Receiver<String>>);
+
+/// Addresses refused by the server until it restarts.
+#[derive(Default)]
+pub struct Bans(HashSet<IpAddr>);
+
+pub fn setup_admin_console(mut commands: Commands) {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) if sender.send(line).is_ok() => (),
+                _ => return,
+            }
+        }
+    });
+    commands.insert_resource(AdminConsole(Mutex::new(receiver)));
+}
+
+fn address_of(net: &NetworkResource, handle: ConnectionHandle) -> Option<IpAddr> {
+    net.connections
+        .get(&handle)
+        .and_then(|connection| connection.remote_address())
+        .map(|address| address.ip())
+}
+
+/// Drops the connection for good: a seated player's seat is freed, which invalidates its token,
+/// and the match under way goes to their opponent.
+fn expel(
+    handle: ConnectionHandle,
+    lobby: &mut Lobby,
+    spectator_delay: &SpectatorDelay,
+    server: &mut Server<GameWorld>,
+    net: &mut NetworkResource,
+) {
+    net.disconnect(handle);
+    lobby.spectators.remove(&handle);
+    if let Some(player_id) = lobby.seat_of(handle) {
+        lobby.seats[player_id.as_usize()] = None;
+        net.broadcast_message(LobbyMessage::Names(lobby.names()));
+        server.issue_command(
+            GameCommand::Forfeit(player_id),
+            &mut spectator_delay.network(net),
+        );
+    }
+}
+
+pub(crate) fn run_admin_commands(
+    console: Res<AdminConsole>,
+    mut bans: ResMut<Bans>,
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+    #[cfg(feature = "history")] history: Res<History>,
+) {
+    let lines = console.0.lock().unwrap().try_iter().collect::<Vec<_>>();
+    for line in lines {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) => (),
+            (Some("list"), None) => {
+                if net.connections.is_empty() {
+                    println!("No clients connected");
+                }
+                for (handle, connection) in net.connections.iter() {
+                    let seat = match lobby.seat_of(*handle) {
+                        Some(player_id) => format!(
+                            "{} ({})",
+                            player_id,
+                            lobby.names()[player_id.as_usize()]
+                                .as_deref()
+                                .unwrap_or("unnamed")
+                        ),
+                        None => "spectator".to_string(),
+                    };
+                    let address = connection
+                        .remote_address()
+                        .map_or("unknown address".to_string(), |address| address.to_string());
+                    println!("{}: {}, {}", handle, address, seat);
+                }
+            }
+            (Some("kick"), Some(client)) => match client.parse() {
+                Ok(handle) if net.connections.contains_key(&handle) => {
+                    info!("Kicking client {}", handle);
+                    expel(handle, &mut lobby, &spectator_delay, &mut server, &mut net);
+                }
+                _ => println!("No client {}, see list", client),
+            },
+            (Some("ban"), Some(target)) => {
+                let address = target.parse().ok().or_else(|| {
+                    target
+                        .parse()
+                        .ok()
+                        .and_then(|handle| address_of(&net, handle))
+                });
+                match address {
+                    Some(address) => {
+                        info!("Banning {}", address);
+                        bans.0.insert(address);
+                        let banned = net
+                            .connections
+                            .keys()
+                            .copied()
+                            .filter(|handle| address_of(&net, *handle) == Some(address))
+                            .collect::<Vec<_>>();
+                        for handle in banned {
+                            expel(handle, &mut lobby, &spectator_delay, &mut server, &mut net);
+                        }
+                    }
+                    None => println!("{} is neither an address nor a client", target),
+                }
+            }
+            (Some("unban"), Some(address)) => match address.parse() {
+                Ok(address) if bans.0.remove(&address) => info!("Unbanned {}", address),
+                _ => println!("{} isn't banned", address),
+            },
+            (Some("bans"), None) => {
+                if bans.0.is_empty() {
+                    println!("No bans");
+                }
+                for address in bans.0.iter() {
+                    println!("{}", address);
+                }
+            }
+            (Some("reset"), None) => {
+                info!("Resetting the match");
+                for seat in lobby.seats.iter_mut().flatten() {
+                    seat.ready = false;
+                }
+                lobby.started = false;
+                server.issue_command(
+                    GameCommand::OpenLobby,
+                    &mut spectator_delay.network(&mut *net),
+                );
+            }
+            #[cfg(feature = "history")]
+            (Some("top"), count) => match count.map_or(Ok(TOP_PLAYERS), str::parse) {
+                Ok(count) => match history.top(count) {
+                    Ok(ratings) if ratings.is_empty() => println!("No rated players"),
+                    Ok(ratings) => {
+                        for (rank, rating) in ratings.iter().enumerate() {
+                            println!(
+                                "{}. {}: {:.0} after {} matches",
+                                rank + 1,
+                                rating.name,
+                                rating.rating,
+                                rating.matches
+                            );
+                        }
+                    }
+                    Err(error) => println!("Can't read the ratings: {}", error),
+                },
+                Err(_) => println!("{}", HELP),
+            },
+            _ => println!("{}", HELP),
+        }
+    }
+}
+
+pub fn enforce_bans(
+    mut event_reader: EventReader<NetworkEvent>,
+    bans: Res<Bans>,
+    mut net: ResMut<NetworkResource>,
+) {
+    for event in event_reader.iter() {
+        if let NetworkEvent::Connected(handle) = event {
+            if let Some(address) = address_of(&net, *handle).filter(|ip| bans.0.contains(ip)) {
+                info!("Refusing banned {}", address);
+                net.disconnect(*handle);
+            }
+        }
+    }
+}
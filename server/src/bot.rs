@@ -0,0 +1,179 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::NetworkResource, crystalorb::server::Server,
+    },
+    game::{AdvantageState, GameCommand, GameDisplayState, GameWorld, PlayerId, PlayerInput},
+    lobby::LobbyMessage,
+};
+
+use crate::{new_seat_token, spectators::SpectatorDelay, Lobby, Seat, Seating};
+
+// A player left alone in an open lobby gets the server's bot to play against, after a while.
+// The bot plays from the server's display state like a client would from its own: with the
+// advantage it aims the cannon at its opponent, without it it runs for its power pad. It agrees
+// to every rematch, and gives the seat back once its opponent is gone and the match is over.
+
+/// Name the bot is shown with.
+pub const BOT_NAME: &str = "Bot";
+
+/// How close the cannon has to be to the opponent, in pixels, for the bot to fire.
+const AIM_TOLERANCE: f32 = 24.0;
+/// How close the bot has to be to where it's heading, in pixels, to stop there.
+const ARRIVAL_TOLERANCE: f32 = 8.0;
+/// How close the bot gets to its power pad, in pixels, before jumping onto it.
+const PAD_REACH: f32 = 60.0;
+/// How close a projectile gets, in pixels, before the bot jumps to dodge it.
+const DODGE_DISTANCE: f32 = 120.0;
+/// The bot fires in bursts, holding the button this many ticks and releasing it as long.
+const FIRE_BURST_TICKS: u32 = 10;
+
+pub struct BotFill {
+    /// How long a player waits alone before the bot takes the empty seat.
+    wait_seconds: f64,
+    alone_since: Option<f64>,
+    /// Last input the bot sent, the game only takes changes.
+    last_input: Option<PlayerInput>,
+}
+
+impl BotFill {
+    pub fn new(wait_seconds: f64) -> Self {
+        Self {
+            wait_seconds,
+            alone_since: None,
+            last_input: None,
+        }
+    }
+}
+
+/// What the bot holds on the tick of the display state.
+fn bot_input(display_state: &GameDisplayState, bot_id: PlayerId) -> PlayerInput {
+    let (own, opponent, pad, advantaged) = match bot_id {
+        PlayerId::Player1 => (
+            display_state.player1_position,
+            display_state.player2_position,
+            display_state.bottom_power_pad_position,
+            display_state.advantage_state == AdvantageState::Player1,
+        ),
+        PlayerId::Player2 => (
+            display_state.player2_position,
+            display_state.player1_position,
+            display_state.top_power_pad_position,
+            display_state.advantage_state == AdvantageState::Player2,
+        ),
+    };
+    // player 2 plays the world upside down, with left and right swapped
+    let mirror_multiplier = match bot_id {
+        PlayerId::Player1 => 1.0,
+        PlayerId::Player2 => -1.0,
+    };
+
+    let (from, to) = if advantaged {
+        (
+            display_state.cannon_x_position,
+            opponent.translation.vector.x,
+        )
+    } else {
+        (own.translation.vector.x, pad.translation.vector.x)
+    };
+    let heading = (to - from) * mirror_multiplier;
+    let mut input = PlayerInput {
+        left: heading < -ARRIVAL_TOLERANCE,
+        right: heading > ARRIVAL_TOLERANCE,
+        ..Default::default()
+    };
+
+    input.action = if advantaged {
+        (to - from).abs() < AIM_TOLERANCE && (display_state.tick / FIRE_BURST_TICKS) % 2 == 0
+    } else {
+        let near_pad = (to - from).abs() < PAD_REACH;
+        let under_fire = display_state
+            .projectile_positions
+            .values()
+            .any(|projectile| {
+                (projectile.translation.vector - own.translation.vector).norm() < DODGE_DISTANCE
+            });
+        near_pad || under_fire
+    };
+    input
+}
+
+/// Seats the bot next to a player who waited alone long enough, and plays it.
+pub(crate) fn fill_empty_seat(
+    time: Res<Time>,
+    mut bot: ResMut<BotFill>,
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let bot_seat = lobby
+        .seats
+        .iter()
+        .position(|seat| matches!(seat, Some(seat) if seat.bot));
+    let bot_index = match bot_seat {
+        Some(bot_index) => bot_index,
+        None => {
+            let alone = matches!(lobby.seating, Seating::Open)
+                && lobby.seats.iter().filter(|seat| seat.is_none()).count() == 1
+                && lobby
+                    .seats
+                    .iter()
+                    .flatten()
+                    .all(|seat| seat.handle.is_some());
+            if !alone {
+                bot.alone_since = None;
+                return;
+            }
+            let now = time.seconds_since_startup();
+            let alone_since = *bot.alone_since.get_or_insert(now);
+            if now - alone_since < bot.wait_seconds {
+                return;
+            }
+            bot.alone_since = None;
+            bot.last_input = None;
+            let index = lobby.seats.iter().position(Option::is_none).unwrap();
+            lobby.seats[index] = Some(Seat {
+                token: new_seat_token(),
+                handle: None,
+                name: Some(BOT_NAME.to_string()),
+                ready: true,
+                bot: true,
+            });
+            info!("The bot takes the empty seat");
+            net.broadcast_message(LobbyMessage::Names(lobby.names()));
+            return;
+        }
+    };
+    let bot_id = [PlayerId::Player1, PlayerId::Player2][bot_index];
+
+    let display_state = server.display_state().inner().clone();
+    let between_matches = display_state.lobby || display_state.match_result.is_some();
+    let opponent_connected =
+        matches!(&lobby.seats[1 - bot_index], Some(seat) if seat.handle.is_some());
+    if between_matches && !opponent_connected {
+        info!("The bot gives up its seat, its opponent is gone");
+        lobby.seats[bot_index] = None;
+        net.broadcast_message(LobbyMessage::Names(lobby.names()));
+        return;
+    }
+
+    // the bot takes every rematch its opponent asks for, until its own vote shows up
+    let votes = display_state.rematch_votes;
+    if display_state.match_result.is_some() && votes[1 - bot_index] && !votes[bot_index] {
+        server.issue_command(
+            GameCommand::Rematch(bot_id),
+            &mut spectator_delay.network(&mut *net),
+        );
+        return;
+    }
+
+    let input = bot_input(&display_state, bot_id);
+    if bot.last_input != Some(input) {
+        bot.last_input = Some(input);
+        server.issue_command(
+            GameCommand::Input(bot_id, input.into()),
+            &mut spectator_delay.network(&mut *net),
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::ConnectionHandle, crystalorb::server::Server,
+    },
+    game::{AdvantageState, GameDisplayState, GameWorld, MatchResult, PlayerId, RoundResult},
+};
+use serde::Serialize;
+use std::{
+    io::Write,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Match events as JSON lines, one object per event, enough to put the timeline of a match back
+// together from the logs. Every line has the wall clock time, the server's uptime and the event
+// name next to the event's own fields.
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MatchEvent {
+    Connected {
+        client: ConnectionHandle,
+        address: Option<String>,
+    },
+    Disconnected {
+        client: ConnectionHandle,
+    },
+    Seated {
+        client: ConnectionHandle,
+        player: PlayerId,
+        name: Option<String>,
+        /// Set when a player reconnected to their seat.
+        reclaimed: bool,
+    },
+    MatchStarted,
+    PadCaptured {
+        player: PlayerId,
+        round: u8,
+    },
+    RoundEnded {
+        round: u8,
+        /// Unset for draws.
+        winner: Option<PlayerId>,
+        scores: [u8; 2],
+    },
+    MatchEnded {
+        /// Unset for draws.
+        winner: Option<PlayerId>,
+        scores: [u8; 2],
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    /// Seconds since the Unix epoch.
+    time: f64,
+    uptime: f64,
+    #[serde(flatten)]
+    event: &'a MatchEvent,
+}
+
+/// Where the match events go, a file or the standard output.
+pub struct EventLog(Mutex<Box<dyn Write + Send>>);
+
+impl EventLog {
+    pub fn new(output: Box<dyn Write + Send>) -> Self {
+        Self(Mutex::new(output))
+    }
+
+    pub fn write(&self, uptime: f64, event: MatchEvent) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |duration| duration.as_secs_f64());
+        let record = Record {
+            time,
+            uptime,
+            event: &event,
+        };
+        let written = serde_json::to_string(&record)
+            .map_err(|error| error.to_string())
+            .and_then(|line| {
+                let mut output = self.0.lock().unwrap();
+                writeln!(output, "{}", line)
+                    .and_then(|_| output.flush())
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(error) = written {
+            warn!("Can't log {:?}: {}", event, error);
+        }
+    }
+}
+
+/// Logs the events read off the game: the match starting and ending, rounds ending and pads
+/// being captured.
+pub fn log_game_events(
+    time: Res<Time>,
+    mut previous: Local<Option<GameDisplayState>>,
+    server: Res<Server<GameWorld>>,
+    log: Res<EventLog>,
+) {
+    let current = server.display_state().inner().clone();
+    let previous = match previous.replace(current.clone()) {
+        Some(previous) => previous,
+        None => return,
+    };
+    let uptime = time.seconds_since_startup();
+
+    if previous.lobby && !current.lobby {
+        log.write(uptime, MatchEvent::MatchStarted);
+    }
+    if previous.advantage_state == AdvantageState::Neutral {
+        let captured_by = match current.advantage_state {
+            AdvantageState::Neutral => None,
+            AdvantageState::Player1 => Some(PlayerId::Player1),
+            AdvantageState::Player2 => Some(PlayerId::Player2),
+        };
+        if let Some(player) = captured_by {
+            log.write(
+                uptime,
+                MatchEvent::PadCaptured {
+                    player,
+                    round: current.round,
+                },
+            );
+        }
+    }
+    if let (None, Some(round_result)) = (previous.round_result, current.round_result) {
+        log.write(
+            uptime,
+            MatchEvent::RoundEnded {
+                round: current.round,
+                winner: match round_result {
+                    RoundResult::Winner(player) => Some(player),
+                    RoundResult::Draw => None,
+                },
+                scores: current.scores,
+            },
+        );
+    }
+    if let (None, Some(match_result)) = (previous.match_result, current.match_result) {
+        log.write(
+            uptime,
+            MatchEvent::MatchEnded {
+                winner: match match_result {
+                    MatchResult::Winner(player) => Some(player),
+                    MatchResult::Draw => None,
+                },
+                scores: current.scores,
+            },
+        );
+    }
+}
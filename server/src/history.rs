@@ -0,0 +1,385 @@
+//! Results of finished matches, kept in a local SQLite database for building match history, and
+//! Elo ratings of the named players drawn from them. Built with the `history` feature.
+
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::crystalorb::server::Server,
+    game::{GameDisplayState, GameWorld, MatchResult, PlayerId, PlayerStats},
+    MAP,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::Serialize;
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Lobby;
+
+/// Database used unless `--history <file>` picks another.
+pub const DEFAULT_HISTORY_PATH: &str = "history.sqlite";
+/// Rating of a player before their first match.
+pub const INITIAL_RATING: f64 = 1500.0;
+/// Most a rating moves in one match.
+const K_FACTOR: f64 = 32.0;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY,
+    ended_at INTEGER NOT NULL,
+    map TEXT NOT NULL,
+    duration_seconds REAL NOT NULL,
+    -- seat of the winner, null for draws
+    winner INTEGER
+);
+CREATE TABLE IF NOT EXISTS match_players (
+    match_id INTEGER NOT NULL REFERENCES matches (id),
+    seat INTEGER NOT NULL,
+    name TEXT,
+    score INTEGER NOT NULL,
+    shots_fired INTEGER NOT NULL,
+    hits INTEGER NOT NULL,
+    pad_captures INTEGER NOT NULL,
+    deaths INTEGER NOT NULL,
+    distance_traveled REAL NOT NULL,
+    PRIMARY KEY (match_id, seat)
+);
+CREATE INDEX IF NOT EXISTS match_players_name ON match_players (name);
+CREATE TABLE IF NOT EXISTS ratings (
+    name TEXT PRIMARY KEY,
+    rating REAL NOT NULL,
+    matches INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ratings_rating ON ratings (rating);
+";
+
+/// A finished match.
+#[derive(Debug, Clone)]
+pub struct MatchRecord {
+    /// Picked by the database, ignored when recording.
+    pub id: i64,
+    /// Seconds since the Unix epoch.
+    pub ended_at: i64,
+    pub map: String,
+    /// From the start of the match, pauses included.
+    pub duration_seconds: f64,
+    /// Unset for draws.
+    pub winner: Option<PlayerId>,
+    /// By seat.
+    pub players: [PlayerRecord; 2],
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerRecord {
+    pub name: Option<String>,
+    pub score: u8,
+    pub stats: PlayerStats,
+}
+
+/// Matches of a player name, across seats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+/// A named player on the ladder.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rating {
+    pub name: String,
+    pub rating: f64,
+    /// Rated matches played.
+    pub matches: u32,
+}
+
+fn rating_of(transaction: &Transaction, name: &str) -> rusqlite::Result<Rating> {
+    let found = transaction
+        .query_row(
+            "SELECT rating, matches FROM ratings WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (rating, matches) = found.unwrap_or((INITIAL_RATING, 0));
+    Ok(Rating {
+        name: name.to_string(),
+        rating,
+        matches,
+    })
+}
+
+/// Moves both players' ratings by the match's result. Only matches between two different named
+/// players are rated, unnamed ones can't be told apart from match to match.
+fn rate(transaction: &Transaction, record: &MatchRecord) -> rusqlite::Result<()> {
+    let (name1, name2) = match (&record.players[0].name, &record.players[1].name) {
+        (Some(name1), Some(name2)) if name1 != name2 => (name1, name2),
+        _ => return Ok(()),
+    };
+    let (player1, player2) = (
+        rating_of(transaction, name1)?,
+        rating_of(transaction, name2)?,
+    );
+    let expected1 = 1.0 / (1.0 + 10f64.powf((player2.rating - player1.rating) / 400.0));
+    let score1 = match record.winner {
+        Some(PlayerId::Player1) => 1.0,
+        Some(PlayerId::Player2) => 0.0,
+        None => 0.5,
+    };
+    let change = K_FACTOR * (score1 - expected1);
+    for (player, change) in [(player1, change), (player2, -change)].iter() {
+        transaction.execute(
+            "INSERT OR REPLACE INTO ratings (name, rating, matches) VALUES (?1, ?2, ?3)",
+            params![player.name, player.rating + change, player.matches + 1],
+        )?;
+    }
+    Ok(())
+}
+
+fn seat_player(seat: u8) -> PlayerId {
+    if seat == 0 {
+        PlayerId::Player1
+    } else {
+        PlayerId::Player2
+    }
+}
+
+fn player_record(row: &Row) -> rusqlite::Result<PlayerRecord> {
+    Ok(PlayerRecord {
+        name: row.get("name")?,
+        score: row.get("score")?,
+        stats: PlayerStats {
+            shots_fired: row.get("shots_fired")?,
+            hits: row.get("hits")?,
+            pad_captures: row.get("pad_captures")?,
+            deaths: row.get("deaths")?,
+            distance_traveled: row.get::<_, f64>("distance_traveled")? as f32,
+        },
+    })
+}
+
+pub struct History(Mutex<Connection>);
+
+impl History {
+    /// Opens the database, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self(Mutex::new(connection)))
+    }
+
+    /// Returns the id the match got. Rates the players too.
+    pub fn record(&self, record: &MatchRecord) -> rusqlite::Result<i64> {
+        let mut connection = self.0.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO matches (ended_at, map, duration_seconds, winner) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                record.ended_at,
+                record.map,
+                record.duration_seconds,
+                record.winner.map(|player_id| player_id.as_usize() as u8),
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+        for (seat, player) in record.players.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO match_players (match_id, seat, name, score, shots_fired, hits, \
+                 pad_captures, deaths, distance_traveled) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    id,
+                    seat as u8,
+                    player.name,
+                    player.score,
+                    player.stats.shots_fired,
+                    player.stats.hits,
+                    player.stats.pad_captures,
+                    player.stats.deaths,
+                    player.stats.distance_traveled as f64,
+                ],
+            )?;
+        }
+        rate(&transaction, record)?;
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    pub fn find(&self, id: i64) -> rusqlite::Result<Option<MatchRecord>> {
+        let connection = self.0.lock().unwrap();
+        let found = connection
+            .query_row(
+                "SELECT ended_at, map, duration_seconds, winner FROM matches WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get::<_, Option<u8>>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let (ended_at, map, duration_seconds, winner) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let mut statement =
+            connection.prepare("SELECT * FROM match_players WHERE match_id = ?1 ORDER BY seat")?;
+        let mut players = statement
+            .query_map(params![id], player_record)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if players.len() != 2 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        let player2 = players.pop().unwrap();
+        let player1 = players.pop().unwrap();
+        Ok(Some(MatchRecord {
+            id,
+            ended_at,
+            map,
+            duration_seconds,
+            winner: winner.map(seat_player),
+            players: [player1, player2],
+        }))
+    }
+
+    /// Latest matches first.
+    pub fn recent(&self, limit: u32) -> rusqlite::Result<Vec<MatchRecord>> {
+        // looked up one by one, with the connection unlocked
+        let ids = self
+            .0
+            .lock()
+            .unwrap()
+            .prepare("SELECT id FROM matches ORDER BY id DESC LIMIT ?1")?
+            .query_map(params![limit], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        let mut records = vec![];
+        for id in ids {
+            records.extend(self.find(id)?);
+        }
+        Ok(records)
+    }
+
+    pub fn tally(&self, name: &str) -> rusqlite::Result<Tally> {
+        let connection = self.0.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT match_players.seat, matches.winner FROM match_players \
+             JOIN matches ON matches.id = match_players.match_id \
+             WHERE match_players.name = ?1",
+        )?;
+        let results = statement
+            .query_map(params![name], |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, Option<u8>>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut tally = Tally::default();
+        for (seat, winner) in results {
+            match winner {
+                Some(winner) if winner == seat => tally.wins += 1,
+                Some(_) => tally.losses += 1,
+                None => tally.draws += 1,
+            }
+        }
+        Ok(tally)
+    }
+
+    /// Highest rated players first.
+    pub fn top(&self, limit: u32) -> rusqlite::Result<Vec<Rating>> {
+        self.0
+            .lock()
+            .unwrap()
+            .prepare("SELECT name, rating, matches FROM ratings ORDER BY rating DESC LIMIT ?1")?
+            .query_map(params![limit], |row| {
+                Ok(Rating {
+                    name: row.get(0)?,
+                    rating: row.get(1)?,
+                    matches: row.get(2)?,
+                })
+            })?
+            .collect()
+    }
+
+    /// Unset for players who haven't played a rated match.
+    pub fn rating(&self, name: &str) -> rusqlite::Result<Option<Rating>> {
+        self.0
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT rating, matches FROM ratings WHERE name = ?1",
+                params![name],
+                |row| {
+                    Ok(Rating {
+                        name: name.to_string(),
+                        rating: row.get(0)?,
+                        matches: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct MatchTracking {
+    previous: Option<GameDisplayState>,
+    started_at: Option<f64>,
+}
+
+/// Records every match once it has a result.
+pub(crate) fn record_matches(
+    time: Res<Time>,
+    mut tracking: Local<MatchTracking>,
+    server: Res<Server<GameWorld>>,
+    lobby: Res<Lobby>,
+    history: Res<History>,
+) {
+    let now = time.seconds_since_startup();
+    let current = server.display_state().inner().clone();
+    let previous = match tracking.previous.replace(current.clone()) {
+        Some(previous) => previous,
+        None => return,
+    };
+    // set anew by every match, rematches included
+    let start_tick =
+        |state: &GameDisplayState| state.match_start.as_ref().map(|(tick, _, _)| *tick);
+    if start_tick(&current).is_some() && start_tick(&current) != start_tick(&previous) {
+        tracking.started_at = Some(now);
+    }
+    let match_result = match (previous.match_result, current.match_result) {
+        (None, Some(match_result)) => match_result,
+        _ => return,
+    };
+
+    let names = lobby.names();
+    // the bot's matches are kept, without its name they aren't rated
+    let player = |player_id: PlayerId| PlayerRecord {
+        name: names[player_id.as_usize()]
+            .clone()
+            .filter(|_| !matches!(&lobby.seats[player_id.as_usize()], Some(seat) if seat.bot)),
+        score: current.scores[player_id.as_usize()],
+        stats: current.stats[player_id.as_usize()],
+    };
+    let record = MatchRecord {
+        id: 0,
+        ended_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64),
+        map: MAP.name.to_string(),
+        duration_seconds: tracking
+            .started_at
+            .map_or(0.0, |started_at| now - started_at),
+        winner: match match_result {
+            MatchResult::Winner(player_id) => Some(player_id),
+            MatchResult::Draw => None,
+        },
+        players: [player(PlayerId::Player1), player(PlayerId::Player2)],
+    };
+    tracking.started_at = None;
+    match history.record(&record) {
+        Ok(id) => info!("Recorded the match as {}", id),
+        Err(error) => warn!("Can't record the match: {}", error),
+    }
+}
@@ -0,0 +1,1103 @@
+//! The game server, run by its own binary or hosted by a client started with `--host`.
+
+// crystalorb's network resource trait has a generic associated type
+#![allow(incomplete_features)]
+#![feature(generic_associated_types)]
+
+mod admin;
+mod bot;
+mod events;
+#[cfg(feature = "history")]
+pub mod history;
+mod relay;
+mod reload;
+mod replay;
+mod spectators;
+#[cfg(feature = "status")]
+mod status;
+mod tournament;
+
+use clap::{App, Arg, ArgMatches};
+use events::{EventLog, MatchEvent};
+#[cfg(feature = "encryption")]
+use platformer_shared::encryption;
+use platformer_shared::{
+    bevy::{
+        self,
+        app::{AppExit, Events, ManualEventReader},
+        prelude::*,
+    },
+    conditions::{self, NetworkConditions},
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{self, ConnectionHandle, NetworkResource, NetworkingPlugin},
+        crystalorb::{server::Server, Config},
+    },
+    discovery::{Beacon, BEACON_PERIOD_SECONDS, DISCOVERY_PORT},
+    game::{GameCommand, GameTuning, GameWorld, MatchSettings, PlayerId, RuleVariants},
+    lobby::{self, sanitize_name, LobbyMessage},
+    net::{self, LastHeard},
+    protocol::{self, PROTOCOL_VERSION},
+    settings::{CrystalOrbSettings, GameSettings, NetworkSettings, Settings, SETTINGS_PATH},
+    MAP,
+};
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Name announced in the LAN beacon.
+struct ServerName(String);
+
+/// Message of the day and rules, as sent to the clients, unset while both are empty.
+struct Motd(Option<LobbyMessage>);
+
+/// Socket the LAN beacon is broadcast from, if it could be opened.
+struct BeaconSocket(Option<UdpSocket>);
+
+/// Addresses the server listens on.
+struct ServerAddress {
+    socket: SocketAddr,
+    /// Signaling endpoint of WebRTC sessions, used when built with the `webrtc` feature.
+    webrtc: SocketAddr,
+    /// Signaling endpoint as seen by the browsers, when behind NAT.
+    public_webrtc: Option<SocketAddr>,
+    /// Relay the clients connect to instead, when the server can't be reached directly.
+    relay: Option<SocketAddr>,
+}
+
+/// Config the crystalorb server runs with, whose timing the clients adopt.
+struct ServerConfig(Config);
+
+/// Runs the app `tick_rate` times a second while clients are connected and `idle_tick_rate` times
+/// while nobody is, sleeping in between.
+fn throttled_runner(tick_rate: f64, idle_tick_rate: f64) -> impl Fn(App) {
+    let period = Duration::from_secs_f64(1.0 / tick_rate);
+    let idle_period = Duration::from_secs_f64(1.0 / idle_tick_rate.min(tick_rate));
+    move |mut app: App| {
+        let mut exits = ManualEventReader::<AppExit>::default();
+        loop {
+            let started_at = Instant::now();
+            app.update();
+            if let Some(events) = app.world.get_resource::<Events<AppExit>>() {
+                if exits.iter(events).next().is_some() {
+                    return;
+                }
+            }
+            let idle = app
+                .world
+                .get_resource::<NetworkResource>()
+                .map_or(true, |net| net.connections.is_empty());
+            let budget = if idle { idle_period } else { period };
+            if let Some(rest) = budget.checked_sub(started_at.elapsed()) {
+                thread::sleep(rest);
+            }
+        }
+    }
+}
+
+/// Parsed value of an option, exiting with a usage error if it's malformed.
+fn value_of<T: FromStr>(matches: &ArgMatches, name: &str) -> Option<T> {
+    matches.value_of(name).map(|value| {
+        value.parse().unwrap_or_else(|_| {
+            clap::Error::value_validation_auto(format!("invalid value for --{}: {}", name, value))
+                .exit()
+        })
+    })
+}
+
+/// Time the reason a client was turned away for has to reach it before it's dropped.
+const REJECTED_GRACE_SECONDS: f64 = 1.0;
+
+/// Port of the JSON status endpoint, built with the `status` feature.
+#[cfg(feature = "status")]
+const STATUS_PORT: u16 = 1214;
+
+fn options<'a, 'b>() -> App<'a, 'b> {
+    let option = |name: &'a str, value_name: &'a str, help: &'a str| {
+        Arg::with_name(name)
+            .long(name)
+            .value_name(value_name)
+            .takes_value(true)
+            .help(help)
+    };
+    App::new("platformer-server")
+        .arg(option(
+            "config",
+            "FILE",
+            "Settings file, created with the defaults if missing",
+        ))
+        .arg(option(
+            "bind",
+            "IP",
+            "Address to listen on, the first local address by default, :: for IPv6 and IPv4",
+        ))
+        .arg(option("port", "PORT", "Port to listen on"))
+        .arg(option(
+            "tick-rate",
+            "HZ",
+            "Updates per second while clients are connected",
+        ))
+        .arg(option(
+            "status-port",
+            "PORT",
+            "Port of the JSON status endpoint, when built with the status feature",
+        ))
+        .arg(
+            Arg::with_name("relay")
+                .long("relay")
+                .help("Only relays matches hosted with --via-relay, on the bound address and port"),
+        )
+        .arg(option(
+            "event-log",
+            "FILE",
+            "Appends the match events to this file as JSON lines, instead of the standard output",
+        ))
+        .arg(option(
+            "tournament",
+            "FILE",
+            "Runs a tournament between the players named in this file, one per line",
+        ))
+        .arg(option(
+            "replays",
+            "DIR",
+            "Saves a replay of every match to this directory",
+        ))
+        .arg(option(
+            "bot-fill",
+            "SECONDS",
+            "Seats the bot next to a player left waiting alone this long",
+        ))
+        .arg(option(
+            "spectator-delay",
+            "SECONDS",
+            "How far behind the players spectators see the game",
+        ))
+        .arg(option(
+            "history",
+            "FILE",
+            "Database the match results go to, when built with the history feature",
+        ))
+        .arg(option(
+            "via-relay",
+            "HOST:PORT",
+            "Hosts the match through a relay, for when clients can't reach this server directly",
+        ))
+        .arg(option(
+            "name",
+            "NAME",
+            "Name clients on the LAN see the server under",
+        ))
+        .arg(option(
+            "webrtc-port",
+            "PORT",
+            "Port browser clients send their WebRTC session offers to",
+        ))
+        .arg(option(
+            "public-webrtc-address",
+            "IP:PORT",
+            "Address browsers reach the WebRTC port on, when it differs from the bound one, \
+             with IPv6 addresses in brackets",
+        ))
+        .arg(option(
+            "score-target",
+            "POINTS",
+            "Points needed to win the match, 0 for an endless match",
+        ))
+        .arg(option(
+            "round-limit",
+            "ROUNDS",
+            "Ends the match after this many rounds",
+        ))
+        .arg(option(
+            "round-time",
+            "SECONDS",
+            "Ends rounds in a draw after this long",
+        ))
+        .arg(option(
+            "disconnect-grace",
+            "SECONDS",
+            "Pauses the game this long for a disconnected player before they forfeit",
+        ))
+        .arg(
+            Arg::with_name("cannon-spread")
+                .long("cannon-spread")
+                .help("Deflects every cannon shot by a small pseudo-random angle"),
+        )
+        .arg(
+            Arg::with_name("crowd-events")
+                .long("crowd-events")
+                .help("Twists the game for a few seconds at fixed times"),
+        )
+        .arg(option(
+            "latency",
+            "MS",
+            "Simulates this much latency each way, for testing",
+        ))
+        .arg(option(
+            "jitter",
+            "MS",
+            "Simulates up to this much more latency per packet, reordering them",
+        ))
+        .arg(option(
+            "loss",
+            "PERCENT",
+            "Simulates losing this share of the packets each way",
+        ))
+        .arg(option("move-speed", "SPEED", "Player movement speed"))
+        .arg(option("jump-velocity", "VELOCITY", "Player jump velocity"))
+        .arg(option("gravity-scale", "SCALE", "Gravity multiplier"))
+        .arg(option("cannon-speed", "SPEED", "Cannon aiming speed"))
+        .arg(option(
+            "projectile-speed",
+            "SPEED",
+            "Cannon projectile speed",
+        ))
+        .arg(option(
+            "max-projectiles",
+            "COUNT",
+            "Most cannon projectiles in flight at once",
+        ))
+        .arg(option(
+            "lag-compensation-latency",
+            "SECONDS",
+            "How far back clients' commands are compensated, adopted by the clients",
+        ))
+        .arg(option(
+            "blend-latency",
+            "SECONDS",
+            "Blending period of the server's own display, unused without one",
+        ))
+        .arg(option(
+            "snapshot-send-period",
+            "SECONDS",
+            "Time between snapshots, adopted by the clients",
+        ))
+        .arg(option(
+            "clock-sync-request-period",
+            "SECONDS",
+            "Time between clock sync requests",
+        ))
+        .arg(option(
+            "max-tolerable-clock-deviation",
+            "SECONDS",
+            "Clock deviation tolerated before resyncing",
+        ))
+        .arg(option(
+            "tweening-method",
+            "METHOD",
+            "most_recently_passed, nearest or interpolated",
+        ))
+}
+
+/// Runs the server with the given command line, until the process ends. A `hosted` server runs
+/// inside a client, which already set up logging.
+pub fn run(args: Vec<String>, hosted: bool) {
+    println!("Server starting");
+    let matches = options().get_matches_from(args);
+    let settings_path = matches.value_of("config").unwrap_or(SETTINGS_PATH);
+    let settings = Settings::load(settings_path);
+
+    // IPv6 addresses are also taken in brackets, like in socket addresses
+    let bind = matches.value_of("bind").map(|value| {
+        value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .unwrap_or(value)
+            .parse::<IpAddr>()
+            .unwrap_or_else(|_| {
+                clap::Error::value_validation_auto(format!("invalid value for --bind: {}", value))
+                    .exit()
+            })
+    });
+    let ip_address = bind.or(settings.network.bind).unwrap_or_else(|| {
+        bevy_networking_turbulence::find_my_ip_address().expect("can't find ip address")
+    });
+    let port = value_of(&matches, "port").unwrap_or(settings.network.port);
+    let tick_rate = value_of(&matches, "tick-rate").unwrap_or(settings.network.tick_rate);
+    if !(tick_rate > 0.0 && settings.network.idle_tick_rate > 0.0) {
+        clap::Error::value_validation_auto("tick rates have to be positive".to_string()).exit();
+    }
+    let spectator_delay = value_of(&matches, "spectator-delay").unwrap_or(0.0);
+    if !(0.0..=spectators::MAX_SPECTATOR_DELAY_SECONDS).contains(&spectator_delay) {
+        clap::Error::value_validation_auto(format!(
+            "the spectator delay has to be 0 to {} seconds",
+            spectators::MAX_SPECTATOR_DELAY_SECONDS
+        ))
+        .exit();
+    }
+    let webrtc_port = value_of(&matches, "webrtc-port").unwrap_or(settings.network.webrtc_port);
+    if matches.is_present("relay") {
+        if let Err(error) = relay::run_relay(SocketAddr::new(ip_address, port)) {
+            eprintln!("Can't run the relay: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let relay = matches.value_of("via-relay").map(|value| {
+        value
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .unwrap_or_else(|| {
+                clap::Error::value_validation_auto(format!(
+                    "invalid value for --via-relay: {}",
+                    value
+                ))
+                .exit()
+            })
+    });
+    let server_address = ServerAddress {
+        socket: SocketAddr::new(ip_address, port),
+        webrtc: SocketAddr::new(ip_address, webrtc_port),
+        public_webrtc: value_of(&matches, "public-webrtc-address"),
+        relay,
+    };
+    let conditions = NetworkConditions {
+        latency_ms: value_of(&matches, "latency").unwrap_or(0),
+        jitter_ms: value_of(&matches, "jitter").unwrap_or(0),
+        loss: value_of::<f32>(&matches, "loss").unwrap_or(0.0) / 100.0,
+    };
+
+    let rules = RuleVariants {
+        cannon_spread: matches.is_present("cannon-spread"),
+        crowd_events: matches.is_present("crowd-events"),
+    };
+    // the command line overrides the settings file, reloaded or not
+    let game_options = GameSettings {
+        score_target: value_of(&matches, "score-target"),
+        round_limit: value_of(&matches, "round-limit"),
+        round_time: value_of(&matches, "round-time"),
+        disconnect_grace: value_of(&matches, "disconnect-grace"),
+        move_speed: value_of(&matches, "move-speed"),
+        jump_velocity: value_of(&matches, "jump-velocity"),
+        gravity_scale: value_of(&matches, "gravity-scale"),
+        cannon_speed: value_of(&matches, "cannon-speed"),
+        projectile_speed: value_of(&matches, "projectile-speed"),
+        max_projectiles: value_of(&matches, "max-projectiles"),
+    };
+    let game_settings = game_options.or(&settings.game);
+    let match_settings = game_settings.match_settings();
+    let tuning = game_settings.tuning();
+    // the environment overrides the settings file, the command line both
+    let mut crystalorb = settings.crystalorb.clone();
+    let overridden = crystalorb
+        .override_with(CrystalOrbSettings::env_var)
+        .and_then(|()| {
+            crystalorb.override_with(|option| matches.value_of(option).map(str::to_string))
+        });
+    if let Err(error) = overridden {
+        clap::Error::value_validation_auto(error).exit();
+    }
+    let crystalorb_config = crystalorb.config();
+    let event_output: Box<dyn Write + Send> = match matches.value_of("event-log") {
+        Some(path) => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap_or_else(|error| panic!("can't open the event log {}: {}", path, error)),
+        ),
+        None => Box::new(io::stdout()),
+    };
+
+    let mut app = App::build();
+    if !hosted {
+        app.add_plugin(bevy::log::LogPlugin);
+    }
+    app.insert_resource(net::command_channel(&settings.channel))
+        .insert_resource(net::snapshot_channel())
+        .insert_resource(net::clock_sync_channel())
+        .add_plugins(MinimalPlugins)
+        .set_runner(throttled_runner(tick_rate, settings.network.idle_tick_rate))
+        // what the crystalorb plugin sets up, only with the server updated through the
+        // spectator delay
+        .add_plugin(NetworkingPlugin::default())
+        .insert_resource(Server::<GameWorld>::new(crystalorb_config.clone(), 0.0))
+        .insert_resource(spectators::SpectatorDelay::new(
+            spectator_delay,
+            crystalorb_config.timestep_seconds,
+        ))
+        .add_system(spectators::update_server.system())
+        .insert_resource(ServerConfig(crystalorb_config))
+        .insert_resource(EventLog::new(event_output))
+        .insert_resource(server_address)
+        .insert_resource(conditions)
+        .insert_resource(settings.network.clone())
+        .insert_resource(ServerName(
+            matches
+                .value_of("name")
+                .map_or(settings.network.server_name.clone(), str::to_string),
+        ))
+        .insert_resource(Motd(lobby::motd_message(
+            &settings.network.motd,
+            &settings.network.rules,
+        )))
+        .add_system(send_motd.system())
+        .insert_resource(rules)
+        .insert_resource(tuning)
+        .insert_resource(match_settings)
+        .insert_resource(reload::SettingsWatch::new(
+            settings_path,
+            game_options,
+            matches.value_of("name").map(str::to_string),
+        ))
+        .add_system(reload::reload_settings.system())
+        .add_startup_system(server_setup.system())
+        .add_startup_system(apply_rules.system())
+        .add_startup_system_to_stage(StartupStage::PostStartup, lobby::setup_channels.system())
+        .init_resource::<Lobby>()
+        .add_system(run_lobby.system())
+        .add_system(drop_idle_clients.system())
+        .add_system(free_abandoned_seats.system())
+        .init_resource::<admin::Bans>()
+        .add_startup_system(admin::setup_admin_console.system())
+        .add_system(admin::run_admin_commands.system())
+        .add_system(admin::enforce_bans.system())
+        .add_startup_system(setup_beacon.system())
+        .add_system(broadcast_beacon.system())
+        .add_system(handle_events.system())
+        .add_system(events::log_game_events.system());
+
+    if let Some(path) = matches.value_of("tournament") {
+        let tournament = tournament::Tournament::load(path)
+            .unwrap_or_else(|error| panic!("can't run the tournament in {}: {}", path, error));
+        app.insert_resource(tournament)
+            .add_system(tournament::run_tournament.system());
+    }
+
+    if let Some(wait_seconds) = value_of(&matches, "bot-fill") {
+        app.insert_resource(bot::BotFill::new(wait_seconds))
+            .add_system(bot::fill_empty_seat.system());
+    }
+
+    if let Some(directory) = matches.value_of("replays") {
+        app.insert_resource(replay::ReplayRecorder::new(directory))
+            .add_system(replay::record_replays.system());
+    }
+
+    #[cfg(feature = "status")]
+    app.insert_resource(status::StatusAddress(SocketAddr::new(
+        ip_address,
+        value_of(&matches, "status-port").unwrap_or(STATUS_PORT),
+    )))
+    .add_startup_system(status::setup_status_endpoint.system())
+    .add_system(status::update_status.system());
+
+    #[cfg(feature = "history")]
+    {
+        let path = matches
+            .value_of("history")
+            .unwrap_or(history::DEFAULT_HISTORY_PATH);
+        let history = history::History::open(path)
+            .unwrap_or_else(|error| panic!("can't open the match history {}: {}", path, error));
+        app.insert_resource(history)
+            .add_system(history::record_matches.system());
+    }
+
+    app.run();
+}
+
+fn server_setup(
+    address: Res<ServerAddress>,
+    conditions: Res<NetworkConditions>,
+    mut net: ResMut<NetworkResource>,
+) {
+    info!("Starting server on address {}", address.socket);
+    if address.socket.ip() == IpAddr::from(Ipv6Addr::UNSPECIFIED) {
+        info!("Accepting IPv4 clients too, unless the system keeps IPv6 sockets IPv6-only");
+    }
+    if cfg!(feature = "webrtc") {
+        info!("Accepting WebRTC sessions on {}", address.webrtc);
+    }
+    // the proxy takes the address, the server listens behind it
+    let socket = if conditions.is_simulated() {
+        info!("Simulating {:?}", *conditions);
+        let inner = conditions::free_local_address().expect("can't find a free local port");
+        conditions::spawn_proxy(address.socket, inner, *conditions)
+            .expect("can't start the network conditions proxy");
+        inner
+    } else {
+        address.socket
+    };
+    // and the encryption tunnel behind the proxy
+    #[cfg(feature = "encryption")]
+    let socket = {
+        info!("Encrypting the traffic of every client");
+        let inner = conditions::free_local_address().expect("can't find a free local port");
+        encryption::spawn_server_tunnel(socket, inner).expect("can't start the encryption tunnel");
+        inner
+    };
+    net.listen(socket, Some(address.webrtc), address.public_webrtc);
+    // relayed clients go through the proxy as well
+    if let Some(relay) = address.relay {
+        info!("Hosting through the relay at {}", relay);
+        relay::spawn_host_agent(relay, address.socket).expect("can't connect to the relay");
+    }
+}
+
+// the rules reach the clients connected by then as commands, and later ones as they join
+fn apply_rules(
+    rules: Res<RuleVariants>,
+    tuning: Res<GameTuning>,
+    match_settings: Res<MatchSettings>,
+    spectator_delay: Res<spectators::SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    info!("Rules: {:?}", *rules);
+    info!("Tuning: {:?}", *tuning);
+    info!("Match settings: {:?}", *match_settings);
+    let mut net = spectator_delay.network(&mut *net);
+    server.issue_command(GameCommand::SetRules(*rules), &mut net);
+    server.issue_command(GameCommand::SetTuning(*tuning), &mut net);
+    server.issue_command(GameCommand::SetMatchSettings(*match_settings), &mut net);
+    // nothing is simulated until both players are ready
+    server.issue_command(GameCommand::OpenLobby, &mut net);
+}
+
+/// A player's seat, kept when their connection drops so they can reconnect to it.
+struct Seat {
+    token: u64,
+    handle: Option<ConnectionHandle>,
+    name: Option<String>,
+    ready: bool,
+    /// Taken by the bot, which plays without a connection.
+    bot: bool,
+}
+
+/// Who may take the seats.
+enum Seating {
+    /// The first to come.
+    Open,
+    /// Only the players of these names, the first one as player 1.
+    Reserved([String; 2]),
+    /// Nobody, everyone spectates.
+    Closed,
+}
+
+impl Default for Seating {
+    fn default() -> Self {
+        Seating::Open
+    }
+}
+
+#[derive(Default)]
+struct Lobby {
+    seats: [Option<Seat>; 2],
+    started: bool,
+    seating: Seating,
+    /// Connected clients without a seat, with their names.
+    spectators: HashMap<ConnectionHandle, Option<String>>,
+    /// Clients turned away by the handshake, with when to drop them. Until then their reason
+    /// gets through, while crystalorb neither sends them snapshots nor takes their commands.
+    rejected: HashMap<ConnectionHandle, f64>,
+}
+
+impl Lobby {
+    fn seat_of(&self, handle: ConnectionHandle) -> Option<PlayerId> {
+        [PlayerId::Player1, PlayerId::Player2]
+            .iter()
+            .copied()
+            .find(|player_id| {
+                matches!(&self.seats[player_id.as_usize()], Some(seat) if seat.handle == Some(handle))
+            })
+    }
+
+    /// The seat the token belongs to if its player is away, otherwise the first free one the
+    /// seating lets the named player have. Also tells if the seat was reclaimed. A connection
+    /// already seated keeps its seat, unless it reclaims the other one, which releases the seat
+    /// it had: one connection never plays both players.
+    fn take_seat(
+        &mut self,
+        handle: ConnectionHandle,
+        token: Option<u64>,
+        name: Option<&str>,
+    ) -> Option<(PlayerId, bool)> {
+        let reclaimed = token.and_then(|token| {
+            self.seats.iter().position(
+                |seat| matches!(seat, Some(seat) if seat.token == token && seat.handle.is_none()),
+            )
+        });
+        if reclaimed.is_some() {
+            self.release(handle);
+        } else if let Some(player_id) = self.seat_of(handle) {
+            return Some((player_id, false));
+        }
+        let index = reclaimed.or_else(|| match &self.seating {
+            Seating::Open => self.seats.iter().position(Option::is_none),
+            Seating::Reserved(names) => names
+                .iter()
+                .position(|reserved| Some(reserved.as_str()) == name)
+                .filter(|index| self.seats[*index].is_none()),
+            Seating::Closed => None,
+        })?;
+        let seat = self.seats[index].get_or_insert_with(|| Seat {
+            token: new_seat_token(),
+            handle: None,
+            name: None,
+            ready: false,
+            bot: false,
+        });
+        seat.handle = Some(handle);
+        self.spectators.remove(&handle);
+        Some((
+            [PlayerId::Player1, PlayerId::Player2][index],
+            reclaimed.is_some(),
+        ))
+    }
+
+    /// Frees the connection's seat for its player to reconnect to, if it had one.
+    fn release(&mut self, handle: ConnectionHandle) -> Option<PlayerId> {
+        let player_id = self.seat_of(handle)?;
+        info!("{} left, their seat is kept for them", player_id);
+        if let Some(seat) = &mut self.seats[player_id.as_usize()] {
+            seat.handle = None;
+        }
+        Some(player_id)
+    }
+
+    fn names(&self) -> [Option<String>; 2] {
+        let name = |seat: &Option<Seat>| seat.as_ref().and_then(|seat| seat.name.clone());
+        [name(&self.seats[0]), name(&self.seats[1])]
+    }
+}
+
+fn new_seat_token() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+        time::SystemTime,
+    };
+
+    // randomly keyed by the standard library, so tokens can't be guessed from one another
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(time) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(time.as_nanos());
+    }
+    hasher.finish()
+}
+
+/// Why a client can't play on this server, if it can't.
+fn handshake_mismatch(protocol_version: u32, content_hash: u64) -> Option<String> {
+    if protocol_version != PROTOCOL_VERSION {
+        Some(format!(
+            "the server speaks protocol version {}, the client {}",
+            PROTOCOL_VERSION, protocol_version
+        ))
+    } else if content_hash != protocol::content_hash() {
+        Some("the client's game data differs from the server's, update the game".to_string())
+    } else {
+        None
+    }
+}
+
+/// Drops the connection, keeping a seated player's seat like any other dropped connection.
+fn drop_client(
+    handle: ConnectionHandle,
+    lobby: &mut Lobby,
+    spectator_delay: &spectators::SpectatorDelay,
+    server: &mut Server<GameWorld>,
+    net: &mut NetworkResource,
+) {
+    net.disconnect(handle);
+    if let Some(player_id) = lobby.release(handle) {
+        server.issue_command(
+            GameCommand::PlayerLeft(player_id),
+            &mut spectator_delay.network(net),
+        );
+    }
+}
+
+/// Drops the clients that went silent, whose keepalive pings stopped coming.
+fn drop_idle_clients(
+    time: Res<Time>,
+    network_settings: Res<NetworkSettings>,
+    mut last_heard: Local<LastHeard>,
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<spectators::SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let silent = last_heard.silent(
+        &net,
+        time.seconds_since_startup(),
+        network_settings.idle_timeout_seconds,
+    );
+    for handle in silent {
+        info!("Client {} stopped responding, dropping it", handle);
+        drop_client(handle, &mut lobby, &spectator_delay, &mut server, &mut net);
+    }
+}
+
+/// Frees the seats of the players still gone once the match is over, forfeited or not, so that
+/// newcomers can take them and play the rematch.
+fn free_abandoned_seats(
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<spectators::SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    if server.display_state().inner().match_result.is_none() {
+        return;
+    }
+    let mut freed = vec![];
+    for (seat, player_id) in lobby
+        .seats
+        .iter_mut()
+        .zip([PlayerId::Player1, PlayerId::Player2].iter())
+    {
+        if matches!(seat, Some(taken) if taken.handle.is_none() && !taken.bot) {
+            info!("{} didn't come back, their seat is free", player_id);
+            *seat = None;
+            freed.push(*player_id);
+        }
+    }
+    if freed.is_empty() {
+        return;
+    }
+    net.broadcast_message(LobbyMessage::Names(lobby.names()));
+    // whoever takes the seat isn't kept waiting for
+    for player_id in freed {
+        server.issue_command(
+            GameCommand::PlayerReturned(player_id),
+            &mut spectator_delay.network(&mut *net),
+        );
+    }
+}
+
+/// Seats the players, collects their names and starts the match once both are ready.
+#[allow(clippy::too_many_arguments)]
+fn run_lobby(
+    time: Res<Time>,
+    config: Res<ServerConfig>,
+    event_log: Res<EventLog>,
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<spectators::SpectatorDelay>,
+    mut event_reader: EventReader<bevy_networking_turbulence::NetworkEvent>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let mut names_changed = false;
+    let mut game_commands = vec![];
+    for event in event_reader.iter() {
+        match event {
+            // newcomers need the names of those already in
+            bevy_networking_turbulence::NetworkEvent::Connected(_) => names_changed = true,
+            // closed and timed out connections alike, the game pauses until the player is back
+            bevy_networking_turbulence::NetworkEvent::Disconnected(handle) => {
+                lobby.spectators.remove(handle);
+                lobby.rejected.remove(handle);
+                if let Some(player_id) = lobby.release(*handle) {
+                    game_commands.push(GameCommand::PlayerLeft(player_id));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let now = time.seconds_since_startup();
+    let due = lobby
+        .rejected
+        .iter()
+        .filter(|(_, drop_at)| **drop_at <= now)
+        .map(|(handle, _)| *handle)
+        .collect::<Vec<_>>();
+    for handle in due {
+        lobby.rejected.remove(&handle);
+        net.disconnect(handle);
+    }
+
+    let match_config = server.display_state().inner().match_config;
+    let mut replies = vec![];
+    for (handle, connection) in net.connections.iter_mut() {
+        let stats = connection.stats();
+        let channels = connection.channels().unwrap();
+        while let Some(message) = channels.recv::<LobbyMessage>() {
+            // turned away, it only waits for the reason to get through
+            if lobby.rejected.contains_key(handle) {
+                continue;
+            }
+            match message {
+                LobbyMessage::Hello {
+                    protocol_version,
+                    content_hash,
+                    name,
+                    seat_token,
+                } => {
+                    if let Some(reason) = handshake_mismatch(protocol_version, content_hash) {
+                        info!("Rejecting client {}: {}", handle, reason);
+                        replies.push((*handle, LobbyMessage::Rejected { reason }));
+                        lobby.rejected.insert(*handle, now + REJECTED_GRACE_SECONDS);
+                        continue;
+                    }
+                    replies.push((
+                        *handle,
+                        LobbyMessage::Timing {
+                            lag_compensation_latency: config.0.lag_compensation_latency,
+                            snapshot_send_period: config.0.snapshot_send_period,
+                        },
+                    ));
+                    replies.push((*handle, LobbyMessage::MatchConfig(match_config)));
+                    let name = sanitize_name(&name);
+                    let held = lobby.seat_of(*handle);
+                    match lobby.take_seat(*handle, seat_token, name.as_deref()) {
+                        Some((player_id, reclaimed)) => {
+                            // the seat it gave up for the reclaimed one waits for its player
+                            if let Some(held) = held.filter(|held| *held != player_id) {
+                                game_commands.push(GameCommand::PlayerLeft(held));
+                            }
+                            let seat = lobby.seats[player_id.as_usize()].as_mut().unwrap();
+                            seat.name = name;
+                            info!("Client {} plays {} as {:?}", handle, player_id, seat.name);
+                            replies.push((
+                                *handle,
+                                LobbyMessage::Welcome {
+                                    player_id,
+                                    seat_token: seat.token,
+                                },
+                            ));
+                            event_log.write(
+                                time.seconds_since_startup(),
+                                MatchEvent::Seated {
+                                    client: *handle,
+                                    player: player_id,
+                                    name: seat.name.clone(),
+                                    reclaimed,
+                                },
+                            );
+                            names_changed = true;
+                            if reclaimed {
+                                game_commands.push(GameCommand::PlayerReturned(player_id));
+                            }
+                        }
+                        None => {
+                            info!("Client {} joined as a spectator", handle);
+                            lobby.spectators.insert(*handle, name);
+                            replies.push((*handle, LobbyMessage::Spectate));
+                        }
+                    }
+                }
+                LobbyMessage::Ready => {
+                    if let Some(player_id) = lobby.seat_of(*handle) {
+                        let seat = lobby.seats[player_id.as_usize()].as_mut().unwrap();
+                        if !seat.ready {
+                            info!("{} is ready", player_id);
+                            seat.ready = true;
+                        }
+                    }
+                }
+                LobbyMessage::Ping { sent_at } => replies.push((
+                    *handle,
+                    LobbyMessage::Pong {
+                        sent_at,
+                        packets_received: stats.packets_rx as u64,
+                        packets_sent: stats.packets_tx as u64,
+                    },
+                )),
+                LobbyMessage::Welcome { .. }
+                | LobbyMessage::Spectate
+                | LobbyMessage::Timing { .. }
+                | LobbyMessage::MatchConfig(_)
+                | LobbyMessage::Rejected { .. }
+                | LobbyMessage::Names(_)
+                | LobbyMessage::Bracket(_)
+                | LobbyMessage::Motd { .. }
+                | LobbyMessage::Pong { .. } => {
+                    warn!("Client {} sent a server message", handle)
+                }
+            }
+        }
+    }
+
+    for (handle, message) in replies {
+        if let Err(error) = net.send_message(handle, message) {
+            error!("Can't reply to client {}: {:?}", handle, error);
+        }
+    }
+    if names_changed {
+        net.broadcast_message(LobbyMessage::Names(lobby.names()));
+    }
+    for command in game_commands {
+        server.issue_command(command, &mut spectator_delay.network(&mut *net));
+    }
+
+    let all_ready = lobby
+        .seats
+        .iter()
+        .all(|seat| matches!(seat, Some(seat) if seat.ready));
+    if !lobby.started && all_ready {
+        info!("Both players are ready, starting the match");
+        server.issue_command(
+            GameCommand::StartMatch,
+            &mut spectator_delay.network(&mut *net),
+        );
+        lobby.started = true;
+    }
+}
+
+fn setup_beacon(mut commands: Commands) {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).and_then(|socket| {
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    });
+    if let Err(error) = &socket {
+        warn!("Can't open the LAN beacon socket: {}", error);
+    }
+    commands.insert_resource(BeaconSocket(socket.ok()));
+}
+
+/// Lets clients on the LAN find the server without typing its address.
+fn broadcast_beacon(
+    time: Res<Time>,
+    mut next_beacon_at: Local<f64>,
+    socket: Res<BeaconSocket>,
+    address: Res<ServerAddress>,
+    name: Res<ServerName>,
+    lobby: Res<Lobby>,
+) {
+    let now = time.seconds_since_startup();
+    let socket = match &socket.0 {
+        Some(socket) if now >= *next_beacon_at => socket,
+        _ => return,
+    };
+    *next_beacon_at = now + BEACON_PERIOD_SECONDS;
+
+    let beacon = Beacon {
+        name: name.0.clone(),
+        port: address.socket.port(),
+        players: lobby
+            .seats
+            .iter()
+            .filter(|seat| matches!(seat, Some(seat) if seat.handle.is_some()))
+            .count() as u8,
+        map: MAP.name.to_string(),
+    };
+    if let Err(error) = socket.send_to(&beacon.encode(), (Ipv4Addr::BROADCAST, DISCOVERY_PORT)) {
+        debug!("Can't broadcast the LAN beacon: {}", error);
+    }
+}
+
+/// Sends the message of the day to the clients as they connect, and to everyone when it changes.
+fn send_motd(
+    motd: Res<Motd>,
+    mut events: EventReader<bevy_networking_turbulence::NetworkEvent>,
+    mut net: ResMut<NetworkResource>,
+) {
+    if motd.is_changed() {
+        // an empty one takes the old one off the clients' screens
+        net.broadcast_message(motd.0.clone().unwrap_or(LobbyMessage::Motd {
+            message: String::new(),
+            rules: String::new(),
+        }));
+    }
+    let message = match &motd.0 {
+        Some(message) => message,
+        None => return,
+    };
+    for event in events.iter() {
+        if let bevy_networking_turbulence::NetworkEvent::Connected(handle) = event {
+            if let Err(error) = net.send_message(*handle, message.clone()) {
+                error!(
+                    "Can't send the message of the day to client {}: {:?}",
+                    handle, error
+                );
+            }
+        }
+    }
+}
+
+fn handle_events(
+    time: Res<Time>,
+    mut event_reader: EventReader<bevy_networking_turbulence::NetworkEvent>,
+    net: Res<NetworkResource>,
+    event_log: Res<EventLog>,
+) {
+    for event in event_reader.iter() {
+        debug!("Got event: {:?}", event);
+        match event {
+            bevy_networking_turbulence::NetworkEvent::Connected(handle) => {
+                let connection = net.connections.get(handle).unwrap();
+                info!(
+                    "Client connected: {:?} {}",
+                    connection.remote_address(),
+                    handle,
+                );
+                event_log.write(
+                    time.seconds_since_startup(),
+                    MatchEvent::Connected {
+                        client: *handle,
+                        address: connection
+                            .remote_address()
+                            .map(|address| address.to_string()),
+                    },
+                );
+            }
+            bevy_networking_turbulence::NetworkEvent::Disconnected(handle) => {
+                info!("Client disconnected: {:?}", handle);
+                event_log.write(
+                    time.seconds_since_startup(),
+                    MatchEvent::Disconnected { client: *handle },
+                );
+            }
+            bevy_networking_turbulence::NetworkEvent::Packet(_, _) => {}
+            bevy_networking_turbulence::NetworkEvent::Error(handle, error) => {
+                error!("Got error on handle {}: {:?}", handle, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_hello_keeps_the_seat() {
+        let mut lobby = Lobby::default();
+        lobby.spectators.insert(1, None);
+        assert_eq!(
+            lobby.take_seat(1, None, None),
+            Some((PlayerId::Player1, false))
+        );
+        assert!(!lobby.spectators.contains_key(&1));
+        assert_eq!(
+            lobby.take_seat(1, None, None),
+            Some((PlayerId::Player1, false))
+        );
+        // the other seat is still there for the second player
+        assert_eq!(
+            lobby.take_seat(2, None, None),
+            Some((PlayerId::Player2, false))
+        );
+    }
+
+    #[test]
+    fn reclaiming_a_seat_releases_the_one_held() {
+        let mut lobby = Lobby::default();
+        lobby.take_seat(1, None, None);
+        lobby.take_seat(2, None, None);
+        let token = lobby.seats[1].as_ref().unwrap().token;
+        lobby.release(2);
+
+        assert_eq!(
+            lobby.take_seat(1, Some(token), None),
+            Some((PlayerId::Player2, true))
+        );
+        assert_eq!(lobby.seat_of(1), Some(PlayerId::Player2));
+        assert_eq!(lobby.seats[0].as_ref().unwrap().handle, None);
+
+        // and the released seat is reclaimed by its own token
+        let token = lobby.seats[0].as_ref().unwrap().token;
+        assert_eq!(
+            lobby.take_seat(3, Some(token), None),
+            Some((PlayerId::Player1, true))
+        );
+        assert_eq!(lobby.seat_of(1), Some(PlayerId::Player2));
+    }
+}
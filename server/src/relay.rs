@@ -0,0 +1,205 @@
+use platformer_shared::bevy::prelude::*;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// Relay for matches where neither player can be reached directly. The relay runs on a reachable
+// machine and simulates nothing: the hosting player's server keeps an outbound connection to it
+// through a host agent, and the clients connect to the relay as if it were the server. Packets
+// between the relay and the agent are prefixed with the client they belong to, which the agent
+// turns back into one local socket per client, so the server still tells the clients apart.
+
+/// Sent by the host agent to claim the relay, repeated to keep its NAT mapping open.
+const HOST_GREETING: &[u8] = b"platformer-relay-host";
+const HOST_GREETING_PERIOD: Duration = Duration::from_secs(1);
+/// Clients silent for this long are forgotten.
+const PEER_TIMEOUT: Duration = Duration::from_secs(30);
+/// Larger than any packet turbulence sends, with room for the client prefix.
+const MAX_PACKET_LEN: usize = 2048 + 4;
+
+fn framed(peer: u32, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 4);
+    packet.extend_from_slice(&peer.to_be_bytes());
+    packet.extend_from_slice(payload);
+    packet
+}
+
+fn unframed(packet: &[u8]) -> Option<(u32, &[u8])> {
+    if packet.len() < 4 {
+        return None;
+    }
+    let (peer, payload) = packet.split_at(4);
+    Some((
+        u32::from_be_bytes([peer[0], peer[1], peer[2], peer[3]]),
+        payload,
+    ))
+}
+
+struct Peer {
+    address: SocketAddr,
+    last_heard: Instant,
+}
+
+/// Forwards packets between the clients and the host agent that greeted the relay last, until
+/// the process ends.
+pub fn run_relay(listen: SocketAddr) -> io::Result<()> {
+    let socket = UdpSocket::bind(listen)?;
+    println!("Relaying on {}", socket.local_addr()?);
+    let mut host: Option<SocketAddr> = None;
+    let mut peers: HashMap<u32, Peer> = HashMap::new();
+    let mut peer_ids: HashMap<SocketAddr, u32> = HashMap::new();
+    let mut next_peer_id = 0;
+    let mut buffer = [0; MAX_PACKET_LEN];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(error) => {
+                eprintln!("Relay can't receive: {}", error);
+                continue;
+            }
+        };
+        let packet = &buffer[..len];
+
+        if packet == HOST_GREETING {
+            if host != Some(from) {
+                println!("Relaying for the server at {}", from);
+                host = Some(from);
+                // the clients of an earlier host start over
+                peers.clear();
+                peer_ids.clear();
+            }
+            let now = Instant::now();
+            peers.retain(|_, peer| now - peer.last_heard < PEER_TIMEOUT);
+            peer_ids.retain(|_, id| peers.contains_key(id));
+            continue;
+        }
+
+        let host = match host {
+            Some(host) => host,
+            // nobody to relay for yet
+            None => continue,
+        };
+        let sent = if from == host {
+            match unframed(packet).and_then(|(id, payload)| Some((peers.get(&id)?, payload))) {
+                Some((peer, payload)) => socket.send_to(payload, peer.address),
+                None => continue,
+            }
+        } else {
+            let id = *peer_ids.entry(from).or_insert_with(|| {
+                next_peer_id += 1;
+                next_peer_id
+            });
+            peers
+                .entry(id)
+                .or_insert(Peer {
+                    address: from,
+                    last_heard: Instant::now(),
+                })
+                .last_heard = Instant::now();
+            socket.send_to(&framed(id, packet), host)
+        };
+        if let Err(error) = sent {
+            eprintln!("Relay can't forward a packet: {}", error);
+        }
+    }
+}
+
+/// The server's address as reached from the same machine.
+fn local_target(server: SocketAddr) -> SocketAddr {
+    match server.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), server.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(Ipv6Addr::LOCALHOST.into(), server.port())
+        }
+        _ => server,
+    }
+}
+
+fn unspecified(address: SocketAddr) -> SocketAddr {
+    let ip: IpAddr = if address.is_ipv4() {
+        Ipv4Addr::UNSPECIFIED.into()
+    } else {
+        Ipv6Addr::UNSPECIFIED.into()
+    };
+    SocketAddr::new(ip, 0)
+}
+
+/// Connects to the relay on behalf of the server listening on `server`, passing every relayed
+/// client to it from a local socket of its own.
+pub fn spawn_host_agent(relay: SocketAddr, server: SocketAddr) -> io::Result<()> {
+    let target = local_target(server);
+    let socket = Arc::new(UdpSocket::bind(unspecified(relay))?);
+    socket.connect(relay)?;
+
+    let greeter = socket.clone();
+    thread::spawn(move || loop {
+        if let Err(error) = greeter.send(HOST_GREETING) {
+            warn!("Can't greet the relay: {}", error);
+        }
+        thread::sleep(HOST_GREETING_PERIOD);
+    });
+
+    thread::spawn(move || {
+        let mut upstreams: HashMap<u32, Arc<UdpSocket>> = HashMap::new();
+        let mut buffer = [0; MAX_PACKET_LEN];
+        loop {
+            let len = match socket.recv(&mut buffer) {
+                Ok(len) => len,
+                // the relay isn't up yet, the greetings go on
+                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => continue,
+                Err(error) => {
+                    warn!("Host agent can't receive from the relay: {}", error);
+                    continue;
+                }
+            };
+            let (peer, payload) = match unframed(&buffer[..len]) {
+                Some(unframed) => unframed,
+                None => continue,
+            };
+            let upstream = match upstreams.entry(peer) {
+                Entry::Occupied(entry) => entry.get().clone(),
+                Entry::Vacant(entry) => {
+                    let upstream = match UdpSocket::bind(unspecified(target))
+                        .and_then(|upstream| upstream.connect(target).map(|_| upstream))
+                    {
+                        Ok(upstream) => Arc::new(upstream),
+                        Err(error) => {
+                            warn!("Host agent can't reach the server: {}", error);
+                            continue;
+                        }
+                    };
+                    let (replies, relay) = (upstream.clone(), socket.clone());
+                    thread::spawn(move || {
+                        let mut buffer = [0; MAX_PACKET_LEN];
+                        loop {
+                            match replies.recv(&mut buffer) {
+                                Ok(len) => {
+                                    if let Err(error) = relay.send(&framed(peer, &buffer[..len])) {
+                                        warn!("Host agent can't reach the relay: {}", error);
+                                    }
+                                }
+                                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {}
+                                Err(error) => {
+                                    warn!("Host agent stops forwarding: {}", error);
+                                    return;
+                                }
+                            }
+                        }
+                    });
+                    entry.insert(upstream).clone()
+                }
+            };
+            if let Err(error) = upstream.send(payload) {
+                warn!("Host agent can't reach the server: {}", error);
+            }
+        }
+    });
+    Ok(())
+}
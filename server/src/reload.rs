@@ -0,0 +1,126 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::NetworkResource, crystalorb::server::Server,
+    },
+    game::{GameCommand, GameTuning, GameWorld, MatchSettings},
+    lobby,
+    settings::{GameSettings, Settings},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{spectators::SpectatorDelay, Motd, ServerName};
+
+// Picking up changes to the settings file while the server runs. Only the settings that are safe
+// to change between matches are reloaded: the match settings and tuning, which wait for the match
+// in progress to end, and the server's name and message of the day. The rest still needs a
+// restart.
+
+const CHECK_PERIOD_SECONDS: f64 = 1.0;
+
+pub struct SettingsWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked_at: f64,
+    /// Given on the command line, which overrides the file.
+    game_options: GameSettings,
+    name_option: Option<String>,
+    /// Changes waiting for the match in progress to end.
+    pending: Option<(MatchSettings, GameTuning)>,
+}
+
+impl SettingsWatch {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        game_options: GameSettings,
+        name_option: Option<String>,
+    ) -> Self {
+        let path = path.into();
+        Self {
+            modified: modified(&path),
+            path,
+            checked_at: 0.0,
+            game_options,
+            name_option,
+            pending: None,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn reload_settings(
+    time: Res<Time>,
+    mut watch: ResMut<SettingsWatch>,
+    mut name: ResMut<ServerName>,
+    mut motd: ResMut<Motd>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut tuning: ResMut<GameTuning>,
+    spectator_delay: Res<SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let now = time.seconds_since_startup();
+    if now - watch.checked_at >= CHECK_PERIOD_SECONDS {
+        watch.checked_at = now;
+        let modified = modified(&watch.path);
+        if modified.is_some() && modified != watch.modified {
+            watch.modified = modified;
+            match Settings::read(&watch.path) {
+                Ok(settings) => {
+                    info!("Reloaded the settings from {}", watch.path.display());
+                    let server_name = watch
+                        .name_option
+                        .clone()
+                        .unwrap_or(settings.network.server_name);
+                    if name.0 != server_name {
+                        info!("Server name: {}", server_name);
+                        name.0 = server_name;
+                    }
+                    let new_motd =
+                        lobby::motd_message(&settings.network.motd, &settings.network.rules);
+                    if motd.0 != new_motd {
+                        info!("Message of the day: {:?}", new_motd);
+                        motd.0 = new_motd;
+                    }
+                    let game_settings = watch.game_options.or(&settings.game);
+                    let changed = (game_settings.match_settings(), game_settings.tuning());
+                    if changed != (*match_settings, *tuning) {
+                        watch.pending = Some(changed);
+                    }
+                }
+                // the previous settings stay until the file is fixed
+                Err(error) => warn!(
+                    "Can't reload the settings from {}: {}",
+                    watch.path.display(),
+                    error
+                ),
+            }
+        }
+    }
+
+    let between_matches = {
+        let display_state = server.display_state();
+        let display_state = display_state.inner();
+        display_state.lobby || display_state.match_result.is_some()
+    };
+    if let Some((new_match_settings, new_tuning)) = watch.pending.filter(|_| between_matches) {
+        watch.pending = None;
+        info!("Match settings: {:?}", new_match_settings);
+        info!("Tuning: {:?}", new_tuning);
+        *match_settings = new_match_settings;
+        *tuning = new_tuning;
+        let mut net = spectator_delay.network(&mut *net);
+        server.issue_command(GameCommand::SetMatchSettings(new_match_settings), &mut net);
+        server.issue_command(GameCommand::SetTuning(new_tuning), &mut net);
+    }
+}
@@ -0,0 +1,87 @@
+use platformer_shared::{
+    bevy::prelude::*, crystalorb_bevy_networking_turbulence::crystalorb::server::Server,
+    game::GameWorld, replay::Replay,
+};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// A replay file for every match, recorded from the commands the server's world reports applying.
+// The world only reports the last couple of seconds of them, plenty for a server updating many
+// times a second.
+
+pub struct ReplayRecorder {
+    directory: PathBuf,
+    recording: Option<Replay>,
+    /// Start of the match recorded last, which stays in the display state after it ends.
+    last_start_tick: Option<u32>,
+}
+
+impl ReplayRecorder {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            recording: None,
+            last_start_tick: None,
+        }
+    }
+
+    fn save(&self, replay: &Replay) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = self
+            .directory
+            .join(format!("{}-{}.replay", time, replay.start_tick));
+        let saved = fs::create_dir_all(&self.directory)
+            .and_then(|_| File::create(&path))
+            .map_err(|error| error.to_string())
+            .and_then(|file| {
+                replay
+                    .write(BufWriter::new(file))
+                    .map_err(|error| error.to_string())
+            });
+        match saved {
+            Ok(()) => info!("Saved the replay to {}", path.display()),
+            Err(error) => warn!("Can't save the replay to {}: {}", path.display(), error),
+        }
+    }
+}
+
+pub fn record_replays(mut recorder: ResMut<ReplayRecorder>, server: Res<Server<GameWorld>>) {
+    let display_state = server.display_state();
+    let display_state = display_state.inner();
+
+    if let Some((start_tick, start, config)) = &display_state.match_start {
+        if recorder.last_start_tick != Some(*start_tick) {
+            // the match in progress was cut short by a new one
+            if let Some(replay) = recorder.recording.take() {
+                recorder.save(&replay);
+            }
+            recorder.last_start_tick = Some(*start_tick);
+            recorder.recording = Some(Replay::new(*start_tick, (**start).clone(), *config));
+        }
+    }
+
+    let replay = match recorder.recording.as_mut() {
+        Some(replay) => replay,
+        None => return,
+    };
+    let recorded_up_to = replay.commands.last().map(|(applied_at, _)| *applied_at);
+    for (applied_at, command) in display_state.applied_commands.iter() {
+        if *applied_at >= replay.start_tick && Some(*applied_at) > recorded_up_to {
+            replay.commands.push((*applied_at, command.clone()));
+        }
+    }
+    replay.end_tick = display_state.tick;
+
+    // an admin reset opens the lobby without the match being decided
+    if display_state.match_result.is_some() || display_state.lobby {
+        if let Some(replay) = recorder.recording.take() {
+            recorder.save(&replay);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{self, ConnectionHandle, NetworkResource},
+        crystalorb::{
+            clocksync::ClockSyncMessage,
+            network_resource::{self, Connection},
+            server::Server,
+            timestamp::Timestamped,
+        },
+    },
+    game::{GameCommand, GameSnapshot, GameWorld, PlayerId},
+    net,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{any::Any, collections::VecDeque, fmt::Debug, sync::Mutex};
+
+use crate::{Lobby, Seat};
+
+// Spectators get the game some seconds late, so that they can't tell the players where their
+// opponent is. The snapshots and relayed commands bound for them are held back, then restamped
+// as if the server sent them just then: their clients show the delayed game as the live one,
+// instead of predicting it up to the present. Clock syncing isn't delayed.
+//
+// The commands the clients send are screened on the way in as well: crystalorb only gets those
+// for the player seated on the connection they came from, as they were sent, timestamp and all.
+// Clients the handshake turned away are left out altogether until they're dropped: they get no
+// snapshots, and nothing they send is read.
+
+/// Longest spectator delay taken.
+pub const MAX_SPECTATOR_DELAY_SECONDS: f64 = 300.0;
+
+enum HeldMessage {
+    Snapshot(Timestamped<GameSnapshot>),
+    Command(Timestamped<GameCommand>),
+}
+
+struct Held {
+    release_at: f64,
+    handle: ConnectionHandle,
+    message: HeldMessage,
+}
+
+pub struct SpectatorDelay {
+    seconds: f64,
+    /// The delay in timestamps, added to the held messages.
+    ticks: i16,
+    held: Mutex<VecDeque<Held>>,
+    /// Connections without a seat as of the last update, and when what they're sent is due.
+    spectators: Vec<ConnectionHandle>,
+    release_at: f64,
+    /// Connection in each seat as of the last update, whose commands are let through.
+    seated: [Option<ConnectionHandle>; 2],
+    /// Connections turned away as of the last update, hidden from crystalorb.
+    rejected: Vec<ConnectionHandle>,
+}
+
+impl SpectatorDelay {
+    pub fn new(seconds: f64, timestep_seconds: f64) -> Self {
+        Self {
+            seconds,
+            ticks: (seconds / timestep_seconds).round() as i16,
+            held: Default::default(),
+            spectators: vec![],
+            release_at: 0.0,
+            seated: [None; 2],
+            rejected: vec![],
+        }
+    }
+
+    /// The server's network, as crystalorb sees it, with the spectators behind the delay. Every
+    /// command issued to the server goes through it, or the spectators would see the lobby and
+    /// admin commands take effect early.
+    pub(crate) fn network<'a>(&'a self, net: &'a mut NetworkResource) -> DelayingNetwork<'a> {
+        DelayingNetwork {
+            net,
+            spectators: &self.spectators,
+            held: &self.held,
+            release_at: self.release_at,
+            seated: self.seated,
+            rejected: &self.rejected,
+        }
+    }
+
+    /// Sends the held messages that are due.
+    fn release(&self, net: &mut NetworkResource, lobby: &Lobby, now: f64) {
+        let mut held = self.held.lock().unwrap();
+        while held.front().map_or(false, |held| held.release_at <= now) {
+            let Held {
+                handle, message, ..
+            } = held.pop_front().unwrap();
+            // seated since, the player gets the live game
+            if lobby.seat_of(handle).is_some() {
+                continue;
+            }
+            let channels = match net
+                .connections
+                .get_mut(&handle)
+                .and_then(|connection| connection.channels())
+            {
+                Some(channels) => channels,
+                // gone since
+                None => continue,
+            };
+            let unsent = match message {
+                HeldMessage::Snapshot(snapshot) => {
+                    net::send_message(channels, self.restamp(snapshot)).map(drop)
+                }
+                HeldMessage::Command(command) => {
+                    net::send_message(channels, self.restamp(command)).map(drop)
+                }
+            };
+            if unsent.is_some() {
+                debug!("Can't release to spectator {}, its channel is full", handle);
+            }
+        }
+    }
+
+    fn restamp<T: Clone>(&self, message: Timestamped<T>) -> Timestamped<T> {
+        Timestamped::new(message.inner().clone(), message.timestamp() + self.ticks)
+    }
+}
+
+pub(crate) struct DelayingNetwork<'n> {
+    net: &'n mut NetworkResource,
+    spectators: &'n [ConnectionHandle],
+    held: &'n Mutex<VecDeque<Held>>,
+    release_at: f64,
+    seated: [Option<ConnectionHandle>; 2],
+    rejected: &'n [ConnectionHandle],
+}
+
+fn seat_of(seated: &[Option<ConnectionHandle>; 2], handle: ConnectionHandle) -> Option<PlayerId> {
+    [PlayerId::Player1, PlayerId::Player2]
+        .iter()
+        .copied()
+        .find(|player_id| seated[player_id.as_usize()] == Some(handle))
+}
+
+impl<'n> network_resource::NetworkResource<GameWorld> for DelayingNetwork<'n> {
+    type ConnectionType<'a> = DelayingConnection<'a>;
+
+    fn get_connection(&mut self, handle: usize) -> Option<Self::ConnectionType<'_>> {
+        let handle = handle as ConnectionHandle;
+        if self.rejected.contains(&handle) {
+            return None;
+        }
+        let delay = self
+            .spectators
+            .contains(&handle)
+            .then(|| (self.held, self.release_at));
+        let seat = seat_of(&self.seated, handle);
+        self.net
+            .connections
+            .get_mut(&handle)
+            .map(|connection| DelayingConnection {
+                handle,
+                connection,
+                delay,
+                seat,
+            })
+    }
+
+    fn connections<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = (usize, Self::ConnectionType<'a>)> + 'a> {
+        let spectators = self.spectators;
+        let held = self.held;
+        let release_at = self.release_at;
+        let seated = self.seated;
+        let rejected = self.rejected;
+        Box::new(
+            self.net
+                .connections
+                .iter_mut()
+                .filter(move |(handle, _)| !rejected.contains(handle))
+                .map(move |(handle, connection)| {
+                    let delay = spectators.contains(handle).then(|| (held, release_at));
+                    let seat = seat_of(&seated, *handle);
+                    (
+                        *handle as usize,
+                        DelayingConnection {
+                            handle: *handle,
+                            connection,
+                            delay,
+                            seat,
+                        },
+                    )
+                }),
+        )
+    }
+}
+
+pub(crate) struct DelayingConnection<'a> {
+    handle: ConnectionHandle,
+    connection: &'a mut Box<dyn bevy_networking_turbulence::Connection>,
+    /// Where to hold the messages back, and until when, for spectators.
+    delay: Option<(&'a Mutex<VecDeque<Held>>, f64)>,
+    /// Player seated on the connection, the only one its commands may be for.
+    seat: Option<PlayerId>,
+}
+
+impl Connection<GameWorld> for DelayingConnection<'_> {
+    fn recv_command(&mut self) -> Option<Timestamped<GameCommand>> {
+        let channels = self.connection.channels()?;
+        while let Some(command) = net::recv_command(channels) {
+            match command.inner() {
+                GameCommand::Input(player_id, _)
+                | GameCommand::SelectCharacter(player_id, _)
+                | GameCommand::Rematch(player_id)
+                | GameCommand::Emote(player_id, _)
+                    if self.seat == Some(*player_id) =>
+                {
+                    return Some(command)
+                }
+                other => warn!(
+                    "Dropping {:?} from client {}, seated as {:?}",
+                    other, self.handle, self.seat
+                ),
+            }
+        }
+        None
+    }
+
+    fn recv_snapshot(&mut self) -> Option<Timestamped<GameSnapshot>> {
+        net::recv_snapshot(self.connection.channels()?)
+    }
+
+    fn recv_clock_sync(&mut self) -> Option<ClockSyncMessage> {
+        self.connection.channels()?.recv()
+    }
+
+    fn send<MessageType>(&mut self, message: MessageType) -> Option<MessageType>
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        if let Some((held, release_at)) = self.delay {
+            let any = &message as &dyn Any;
+            let held_message =
+                if let Some(snapshot) = any.downcast_ref::<Timestamped<GameSnapshot>>() {
+                    Some(HeldMessage::Snapshot(snapshot.clone()))
+                } else {
+                    any.downcast_ref::<Timestamped<GameCommand>>()
+                        .map(|command| HeldMessage::Command(command.clone()))
+                };
+            if let Some(message) = held_message {
+                held.lock().unwrap().push_back(Held {
+                    release_at,
+                    handle: self.handle,
+                    message,
+                });
+                return None;
+            }
+        }
+        match self.connection.channels() {
+            Some(channels) => net::send_message(channels, message),
+            None => Some(message),
+        }
+    }
+
+    fn flush<MessageType>(&mut self)
+    where
+        MessageType: Debug + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        if let Some(channels) = self.connection.channels() {
+            net::flush_messages::<MessageType>(channels);
+        }
+    }
+}
+
+/// Updates the server the way the crystalorb plugin would, only through the spectator delay.
+pub(crate) fn update_server(
+    time: Res<Time>,
+    lobby: Res<Lobby>,
+    mut delay: ResMut<SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    let now = time.seconds_since_startup();
+    let seat_handle = |seat: &Option<Seat>| seat.as_ref().and_then(|seat| seat.handle);
+    delay.seated = [seat_handle(&lobby.seats[0]), seat_handle(&lobby.seats[1])];
+    delay.rejected = lobby.rejected.keys().copied().collect();
+    if delay.seconds > 0.0 {
+        delay.spectators = net
+            .connections
+            .keys()
+            .copied()
+            .filter(|handle| lobby.seat_of(*handle).is_none())
+            .collect();
+        delay.release_at = now + delay.seconds;
+    }
+    server.update(time.delta_seconds_f64(), now, &mut delay.network(&mut *net));
+    delay.release(&mut *net, &lobby, now);
+}
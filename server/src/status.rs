@@ -0,0 +1,199 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{ConnectionHandle, NetworkResource},
+        crystalorb::server::Server,
+    },
+    game::{GameWorld, PlayerId},
+    TIMESTEP,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "history")]
+use crate::history::{History, Rating};
+use crate::{Lobby, ServerName};
+
+// JSON status of the server over HTTP, for monitoring public instances. Every request gets the
+// status, whatever its path. Turbulence only counts bytes per connection, so the bandwidth covers
+// all channels of a connection together.
+
+const UPDATE_PERIOD_SECONDS: f64 = 1.0;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Players on the leaderboard, built with the `history` feature.
+#[cfg(feature = "history")]
+const LEADERBOARD_LEN: u32 = 10;
+
+/// Latest status, as served.
+pub struct StatusPage(Arc<Mutex<String>>);
+
+/// Address the status endpoint listens on.
+pub struct StatusAddress(pub SocketAddr);
+
+/// Bytes sent and received by every connection at the previous update, for the bandwidth.
+#[derive(Default)]
+pub struct Sampling {
+    updated_at: Option<f64>,
+    bytes: HashMap<ConnectionHandle, (usize, usize)>,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    name: &'a str,
+    uptime_seconds: f64,
+    tick_rate: f64,
+    round: u8,
+    scores: [u8; 2],
+    /// Set while waiting for the players to ready up.
+    lobby: bool,
+    players: Vec<PlayerStatus>,
+    connections: Vec<ConnectionStatus>,
+    /// Highest rated players first.
+    #[cfg(feature = "history")]
+    leaderboard: Vec<Rating>,
+}
+
+#[derive(Serialize)]
+struct PlayerStatus {
+    player: String,
+    name: Option<String>,
+    connected: bool,
+    ready: bool,
+}
+
+#[derive(Serialize)]
+struct ConnectionStatus {
+    client: ConnectionHandle,
+    address: Option<String>,
+    player: Option<String>,
+    bytes_sent_per_second: f32,
+    bytes_received_per_second: f32,
+}
+
+fn respond(mut stream: TcpStream, page: &Mutex<String>) {
+    let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+    // the request is read only to be polite, every path gets the status
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request);
+    let body = page.lock().unwrap().clone();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+pub fn setup_status_endpoint(mut commands: Commands, address: Res<StatusAddress>) {
+    let page = Arc::new(Mutex::new("{}".to_string()));
+    commands.insert_resource(StatusPage(page.clone()));
+    let listener = match TcpListener::bind(address.0) {
+        Ok(listener) => listener,
+        Err(error) => {
+            warn!("Can't serve the status on {}: {}", address.0, error);
+            return;
+        }
+    };
+    info!("Serving the status on http://{}", address.0);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            respond(stream, &page);
+        }
+    });
+}
+
+pub(crate) fn update_status(
+    time: Res<Time>,
+    mut sampling: Local<Sampling>,
+    name: Res<ServerName>,
+    lobby: Res<Lobby>,
+    server: Res<Server<GameWorld>>,
+    net: Res<NetworkResource>,
+    page: Res<StatusPage>,
+    #[cfg(feature = "history")] history: Res<History>,
+) {
+    let now = time.seconds_since_startup();
+    let elapsed = match sampling.updated_at {
+        Some(updated_at) if now - updated_at < UPDATE_PERIOD_SECONDS => return,
+        Some(updated_at) => (now - updated_at) as f32,
+        None => 0.0,
+    };
+    sampling.updated_at = Some(now);
+
+    let players = [PlayerId::Player1, PlayerId::Player2]
+        .iter()
+        .zip(lobby.seats.iter())
+        .filter_map(|(player_id, seat)| {
+            seat.as_ref().map(|seat| PlayerStatus {
+                player: player_id.to_string(),
+                name: seat.name.clone(),
+                connected: seat.handle.is_some(),
+                ready: seat.ready,
+            })
+        })
+        .collect();
+
+    let connections = net
+        .connections
+        .iter()
+        .map(|(handle, connection)| {
+            let stats = connection.stats();
+            // new connections show up with no traffic until the next update
+            let (bytes_sent, bytes_received) = sampling
+                .bytes
+                .insert(*handle, (stats.bytes_tx, stats.bytes_rx))
+                .unwrap_or((stats.bytes_tx, stats.bytes_rx));
+            let rate = |bytes: usize, sampled: usize| {
+                if elapsed > 0.0 {
+                    bytes.saturating_sub(sampled) as f32 / elapsed
+                } else {
+                    0.0
+                }
+            };
+            ConnectionStatus {
+                client: *handle,
+                address: connection
+                    .remote_address()
+                    .map(|address| address.to_string()),
+                player: lobby
+                    .seat_of(*handle)
+                    .map(|player_id| player_id.to_string()),
+                bytes_sent_per_second: rate(stats.bytes_tx, bytes_sent),
+                bytes_received_per_second: rate(stats.bytes_rx, bytes_received),
+            }
+        })
+        .collect();
+    sampling
+        .bytes
+        .retain(|handle, _| net.connections.contains_key(handle));
+
+    let display_state = server.display_state();
+    let display_state = display_state.inner();
+    let status = Status {
+        name: &name.0,
+        uptime_seconds: now,
+        tick_rate: 1.0 / TIMESTEP,
+        round: display_state.round,
+        scores: display_state.scores,
+        lobby: display_state.lobby,
+        players,
+        connections,
+        #[cfg(feature = "history")]
+        leaderboard: history.top(LEADERBOARD_LEN).unwrap_or_else(|error| {
+            warn!("Can't read the leaderboard: {}", error);
+            vec![]
+        }),
+    };
+    match serde_json::to_string(&status) {
+        Ok(json) => *page.0.lock().unwrap() = json,
+        Err(error) => warn!("Can't serialize the status: {}", error),
+    }
+}
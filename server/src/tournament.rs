@@ -0,0 +1,200 @@
+use platformer_shared::{
+    bevy::prelude::*,
+    crystalorb_bevy_networking_turbulence::{
+        bevy_networking_turbulence::{NetworkEvent, NetworkResource},
+        crystalorb::server::Server,
+    },
+    game::{GameCommand, GameWorld, MatchResult},
+    lobby::{sanitize_name, LobbyMessage},
+    tournament::Bracket,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{spectators::SpectatorDelay, Lobby, Seat, Seating};
+
+// Tournament mode: the server runs the matches of a single elimination bracket one after the
+// other. Only the two players of the match up next get the seats, by name, and everyone else
+// watches. The bracket is saved after every match, so a restarted server picks up where it left.
+
+pub struct Tournament {
+    bracket: Bracket,
+    /// Where the bracket is saved.
+    path: PathBuf,
+    /// Match being played, as its round and index in the bracket.
+    current: Option<(usize, usize)>,
+    /// Set once the current match was seen under way, its result is only taken after that.
+    under_way: bool,
+    finished: bool,
+}
+
+impl Tournament {
+    /// Reads the participants from a file, one name per line, and the bracket saved next to it
+    /// if a tournament with them was already under way.
+    pub fn load(participants_path: impl AsRef<Path>) -> Result<Self, String> {
+        let participants_path = participants_path.as_ref();
+        let path = participants_path.with_extension("bracket.json");
+        let bracket = match fs::read_to_string(&path) {
+            Ok(saved) => {
+                info!("Resuming the tournament saved in {}", path.display());
+                serde_json::from_str(&saved).map_err(|error| error.to_string())?
+            }
+            Err(_) => {
+                let participants = fs::read_to_string(participants_path)
+                    .map_err(|error| error.to_string())?
+                    .lines()
+                    .filter_map(sanitize_name)
+                    .collect::<Vec<_>>();
+                Bracket::new(&participants)?
+            }
+        };
+        Ok(Self {
+            bracket,
+            path,
+            current: None,
+            under_way: false,
+            finished: false,
+        })
+    }
+
+    fn save(&self) {
+        let saved = serde_json::to_string_pretty(&self.bracket)
+            .map_err(|error| error.to_string())
+            .and_then(|json| fs::write(&self.path, json).map_err(|error| error.to_string()));
+        if let Err(error) = saved {
+            warn!(
+                "Can't save the bracket to {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Gives the seats to the named players, sending everyone else seated to watch.
+fn seat_players(names: [String; 2], lobby: &mut Lobby, net: &mut NetworkResource) {
+    let mut messages = vec![];
+    for seat in lobby.seats.iter_mut() {
+        if let Some(Seat {
+            handle: Some(handle),
+            name,
+            ..
+        }) = seat.take()
+        {
+            lobby.spectators.insert(handle, name);
+            messages.push((handle, LobbyMessage::Spectate));
+        }
+    }
+    lobby.seating = Seating::Reserved(names.clone());
+    lobby.started = false;
+
+    let waiting = lobby
+        .spectators
+        .iter()
+        .filter(|(_, name)| matches!(name, Some(name) if names.contains(name)))
+        .map(|(handle, name)| (*handle, name.clone()))
+        .collect::<Vec<_>>();
+    for (handle, name) in waiting {
+        if let Some((player_id, _)) = lobby.take_seat(handle, None, name.as_deref()) {
+            let seat = lobby.seats[player_id.as_usize()].as_mut().unwrap();
+            seat.name = name;
+            // replaces the spectate message if the player stays seated
+            messages.retain(|(to, _)| *to != handle);
+            messages.push((
+                handle,
+                LobbyMessage::Welcome {
+                    player_id,
+                    seat_token: seat.token,
+                },
+            ));
+        }
+    }
+    for (handle, message) in messages {
+        if let Err(error) = net.send_message(handle, message) {
+            error!("Can't reseat client {}: {:?}", handle, error);
+        }
+    }
+    net.broadcast_message(LobbyMessage::Names(lobby.names()));
+}
+
+pub(crate) fn run_tournament(
+    mut events: EventReader<NetworkEvent>,
+    mut tournament: ResMut<Tournament>,
+    mut lobby: ResMut<Lobby>,
+    spectator_delay: Res<SpectatorDelay>,
+    mut server: ResMut<Server<GameWorld>>,
+    mut net: ResMut<NetworkResource>,
+) {
+    for event in events.iter() {
+        if let NetworkEvent::Connected(handle) = event {
+            let bracket = LobbyMessage::Bracket(tournament.bracket.clone());
+            if let Err(error) = net.send_message(*handle, bracket) {
+                error!("Can't send the bracket to client {}: {:?}", handle, error);
+            }
+        }
+    }
+
+    let (under_way, match_result) = {
+        let display_state = server.display_state();
+        let display_state = display_state.inner();
+        (
+            lobby.started && !display_state.lobby && display_state.match_result.is_none(),
+            display_state.match_result,
+        )
+    };
+    if let Some((round, index)) = tournament.current {
+        tournament.under_way |= under_way;
+        match match_result {
+            Some(MatchResult::Winner(player_id)) if tournament.under_way => {
+                tournament
+                    .bracket
+                    .record(round, index, player_id.as_usize());
+                let decided = tournament.bracket.get(round, index);
+                info!(
+                    "{} won their tournament match",
+                    decided.winner_name().unwrap_or("?")
+                );
+                tournament.save();
+                net.broadcast_message(LobbyMessage::Bracket(tournament.bracket.clone()));
+                tournament.current = None;
+            }
+            // a tournament match needs a winner, draws are played again
+            Some(MatchResult::Draw) if tournament.under_way => {
+                info!("The tournament match ended in a draw, playing it again");
+                tournament.current = None;
+            }
+            _ => return,
+        }
+    }
+    if tournament.finished {
+        return;
+    }
+
+    match tournament.bracket.next_match() {
+        Some((round, index)) => {
+            let players = &tournament.bracket.get(round, index).players;
+            let names = [
+                players[0].clone().unwrap_or_default(),
+                players[1].clone().unwrap_or_default(),
+            ];
+            info!("Next tournament match: {} vs {}", names[0], names[1]);
+            seat_players(names, &mut lobby, &mut net);
+            tournament.current = Some((round, index));
+            tournament.under_way = false;
+            server.issue_command(
+                GameCommand::OpenLobby,
+                &mut spectator_delay.network(&mut *net),
+            );
+        }
+        None => {
+            info!(
+                "{} wins the tournament",
+                tournament.bracket.champion().unwrap_or("nobody")
+            );
+            lobby.seating = Seating::Closed;
+            tournament.finished = true;
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Simulated network conditions, for trying out prediction and rollback locally: a UDP proxy that
+//! delays, jitters, reorders and drops the packets going through it. Clients run one between
+//! themselves and the server, servers one in front of their socket. Browsers can't run it, and
+//! WebRTC sessions bypass it.
+
+use bevy::log::warn;
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, BinaryHeap, HashMap},
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Larger than any packet turbulence sends.
+const MAX_PACKET_LEN: usize = 2048;
+
+/// Applied to the packets going either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// Delay of every packet, in milliseconds.
+    pub latency_ms: u64,
+    /// Up to this many more milliseconds of delay, picked for every packet, which also reorders
+    /// them.
+    pub jitter_ms: u64,
+    /// Share of the packets dropped, from 0 to 1.
+    pub loss: f32,
+}
+
+impl NetworkConditions {
+    /// Perfect conditions need no proxy.
+    pub fn is_simulated(&self) -> bool {
+        self.latency_ms > 0 || self.jitter_ms > 0 || self.loss > 0.0
+    }
+}
+
+/// Xorshift, good enough to pick delays and drops.
+struct Rng(u64);
+
+impl Rng {
+    fn new(salt: u64) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos() as u64);
+        Self((nanos ^ salt.rotate_left(32)) | 1)
+    }
+
+    /// Between 0 and 1.
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+struct DelayedPacket {
+    deliver_at: Instant,
+    /// Keeps packets of the same delay in order.
+    sequence: u64,
+    socket: Arc<UdpSocket>,
+    /// Unset for sockets connected to their destination.
+    to: Option<SocketAddr>,
+    payload: Vec<u8>,
+}
+
+impl PartialEq for DelayedPacket {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DelayedPacket {}
+
+impl PartialOrd for DelayedPacket {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DelayedPacket {
+    // reversed, the heap pops the earliest packet first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.deliver_at, other.sequence).cmp(&(self.deliver_at, self.sequence))
+    }
+}
+
+impl DelayedPacket {
+    fn deliver(self) {
+        let sent = match self.to {
+            Some(to) => self.socket.send_to(&self.payload, to),
+            None => self.socket.send(&self.payload),
+        };
+        if let Err(error) = sent {
+            warn!("Network conditions proxy can't forward a packet: {}", error);
+        }
+    }
+}
+
+/// Reads the packets arriving at a socket and schedules them for delivery, unless they're dropped.
+struct Forwarder {
+    conditions: NetworkConditions,
+    rng: Rng,
+    sequence: u64,
+    scheduler: Sender<DelayedPacket>,
+}
+
+impl Forwarder {
+    fn forward(&mut self, payload: &[u8], socket: &Arc<UdpSocket>, to: Option<SocketAddr>) {
+        self.sequence += 1;
+        if self.rng.next() < self.conditions.loss {
+            return;
+        }
+        let delay_ms =
+            self.conditions.latency_ms as f32 + self.conditions.jitter_ms as f32 * self.rng.next();
+        let _ = self.scheduler.send(DelayedPacket {
+            deliver_at: Instant::now() + Duration::from_secs_f32(delay_ms / 1000.0),
+            sequence: self.sequence,
+            socket: socket.clone(),
+            to,
+            payload: payload.to_vec(),
+        });
+    }
+}
+
+fn run_scheduler(packets: Receiver<DelayedPacket>) {
+    let mut pending = BinaryHeap::new();
+    loop {
+        let now = Instant::now();
+        while matches!(pending.peek(), Some(packet) if packet.deliver_at <= now) {
+            pending.pop().unwrap().deliver();
+        }
+        let received = match pending.peek() {
+            Some(packet) => packets.recv_timeout(packet.deliver_at - now),
+            None => packets.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(packet) => pending.push(packet),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Starts a proxy listening on `listen`, passing the packets of every peer to `target` from a
+/// socket of its own, so that the target still tells the peers apart. Returns the address it
+/// listens on.
+pub fn spawn_proxy(
+    listen: SocketAddr,
+    target: SocketAddr,
+    conditions: NetworkConditions,
+) -> io::Result<SocketAddr> {
+    if cfg!(target_arch = "wasm32") {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "browsers can't open sockets for the proxy",
+        ));
+    }
+    let listener = Arc::new(UdpSocket::bind(listen)?);
+    let listening = listener.local_addr()?;
+    let (scheduler, packets) = mpsc::channel();
+    thread::spawn(move || run_scheduler(packets));
+
+    let mut forwarder = Forwarder {
+        conditions,
+        rng: Rng::new(listening.port() as u64),
+        sequence: 0,
+        scheduler: scheduler.clone(),
+    };
+    let upstream_address = SocketAddr::new(
+        if target.is_ipv4() {
+            Ipv4Addr::UNSPECIFIED.into()
+        } else {
+            Ipv6Addr::UNSPECIFIED.into()
+        },
+        0,
+    );
+    thread::spawn(move || {
+        let mut upstreams: HashMap<SocketAddr, Arc<UdpSocket>> = HashMap::new();
+        let mut buffer = [0; MAX_PACKET_LEN];
+        loop {
+            let (len, peer) = match listener.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) => {
+                    warn!("Network conditions proxy can't receive: {}", error);
+                    continue;
+                }
+            };
+            let upstream = match upstreams.entry(peer) {
+                Entry::Occupied(entry) => entry.get().clone(),
+                Entry::Vacant(entry) => {
+                    let socket = match UdpSocket::bind(upstream_address)
+                        .and_then(|socket| socket.connect(target).map(|_| socket))
+                    {
+                        Ok(socket) => Arc::new(socket),
+                        Err(error) => {
+                            warn!("Network conditions proxy can't reach {}: {}", target, error);
+                            continue;
+                        }
+                    };
+                    let mut backward = Forwarder {
+                        conditions,
+                        rng: Rng::new(peer.port() as u64),
+                        sequence: 0,
+                        scheduler: scheduler.clone(),
+                    };
+                    let (upstream, listener) = (socket.clone(), listener.clone());
+                    thread::spawn(move || {
+                        let mut buffer = [0; MAX_PACKET_LEN];
+                        loop {
+                            match upstream.recv(&mut buffer) {
+                                Ok(len) => backward.forward(&buffer[..len], &listener, Some(peer)),
+                                // an earlier packet found the target closed, it may be back
+                                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {}
+                                Err(error) => {
+                                    warn!("Network conditions proxy stops forwarding: {}", error);
+                                    return;
+                                }
+                            }
+                        }
+                    });
+                    entry.insert(socket).clone()
+                }
+            };
+            forwarder.forward(&buffer[..len], &upstream, None);
+        }
+    });
+    Ok(listening)
+}
+
+/// A loopback address with a port that was free a moment ago, for a server hiding behind a proxy.
+pub fn free_local_address() -> io::Result<SocketAddr> {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()
+}
@@ -0,0 +1,43 @@
+//! LAN discovery: servers broadcast a beacon every second, which clients on the same network
+//! listen for to list the servers on their connect screen.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codec::{Codec, DefaultCodec},
+    SERVER_PORT,
+};
+
+/// Port the beacons are broadcast to.
+pub const DISCOVERY_PORT: u16 = SERVER_PORT + 2;
+pub const BEACON_PERIOD_SECONDS: f64 = 1.0;
+/// Servers whose beacon wasn't heard for this long are dropped from the list.
+pub const BEACON_TIMEOUT_SECONDS: f64 = 3.0 * BEACON_PERIOD_SECONDS;
+
+/// Tells beacons apart from other traffic on the discovery port.
+const BEACON_MAGIC: [u8; 4] = *b"BNPB";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Beacon {
+    pub name: String,
+    /// Game port, on the address the beacon came from.
+    pub port: u16,
+    /// Taken seats, out of two.
+    pub players: u8,
+    pub map: String,
+}
+
+impl Beacon {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = BEACON_MAGIC.to_vec();
+        bytes.extend(DefaultCodec::encode(self).expect("can't encode beacon"));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Beacon> {
+        if bytes.len() < BEACON_MAGIC.len() || bytes[..BEACON_MAGIC.len()] != BEACON_MAGIC {
+            return None;
+        }
+        DefaultCodec::decode(&bytes[BEACON_MAGIC.len()..]).ok()
+    }
+}
@@ -0,0 +1,514 @@
+//! Encrypted UDP transport, for playing over the internet. Both ends run a tunnel next to their
+//! turbulence socket, which agree on a key with an X25519 exchange as the tunnel opens, ahead of
+//! turbulence's own handshake, and seal every packet with ChaCha20-Poly1305 from then on. The key
+//! is derived with HKDF from the exchange and both public keys, and packets sent again on the way
+//! are dropped. The keys aren't authenticated, so this keeps eavesdroppers out, not someone able
+//! to rewrite the traffic in between. Browsers can't run the tunnel, and WebRTC sessions have
+//! encryption of their own.
+//!
+//! The server tunnel only gives a client a socket and a thread of its own once it sent a sealed
+//! packet, which spoofed greetings can't, and takes at most `MAX_PEERS` of them.
+
+use bevy::log::{info, warn};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// First byte of a key exchange packet, followed by the sender's public key.
+const HANDSHAKE: u8 = 0;
+/// First byte of a sealed packet, followed by its counter and the ciphertext.
+const DATA: u8 = 1;
+const HEADER_LEN: usize = 1 + 8;
+/// Larger than any packet turbulence sends, with room for the header and the authentication tag.
+const MAX_PACKET_LEN: usize = 2048 + HEADER_LEN + 16;
+const HANDSHAKE_RETRY_PERIOD: Duration = Duration::from_millis(250);
+/// Mixed into the key, so that it's only ever used by this protocol.
+const KEY_INFO: &[u8] = b"platformer encryption tunnel";
+/// Counters this far behind the highest one opened are still taken, packets overtake each other.
+const REPLAY_WINDOW: u64 = 64;
+
+/// Clients the server tunnel forwards for at once.
+const MAX_PEERS: usize = 64;
+/// Clients through the key exchange that haven't sent a sealed packet yet. When full, the oldest
+/// is forgotten to make room.
+const MAX_PENDING: usize = 64;
+/// Time a client has to send its first sealed packet after the key exchange.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time after which a silent client is forgotten, and its socket and thread closed.
+const PEER_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the server tunnel forgets the clients that timed out.
+const SWEEP_PERIOD: Duration = Duration::from_secs(1);
+
+/// Side that sealed a packet, part of the nonce so that both directions never share one.
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    ToServer = 0,
+    ToClient = 1,
+}
+
+/// Counters of the packets opened lately, so that each is only opened once. Packets older than
+/// the window are dropped as well.
+#[derive(Default)]
+struct ReplayWindow {
+    /// Highest counter opened plus one, zero before the first packet.
+    next: u64,
+    /// Bit `i` is set once the counter `next - 1 - i` was opened.
+    seen: u64,
+}
+
+impl ReplayWindow {
+    fn is_fresh(&self, counter: u64) -> bool {
+        if counter >= self.next {
+            return true;
+        }
+        let age = self.next - 1 - counter;
+        age < REPLAY_WINDOW && self.seen & (1 << age) == 0
+    }
+
+    /// Only called once the packet opened, so that forged counters can't move the window.
+    fn mark(&mut self, counter: u64) {
+        if counter >= self.next {
+            let shift = counter - self.next + 1;
+            self.seen = if shift >= REPLAY_WINDOW {
+                0
+            } else {
+                self.seen << shift
+            };
+            self.seen |= 1;
+            self.next = counter + 1;
+        } else {
+            self.seen |= 1 << (self.next - 1 - counter);
+        }
+    }
+}
+
+struct Session {
+    cipher: ChaCha20Poly1305,
+    /// Packets sealed so far, the counter of the next one.
+    sealed: AtomicU64,
+    opened: Mutex<ReplayWindow>,
+}
+
+impl Session {
+    /// Takes the direction this end seals in, to tell which public key is the client's. Fails
+    /// on a malformed or low order public key.
+    fn new(secret: EphemeralSecret, their_public: &[u8], sealing: Direction) -> Option<Self> {
+        if their_public.len() != 32 {
+            return None;
+        }
+        let mut public = [0; 32];
+        public.copy_from_slice(their_public);
+        let their_public = PublicKey::from(public);
+        let our_public = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&their_public);
+        // low order points give away the key
+        if shared.as_bytes() == &[0; 32] {
+            return None;
+        }
+        let (client_public, server_public) = match sealing {
+            Direction::ToServer => (our_public, their_public),
+            Direction::ToClient => (their_public, our_public),
+        };
+        let mut info = KEY_INFO.to_vec();
+        info.extend_from_slice(client_public.as_bytes());
+        info.extend_from_slice(server_public.as_bytes());
+        let mut key = [0; 32];
+        Hkdf::<Sha256>::new(None, shared.as_bytes())
+            .expand(&info, &mut key)
+            .ok()?;
+        Some(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            sealed: AtomicU64::new(0),
+            opened: Default::default(),
+        })
+    }
+
+    fn seal(&self, direction: Direction, payload: &[u8]) -> Option<Vec<u8>> {
+        let counter = self.sealed.fetch_add(1, Ordering::Relaxed);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce(direction, counter), payload)
+            .ok()?;
+        let mut packet = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        packet.push(DATA);
+        packet.extend_from_slice(&counter.to_be_bytes());
+        packet.extend_from_slice(&ciphertext);
+        Some(packet)
+    }
+
+    /// Fails on packets that weren't sealed with this session's key, were tampered with, or
+    /// were opened before.
+    fn open(&self, direction: Direction, packet: &[u8]) -> Option<Vec<u8>> {
+        if packet.len() < HEADER_LEN || packet[0] != DATA {
+            return None;
+        }
+        let mut counter = [0; 8];
+        counter.copy_from_slice(&packet[1..HEADER_LEN]);
+        let counter = u64::from_be_bytes(counter);
+        let mut opened = self.opened.lock().unwrap();
+        if !opened.is_fresh(counter) {
+            return None;
+        }
+        let payload = self
+            .cipher
+            .decrypt(&nonce(direction, counter), &packet[HEADER_LEN..])
+            .ok()?;
+        opened.mark(counter);
+        Some(payload)
+    }
+}
+
+fn nonce(direction: Direction, counter: u64) -> Nonce {
+    let mut nonce = [0; 12];
+    nonce[0] = direction as u8;
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::clone_from_slice(&nonce)
+}
+
+fn handshake(public: &PublicKey) -> Vec<u8> {
+    let mut packet = vec![HANDSHAKE];
+    packet.extend_from_slice(public.as_bytes());
+    packet
+}
+
+fn unspecified(address: SocketAddr) -> SocketAddr {
+    let ip: IpAddr = if address.is_ipv4() {
+        Ipv4Addr::UNSPECIFIED.into()
+    } else {
+        Ipv6Addr::UNSPECIFIED.into()
+    };
+    SocketAddr::new(ip, 0)
+}
+
+/// Starts a tunnel listening on `listen` that seals the packets of the local client and passes
+/// them to the server tunnel at `server`. Returns the address it listens on, for the client to
+/// connect to. Packets are dropped until the key exchange is done, turbulence sends them again.
+pub fn spawn_client_tunnel(listen: SocketAddr, server: SocketAddr) -> io::Result<SocketAddr> {
+    if cfg!(target_arch = "wasm32") {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "browsers can't open sockets for the tunnel",
+        ));
+    }
+    let local = Arc::new(UdpSocket::bind(listen)?);
+    let listening = local.local_addr()?;
+    let remote = Arc::new(UdpSocket::bind(unspecified(server))?);
+    remote.connect(server)?;
+    remote.set_read_timeout(Some(HANDSHAKE_RETRY_PERIOD))?;
+
+    let session: Arc<Mutex<Option<Arc<Session>>>> = Arc::default();
+    // the client's own socket, known once it sent something
+    let client: Arc<Mutex<Option<SocketAddr>>> = Arc::default();
+
+    let (inbound_session, inbound_client) = (session.clone(), client.clone());
+    let (inbound_local, inbound_remote) = (local.clone(), remote.clone());
+    thread::spawn(move || {
+        let mut secret = Some(EphemeralSecret::new(OsRng));
+        let hello = handshake(&PublicKey::from(secret.as_ref().unwrap()));
+        let mut buffer = [0; MAX_PACKET_LEN];
+        loop {
+            let opened = inbound_session.lock().unwrap().clone();
+            let opened = match opened {
+                Some(opened) => opened,
+                None => {
+                    if let Err(error) = inbound_remote.send(&hello) {
+                        warn!("Encryption tunnel can't greet the server: {}", error);
+                    }
+                    match inbound_remote.recv(&mut buffer) {
+                        Ok(len) if len > 0 && buffer[0] == HANDSHAKE => {
+                            let their_public = &buffer[1..len];
+                            match Session::new(
+                                secret.take().unwrap(),
+                                their_public,
+                                Direction::ToServer,
+                            ) {
+                                Some(opened) => {
+                                    info!("Encrypting the traffic to {}", server);
+                                    let _ = inbound_remote.set_read_timeout(None);
+                                    *inbound_session.lock().unwrap() = Some(Arc::new(opened));
+                                }
+                                None => {
+                                    warn!("The server sent a malformed key");
+                                    return;
+                                }
+                            }
+                        }
+                        // no answer yet, greeting again
+                        _ => (),
+                    }
+                    continue;
+                }
+            };
+            let len = match inbound_remote.recv(&mut buffer) {
+                Ok(len) => len,
+                // an earlier packet found the server closed, it may be back
+                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => continue,
+                Err(error) => {
+                    warn!("Encryption tunnel stops receiving: {}", error);
+                    return;
+                }
+            };
+            let client = *inbound_client.lock().unwrap();
+            if let (Some(payload), Some(client)) =
+                (opened.open(Direction::ToClient, &buffer[..len]), client)
+            {
+                if let Err(error) = inbound_local.send_to(&payload, client) {
+                    warn!("Encryption tunnel can't reach the client: {}", error);
+                }
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let mut buffer = [0; MAX_PACKET_LEN];
+        loop {
+            let (len, from) = match local.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) => {
+                    warn!("Encryption tunnel can't receive: {}", error);
+                    continue;
+                }
+            };
+            *client.lock().unwrap() = Some(from);
+            let opened = session.lock().unwrap().clone();
+            if let Some(packet) =
+                opened.and_then(|opened| opened.seal(Direction::ToServer, &buffer[..len]))
+            {
+                if let Err(error) = remote.send(&packet) {
+                    warn!("Encryption tunnel can't reach the server: {}", error);
+                }
+            }
+        }
+    });
+    Ok(listening)
+}
+
+/// Client through the key exchange, which hasn't sent a sealed packet yet.
+struct Pending {
+    client_public: Vec<u8>,
+    server_public: PublicKey,
+    session: Arc<Session>,
+    greeted_at: Instant,
+}
+
+struct Peer {
+    client_public: Vec<u8>,
+    server_public: PublicKey,
+    session: Arc<Session>,
+    upstream: Arc<UdpSocket>,
+    /// Tells the thread passing on the target's packets to stop, once the peer is dropped.
+    closed: Arc<AtomicBool>,
+    last_heard: Instant,
+}
+
+impl Peer {
+    /// Gives the client a socket of its own towards `target`, and a thread sealing what comes
+    /// back on it.
+    fn connect(
+        pending: Pending,
+        from: SocketAddr,
+        target: SocketAddr,
+        listener: &Arc<UdpSocket>,
+    ) -> io::Result<Self> {
+        let upstream = UdpSocket::bind(unspecified(target))?;
+        upstream.connect(target)?;
+        // wakes up now and then to see if the peer was dropped
+        upstream.set_read_timeout(Some(SWEEP_PERIOD))?;
+        let upstream = Arc::new(upstream);
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let (replies, sealing, listener, stop) = (
+            upstream.clone(),
+            pending.session.clone(),
+            listener.clone(),
+            closed.clone(),
+        );
+        thread::spawn(move || {
+            let mut buffer = [0; MAX_PACKET_LEN];
+            while !stop.load(Ordering::Relaxed) {
+                match replies.recv(&mut buffer) {
+                    Ok(len) => {
+                        if let Some(packet) = sealing.seal(Direction::ToClient, &buffer[..len]) {
+                            if let Err(error) = listener.send_to(&packet, from) {
+                                warn!("Encryption tunnel can't reach {}: {}", from, error);
+                            }
+                        }
+                    }
+                    Err(error) if is_timeout(&error) => (),
+                    Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => (),
+                    Err(error) => {
+                        warn!("Encryption tunnel stops forwarding: {}", error);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            client_public: pending.client_public,
+            server_public: pending.server_public,
+            session: pending.session,
+            upstream,
+            closed,
+            last_heard: Instant::now(),
+        })
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Starts a tunnel listening on `listen` that opens the packets of every client and passes them
+/// to `target` from a socket of its own, so that the target still tells the clients apart.
+pub fn spawn_server_tunnel(listen: SocketAddr, target: SocketAddr) -> io::Result<()> {
+    let listener = Arc::new(UdpSocket::bind(listen)?);
+    listener.set_read_timeout(Some(SWEEP_PERIOD))?;
+    thread::spawn(move || {
+        let mut pending: HashMap<SocketAddr, Pending> = HashMap::new();
+        let mut peers: HashMap<SocketAddr, Peer> = HashMap::new();
+        let mut swept_at = Instant::now();
+        let mut buffer = [0; MAX_PACKET_LEN];
+        loop {
+            let now = Instant::now();
+            if now - swept_at >= SWEEP_PERIOD {
+                swept_at = now;
+                pending.retain(|_, pending| now - pending.greeted_at < PENDING_TIMEOUT);
+                peers.retain(|from, peer| {
+                    let alive = now - peer.last_heard < PEER_TIMEOUT;
+                    if !alive {
+                        info!("Encryption tunnel forgets {}, silent for too long", from);
+                    }
+                    alive
+                });
+            }
+
+            let (len, from) = match listener.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) if is_timeout(&error) => continue,
+                Err(error) => {
+                    warn!("Encryption tunnel can't receive: {}", error);
+                    continue;
+                }
+            };
+            let packet = &buffer[..len];
+
+            if packet.first() == Some(&HANDSHAKE) {
+                let client_public = &packet[1..];
+                // a repeated greeting means the answer got lost, it gets the same key again
+                let known = pending
+                    .get(&from)
+                    .map(|pending| (&pending.client_public, pending.server_public))
+                    .into_iter()
+                    .chain(
+                        peers
+                            .get(&from)
+                            .map(|peer| (&peer.client_public, peer.server_public)),
+                    )
+                    .find(|(known_public, _)| known_public.as_slice() == client_public)
+                    .map(|(_, server_public)| server_public);
+                let server_public = match known {
+                    Some(server_public) => server_public,
+                    // a new key from a known client replaces its peer once it's used
+                    None => {
+                        let secret = EphemeralSecret::new(OsRng);
+                        let server_public = PublicKey::from(&secret);
+                        let session = match Session::new(secret, client_public, Direction::ToClient)
+                        {
+                            Some(session) => Arc::new(session),
+                            None => continue,
+                        };
+                        if pending.len() >= MAX_PENDING && !pending.contains_key(&from) {
+                            let oldest = pending
+                                .iter()
+                                .min_by_key(|(_, pending)| pending.greeted_at)
+                                .map(|(address, _)| *address);
+                            if let Some(oldest) = oldest {
+                                pending.remove(&oldest);
+                            }
+                        }
+                        pending.insert(
+                            from,
+                            Pending {
+                                client_public: client_public.to_vec(),
+                                server_public,
+                                session,
+                                greeted_at: now,
+                            },
+                        );
+                        server_public
+                    }
+                };
+                let answer = handshake(&server_public);
+                if let Err(error) = listener.send_to(&answer, from) {
+                    warn!("Encryption tunnel can't answer {}: {}", from, error);
+                }
+                continue;
+            }
+
+            // the first sealed packet shows the client holds the key, it gets in then
+            let first = pending
+                .get(&from)
+                .and_then(|pending| pending.session.open(Direction::ToServer, packet));
+            if let Some(payload) = first {
+                if peers.len() >= MAX_PEERS && !peers.contains_key(&from) {
+                    warn!(
+                        "Encryption tunnel keeps {} waiting, {} clients are in",
+                        from, MAX_PEERS
+                    );
+                    continue;
+                }
+                let greeted = pending.remove(&from).unwrap();
+                match Peer::connect(greeted, from, target, &listener) {
+                    Ok(peer) => {
+                        if let Err(error) = peer.upstream.send(&payload) {
+                            warn!("Encryption tunnel can't reach the server: {}", error);
+                        }
+                        // drops the peer of the previous key, stopping its thread
+                        peers.insert(from, peer);
+                    }
+                    Err(error) => warn!("Encryption tunnel can't reach the server: {}", error),
+                }
+                continue;
+            }
+
+            // unsealed packets and those of unknown clients are dropped
+            let peer = match peers.get_mut(&from) {
+                Some(peer) => peer,
+                None => continue,
+            };
+            if let Some(payload) = peer.session.open(Direction::ToServer, packet) {
+                peer.last_heard = now;
+                if let Err(error) = peer.upstream.send(&payload) {
+                    warn!("Encryption tunnel can't reach the server: {}", error);
+                }
+            }
+        }
+    });
+    Ok(())
+}
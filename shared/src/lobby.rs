@@ -0,0 +1,171 @@
+//! Lobby messages, sent on their own reliable channel next to the crystalorb ones. The server
+//! keeps the `GameWorld` frozen until both players sent theirs.
+
+use bevy::prelude::*;
+use crystalorb_bevy_networking_turbulence::{
+    bevy_networking_turbulence::{
+        ConnectionChannelsBuilder, MessageChannelMode, MessageChannelSettings, NetworkResource,
+        ReliableChannelSettings,
+    },
+    crystalorb::clocksync::ClockSyncMessage,
+    ClockSyncChannelSettings, CommandChannelSettings, SnapshotChannelSettings,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    game::{MatchConfig, PlayerId},
+    net::{self, EncodedCommand, EncodedSnapshot},
+    tournament::Bracket,
+};
+
+/// Longest player name, in characters. The server cuts longer ones.
+pub const MAX_NAME_CHARS: usize = 16;
+
+/// Longest message of the day and rules, in bytes each, so that both fit in a lobby message.
+/// The server cuts longer ones.
+pub const MAX_MOTD_BYTES: usize = 400;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LobbyMessage {
+    /// Sent by a client right after connecting, with the name its player picked and the seat
+    /// token it got in an earlier session, if it's reconnecting. The protocol version goes
+    /// first, so that it can be read even when the rest of the message changed.
+    Hello {
+        protocol_version: u32,
+        content_hash: u64,
+        name: String,
+        seat_token: Option<u64>,
+    },
+    /// Sent by the server to a client that got a seat, with the token that reclaims the seat
+    /// after a dropped connection.
+    Welcome {
+        player_id: PlayerId,
+        seat_token: u64,
+    },
+    /// Sent by the server to a client that found both seats taken, which only watches the match.
+    /// Also sent to players leaving their seat for the next match of a tournament.
+    Spectate,
+    /// Sent by the server to every client it accepts, before seating it, with the crystalorb
+    /// timing the server runs with, in seconds. The client adopts it so that both ends agree on
+    /// how far back commands are compensated and how often snapshots come.
+    Timing {
+        lag_compensation_latency: f64,
+        snapshot_send_period: f64,
+    },
+    /// Sent by the server to every client it accepts, with the rules, tuning and settings of the
+    /// match, which the snapshots leave out. Later changes reach the clients as commands.
+    MatchConfig(MatchConfig),
+    /// Sent by the server to a client it can't play with, before dropping it.
+    Rejected { reason: String },
+    /// Sent by a client once its player is ready to start.
+    Ready,
+    /// Broadcast by the server whenever a player's name changes.
+    Names([Option<String>; 2]),
+    /// Broadcast by the server running a tournament whenever a match of it is decided, and sent
+    /// to every client as it connects.
+    Bracket(Bracket),
+    /// Sent by the server to every client as it connects, and broadcast when the server's
+    /// settings change, with its message of the day and rules. Only sent empty to take the
+    /// previous ones down.
+    Motd { message: String, rules: String },
+    /// Sent by a client every second to measure the round trip time, with the time it was sent.
+    Ping { sent_at: f64 },
+    /// The server's answer to a ping, with the packets it received from and sent to the client
+    /// so far, which the client compares with its own counts to tell how many got lost.
+    Pong {
+        sent_at: f64,
+        packets_received: u64,
+        packets_sent: u64,
+    },
+}
+
+/// Trimmed and cut to `MAX_NAME_CHARS`, empty names are left out.
+pub fn sanitize_name(name: &str) -> Option<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_CHARS)
+        .collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// The server's message of the day and rules, cut to fit and without control characters other
+/// than line breaks, or `None` while both are empty.
+pub fn motd_message(message: &str, rules: &str) -> Option<LobbyMessage> {
+    let sanitize = |text: &str| {
+        let mut length = 0;
+        text.trim()
+            .chars()
+            .filter(|c| *c == '\n' || !c.is_control())
+            .take_while(|c| {
+                length += c.len_utf8();
+                length <= MAX_MOTD_BYTES
+            })
+            .collect::<String>()
+    };
+    let (message, rules) = (sanitize(message), sanitize(rules));
+    if message.is_empty() && rules.is_empty() {
+        None
+    } else {
+        Some(LobbyMessage::Motd { message, rules })
+    }
+}
+
+/// Follows the crystalorb command, snapshot and clock sync channels.
+pub const LOBBY_CHANNEL: u8 = net::CLOCK_SYNC_CHANNEL + 1;
+
+const LOBBY_CHANNEL_SETTINGS: MessageChannelSettings = MessageChannelSettings {
+    channel: LOBBY_CHANNEL,
+    channel_mode: MessageChannelMode::Reliable {
+        reliability_settings: ReliableChannelSettings {
+            bandwidth: 1024,
+            recv_window_size: 1024,
+            send_window_size: 1024,
+            burst_bandwidth: 1024,
+            init_send: 512,
+            wakeup_time: Duration::from_millis(100),
+            initial_rtt: Duration::from_millis(200),
+            max_rtt: Duration::from_secs(2),
+            rtt_update_factor: 0.1,
+            rtt_resend_factor: 1.5,
+        },
+        // room for a full tournament bracket
+        max_message_len: 1024,
+    },
+    message_buffer_size: 8,
+    packet_buffer_size: 8,
+};
+
+/// Registers the crystalorb channels, for the encoded snapshots and commands, together with the
+/// lobby one. The network resource only keeps one channels builder, which this sets for all of
+/// them.
+pub fn setup_channels(
+    mut net: ResMut<NetworkResource>,
+    command_channel_settings: Res<CommandChannelSettings>,
+    snapshot_channel_settings: Res<SnapshotChannelSettings>,
+    clock_sync_channel_settings: Res<ClockSyncChannelSettings>,
+) {
+    let command_channel_settings = command_channel_settings.0.clone();
+    let snapshot_channel_settings = snapshot_channel_settings.0.clone();
+    let clock_sync_channel_settings = clock_sync_channel_settings.0.clone();
+    net.set_channels_builder(move |builder: &mut ConnectionChannelsBuilder| {
+        builder
+            .register::<EncodedCommand>(command_channel_settings.clone())
+            .unwrap();
+        builder
+            .register::<EncodedSnapshot>(snapshot_channel_settings.clone())
+            .unwrap();
+        builder
+            .register::<ClockSyncMessage>(clock_sync_channel_settings.clone())
+            .unwrap();
+        builder
+            .register::<LobbyMessage>(LOBBY_CHANNEL_SETTINGS)
+            .unwrap();
+    });
+}
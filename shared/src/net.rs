@@ -0,0 +1,175 @@
+//! Settings of the crystalorb channels, which have to be the same on the client and the server,
+//! and `lobby::setup_channels` registers the channels with. The snapshots and commands travel
+//! encoded with `DefaultCodec`, which turbulence then only frames. Also tells when connections
+//! went silent.
+
+use bevy::log::warn;
+use crystalorb_bevy_networking_turbulence::{
+    bevy_networking_turbulence::{
+        ConnectionHandle, MessageChannelMode, MessageChannelSettings, MessageChannels,
+        NetworkResource, ReliableChannelSettings,
+    },
+    crystalorb::timestamp::Timestamped,
+    ClockSyncChannelSettings, CommandChannelSettings, SnapshotChannelSettings,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    time::Duration,
+};
+
+use crate::{
+    codec::{Codec, DefaultCodec},
+    game::{GameCommand, GameSnapshot},
+    settings::ChannelSettings,
+};
+
+pub const COMMAND_CHANNEL: u8 = 0;
+pub const SNAPSHOT_CHANNEL: u8 = 1;
+pub const CLOCK_SYNC_CHANNEL: u8 = 2;
+
+/// Reliable, with the reliability settings from the settings file.
+pub fn command_channel(settings: &ChannelSettings) -> CommandChannelSettings {
+    CommandChannelSettings(MessageChannelSettings {
+        channel: COMMAND_CHANNEL,
+        channel_mode: MessageChannelMode::Compressed {
+            reliability_settings: ReliableChannelSettings {
+                bandwidth: settings.bandwidth,
+                recv_window_size: settings.recv_window_size,
+                send_window_size: settings.send_window_size,
+                burst_bandwidth: settings.burst_bandwidth,
+                init_send: settings.init_send,
+                wakeup_time: Duration::from_millis(settings.wakeup_time_ms),
+                initial_rtt: Duration::from_millis(settings.initial_rtt_ms),
+                max_rtt: Duration::from_millis(settings.max_rtt_ms),
+                rtt_update_factor: settings.rtt_update_factor,
+                rtt_resend_factor: settings.rtt_resend_factor,
+            },
+            max_chunk_len: settings.max_chunk_len,
+        },
+        message_buffer_size: settings.message_buffer_size,
+        packet_buffer_size: settings.packet_buffer_size,
+    })
+}
+
+/// Unreliable, a lost snapshot is superseded by the next one anyway.
+pub fn snapshot_channel() -> SnapshotChannelSettings {
+    SnapshotChannelSettings(MessageChannelSettings {
+        channel: SNAPSHOT_CHANNEL,
+        channel_mode: MessageChannelMode::Unreliable,
+        message_buffer_size: 64,
+        packet_buffer_size: 64,
+    })
+}
+
+/// Unreliable, clock sync requests are sent periodically.
+pub fn clock_sync_channel() -> ClockSyncChannelSettings {
+    ClockSyncChannelSettings(MessageChannelSettings {
+        channel: CLOCK_SYNC_CHANNEL,
+        channel_mode: MessageChannelMode::Unreliable,
+        message_buffer_size: 64,
+        packet_buffer_size: 64,
+    })
+}
+
+/// A snapshot encoded with `DefaultCodec`, as the snapshot channel carries it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodedSnapshot(Vec<u8>);
+
+/// A command encoded with `DefaultCodec`, as the command channel carries it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodedCommand(Vec<u8>);
+
+fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    DefaultCodec::encode(message).expect("can't encode message")
+}
+
+/// Sends a crystalorb message, encoding the snapshots and commands. Gives the message back if
+/// the channel is full, like `MessageChannels::send`.
+pub fn send_message<M>(channels: &mut MessageChannels, message: M) -> Option<M>
+where
+    M: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    let any = &message as &dyn Any;
+    let unsent = if let Some(snapshot) = any.downcast_ref::<Timestamped<GameSnapshot>>() {
+        channels.send(EncodedSnapshot(encode(snapshot))).map(drop)
+    } else if let Some(command) = any.downcast_ref::<Timestamped<GameCommand>>() {
+        channels.send(EncodedCommand(encode(command))).map(drop)
+    } else {
+        return channels.send(message);
+    };
+    unsent.map(|()| message)
+}
+
+/// Flushes the channel `send_message` sends messages of the type on.
+pub fn flush_messages<M>(channels: &mut MessageChannels)
+where
+    M: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    let message_type = TypeId::of::<M>();
+    if message_type == TypeId::of::<Timestamped<GameSnapshot>>() {
+        channels.flush::<EncodedSnapshot>();
+    } else if message_type == TypeId::of::<Timestamped<GameCommand>>() {
+        channels.flush::<EncodedCommand>();
+    } else {
+        channels.flush::<M>();
+    }
+}
+
+/// Next snapshot received that decodes, the others are dropped.
+pub fn recv_snapshot(channels: &mut MessageChannels) -> Option<Timestamped<GameSnapshot>> {
+    while let Some(EncodedSnapshot(bytes)) = channels.recv() {
+        match DefaultCodec::decode(&bytes) {
+            Ok(snapshot) => return Some(snapshot),
+            Err(error) => warn!("Dropping a snapshot that doesn't decode: {}", error),
+        }
+    }
+    None
+}
+
+/// Next command received that decodes, the others are dropped.
+pub fn recv_command(channels: &mut MessageChannels) -> Option<Timestamped<GameCommand>> {
+    while let Some(EncodedCommand(bytes)) = channels.recv() {
+        match DefaultCodec::decode(&bytes) {
+            Ok(command) => return Some(command),
+            Err(error) => warn!("Dropping a command that doesn't decode: {}", error),
+        }
+    }
+    None
+}
+
+/// When each connection last received a packet, to drop the ones that went silent. Both sides
+/// keep sending at least the keepalive pings, so a connection that stays silent for longer than
+/// the idle timeout is gone.
+#[derive(Default)]
+pub struct LastHeard(HashMap<ConnectionHandle, (f64, usize)>);
+
+impl LastHeard {
+    /// Notes which connections received packets since the last call, and returns the ones that
+    /// didn't for longer than `timeout` seconds.
+    pub fn silent(
+        &mut self,
+        net: &NetworkResource,
+        now: f64,
+        timeout: f64,
+    ) -> Vec<ConnectionHandle> {
+        self.0
+            .retain(|handle, _| net.connections.contains_key(handle));
+        let mut silent = vec![];
+        for (handle, connection) in net.connections.iter() {
+            let packets = connection.stats().packets_rx;
+            match self.0.get(handle) {
+                Some((heard_at, heard_packets)) if *heard_packets == packets => {
+                    if now - heard_at > timeout {
+                        silent.push(*handle);
+                    }
+                }
+                _ => {
+                    self.0.insert(*handle, (now, packets));
+                }
+            }
+        }
+        silent
+    }
+}
@@ -0,0 +1,517 @@
+//! Settings read from a TOML file at startup, written with the defaults when it's missing.
+//! Command line options take precedence over them. The server reads the file again when it
+//! changes, see `Settings::read`.
+
+use bevy::input::keyboard::KeyCode;
+use crystalorb_bevy_networking_turbulence::crystalorb::{Config, TweeningMethod};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, net::IpAddr, path::Path, str::FromStr};
+
+use crate::{
+    game::{GameTuning, MatchSettings},
+    SERVER_PORT,
+};
+
+/// Both the client and the server read this file, so run them from the same directory to share
+/// the channel settings.
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub player: PlayerSettings,
+    pub network: NetworkSettings,
+    pub channel: ChannelSettings,
+    pub crystalorb: CrystalOrbSettings,
+    pub game: GameSettings,
+    pub keybinds: KeyBinds,
+    pub controller: ControllerSettings,
+    pub audio: AudioSettings,
+    pub graphics: GraphicsSettings,
+    pub display: DisplaySettings,
+}
+
+impl Settings {
+    /// Reads the settings, first writing the defaults to `path` if there's no file there yet.
+    /// Panics if the file can't be parsed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|error| {
+                panic!("invalid settings in {}: {}", path.display(), error)
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let settings = Settings::default();
+                let written = toml::to_string_pretty(&settings)
+                    .map_err(|error| error.to_string())
+                    .and_then(|contents| {
+                        fs::write(path, contents).map_err(|error| error.to_string())
+                    });
+                if let Err(error) = written {
+                    eprintln!(
+                        "Can't write default settings to {}: {}",
+                        path.display(),
+                        error
+                    );
+                }
+                settings
+            }
+            Err(error) => panic!("can't read settings from {}: {}", path.display(), error),
+        }
+    }
+
+    /// Reads the settings again, failing instead of panicking when the file went missing or
+    /// doesn't parse.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, String> {
+        fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| toml::from_str(&contents).map_err(|error| error.to_string()))
+    }
+
+    /// Writes the settings to `path`, as the client does with the ones changed in its menu.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        toml::to_string_pretty(self)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| fs::write(path, contents).map_err(|error| error.to_string()))
+    }
+
+    /// Browsers have no file system to keep the settings in, so the defaults are used.
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(_path: impl AsRef<Path>) -> Self {
+        Settings::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PlayerSettings {
+    /// Name shown to both players, prefilled on the connect screen.
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// `host:port` prefilled on the client's connect screen.
+    pub server: Option<String>,
+    /// Name the server announces itself with on the LAN.
+    pub server_name: String,
+    /// Address the server listens on, the first local address when unset.
+    pub bind: Option<IpAddr>,
+    pub port: u16,
+    /// Port of the WebRTC signaling endpoint, which browser clients connect to.
+    pub webrtc_port: u16,
+    /// Seconds between the keepalive pings clients send, which the server answers.
+    pub keepalive_seconds: f64,
+    /// Seconds of silence after which either side drops the connection.
+    pub idle_timeout_seconds: f64,
+    /// Updates per second the server runs while clients are connected.
+    pub tick_rate: f64,
+    /// Updates per second the server runs while nobody is connected, low to leave the CPU alone.
+    pub idle_tick_rate: f64,
+    /// Message of the day and rules the server shows its clients in the lobby, empty for none.
+    pub motd: String,
+    pub rules: String,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            server: None,
+            server_name: "platformer server".to_string(),
+            bind: None,
+            port: SERVER_PORT,
+            webrtc_port: SERVER_PORT + 1,
+            keepalive_seconds: 1.0,
+            idle_timeout_seconds: 5.0,
+            tick_rate: 60.0,
+            idle_tick_rate: 2.0,
+            motd: String::new(),
+            rules: String::new(),
+        }
+    }
+}
+
+/// Match settings and tuning the server starts matches with, unset ones taking their defaults.
+/// The command line options of the same names override them. Changes made while the server runs
+/// apply from the next match.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Zero for an endless match.
+    pub score_target: Option<u8>,
+    pub round_limit: Option<u8>,
+    /// Seconds.
+    pub round_time: Option<u16>,
+    /// Seconds.
+    pub disconnect_grace: Option<u16>,
+    pub move_speed: Option<f32>,
+    pub jump_velocity: Option<f32>,
+    pub gravity_scale: Option<f32>,
+    pub cannon_speed: Option<f32>,
+    pub projectile_speed: Option<f32>,
+    pub max_projectiles: Option<u8>,
+}
+
+impl GameSettings {
+    /// These settings, with the unset ones taken from `fallback`.
+    pub fn or(&self, fallback: &GameSettings) -> Self {
+        Self {
+            score_target: self.score_target.or(fallback.score_target),
+            round_limit: self.round_limit.or(fallback.round_limit),
+            round_time: self.round_time.or(fallback.round_time),
+            disconnect_grace: self.disconnect_grace.or(fallback.disconnect_grace),
+            move_speed: self.move_speed.or(fallback.move_speed),
+            jump_velocity: self.jump_velocity.or(fallback.jump_velocity),
+            gravity_scale: self.gravity_scale.or(fallback.gravity_scale),
+            cannon_speed: self.cannon_speed.or(fallback.cannon_speed),
+            projectile_speed: self.projectile_speed.or(fallback.projectile_speed),
+            max_projectiles: self.max_projectiles.or(fallback.max_projectiles),
+        }
+    }
+
+    pub fn match_settings(&self) -> MatchSettings {
+        let defaults = MatchSettings::default();
+        MatchSettings {
+            score_target: match self.score_target {
+                Some(0) => None,
+                Some(score_target) => Some(score_target),
+                None => defaults.score_target,
+            },
+            round_limit: self.round_limit.or(defaults.round_limit),
+            round_time_limit: self.round_time.or(defaults.round_time_limit),
+            disconnect_grace: self.disconnect_grace.unwrap_or(defaults.disconnect_grace),
+        }
+    }
+
+    pub fn tuning(&self) -> GameTuning {
+        let defaults = GameTuning::default();
+        GameTuning {
+            move_speed: self.move_speed.unwrap_or(defaults.move_speed),
+            jump_velocity: self.jump_velocity.unwrap_or(defaults.jump_velocity),
+            gravity_scale: self.gravity_scale.unwrap_or(defaults.gravity_scale),
+            cannon_speed: self.cannon_speed.unwrap_or(defaults.cannon_speed),
+            projectile_speed: self.projectile_speed.unwrap_or(defaults.projectile_speed),
+            max_projectiles: self.max_projectiles.unwrap_or(defaults.max_projectiles),
+        }
+    }
+}
+
+/// Reliability settings of the command channel, see `net::command_channel`. Has to match between
+/// the client and the server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub bandwidth: usize,
+    pub recv_window_size: usize,
+    pub send_window_size: usize,
+    pub burst_bandwidth: usize,
+    pub init_send: usize,
+    pub wakeup_time_ms: u64,
+    pub initial_rtt_ms: u64,
+    pub max_rtt_ms: u64,
+    pub rtt_update_factor: f64,
+    pub rtt_resend_factor: f64,
+    pub max_chunk_len: usize,
+    pub message_buffer_size: usize,
+    pub packet_buffer_size: usize,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            bandwidth: 4096,
+            recv_window_size: 1024,
+            send_window_size: 1024,
+            burst_bandwidth: 1024,
+            init_send: 512,
+            wakeup_time_ms: 100,
+            initial_rtt_ms: 200,
+            max_rtt_ms: 2000,
+            rtt_update_factor: 0.1,
+            rtt_resend_factor: 1.5,
+            max_chunk_len: 1024,
+            message_buffer_size: 64,
+            packet_buffer_size: 64,
+        }
+    }
+}
+
+/// Overrides of the crystalorb defaults, in seconds. The timestep is fixed by the game. The
+/// server's lag compensation latency and snapshot send period apply to all of its clients.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CrystalOrbSettings {
+    pub lag_compensation_latency: Option<f64>,
+    pub blend_latency: Option<f64>,
+    pub snapshot_send_period: Option<f64>,
+    pub clock_sync_request_period: Option<f64>,
+    pub max_tolerable_clock_deviation: Option<f64>,
+    pub tweening_method: Option<Tweening>,
+}
+
+/// How the displayed state is picked between two simulated ticks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Tweening {
+    MostRecentlyPassed,
+    Nearest,
+    Interpolated,
+}
+
+impl FromStr for Tweening {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "most_recently_passed" => Ok(Tweening::MostRecentlyPassed),
+            "nearest" => Ok(Tweening::Nearest),
+            "interpolated" => Ok(Tweening::Interpolated),
+            _ => Err(format!(
+                "unknown tweening method {}, expected most_recently_passed, nearest or \
+                 interpolated",
+                s
+            )),
+        }
+    }
+}
+
+impl Tweening {
+    /// From the most responsive to the smoothest.
+    pub const ALL: [Tweening; 3] = [
+        Tweening::MostRecentlyPassed,
+        Tweening::Nearest,
+        Tweening::Interpolated,
+    ];
+}
+
+impl From<TweeningMethod> for Tweening {
+    fn from(tweening_method: TweeningMethod) -> Self {
+        match tweening_method {
+            TweeningMethod::MostRecentlyPassed => Tweening::MostRecentlyPassed,
+            TweeningMethod::Nearest => Tweening::Nearest,
+            TweeningMethod::Interpolated => Tweening::Interpolated,
+        }
+    }
+}
+
+impl From<Tweening> for TweeningMethod {
+    fn from(tweening: Tweening) -> Self {
+        match tweening {
+            Tweening::MostRecentlyPassed => TweeningMethod::MostRecentlyPassed,
+            Tweening::Nearest => TweeningMethod::Nearest,
+            Tweening::Interpolated => TweeningMethod::Interpolated,
+        }
+    }
+}
+
+impl CrystalOrbSettings {
+    /// Options overriding the settings file, the same on the command line (`--blend-latency`)
+    /// and in the environment (`PLATFORMER_BLEND_LATENCY`), which the command line overrides.
+    pub const OPTIONS: [&'static str; 6] = [
+        "lag-compensation-latency",
+        "blend-latency",
+        "snapshot-send-period",
+        "clock-sync-request-period",
+        "max-tolerable-clock-deviation",
+        "tweening-method",
+    ];
+
+    pub fn env_var(option: &str) -> Option<String> {
+        std::env::var(format!(
+            "PLATFORMER_{}",
+            option.to_uppercase().replace('-', "_")
+        ))
+        .ok()
+    }
+
+    /// Overrides the settings with the options `value_of` finds, failing on malformed values.
+    pub fn override_with(
+        &mut self,
+        value_of: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), String> {
+        fn parse<T: FromStr>(option: &str, value: Option<String>) -> Result<Option<T>, String> {
+            value
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid value for {}: {}", option, value))
+                })
+                .transpose()
+        }
+        let mut seconds = [
+            (
+                "lag-compensation-latency",
+                &mut self.lag_compensation_latency,
+            ),
+            ("blend-latency", &mut self.blend_latency),
+            ("snapshot-send-period", &mut self.snapshot_send_period),
+            (
+                "clock-sync-request-period",
+                &mut self.clock_sync_request_period,
+            ),
+            (
+                "max-tolerable-clock-deviation",
+                &mut self.max_tolerable_clock_deviation,
+            ),
+        ];
+        for (option, setting) in seconds.iter_mut() {
+            if let Some(value) = parse(option, value_of(option))? {
+                **setting = Some(value);
+            }
+        }
+        if let Some(value) = parse("tweening-method", value_of("tweening-method"))? {
+            self.tweening_method = Some(value);
+        }
+        Ok(())
+    }
+
+    pub fn config(&self) -> Config {
+        let mut config = crate::crystal_orb_config();
+        if let Some(lag_compensation_latency) = self.lag_compensation_latency {
+            config.lag_compensation_latency = lag_compensation_latency;
+        }
+        if let Some(blend_latency) = self.blend_latency {
+            config.blend_latency = blend_latency;
+        }
+        if let Some(snapshot_send_period) = self.snapshot_send_period {
+            config.snapshot_send_period = snapshot_send_period;
+        }
+        if let Some(clock_sync_request_period) = self.clock_sync_request_period {
+            config.clock_sync_request_period = clock_sync_request_period;
+        }
+        if let Some(max_tolerable_clock_deviation) = self.max_tolerable_clock_deviation {
+            config.max_tolerable_clock_deviation = max_tolerable_clock_deviation;
+        }
+        if let Some(tweening_method) = self.tweening_method {
+            config.tweening_method = tweening_method.into();
+        }
+        config
+    }
+}
+
+/// Keyboard controls of the client.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct KeyBinds {
+    pub action: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub grapple: KeyCode,
+    pub ability: KeyCode,
+    pub mortar: KeyCode,
+    pub ricochet: KeyCode,
+}
+
+impl Default for KeyBinds {
+    fn default() -> Self {
+        Self {
+            action: KeyCode::Space,
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            grapple: KeyCode::Up,
+            ability: KeyCode::Down,
+            mortar: KeyCode::LShift,
+            ricochet: KeyCode::LControl,
+        }
+    }
+}
+
+/// Controllers of the client, played alongside the keyboard.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ControllerSettings {
+    pub enabled: bool,
+    /// How far the stick has to be pushed, from 0 to 1, to count.
+    pub dead_zone: f32,
+}
+
+impl Default for ControllerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dead_zone: 0.3,
+        }
+    }
+}
+
+/// Sound of the client.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// From 0 for silence to 1 for full volume.
+    pub volume: f32,
+    /// Background music during the match.
+    pub music: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            music: true,
+        }
+    }
+}
+
+/// What the client draws besides the game.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Names above the players.
+    pub name_tags: bool,
+    /// Camera shake on deaths and impacts.
+    pub screen_shake: bool,
+    /// Multiplier of the size of the HUD's text and of its distance from the edges.
+    pub ui_scale: f32,
+    /// Pixels the HUD keeps clear of the edges of the window, for screens that cut them off.
+    pub safe_area: f32,
+    /// Dims the background, outlines the players and projectiles and enlarges the cannon's
+    /// indicators.
+    pub high_contrast: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            name_tags: true,
+            screen_shake: true,
+            ui_scale: 1.0,
+            safe_area: 0.0,
+            high_contrast: false,
+        }
+    }
+}
+
+/// Window of the native client.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    /// Frames per second the client doesn't go over, none to draw as fast as it can.
+    pub frame_cap: Option<u32>,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            mode: DisplayMode::Windowed,
+            vsync: true,
+            frame_cap: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    Windowed,
+    /// A window without decorations covering the screen.
+    Borderless,
+    /// Exclusive fullscreen, at the screen's resolution.
+    Fullscreen,
+}
@@ -0,0 +1,136 @@
+//! Single elimination brackets for tournaments, run by the server and shown to the clients.
+
+use serde::{Deserialize, Serialize};
+
+/// Most participants a bracket takes, so that it fits in a lobby message.
+pub const MAX_PARTICIPANTS: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BracketMatch {
+    /// Unset while the match feeding the slot is undecided, and for byes.
+    pub players: [Option<String>; 2],
+    /// Index into `players`.
+    pub winner: Option<usize>,
+}
+
+impl BracketMatch {
+    pub fn winner_name(&self) -> Option<&str> {
+        self.winner
+            .and_then(|winner| self.players[winner].as_deref())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bracket {
+    /// From the first round to the final, every round with half the matches of the one before.
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+impl Bracket {
+    /// Pairs the participants up in order. Fields that aren't a power of two get byes, spread
+    /// so that every first round match has at least one player.
+    pub fn new(participants: &[String]) -> Result<Self, String> {
+        if participants.len() < 2 || participants.len() > MAX_PARTICIPANTS {
+            return Err(format!(
+                "a bracket takes 2 to {} participants, not {}",
+                MAX_PARTICIPANTS,
+                participants.len()
+            ));
+        }
+        let first_round_matches = participants.len().next_power_of_two() / 2;
+        let mut rounds = vec![];
+        let mut matches = first_round_matches;
+        while matches > 0 {
+            rounds.push(vec![
+                BracketMatch {
+                    players: [None, None],
+                    winner: None,
+                };
+                matches
+            ]);
+            matches /= 2;
+        }
+        for (index, participant) in participants.iter().enumerate() {
+            let (slot, side) = if index < first_round_matches {
+                (index, 0)
+            } else {
+                // the first participants are the ones left with byes, like top seeds
+                (2 * first_round_matches - 1 - index, 1)
+            };
+            rounds[0][slot].players[side] = Some(participant.clone());
+        }
+        let mut bracket = Self { rounds };
+        for index in 0..first_round_matches {
+            if bracket.rounds[0][index].players[1].is_none() {
+                bracket.record(0, index, 0);
+            }
+        }
+        Ok(bracket)
+    }
+
+    /// First match that has both players and no winner yet, as its round and index.
+    pub fn next_match(&self) -> Option<(usize, usize)> {
+        self.rounds.iter().enumerate().find_map(|(round, matches)| {
+            matches
+                .iter()
+                .position(|bracket_match| {
+                    bracket_match.winner.is_none()
+                        && bracket_match.players.iter().all(Option::is_some)
+                })
+                .map(|index| (round, index))
+        })
+    }
+
+    pub fn get(&self, round: usize, index: usize) -> &BracketMatch {
+        &self.rounds[round][index]
+    }
+
+    /// Sets the winner of a match, moving them on to the next round.
+    pub fn record(&mut self, round: usize, index: usize, winner: usize) {
+        let bracket_match = &mut self.rounds[round][index];
+        bracket_match.winner = Some(winner);
+        let name = bracket_match.players[winner].clone();
+        if let Some(next_round) = self.rounds.get_mut(round + 1) {
+            next_round[index / 2].players[index % 2] = name;
+        }
+    }
+
+    /// Set once the final is decided.
+    pub fn champion(&self) -> Option<&str> {
+        self.rounds.last().and_then(|last| last[0].winner_name())
+    }
+
+    /// A line for every decided or ready match, round by round, for showing the bracket.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for (round, matches) in self.rounds.iter().enumerate() {
+            let title = if round + 1 == self.rounds.len() {
+                "Final".to_string()
+            } else {
+                format!("Round {}", round + 1)
+            };
+            for bracket_match in matches {
+                let line = match (&bracket_match.players, bracket_match.winner) {
+                    ([Some(player), None], Some(_)) => format!("{} has a bye", player),
+                    ([Some(player1), Some(player2)], Some(winner)) => {
+                        let (winner, loser) = if winner == 0 {
+                            (player1, player2)
+                        } else {
+                            (player2, player1)
+                        };
+                        format!("{} beat {}", winner, loser)
+                    }
+                    ([Some(player1), Some(player2)], None) => {
+                        format!("{} vs {}", player1, player2)
+                    }
+                    _ => continue,
+                };
+                lines.push(format!("{}: {}", title, line));
+            }
+        }
+        if let Some(champion) = self.champion() {
+            lines.push(format!("{} wins the tournament", champion));
+        }
+        lines
+    }
+}
@@ -0,0 +1,176 @@
+//! The encryption tunnels have to carry packets both ways, and drop the ones tampered with or
+//! sent again on the way.
+
+#![cfg(feature = "encryption")]
+
+use platformer_shared::{
+    conditions::free_local_address,
+    encryption::{spawn_client_tunnel, spawn_server_tunnel},
+};
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// First byte of the sealed packets.
+const DATA: u8 = 1;
+const PACKETS: u8 = 20;
+
+/// What the relay between the tunnels does to the sealed packets bound for the server, besides
+/// passing them on.
+#[derive(Clone, Copy)]
+enum Meddling {
+    None,
+    /// Sends each one twice.
+    Replay,
+    /// Sends a copy with a flipped bit ahead of each one.
+    Tamper,
+}
+
+struct Tunnels {
+    /// The client's socket, and the client tunnel it sends to.
+    client: UdpSocket,
+    client_tunnel: SocketAddr,
+    /// The server's socket, behind the server tunnel.
+    server: UdpSocket,
+}
+
+fn bind() -> UdpSocket {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .unwrap();
+    socket
+}
+
+/// Relays between the client tunnel and the server tunnel at `server_tunnel`.
+fn spawn_relay(server_tunnel: SocketAddr, meddling: Meddling) -> SocketAddr {
+    let socket = bind();
+    let address = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut client_tunnel = None;
+        let mut buffer = [0; 4096];
+        loop {
+            let (len, from) = match socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            let packet = &buffer[..len];
+            if from == server_tunnel {
+                if let Some(client_tunnel) = client_tunnel {
+                    let _ = socket.send_to(packet, client_tunnel);
+                }
+                continue;
+            }
+            client_tunnel = Some(from);
+            if packet.first() == Some(&DATA) {
+                match meddling {
+                    Meddling::None => (),
+                    Meddling::Replay => {
+                        let _ = socket.send_to(packet, server_tunnel);
+                    }
+                    Meddling::Tamper => {
+                        let mut tampered = packet.to_vec();
+                        *tampered.last_mut().unwrap() ^= 1;
+                        let _ = socket.send_to(&tampered, server_tunnel);
+                    }
+                }
+            }
+            let _ = socket.send_to(packet, server_tunnel);
+        }
+    });
+    address
+}
+
+fn open_tunnels(meddling: Meddling) -> Tunnels {
+    let server = bind();
+    let server_tunnel = free_local_address().unwrap();
+    spawn_server_tunnel(server_tunnel, server.local_addr().unwrap()).unwrap();
+    let relay = spawn_relay(server_tunnel, meddling);
+    let client_tunnel = spawn_client_tunnel((Ipv4Addr::LOCALHOST, 0).into(), relay).unwrap();
+    Tunnels {
+        client: bind(),
+        client_tunnel,
+        server,
+    }
+}
+
+/// Greets the server until it hears the client, the packets sent before the key exchange is
+/// done are dropped. Returns where the server hears the client from.
+fn wait_for_key_exchange(tunnels: &Tunnels) -> SocketAddr {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut buffer = [0; 64];
+    while Instant::now() < deadline {
+        tunnels
+            .client
+            .send_to(b"hello", tunnels.client_tunnel)
+            .unwrap();
+        if let Ok((len, from)) = tunnels.server.recv_from(&mut buffer) {
+            if &buffer[..len] == b"hello" {
+                return from;
+            }
+        }
+    }
+    panic!("the tunnels didn't agree on a key");
+}
+
+/// The packets arriving on the socket for the next half second.
+fn receive_all(socket: &UdpSocket) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + Duration::from_millis(500);
+    let mut received = vec![];
+    let mut buffer = [0; 64];
+    while Instant::now() < deadline {
+        if let Ok(len) = socket.recv(&mut buffer) {
+            received.push(buffer[..len].to_vec());
+        }
+    }
+    received
+}
+
+/// Sends numbered packets to the server, once each, and returns what it got.
+fn send_numbered(tunnels: &Tunnels) -> Vec<Vec<u8>> {
+    wait_for_key_exchange(tunnels);
+    // the greetings still on their way
+    receive_all(&tunnels.server);
+    for number in 0..PACKETS {
+        tunnels
+            .client
+            .send_to(&[number], tunnels.client_tunnel)
+            .unwrap();
+    }
+    receive_all(&tunnels.server)
+}
+
+#[test]
+fn packets_go_through_both_ways() {
+    let tunnels = open_tunnels(Meddling::None);
+    let client = wait_for_key_exchange(&tunnels);
+    tunnels.server.send_to(b"welcome", client).unwrap();
+    assert!(receive_all(&tunnels.client)
+        .iter()
+        .any(|packet| packet.as_slice() == b"welcome"));
+}
+
+#[test]
+fn replayed_packets_are_dropped() {
+    let tunnels = open_tunnels(Meddling::Replay);
+    let received = send_numbered(&tunnels);
+    let unique = received.iter().collect::<HashSet<_>>();
+    assert_eq!(unique.len(), received.len(), "replays got through");
+    assert_eq!(received.len(), PACKETS as usize);
+}
+
+#[test]
+fn tampered_packets_are_dropped() {
+    let tunnels = open_tunnels(Meddling::Tamper);
+    let received = send_numbered(&tunnels);
+    let sent = (0..PACKETS).map(|number| vec![number]).collect::<Vec<_>>();
+    assert!(
+        received.iter().all(|packet| sent.contains(packet)),
+        "tampered packets got through"
+    );
+    // the tampered copies went first, without keeping the genuine ones out
+    assert_eq!(received.len(), PACKETS as usize);
+}